@@ -18,8 +18,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // AI config types
     types.register::<app_lib::services::config::AiProvider>();
+    types.register::<app_lib::services::ai::ReasoningEffort>();
+    types.register::<app_lib::services::ai::GenerationParams>();
+    types.register::<app_lib::services::ai::ResponseFormat>();
     types.register::<app_lib::services::config::AiModel>();
+    types.register::<app_lib::services::config::AiFallbackTarget>();
     types.register::<app_lib::services::config::AiConfig>();
+    types.register::<app_lib::services::config::VrmEmotionMotion>();
+    types.register::<app_lib::services::config::Persona>();
+    types.register::<app_lib::services::config::PersonaSettings>();
+    types.register::<app_lib::services::config::PromptTemplate>();
+    types.register::<app_lib::services::config::McpServerConfig>();
+    types.register::<app_lib::services::config::McpTransport>();
+    types.register::<app_lib::services::mcp::McpServerState>();
+    types.register::<app_lib::services::mcp::McpServerStatus>();
+    types.register::<app_lib::services::config::ToolApprovalRule>();
+    types.register::<app_lib::services::ai::LocalModelStatus>();
+
+    // Chat stream protocol types
+    types.register::<app_lib::services::ai::ChatToolCallPayload>();
+    types.register::<app_lib::services::ai::ChatProfilePayload>();
+    types.register::<app_lib::services::ai::ContextTrimReport>();
+    types.register::<app_lib::services::ai::ToolApprovalRequest>();
+    types.register::<app_lib::services::ai::ToolApprovalDecision>();
+    types.register::<app_lib::services::ai::ChatAttachment>();
 
     // Vision module types
     #[cfg(feature = "vision")]
@@ -31,9 +53,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // History module types
     types.register::<app_lib::services::history::ConversationSummary>();
+    types.register::<app_lib::services::history::ArchivedConversation>();
     types.register::<app_lib::services::history::ConversationMessage>();
+    types.register::<app_lib::services::history::MessageStatus>();
+    types.register::<app_lib::services::history::MessageVariant>();
+    types.register::<app_lib::services::history::SearchHit>();
+    types.register::<app_lib::services::history::SearchResults>();
+    types.register::<app_lib::services::history::ExportFormat>();
+    types.register::<app_lib::services::history::ImportFormat>();
+    types.register::<app_lib::services::history::ImportProgress>();
     types.register::<app_lib::services::history::ConversationDetail>();
     types.register::<app_lib::services::history::HistoryBootstrap>();
+    types.register::<app_lib::services::history::UsageTotals>();
+    types.register::<app_lib::services::history::AuditEntry>();
+    types.register::<app_lib::services::history::AuditKind>();
     types.register::<app_lib::services::history::HistoryError>();
 
     let mut exporter = Typescript::new()
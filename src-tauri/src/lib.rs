@@ -289,6 +289,8 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(log_builder.build())
         .manage(services::ai::AiStreamManager::default())
+        .manage(services::ai::ToolRegistry::default())
+        .manage(plugins::mcp::McpManager::default())
         .manage(services::voice::VoiceState::new())
         .manage(services::voice_conversation::VoiceConversationController::new())
         .manage(WindowStateStore::new())
@@ -298,12 +300,18 @@ pub fn run() {
             resize_window,
             set_window_min_size,
             services::ai::commands::chat_stream,
+            services::ai::commands::chat_regenerate,
+            services::ai::commands::chat_continue,
             services::ai::commands::chat_abort,
             services::ai::commands::chat_abort_conversation,
             services::config::get_ai_config,
             services::config::set_ai_provider,
             services::config::set_ai_profile,
             services::config::test_ai_profile,
+            services::config::discover_ai_models,
+            services::config::set_ai_fallbacks,
+            services::config::get_personas,
+            services::config::set_personas,
             services::config::get_vrm_fps_mode,
             services::config::set_vrm_fps_mode,
             services::config::get_vrm_view_state,
@@ -325,6 +333,20 @@ pub fn run() {
             services::voice_conversation::voice_conversation_start,
             services::voice_conversation::voice_conversation_stop,
             services::voice_conversation::voice_conversation_status,
+            // Prompt template commands
+            services::prompt_templates::list_prompt_templates,
+            services::prompt_templates::set_prompt_templates,
+            services::prompt_templates::render_prompt_template,
+            services::prompt_templates::run_prompt_template,
+            // Tool approval commands
+            services::ai::tool_approval::tool_approval_respond,
+            services::ai::tool_approval::get_tool_approval_rules,
+            services::ai::tool_approval::set_tool_approval_rules,
+            // MCP commands
+            services::mcp::get_mcp_servers,
+            services::mcp::set_mcp_servers,
+            services::mcp::mcp_server_status,
+            services::mcp::mcp_restart_server,
             // History commands
             services::history::history_bootstrap,
             services::history::history_list_conversations,
@@ -335,8 +357,26 @@ pub fn run() {
             services::history::history_mark_seen,
             services::history::history_clear_conversation,
             services::history::history_delete_conversation,
+            services::history::history_list_archived,
+            services::history::history_restore_conversation,
+            services::history::history_purge_conversations,
+            services::history::history_get_trash_retention_days,
+            services::history::history_set_trash_retention_days,
             services::history::history_fork_conversation,
             services::history::history_rename_conversation,
+            services::history::history_list_message_variants,
+            services::history::history_select_message_variant,
+            services::history::history_set_message_pinned,
+            services::history::history_search,
+            services::history::history_export_conversation,
+            services::history::history_export_all,
+            services::history::history_import,
+            services::history::history_set_conversation_persona,
+            services::history::history_conversation_usage,
+            services::history::history_usage_by_day,
+            services::history::history_usage_by_model,
+            services::history::history_audit_log,
+            services::history::history_purge_audit_log,
             // Vision commands
             #[cfg(feature = "vision")]
             services::vision::capture_screen_text,
@@ -403,8 +443,18 @@ pub fn run() {
 
             // History store must be available before the frontend boots.
             let history_store = plugins::history::HistoryStore::init(&app_handle)?;
+            // Empty the trash of conversations deleted longer ago than the retention period.
+            history_store.spawn_trash_purge_task();
             app.manage(history_store);
 
+            // Model-callable tools: each subsystem registers its own schema + executor.
+            #[cfg(feature = "vision")]
+            plugins::vision::register_ai_tools(
+                &app_handle,
+                &app.state::<services::ai::ToolRegistry>(),
+            );
+            services::mcp::start(&app_handle, &app.state::<plugins::mcp::McpManager>());
+
             let window_state = app.state::<WindowStateStore>();
             window_state.load_from_disk(&app_handle);
             window_state.spawn_persist_task(app_handle.clone());
@@ -0,0 +1,510 @@
+//! Conversation export: Markdown, structured JSON and standalone HTML.
+//!
+//! Rendering is pure; `HistoryStore::export_*` loads the conversation and writes the file. Times
+//! are shown in the caller's UTC offset. Markdown and HTML put reasoning and tool calls in
+//! collapsible `<details>` blocks; only HTML embeds image attachments.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::services::ai::ChatAttachment;
+use crate::services::prompt_templates::format_date;
+
+use super::HistoryError;
+use super::types::{
+    ConversationDetail, ConversationMessage, ConversationSummary, ExportFormat, MessageStatus,
+};
+
+/// Bumped when the JSON layout changes.
+const JSON_EXPORT_VERSION: u32 = 1;
+/// Title characters kept in generated file names.
+const MAX_FILE_TITLE_CHARS: usize = 48;
+
+impl ExportFormat {
+    pub(super) fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonExport<'a> {
+    version: u32,
+    exported_at_ms: u64,
+    conversation: &'a ConversationSummary,
+    messages: &'a [ConversationMessage],
+}
+
+/// Render `detail` in `format`.
+pub(super) fn render(
+    detail: &ConversationDetail,
+    format: ExportFormat,
+    utc_offset_minutes: i32,
+    exported_at_ms: u64,
+) -> Result<String, HistoryError> {
+    let clock = Clock { utc_offset_minutes };
+    match format {
+        ExportFormat::Markdown => Ok(render_markdown(detail, clock, exported_at_ms)),
+        ExportFormat::Json => serde_json::to_string_pretty(&JsonExport {
+            version: JSON_EXPORT_VERSION,
+            exported_at_ms,
+            conversation: &detail.conversation,
+            messages: &detail.messages,
+        })
+        .map_err(|e| HistoryError::internal(format!("Failed to encode export: {e}"))),
+        ExportFormat::Html => Ok(render_html(detail, clock, exported_at_ms)),
+    }
+}
+
+/// `YYYY-MM-DD <title> (<id suffix>).<ext>`, safe on every platform.
+pub(super) fn file_name(
+    summary: &ConversationSummary,
+    format: ExportFormat,
+    utc_offset_minutes: i32,
+) -> String {
+    let title: String = summary
+        .title
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_FILE_TITLE_CHARS)
+        .collect();
+    let title = title.trim().trim_end_matches('.');
+    let title = if title.is_empty() {
+        "conversation"
+    } else {
+        title
+    };
+    let id_suffix: String = summary
+        .id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .rev()
+        .take(8)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!(
+        "{} {} ({}).{}",
+        format_date(summary.created_at_ms, utc_offset_minutes),
+        title,
+        id_suffix,
+        format.extension()
+    )
+}
+
+/// Write `contents` to `path`, creating missing parent directories.
+pub(super) fn write_file(path: &Path, contents: &str) -> Result<(), HistoryError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            HistoryError::internal(format!("Failed to create {}: {e}", parent.display()))
+        })?;
+    }
+    std::fs::write(path, contents)
+        .map_err(|e| HistoryError::internal(format!("Failed to write {}: {e}", path.display())))
+}
+
+#[derive(Clone, Copy)]
+struct Clock {
+    utc_offset_minutes: i32,
+}
+
+impl Clock {
+    /// `YYYY-MM-DD HH:MM` in the export's offset.
+    fn format(self, ms: u64) -> String {
+        let local_ms = ms as i64 + self.utc_offset_minutes as i64 * 60_000;
+        let minutes = local_ms.rem_euclid(86_400_000) / 60_000;
+        format!(
+            "{} {:02}:{:02}",
+            format_date(ms, self.utc_offset_minutes),
+            minutes / 60,
+            minutes % 60
+        )
+    }
+
+    /// `UTC+08:00`
+    fn zone(self) -> String {
+        let sign = if self.utc_offset_minutes < 0 {
+            '-'
+        } else {
+            '+'
+        };
+        let offset = self.utc_offset_minutes.unsigned_abs();
+        format!("UTC{sign}{:02}:{:02}", offset / 60, offset % 60)
+    }
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "system" => "System",
+        other => other,
+    }
+}
+
+/// `Assistant · 2026-01-02 10:30 · model` (plus how an interrupted answer ended).
+fn message_heading(message: &ConversationMessage, clock: Clock) -> Vec<String> {
+    let mut parts = vec![
+        role_label(&message.role).to_string(),
+        clock.format(message.created_at_ms),
+    ];
+    if let Some(model) = message.model.as_deref().filter(|m| !m.trim().is_empty()) {
+        parts.push(model.to_string());
+    }
+    match message.status {
+        MessageStatus::Complete => {}
+        MessageStatus::Aborted => parts.push("stopped".to_string()),
+        MessageStatus::Errored => parts.push("failed".to_string()),
+    }
+    parts
+}
+
+fn image_urls(message: &ConversationMessage) -> impl Iterator<Item = &str> {
+    message
+        .attachments
+        .iter()
+        .filter_map(|attachment| match attachment {
+            // Only inline images: an export must not reference other files or scripts.
+            ChatAttachment::Image { url } if url.starts_with("data:image/") => Some(url.as_str()),
+            _ => None,
+        })
+}
+
+fn render_markdown(detail: &ConversationDetail, clock: Clock, exported_at_ms: u64) -> String {
+    let summary = &detail.conversation;
+    let mut out = format!("# {}\n\n", summary.title.trim());
+    out.push_str(&format!("- Conversation: `{}`\n", summary.id));
+    out.push_str(&format!(
+        "- Created: {} ({})\n",
+        clock.format(summary.created_at_ms),
+        clock.zone()
+    ));
+    out.push_str(&format!(
+        "- Updated: {}\n",
+        clock.format(summary.updated_at_ms)
+    ));
+    out.push_str(&format!("- Messages: {}\n", detail.messages.len()));
+    out.push_str(&format!("- Exported: {}\n", clock.format(exported_at_ms)));
+
+    for message in &detail.messages {
+        out.push_str("\n---\n\n");
+        let heading = message_heading(message, clock);
+        out.push_str(&format!("### {}\n\n", heading.join(" · ")));
+
+        if let Some(reasoning) = message
+            .reasoning
+            .as_deref()
+            .filter(|r| !r.trim().is_empty())
+        {
+            out.push_str("<details>\n<summary>Reasoning</summary>\n\n");
+            out.push_str(reasoning.trim());
+            out.push_str("\n\n</details>\n\n");
+        }
+        if !message.tool_calls.is_empty() {
+            out.push_str(&format!(
+                "<details>\n<summary>Tool calls ({})</summary>\n\n",
+                message.tool_calls.len()
+            ));
+            for call in &message.tool_calls {
+                let status = if call.is_error { " (error)" } else { "" };
+                out.push_str(&format!("**`{}`**{status}\n\n", call.name));
+                out.push_str(&format!("~~~json\n{}\n~~~\n\n", call.arguments.trim()));
+            }
+            out.push_str("</details>\n\n");
+        }
+
+        let content = message.content.trim();
+        if !content.is_empty() {
+            out.push_str(content);
+            out.push_str("\n\n");
+        }
+        let images = image_urls(message).count();
+        if images > 0 {
+            out.push_str(&format!("_({images} image(s) attached)_\n\n"));
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+const HTML_STYLE: &str = "body{margin:0;background:#f6f6f4;color:#1f2328;font:15px/1.6 -apple-system,BlinkMacSystemFont,\"Segoe UI\",\"PingFang SC\",\"Microsoft YaHei\",sans-serif}\
+main{max-width:820px;margin:0 auto;padding:32px 20px}\
+h1{font-size:22px;margin:0 0 4px}\
+.meta{color:#656d76;font-size:13px;margin-bottom:24px}\
+.message{background:#fff;border:1px solid #d8dee4;border-radius:10px;padding:12px 16px;margin:12px 0}\
+.message.user{background:#eef4ff;border-color:#c8d8f5}\
+.heading{color:#656d76;font-size:12px;margin-bottom:6px}\
+.role{color:#1f2328;font-weight:600}\
+.content{white-space:pre-wrap;word-wrap:break-word}\
+details{margin:6px 0;color:#57606a;font-size:13px}\
+summary{cursor:pointer}\
+details .body{white-space:pre-wrap;border-left:3px solid #d8dee4;padding:4px 10px;margin-top:4px}\
+pre{white-space:pre-wrap;background:#f6f8fa;border-radius:6px;padding:6px 8px;font-size:12px}\
+img{max-width:100%;border-radius:6px;margin-top:8px;display:block}\
+@media (prefers-color-scheme:dark){body{background:#0d1117;color:#e6edf3}.message{background:#161b22;border-color:#30363d}.message.user{background:#132038;border-color:#1f3a66}.role{color:#e6edf3}pre{background:#0d1117}details .body{border-color:#30363d}}";
+
+fn render_html(detail: &ConversationDetail, clock: Clock, exported_at_ms: u64) -> String {
+    let summary = &detail.conversation;
+    let title = escape_html(summary.title.trim());
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n<main>\n<h1>{title}</h1>\n"
+    );
+    out.push_str(&format!(
+        "<div class=\"meta\">Created {} ({}) · Updated {} · {} messages · Exported {}</div>\n",
+        clock.format(summary.created_at_ms),
+        clock.zone(),
+        clock.format(summary.updated_at_ms),
+        detail.messages.len(),
+        clock.format(exported_at_ms)
+    ));
+
+    for message in &detail.messages {
+        let heading = message_heading(message, clock);
+        out.push_str(&format!(
+            "<section class=\"message {}\">\n<div class=\"heading\"><span class=\"role\">{}</span>",
+            escape_html(&message.role),
+            escape_html(&heading[0])
+        ));
+        for part in &heading[1..] {
+            out.push_str(&format!(" · {}", escape_html(part)));
+        }
+        out.push_str("</div>\n");
+
+        if let Some(reasoning) = message
+            .reasoning
+            .as_deref()
+            .filter(|r| !r.trim().is_empty())
+        {
+            out.push_str(&format!(
+                "<details><summary>Reasoning</summary><div class=\"body\">{}</div></details>\n",
+                escape_html(reasoning.trim())
+            ));
+        }
+        if !message.tool_calls.is_empty() {
+            out.push_str(&format!(
+                "<details><summary>Tool calls ({})</summary>",
+                message.tool_calls.len()
+            ));
+            for call in &message.tool_calls {
+                let status = if call.is_error { " (error)" } else { "" };
+                out.push_str(&format!(
+                    "<pre>{}{status}\n{}\n→ {}</pre>",
+                    escape_html(&call.name),
+                    escape_html(call.arguments.trim()),
+                    escape_html(call.result.trim())
+                ));
+            }
+            out.push_str("</details>\n");
+        }
+
+        out.push_str(&format!(
+            "<div class=\"content\">{}</div>\n",
+            escape_html(message.content.trim())
+        ));
+        for url in image_urls(message) {
+            out.push_str(&format!("<img src=\"{}\" alt=\"\">\n", escape_html(url)));
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ai::ToolCallRecord;
+
+    const HOUR_MS: u64 = 3_600_000;
+
+    fn summary(title: &str) -> ConversationSummary {
+        ConversationSummary {
+            id: "conv-1234abcd".to_string(),
+            title: title.to_string(),
+            title_auto: false,
+            created_at_ms: 0,
+            updated_at_ms: HOUR_MS,
+            last_seen_at_ms: HOUR_MS,
+            message_count: 2,
+            last_message_at_ms: HOUR_MS,
+            last_role: "assistant".to_string(),
+            persona_id: None,
+            has_unseen: false,
+            is_active: true,
+        }
+    }
+
+    fn message(seq: u32, role: &str, content: &str) -> ConversationMessage {
+        ConversationMessage {
+            id: format!("msg-{seq}"),
+            conversation_id: "conv-1234abcd".to_string(),
+            seq,
+            role: role.to_string(),
+            content: content.to_string(),
+            reasoning: None,
+            tool_calls: Vec::new(),
+            attachments: Vec::new(),
+            model: None,
+            usage: None,
+            status: MessageStatus::Complete,
+            created_at_ms: seq as u64 * 60_000,
+            active_variant: 0,
+            variant_count: 0,
+            pinned: false,
+        }
+    }
+
+    fn tool_call(name: &str, result: &str, is_error: bool) -> ToolCallRecord {
+        ToolCallRecord {
+            round: 0,
+            id: format!("call_{name}"),
+            name: name.to_string(),
+            arguments: "{\"app\":\"Notes\"}".to_string(),
+            result: result.to_string(),
+            is_error,
+        }
+    }
+
+    /// A user question and a regenerated, stopped answer that used two tools.
+    fn detail(title: &str) -> ConversationDetail {
+        let mut question = message(1, "user", "What's <open> & why?");
+        question.attachments = vec![
+            ChatAttachment::Image {
+                url: "data:image/png;base64,AAAA".to_string(),
+            },
+            ChatAttachment::Image {
+                url: "https://example.com/tracker.png".to_string(),
+            },
+            ChatAttachment::ImageFile {
+                path: "/tmp/shot.png".to_string(),
+            },
+        ];
+        let mut answer = message(2, "assistant", "Second answer: Notes is \"open\".");
+        answer.model = Some("gpt-4o".to_string());
+        answer.reasoning = Some("  Check the windows.  ".to_string());
+        answer.tool_calls = vec![
+            tool_call("list_windows", "Notes <main>", false),
+            tool_call("read_window", "denied", true),
+        ];
+        answer.status = MessageStatus::Aborted;
+        answer.active_variant = 1;
+        answer.variant_count = 2;
+        ConversationDetail {
+            conversation: summary(title),
+            messages: vec![question, answer],
+        }
+    }
+
+    fn render_as(detail: &ConversationDetail, format: ExportFormat) -> String {
+        render(detail, format, 480, 2 * HOUR_MS).unwrap()
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("plain 文本"), "plain 文本");
+    }
+
+    #[test]
+    fn test_html_export_escapes_text_and_inlines_only_data_images() {
+        let html = render_as(&detail("<script>alert(1)</script>"), ExportFormat::Html);
+
+        assert!(html.contains("<title>&lt;script&gt;alert(1)&lt;/script&gt;</title>"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("What&#39;s &lt;open&gt; &amp; why?"));
+        assert!(html.contains("Second answer: Notes is &quot;open&quot;."));
+        assert!(html.contains("<img src=\"data:image/png;base64,AAAA\" alt=\"\">"));
+        assert!(!html.contains("example.com"));
+        assert!(!html.contains("/tmp/shot.png"));
+        assert!(html.contains("Created 1970-01-01 08:00 (UTC+08:00)"));
+    }
+
+    #[test]
+    fn test_tool_calls_and_variant_rendering() {
+        let detail = detail("Windows");
+
+        let markdown = render_as(&detail, ExportFormat::Markdown);
+        assert!(markdown.starts_with("# Windows\n\n- Conversation: `conv-1234abcd`\n"));
+        assert!(markdown.contains("### Assistant · 1970-01-01 08:02 · gpt-4o · stopped\n"));
+        assert!(markdown.contains(
+            "<details>\n<summary>Reasoning</summary>\n\nCheck the windows.\n\n</details>"
+        ));
+        assert!(markdown.contains("<summary>Tool calls (2)</summary>"));
+        assert!(markdown.contains("**`list_windows`**\n\n~~~json\n{\"app\":\"Notes\"}\n~~~"));
+        assert!(markdown.contains("**`read_window`** (error)"));
+        assert!(markdown.contains("Second answer: Notes is \"open\"."));
+        assert!(markdown.contains("_(1 image(s) attached)_"));
+
+        let html = render_as(&detail, ExportFormat::Html);
+        assert!(html.contains(
+            "<div class=\"heading\"><span class=\"role\">Assistant</span> · 1970-01-01 08:02 · gpt-4o · stopped</div>"
+        ));
+        assert!(html.contains("<summary>Tool calls (2)</summary>"));
+        assert!(html.contains(
+            "<pre>list_windows\n{&quot;app&quot;:&quot;Notes&quot;}\n→ Notes &lt;main&gt;</pre>"
+        ));
+        assert!(html.contains("<pre>read_window (error)\n"));
+        // Only the variant on display is exported.
+        assert_eq!(html.matches("Second answer").count(), 1);
+    }
+
+    #[test]
+    fn test_json_export_shape() {
+        let json: serde_json::Value =
+            serde_json::from_str(&render_as(&detail("Windows"), ExportFormat::Json)).unwrap();
+
+        assert_eq!(json["version"], JSON_EXPORT_VERSION);
+        assert_eq!(json["exportedAtMs"], 2 * HOUR_MS);
+        assert_eq!(json["conversation"]["id"], "conv-1234abcd");
+        assert_eq!(json["conversation"]["title"], "Windows");
+
+        let messages = json["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(messages[0]["attachments"][0]["type"], "image");
+        let answer = &messages[1];
+        assert_eq!(answer["status"], "aborted");
+        assert_eq!(answer["activeVariant"], 1);
+        assert_eq!(answer["variantCount"], 2);
+        assert_eq!(answer["toolCalls"][1]["name"], "read_window");
+        assert_eq!(answer["toolCalls"][1]["isError"], true);
+    }
+
+    #[test]
+    fn test_file_name_is_safe() {
+        let name = file_name(&summary("a/b: <c>?. "), ExportFormat::Markdown, 0);
+        assert_eq!(name, "1970-01-01 a_b_ _c__ (1234abcd).md");
+        let name = file_name(&summary("  "), ExportFormat::Html, 0);
+        assert_eq!(name, "1970-01-01 conversation (1234abcd).html");
+    }
+}
@@ -0,0 +1,710 @@
+//! Conversation import from other chat apps.
+//!
+//! Parsing is pure; `HistoryStore::import_file` writes the result. Supported sources:
+//! - ChatGPT `conversations.json` (from the data export zip): a message tree per conversation;
+//!   the branch ending at `current_node` is imported.
+//! - DeepSeek `conversations.json`: the same tree layout with `fragments`; the latest answer at
+//!   every fork is imported.
+//! - JSONL: one `{ role, content }` object per line, grouped by an optional `conversation_id`.
+//!
+//! Only user and assistant text is kept (system prompts, tool traffic and images are dropped).
+//! Every conversation carries a source id so importing the same file again skips it.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::services::ai::parse_rfc3339_ms;
+
+use super::HistoryError;
+use super::types::ImportFormat;
+
+impl ImportFormat {
+    fn source_prefix(self) -> &'static str {
+        match self {
+            ImportFormat::ChatGpt => "chatgpt",
+            ImportFormat::DeepSeek => "deepseek",
+            ImportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+pub(super) struct ImportedMessage {
+    pub(super) role: &'static str,
+    pub(super) content: String,
+    pub(super) reasoning: Option<String>,
+    pub(super) model: Option<String>,
+    pub(super) created_at_ms: u64,
+}
+
+pub(super) struct ImportedConversation {
+    /// `<format>:<id in the source>`; unique among imported conversations.
+    pub(super) source_id: String,
+    /// May be empty (the store falls back to the first user message).
+    pub(super) title: String,
+    pub(super) created_at_ms: u64,
+    pub(super) updated_at_ms: u64,
+    pub(super) messages: Vec<ImportedMessage>,
+}
+
+pub(super) struct ParsedImport {
+    pub(super) conversations: Vec<ImportedConversation>,
+    /// Entries that were malformed or had no text to import.
+    pub(super) skipped: u32,
+}
+
+/// Parse an export file; `now_ms` stands in for missing timestamps.
+pub(super) fn parse(
+    format: ImportFormat,
+    text: &str,
+    now_ms: u64,
+) -> Result<ParsedImport, HistoryError> {
+    let mut parsed = ParsedImport {
+        conversations: Vec::new(),
+        skipped: 0,
+    };
+    let drafts: Vec<Option<Draft>> = match format {
+        ImportFormat::ChatGpt => tree_entries(text)?
+            .into_iter()
+            .map(|entry| serde_json::from_value(entry).ok().map(chatgpt_draft))
+            .collect(),
+        ImportFormat::DeepSeek => tree_entries(text)?
+            .into_iter()
+            .map(|entry| serde_json::from_value(entry).ok().map(deepseek_draft))
+            .collect(),
+        ImportFormat::Jsonl => {
+            let (drafts, bad_lines) = jsonl_drafts(text)?;
+            parsed.skipped += bad_lines;
+            drafts.into_iter().map(Some).collect()
+        }
+    };
+    for draft in drafts {
+        match draft.and_then(|draft| draft.finish(format, now_ms)) {
+            Some(conversation) => parsed.conversations.push(conversation),
+            None => parsed.skipped += 1,
+        }
+    }
+    Ok(parsed)
+}
+
+/// A conversation before timestamps are filled in.
+#[derive(Default)]
+struct Draft {
+    id: String,
+    title: String,
+    created_at_ms: Option<u64>,
+    updated_at_ms: Option<u64>,
+    messages: Vec<DraftMessage>,
+}
+
+struct DraftMessage {
+    role: &'static str,
+    content: String,
+    reasoning: Option<String>,
+    model: Option<String>,
+    created_at_ms: Option<u64>,
+}
+
+impl Draft {
+    /// Append a message, merging it into the previous one when the role repeats (e.g. an
+    /// assistant answer split around a tool call).
+    fn push(&mut self, message: DraftMessage) {
+        if let Some(last) = self.messages.last_mut()
+            && last.role == message.role
+        {
+            append_text(&mut last.content, &message.content);
+            if let Some(reasoning) = message.reasoning {
+                append_text(last.reasoning.get_or_insert_with(String::new), &reasoning);
+            }
+            if last.model.is_none() {
+                last.model = message.model;
+            }
+            return;
+        }
+        self.messages.push(message);
+    }
+
+    fn finish(self, format: ImportFormat, now_ms: u64) -> Option<ImportedConversation> {
+        let id = self.id.trim();
+        if id.is_empty() {
+            return None;
+        }
+        let messages: Vec<DraftMessage> = self
+            .messages
+            .into_iter()
+            .filter(|m| !m.content.trim().is_empty())
+            .collect();
+        let created_at_ms = self
+            .created_at_ms
+            .or_else(|| messages.iter().find_map(|m| m.created_at_ms))
+            .unwrap_or(now_ms);
+
+        // Untimed messages inherit the previous message's time.
+        let mut at_ms = created_at_ms;
+        let messages: Vec<ImportedMessage> = messages
+            .into_iter()
+            .map(|m| {
+                at_ms = m.created_at_ms.unwrap_or(at_ms);
+                ImportedMessage {
+                    role: m.role,
+                    content: m.content.trim().to_string(),
+                    reasoning: m
+                        .reasoning
+                        .map(|r| r.trim().to_string())
+                        .filter(|r| !r.is_empty()),
+                    model: m.model.filter(|model| !model.trim().is_empty()),
+                    created_at_ms: at_ms,
+                }
+            })
+            .collect();
+        if messages.is_empty() {
+            return None;
+        }
+
+        let last_at_ms = messages.iter().map(|m| m.created_at_ms).max().unwrap_or(0);
+        Some(ImportedConversation {
+            source_id: format!("{}:{}", format.source_prefix(), id),
+            title: self.title.trim().to_string(),
+            created_at_ms,
+            updated_at_ms: self
+                .updated_at_ms
+                .unwrap_or(0)
+                .max(last_at_ms)
+                .max(created_at_ms),
+            messages,
+        })
+    }
+}
+
+fn append_text(target: &mut String, text: &str) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    if !target.trim().is_empty() {
+        target.push_str("\n\n");
+    }
+    target.push_str(text);
+}
+
+fn import_role(role: &str) -> Option<&'static str> {
+    match role.trim().to_ascii_lowercase().as_str() {
+        "user" | "human" => Some("user"),
+        "assistant" | "ai" | "bot" | "model" => Some("assistant"),
+        _ => None,
+    }
+}
+
+/// Conversations of a tree export: a JSON array (or a single conversation object).
+fn tree_entries(text: &str) -> Result<Vec<Value>, HistoryError> {
+    let root: Value = serde_json::from_str(text)
+        .map_err(|e| HistoryError::invalid_input(format!("Not a JSON export: {e}")))?;
+    match root {
+        Value::Array(entries) => Ok(entries),
+        Value::Object(_) => Ok(vec![root]),
+        _ => Err(HistoryError::invalid_input(
+            "Expected a list of conversations",
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "M: Deserialize<'de>")]
+struct TreeNode<M> {
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+    #[serde(default)]
+    message: Option<M>,
+}
+
+/// Messages on one root-to-leaf path: the one ending at `current` when given, else the newest
+/// (last) child at every fork.
+fn tree_branch<'a, M>(
+    mapping: &'a HashMap<String, TreeNode<M>>,
+    current: Option<&str>,
+) -> Vec<&'a M> {
+    let mut path: Vec<&TreeNode<M>> = Vec::new();
+    if let Some(mut node) = current.and_then(|id| mapping.get(id)) {
+        // Bounded: a malformed parent cycle must not loop forever.
+        for _ in 0..mapping.len() {
+            path.push(node);
+            match node.parent.as_deref().and_then(|id| mapping.get(id)) {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+        path.reverse();
+    } else {
+        let root = mapping.values().find(|node| {
+            node.parent
+                .as_deref()
+                .is_none_or(|id| !mapping.contains_key(id))
+        });
+        let mut next = root;
+        while let Some(node) = next {
+            if path.len() == mapping.len() {
+                break;
+            }
+            path.push(node);
+            next = node.children.last().and_then(|id| mapping.get(id));
+        }
+    }
+    path.into_iter()
+        .filter_map(|node| node.message.as_ref())
+        .collect()
+}
+
+/// Seconds (ChatGPT floats), milliseconds, or an RFC 3339 string.
+fn timestamp_ms(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => {
+            let n = n.as_f64()?;
+            if !n.is_finite() || n <= 0.0 {
+                return None;
+            }
+            // Seconds until the year 5138; anything larger is milliseconds.
+            Some(if n < 1e11 { n * 1000.0 } else { n } as u64)
+        }
+        Value::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) => timestamp_ms(&Value::from(n)),
+            Err(_) => parse_rfc3339_ms(s.trim()),
+        },
+        _ => None,
+    }
+}
+
+// --- ChatGPT ---
+
+#[derive(Deserialize)]
+struct ChatGptConversation {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    conversation_id: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    create_time: Value,
+    #[serde(default)]
+    update_time: Value,
+    #[serde(default)]
+    mapping: HashMap<String, TreeNode<ChatGptMessage>>,
+    #[serde(default)]
+    current_node: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatGptMessage {
+    author: ChatGptAuthor,
+    #[serde(default)]
+    create_time: Value,
+    #[serde(default)]
+    content: Value,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Deserialize)]
+struct ChatGptAuthor {
+    role: String,
+}
+
+fn chatgpt_draft(conversation: ChatGptConversation) -> Draft {
+    let mut draft = Draft {
+        id: conversation
+            .conversation_id
+            .or(conversation.id)
+            .unwrap_or_default(),
+        title: conversation.title.unwrap_or_default(),
+        created_at_ms: timestamp_ms(&conversation.create_time),
+        updated_at_ms: timestamp_ms(&conversation.update_time),
+        messages: Vec::new(),
+    };
+
+    // Reasoning models store their thoughts as separate messages before the answer.
+    let mut pending_reasoning: Option<String> = None;
+    let branch = tree_branch(&conversation.mapping, conversation.current_node.as_deref());
+    for message in branch {
+        if message.metadata["is_visually_hidden_from_conversation"] == Value::Bool(true) {
+            continue;
+        }
+        let Some(role) = import_role(&message.author.role) else {
+            continue;
+        };
+        let content = &message.content;
+        match content["content_type"].as_str().unwrap_or("text") {
+            "text" | "multimodal_text" => {
+                // Non-string parts are images and other attachments.
+                let text = content["parts"]
+                    .as_array()
+                    .map(|parts| {
+                        parts
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .unwrap_or_default();
+                if text.trim().is_empty() {
+                    continue;
+                }
+                draft.push(DraftMessage {
+                    role,
+                    content: text,
+                    reasoning: if role == "assistant" {
+                        pending_reasoning.take()
+                    } else {
+                        None
+                    },
+                    model: message.metadata["model_slug"].as_str().map(str::to_string),
+                    created_at_ms: timestamp_ms(&message.create_time),
+                });
+            }
+            "thoughts" if role == "assistant" => {
+                let reasoning = pending_reasoning.get_or_insert_with(String::new);
+                for thought in content["thoughts"].as_array().into_iter().flatten() {
+                    append_text(reasoning, thought["content"].as_str().unwrap_or_default());
+                }
+            }
+            _ => {}
+        }
+    }
+    draft
+}
+
+// --- DeepSeek ---
+
+#[derive(Deserialize)]
+struct DeepSeekConversation {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    inserted_at: Value,
+    #[serde(default)]
+    updated_at: Value,
+    #[serde(default)]
+    mapping: HashMap<String, TreeNode<DeepSeekMessage>>,
+}
+
+#[derive(Deserialize)]
+struct DeepSeekMessage {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    inserted_at: Value,
+    #[serde(default)]
+    fragments: Vec<DeepSeekFragment>,
+}
+
+#[derive(Deserialize)]
+struct DeepSeekFragment {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    content: String,
+}
+
+fn deepseek_draft(conversation: DeepSeekConversation) -> Draft {
+    let mut draft = Draft {
+        id: conversation.id,
+        title: conversation.title.unwrap_or_default(),
+        created_at_ms: timestamp_ms(&conversation.inserted_at),
+        updated_at_ms: timestamp_ms(&conversation.updated_at),
+        messages: Vec::new(),
+    };
+
+    for message in tree_branch(&conversation.mapping, None) {
+        let created_at_ms = timestamp_ms(&message.inserted_at);
+        let (mut request, mut reasoning, mut response) =
+            (String::new(), String::new(), String::new());
+        for fragment in &message.fragments {
+            match fragment.kind.as_str() {
+                "REQUEST" => append_text(&mut request, &fragment.content),
+                "THINK" => append_text(&mut reasoning, &fragment.content),
+                "RESPONSE" => append_text(&mut response, &fragment.content),
+                _ => {}
+            }
+        }
+        if !request.is_empty() {
+            draft.push(DraftMessage {
+                role: "user",
+                content: request,
+                reasoning: None,
+                model: None,
+                created_at_ms,
+            });
+        }
+        if !response.is_empty() {
+            draft.push(DraftMessage {
+                role: "assistant",
+                content: response,
+                reasoning: Some(reasoning),
+                model: message.model.clone(),
+                created_at_ms,
+            });
+        }
+    }
+    draft
+}
+
+// --- JSONL ---
+
+#[derive(Deserialize)]
+struct JsonlLine {
+    #[serde(default, alias = "conversationId")]
+    conversation_id: Option<Value>,
+    #[serde(default)]
+    title: Option<String>,
+    role: String,
+    #[serde(default)]
+    content: Value,
+    #[serde(default, alias = "reasoning_content")]
+    reasoning: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default, alias = "createdAt", alias = "timestamp")]
+    created_at: Value,
+}
+
+/// Text of a JSONL `content`: a string, or OpenAI-style `[{ type: "text", text }]` parts.
+fn jsonl_content(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.as_str().or_else(|| part["text"].as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// FNV-1a: a stable id for JSONL conversations that name none.
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Conversations of a JSONL export and the number of malformed lines skipped.
+fn jsonl_drafts(text: &str) -> Result<(Vec<Draft>, u32), HistoryError> {
+    let mut drafts: Vec<Draft> = Vec::new();
+    let mut by_id: HashMap<String, usize> = HashMap::new();
+    // Lines without a conversation id form one conversation, identified by its contents.
+    let mut unnamed: Option<Draft> = None;
+    let mut unnamed_hash = Vec::new();
+    let (mut lines, mut bad_lines) = (0u32, 0u32);
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        lines += 1;
+        let entry: JsonlLine = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::debug!("History import: skipping JSONL line {}: {}", index + 1, err);
+                bad_lines += 1;
+                continue;
+            }
+        };
+        let conversation_id = match &entry.conversation_id {
+            Some(Value::String(id)) => Some(id.trim().to_string()),
+            Some(Value::Number(id)) => Some(id.to_string()),
+            _ => None,
+        }
+        .filter(|id| !id.is_empty());
+
+        let draft = match &conversation_id {
+            Some(id) => {
+                let index = *by_id.entry(id.clone()).or_insert_with(|| {
+                    drafts.push(Draft {
+                        id: id.clone(),
+                        ..Draft::default()
+                    });
+                    drafts.len() - 1
+                });
+                &mut drafts[index]
+            }
+            None => unnamed.get_or_insert_with(Draft::default),
+        };
+        if draft.title.is_empty() {
+            draft.title = entry.title.unwrap_or_default();
+        }
+        let Some(role) = import_role(&entry.role) else {
+            continue;
+        };
+        let content = jsonl_content(&entry.content);
+        if conversation_id.is_none() {
+            unnamed_hash.extend_from_slice(role.as_bytes());
+            unnamed_hash.push(0);
+            unnamed_hash.extend_from_slice(content.as_bytes());
+            unnamed_hash.push(0);
+        }
+        draft.push(DraftMessage {
+            role,
+            content,
+            reasoning: entry.reasoning,
+            model: entry.model,
+            created_at_ms: timestamp_ms(&entry.created_at),
+        });
+    }
+
+    if let Some(mut draft) = unnamed {
+        draft.id = format!("#{:016x}", fnv1a(unnamed_hash));
+        drafts.push(draft);
+    }
+    if lines > 0 && bad_lines == lines {
+        return Err(HistoryError::invalid_input("Not a JSONL export"));
+    }
+    Ok((drafts, bad_lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW_MS: u64 = 1_800_000_000_000;
+
+    fn roles_and_text(conversation: &ImportedConversation) -> Vec<(&str, &str)> {
+        conversation
+            .messages
+            .iter()
+            .map(|m| (m.role, m.content.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_chatgpt_export() {
+        let parsed = parse(
+            ImportFormat::ChatGpt,
+            include_str!("testdata/chatgpt.json"),
+            NOW_MS,
+        )
+        .unwrap();
+        // An empty conversation and one with a malformed mapping.
+        assert_eq!(parsed.skipped, 2);
+        assert_eq!(parsed.conversations.len(), 2);
+
+        // `current_node` picks the first answer even though a newer branch exists.
+        let on_screen = &parsed.conversations[0];
+        assert_eq!(on_screen.source_id, "chatgpt:c1");
+        assert_eq!(on_screen.title, "Regenerated answer");
+        assert_eq!(on_screen.created_at_ms, 1_700_000_000_500);
+        assert_eq!(on_screen.updated_at_ms, 1_700_000_300_000);
+        assert_eq!(
+            roles_and_text(on_screen),
+            [("user", "What is this?"), ("assistant", "A cat.")]
+        );
+        assert_eq!(on_screen.messages[1].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(on_screen.messages[1].created_at_ms, 1_700_000_020_000);
+        assert_eq!(on_screen.messages[1].reasoning, None);
+
+        // Without `current_node` the newest child wins; thoughts become reasoning.
+        let latest = &parsed.conversations[1];
+        assert_eq!(
+            roles_and_text(latest),
+            [("user", "Hi"), ("assistant", "New")]
+        );
+        assert_eq!(latest.messages[1].reasoning.as_deref(), Some("Be nice."));
+        assert_eq!(latest.messages[1].created_at_ms, 1_700_001_000_000);
+    }
+
+    #[test]
+    fn test_parse_deepseek_export() {
+        let parsed = parse(
+            ImportFormat::DeepSeek,
+            include_str!("testdata/deepseek.json"),
+            NOW_MS,
+        )
+        .unwrap();
+        assert_eq!(parsed.skipped, 1);
+        let [conversation] = parsed.conversations.as_slice() else {
+            panic!("expected one conversation");
+        };
+        assert_eq!(conversation.source_id, "deepseek:d1");
+        assert_eq!(conversation.created_at_ms, 1_738_375_200_250);
+        assert_eq!(
+            roles_and_text(conversation),
+            [("user", "1+1=?"), ("assistant", "2")]
+        );
+        let answer = &conversation.messages[1];
+        assert_eq!(answer.reasoning.as_deref(), Some("一加一。"));
+        assert_eq!(answer.model.as_deref(), Some("deepseek-reasoner"));
+        assert_eq!(answer.created_at_ms, 1_738_375_260_000);
+    }
+
+    #[test]
+    fn test_parse_jsonl_export() {
+        let text = include_str!("testdata/export.jsonl");
+        let parsed = parse(ImportFormat::Jsonl, text, NOW_MS).unwrap();
+        // The line that is not JSON.
+        assert_eq!(parsed.skipped, 1);
+        assert_eq!(parsed.conversations.len(), 3);
+
+        let named = &parsed.conversations[0];
+        assert_eq!(named.source_id, "jsonl:a");
+        assert_eq!(named.title, "First");
+        assert_eq!(
+            roles_and_text(named),
+            [("user", "hello"), ("assistant", "hi")]
+        );
+        assert_eq!(named.messages[1].reasoning.as_deref(), Some("greet"));
+        assert_eq!(named.messages[1].created_at_ms, 1_700_000_005_000);
+
+        let numbered = &parsed.conversations[1];
+        assert_eq!(numbered.source_id, "jsonl:7");
+        assert_eq!(roles_and_text(numbered), [("user", "numbered")]);
+        assert_eq!(numbered.created_at_ms, 1_700_000_000_000);
+
+        // Lines without an id form one conversation; repeated roles are merged.
+        let unnamed = &parsed.conversations[2];
+        assert!(unnamed.source_id.starts_with("jsonl:#"));
+        assert_eq!(
+            roles_and_text(unnamed),
+            [
+                ("user", "loose one\n\nloose two"),
+                ("assistant", "loose reply")
+            ]
+        );
+        assert_eq!(unnamed.created_at_ms, NOW_MS);
+
+        // The id of an unnamed conversation is stable across imports.
+        let again = parse(ImportFormat::Jsonl, text, NOW_MS + 1).unwrap();
+        assert_eq!(again.conversations[2].source_id, unnamed.source_id);
+
+        assert!(parse(ImportFormat::Jsonl, "nope\n{]\n", NOW_MS).is_err());
+        assert!(parse(ImportFormat::ChatGpt, "nope", NOW_MS).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_forms() {
+        let expected = Some(1_700_000_000_000);
+        assert_eq!(timestamp_ms(&Value::from(1_700_000_000)), expected);
+        assert_eq!(timestamp_ms(&Value::from(1_700_000_000_000u64)), expected);
+        assert_eq!(timestamp_ms(&Value::from("1700000000")), expected);
+        assert_eq!(timestamp_ms(&Value::from("2023-11-14T22:13:20Z")), expected);
+        assert_eq!(timestamp_ms(&Value::from("2023-11-14 22:13:20")), expected);
+        assert_eq!(
+            timestamp_ms(&Value::from("2023-11-15T06:13:20.000+08:00")),
+            expected
+        );
+        assert_eq!(
+            timestamp_ms(&Value::from("2023-11-14T17:13:20-0500")),
+            expected
+        );
+        assert_eq!(
+            timestamp_ms(&Value::from("2023-11-14T22:13:20.5Z")),
+            Some(1_700_000_000_500)
+        );
+        assert_eq!(timestamp_ms(&Value::from(-5)), None);
+        assert_eq!(timestamp_ms(&Value::from("yesterday")), None);
+        assert_eq!(timestamp_ms(&Value::Null), None);
+    }
+}
@@ -3,10 +3,18 @@
 //! The Tauri command surface lives in `crate::services::history`.
 
 mod error;
+mod export;
+mod importer;
+mod search;
 mod store;
 mod title;
 mod types;
 
 pub use error::HistoryError;
 pub use store::HistoryStore;
-pub use types::{ConversationDetail, ConversationMessage, ConversationSummary, HistoryBootstrap};
+pub(crate) use store::record_audit;
+pub use types::{
+    ArchivedConversation, AuditContext, AuditEntry, AuditKind, ConversationDetail,
+    ConversationMessage, ConversationSummary, ExportFormat, HistoryBootstrap, ImportFormat,
+    ImportProgress, MessageStatus, MessageVariant, SearchHit, SearchResults, UsageTotals,
+};
@@ -0,0 +1,176 @@
+//! Full-text search helpers.
+//!
+//! Titles and message text are indexed by FTS5 tables with the `trigram` tokenizer (kept in sync
+//! by triggers, see `HistoryStore::migrate_search`), which also matches inside CJK text. Trigrams
+//! cannot match terms shorter than three characters, so those queries (and databases without
+//! FTS5) fall back to `LIKE` scans with snippets cut here.
+
+/// Marks the start of a match in [`super::SearchHit::snippet`].
+pub(super) const MATCH_START: char = '\u{2}';
+/// Marks the end of a match in [`super::SearchHit::snippet`].
+pub(super) const MATCH_END: char = '\u{3}';
+
+/// Terms beyond this are ignored.
+const MAX_TERMS: usize = 8;
+/// Shortest term the trigram index can match.
+const MIN_FTS_TERM_CHARS: usize = 3;
+
+/// Whitespace-separated terms of a query; every term must match.
+pub(super) fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in query.split_whitespace() {
+        if terms.len() == MAX_TERMS {
+            break;
+        }
+        if !terms.iter().any(|t| t == term) {
+            terms.push(term.to_string());
+        }
+    }
+    terms
+}
+
+/// FTS5 `MATCH` expression (each term a quoted phrase), or `None` when a term is too short.
+pub(super) fn fts_query(terms: &[String]) -> Option<String> {
+    if terms.is_empty()
+        || terms
+            .iter()
+            .any(|term| term.chars().count() < MIN_FTS_TERM_CHARS)
+    {
+        return None;
+    }
+    let phrases: Vec<String> = terms
+        .iter()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    Some(phrases.join(" "))
+}
+
+/// `LIKE` pattern matching `term` anywhere (used with `ESCAPE '\'`).
+pub(super) fn like_pattern(term: &str) -> String {
+    let mut pattern = String::with_capacity(term.len() + 2);
+    pattern.push('%');
+    for c in term.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// About `max_chars` of `text` around the first match, with every match marked.
+///
+/// Case folding is ASCII-only, like SQLite's `LIKE`.
+pub(super) fn highlight_snippet(text: &str, terms: &[String], max_chars: usize) -> String {
+    let chars: Vec<char> = text
+        .trim()
+        .chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect();
+    let folded: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let needles: Vec<Vec<char>> = terms
+        .iter()
+        .map(|term| term.chars().map(|c| c.to_ascii_lowercase()).collect())
+        .filter(|needle: &Vec<char>| !needle.is_empty())
+        .collect();
+
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i < folded.len() {
+        let longest = needles
+            .iter()
+            .filter(|needle| folded[i..].starts_with(needle))
+            .map(|needle| needle.len())
+            .max();
+        match longest {
+            Some(len) => {
+                matches.push((i, i + len));
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+
+    // Keep some context before the first match.
+    let first = matches.first().map_or(0, |&(start, _)| start);
+    let start = first.saturating_sub(max_chars / 4);
+    let end = (start + max_chars).min(chars.len());
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut pos = start;
+    for &(match_start, match_end) in matches.iter().filter(|&&(s, e)| s >= start && e <= end) {
+        out.extend(&chars[pos..match_start]);
+        out.push(MATCH_START);
+        out.extend(&chars[match_start..match_end]);
+        out.push(MATCH_END);
+        pos = match_end;
+    }
+    out.extend(&chars[pos..end]);
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(query: &str) -> Vec<String> {
+        search_terms(query)
+    }
+
+    #[test]
+    fn test_search_terms() {
+        assert_eq!(terms("  天气  weather 天气\n"), ["天气", "weather"]);
+        assert_eq!(terms("a b c d e f g h i j").len(), MAX_TERMS);
+        assert!(terms(" \t").is_empty());
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(
+            fts_query(&terms("天气预报 say\"hi")).as_deref(),
+            Some("\"天气预报\" \"say\"\"hi\"")
+        );
+        // Shorter than a trigram: callers fall back to `LIKE`.
+        assert_eq!(fts_query(&terms("天气")), None);
+        assert_eq!(fts_query(&terms("天气预报 雨")), None);
+        assert_eq!(fts_query(&terms("ok")), None);
+        assert_eq!(fts_query(&[]), None);
+    }
+
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!(like_pattern("天气"), "%天气%");
+        assert_eq!(like_pattern("50%_off\\"), "%50\\%\\_off\\\\%");
+    }
+
+    #[test]
+    fn test_highlight_snippet() {
+        let (start, end) = (MATCH_START, MATCH_END);
+        assert_eq!(
+            highlight_snippet("Hello world,\nhello again", &terms("hello"), 64),
+            format!("{start}Hello{end} world, {start}hello{end} again")
+        );
+        // The longest term wins where terms overlap.
+        assert_eq!(
+            highlight_snippet("明天天气很好", &terms("天 天气"), 64),
+            format!("明{start}天{end}{start}天气{end}很好")
+        );
+
+        // Context before the first match is cut to a quarter of the snippet.
+        let text = format!("{}今天下雨{}", "前".repeat(20), "后".repeat(20));
+        assert_eq!(
+            highlight_snippet(&text, &terms("下雨"), 12),
+            format!("…前今天{start}下雨{end}{}…", "后".repeat(7))
+        );
+
+        // No match: the start of the text.
+        assert_eq!(highlight_snippet("abcdef", &terms("xyz"), 3), "abc…");
+    }
+}
@@ -6,20 +6,31 @@
 
 use std::collections::HashMap;
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{future::Future, time::Duration};
 
 use libsql::{params, Builder, Database, Statement, Value};
+use tauri::Manager;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use uuid::Uuid;
 
-use crate::services::ai::ChatMessage;
+use crate::services::ai::{
+    AiStreamManager, ChatAttachment, ChatMessage, ChatOutput, RateLimiter, TokenUsage,
+    ToolCallRecord,
+};
+use crate::services::config;
 
+use super::export;
+use super::importer::{self, ImportedConversation};
+use super::search;
 use super::title;
 use super::types::{
-    ConversationDetail, ConversationMessage, ConversationSummary, HistoryBootstrap,
+    ArchivedConversation, AuditEntry, AuditKind, ConversationDetail, ConversationMessage,
+    ConversationSummary, ExportFormat, HistoryBootstrap, ImportFormat, ImportProgress,
+    MessageStatus, MessageVariant, SearchHit, SearchResults, UsageTotals,
 };
 use super::HistoryError;
 
@@ -31,6 +42,17 @@ const MAX_LOCAL_CONNECTIONS: usize = 4;
 const DEFAULT_PAGE_LIMIT: u32 = 80;
 const MAX_PAGE_LIMIT: u32 = 500;
 const TITLE_AUTO_COOLDOWN_MS: u64 = 120_000;
+const DEFAULT_AUDIT_LIMIT: u32 = 200;
+const MAX_AUDIT_LIMIT: u32 = 2000;
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+const MAX_SEARCH_LIMIT: u32 = 100;
+/// Snippet length (characters) of `LIKE` search hits.
+const SEARCH_SNIPPET_CHARS: usize = 64;
+/// Messages per multi-row `INSERT` when importing.
+const IMPORT_CHUNK_SIZE: usize = 100;
+const DAY_MS: u64 = 86_400_000;
+/// How often a running app re-checks the trash for conversations past their retention period.
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum DbMode {
@@ -53,6 +75,10 @@ struct HistoryStoreInner {
     conn_gate: Arc<Semaphore>,
     conn_pool: Mutex<Vec<libsql::Connection>>,
     title_cooldowns: Mutex<HashMap<String, u64>>,
+    /// The FTS5 search index exists (set by `migrate`).
+    search_fts: AtomicBool,
+    /// Shared with chat streams so title generation respects provider throttling.
+    rate_limiter: RateLimiter,
 }
 
 /// A pooled libSQL connection (returned to the pool on drop).
@@ -104,6 +130,152 @@ fn truncate_title(source: &str) -> String {
     first_line.chars().take(max_chars).collect::<String>() + "…"
 }
 
+fn encode_tool_calls(tool_calls: &[ToolCallRecord]) -> Option<String> {
+    if tool_calls.is_empty() {
+        return None;
+    }
+    serde_json::to_string(tool_calls).ok()
+}
+
+fn decode_tool_calls(raw: Option<String>) -> Vec<ToolCallRecord> {
+    raw.and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn encode_attachments(attachments: &[ChatAttachment]) -> Option<String> {
+    if attachments.is_empty() {
+        return None;
+    }
+    serde_json::to_string(attachments).ok()
+}
+
+fn decode_attachments(raw: Option<String>) -> Vec<ChatAttachment> {
+    raw.and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Decode a row selected as
+/// `id, seq, role, content, reasoning, created_at_ms, tool_calls, model, prompt_tokens,
+/// completion_tokens, reasoning_tokens, cached_tokens, attachments, active_variant, variant count,
+/// status, pinned`.
+fn message_from_row(
+    row: &libsql::Row,
+    conversation_id: &str,
+) -> Result<ConversationMessage, HistoryError> {
+    let id: String = row.get(0)?;
+    let seq: i64 = row.get(1)?;
+    let role: String = row.get(2)?;
+    let content: String = row.get(3)?;
+    let reasoning: Option<String> = row.get(4).ok();
+    let created_at_ms: i64 = row.get(5)?;
+    let tool_calls = decode_tool_calls(row.get(6).ok());
+    let model: Option<String> = row.get(7).ok();
+    // Usage columns are NULL when the provider did not report usage.
+    let usage = row.get::<i64>(8).ok().map(|prompt_tokens| TokenUsage {
+        prompt_tokens: prompt_tokens.max(0) as u32,
+        completion_tokens: row.get::<i64>(9).unwrap_or(0).max(0) as u32,
+        reasoning_tokens: row.get::<i64>(10).unwrap_or(0).max(0) as u32,
+        cached_tokens: row.get::<i64>(11).unwrap_or(0).max(0) as u32,
+    });
+    let attachments = decode_attachments(row.get(12).ok());
+    let active_variant: i64 = row.get(13).unwrap_or(0);
+    let variant_count: i64 = row.get(14).unwrap_or(0);
+    let status = MessageStatus::parse(&row.get::<String>(15).unwrap_or_default());
+    let pinned: i64 = row.get(16).unwrap_or(0);
+
+    Ok(ConversationMessage {
+        id,
+        conversation_id: conversation_id.to_string(),
+        seq: seq.max(0) as u32,
+        role,
+        content,
+        reasoning,
+        tool_calls,
+        attachments,
+        model,
+        usage,
+        status,
+        created_at_ms: created_at_ms.max(0) as u64,
+        active_variant: active_variant.max(0) as u32,
+        variant_count: variant_count.max(0) as u32,
+        pinned: pinned != 0,
+    })
+}
+
+/// Decode a row selected as
+/// `active_variant, variant, content, reasoning, tool_calls, model, prompt_tokens,
+/// completion_tokens, reasoning_tokens, cached_tokens, created_at_ms, status`.
+fn variant_from_row(row: &libsql::Row) -> Result<MessageVariant, HistoryError> {
+    let active_variant: i64 = row.get(0)?;
+    let variant: i64 = row.get(1)?;
+    let created_at_ms: i64 = row.get(10)?;
+    Ok(MessageVariant {
+        variant: variant.max(0) as u32,
+        content: row.get(2)?,
+        reasoning: row.get(3).ok(),
+        tool_calls: decode_tool_calls(row.get(4).ok()),
+        model: row.get(5).ok(),
+        usage: row.get::<i64>(6).ok().map(|prompt_tokens| TokenUsage {
+            prompt_tokens: prompt_tokens.max(0) as u32,
+            completion_tokens: row.get::<i64>(7).unwrap_or(0).max(0) as u32,
+            reasoning_tokens: row.get::<i64>(8).unwrap_or(0).max(0) as u32,
+            cached_tokens: row.get::<i64>(9).unwrap_or(0).max(0) as u32,
+        }),
+        status: MessageStatus::parse(&row.get::<String>(11).unwrap_or_default()),
+        created_at_ms: created_at_ms.max(0) as u64,
+        is_active: variant == active_variant,
+    })
+}
+
+/// Columns: conversation_id, title, seq, role, (snippet source), at_ms.
+fn search_hit_from_row(row: &libsql::Row, snippet: String) -> Result<SearchHit, HistoryError> {
+    let seq: Option<i64> = row.get(2)?;
+    let at_ms: i64 = row.get(5)?;
+    Ok(SearchHit {
+        conversation_id: row.get(0)?,
+        conversation_title: row.get(1)?,
+        seq: seq.map(|seq| seq.max(0) as u32),
+        role: row.get(3)?,
+        snippet,
+        created_at_ms: at_ms.max(0) as u64,
+    })
+}
+
+fn usage_totals_from_row(row: &libsql::Row) -> Result<UsageTotals, HistoryError> {
+    let key: String = row.get(0)?;
+    let message_count: i64 = row.get(1)?;
+    let prompt_tokens: i64 = row.get(2)?;
+    let completion_tokens: i64 = row.get(3)?;
+    let reasoning_tokens: i64 = row.get(4)?;
+    let cached_tokens: i64 = row.get(5)?;
+    Ok(UsageTotals {
+        key,
+        message_count: message_count.max(0) as u32,
+        prompt_tokens: prompt_tokens.max(0) as u64,
+        completion_tokens: completion_tokens.max(0) as u64,
+        reasoning_tokens: reasoning_tokens.max(0) as u64,
+        cached_tokens: cached_tokens.max(0) as u64,
+    })
+}
+
+/// Clamp an optional `[since, until)` range (ms) to SQLite integers.
+fn usage_range(since_ms: Option<u64>, until_ms: Option<u64>) -> (i64, i64) {
+    let since = since_ms.unwrap_or(0).min(i64::MAX as u64) as i64;
+    let until = until_ms.unwrap_or(u64::MAX).min(i64::MAX as u64) as i64;
+    (since, until)
+}
+
+/// Append to the audit log when the store is up; failures are logged, never surfaced.
+pub(crate) async fn record_audit(app: &tauri::AppHandle, entry: AuditEntry) {
+    let Some(store) = app.try_state::<HistoryStore>() else {
+        return;
+    };
+    let kind = entry.kind;
+    if let Err(err) = store.append_audit(entry).await {
+        log::warn!("Audit log write failed (kind={:?}): {}", kind, err);
+    }
+}
+
 fn new_id(prefix: &str) -> String {
     format!("{}_{}", prefix, Uuid::new_v4())
 }
@@ -173,25 +345,41 @@ impl HistoryStore {
     pub(crate) fn init(app: &tauri::AppHandle) -> Result<Self, String> {
         tauri::async_runtime::block_on(async {
             let (db, db_mode) = open_database(app).await?;
-            let (conn_limit, write_gate) = match db_mode {
-                DbMode::Remote => (MAX_REMOTE_CONNECTIONS, None),
-                DbMode::Local => (MAX_LOCAL_CONNECTIONS, Some(Arc::new(Semaphore::new(1)))),
-            };
-            let store = Self {
-                inner: Arc::new(HistoryStoreInner {
-                    db,
-                    db_mode,
-                    write_gate,
-                    conn_gate: Arc::new(Semaphore::new(conn_limit)),
-                    conn_pool: Mutex::new(Vec::new()),
-                    title_cooldowns: Mutex::new(HashMap::new()),
-                }),
-            };
-            store.migrate().await.map_err(|e| e.to_string())?;
-            Ok(store)
+            let rate_limiter = app
+                .try_state::<AiStreamManager>()
+                .map(|streams| streams.rate_limiter())
+                .unwrap_or_default();
+            Self::open(db, db_mode, rate_limiter)
+                .await
+                .map_err(|e| e.to_string())
         })
     }
 
+    async fn open(
+        db: Database,
+        db_mode: DbMode,
+        rate_limiter: RateLimiter,
+    ) -> Result<Self, HistoryError> {
+        let (conn_limit, write_gate) = match db_mode {
+            DbMode::Remote => (MAX_REMOTE_CONNECTIONS, None),
+            DbMode::Local => (MAX_LOCAL_CONNECTIONS, Some(Arc::new(Semaphore::new(1)))),
+        };
+        let store = Self {
+            inner: Arc::new(HistoryStoreInner {
+                db,
+                db_mode,
+                write_gate,
+                conn_gate: Arc::new(Semaphore::new(conn_limit)),
+                conn_pool: Mutex::new(Vec::new()),
+                title_cooldowns: Mutex::new(HashMap::new()),
+                search_fts: AtomicBool::new(false),
+                rate_limiter,
+            }),
+        };
+        store.migrate().await?;
+        Ok(store)
+    }
+
     async fn connect(&self) -> Result<PooledConnection, HistoryError> {
         let permit = self
             .inner
@@ -258,6 +446,20 @@ impl HistoryStore {
         Ok(false)
     }
 
+    async fn table_exists(
+        &self,
+        conn: &libsql::Connection,
+        table: &str,
+    ) -> Result<bool, HistoryError> {
+        let mut rows = conn
+            .query(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1 LIMIT 1;",
+                params![table],
+            )
+            .await?;
+        Ok(rows.next().await?.is_some())
+    }
+
     async fn migrate(&self) -> Result<(), HistoryError> {
         let conn = self.connect().await?;
 
@@ -274,13 +476,13 @@ impl HistoryStore {
         .await?;
 
         conn.execute(
-            "CREATE TABLE IF NOT EXISTS conversations (\n  id TEXT PRIMARY KEY NOT NULL,\n  title TEXT NOT NULL,\n  title_auto INTEGER NOT NULL DEFAULT 0,\n  created_at_ms INTEGER NOT NULL,\n  updated_at_ms INTEGER NOT NULL,\n  last_seen_at_ms INTEGER NOT NULL,\n  last_message_at_ms INTEGER NOT NULL DEFAULT 0,\n  last_role TEXT NOT NULL DEFAULT '',\n  archived INTEGER NOT NULL DEFAULT 0,\n  message_count INTEGER NOT NULL DEFAULT 0\n);",
+            "CREATE TABLE IF NOT EXISTS conversations (\n  id TEXT PRIMARY KEY NOT NULL,\n  title TEXT NOT NULL,\n  title_auto INTEGER NOT NULL DEFAULT 0,\n  created_at_ms INTEGER NOT NULL,\n  updated_at_ms INTEGER NOT NULL,\n  last_seen_at_ms INTEGER NOT NULL,\n  last_message_at_ms INTEGER NOT NULL DEFAULT 0,\n  last_role TEXT NOT NULL DEFAULT '',\n  archived INTEGER NOT NULL DEFAULT 0,\n  message_count INTEGER NOT NULL DEFAULT 0,\n  persona_id TEXT,\n  source_id TEXT,\n  archived_at_ms INTEGER\n);",
             (),
         )
         .await?;
 
         conn.execute(
-            "CREATE TABLE IF NOT EXISTS messages (\n  id TEXT PRIMARY KEY NOT NULL,\n  conversation_id TEXT NOT NULL,\n  seq INTEGER NOT NULL,\n  role TEXT NOT NULL,\n  content TEXT NOT NULL,\n  reasoning TEXT,\n  created_at_ms INTEGER NOT NULL,\n  FOREIGN KEY(conversation_id) REFERENCES conversations(id) ON DELETE CASCADE\n);",
+            "CREATE TABLE IF NOT EXISTS messages (\n  id TEXT PRIMARY KEY NOT NULL,\n  conversation_id TEXT NOT NULL,\n  seq INTEGER NOT NULL,\n  role TEXT NOT NULL,\n  content TEXT NOT NULL,\n  reasoning TEXT,\n  tool_calls TEXT,\n  model TEXT,\n  prompt_tokens INTEGER,\n  completion_tokens INTEGER,\n  reasoning_tokens INTEGER,\n  cached_tokens INTEGER,\n  attachments TEXT,\n  active_variant INTEGER NOT NULL DEFAULT 0,\n  status TEXT NOT NULL DEFAULT 'complete',\n  pinned INTEGER NOT NULL DEFAULT 0,\n  created_at_ms INTEGER NOT NULL,\n  FOREIGN KEY(conversation_id) REFERENCES conversations(id) ON DELETE CASCADE\n);",
             (),
         )
         .await?;
@@ -341,6 +543,161 @@ impl HistoryStore {
             backfill_last_fields = true;
         }
 
+        // Persona (`services::config::Persona::id`) the conversation talks to; NULL = default prompt.
+        if !self.table_has_column(&conn, "conversations", "persona_id").await? {
+            conn.execute("ALTER TABLE conversations ADD COLUMN persona_id TEXT;", ())
+                .await?;
+        }
+
+        // `<format>:<id>` of conversations imported from other apps (see `importer`).
+        if !self
+            .table_has_column(&conn, "conversations", "source_id")
+            .await?
+        {
+            conn.execute("ALTER TABLE conversations ADD COLUMN source_id TEXT;", ())
+                .await?;
+        }
+
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_conversations_source ON conversations(source_id) WHERE source_id IS NOT NULL;",
+            (),
+        )
+        .await?;
+
+        // When a conversation was deleted (moved to the trash); NULL while it is not.
+        if !self
+            .table_has_column(&conn, "conversations", "archived_at_ms")
+            .await?
+        {
+            conn.execute(
+                "ALTER TABLE conversations ADD COLUMN archived_at_ms INTEGER;",
+                (),
+            )
+            .await?;
+            // Conversations deleted before the trash existed get a full retention period.
+            conn.execute(
+                "UPDATE conversations SET archived_at_ms = ?1 WHERE archived = 1;",
+                params![now_ms() as i64],
+            )
+            .await?;
+        }
+
+        // JSON array of `ToolCallRecord` made while producing an assistant message.
+        if !self.table_has_column(&conn, "messages", "tool_calls").await? {
+            conn.execute("ALTER TABLE messages ADD COLUMN tool_calls TEXT;", ())
+                .await?;
+        }
+
+        // Model + provider-reported token usage of assistant messages (NULL when unknown).
+        for (column, ty) in [
+            ("model", "TEXT"),
+            ("prompt_tokens", "INTEGER"),
+            ("completion_tokens", "INTEGER"),
+            ("reasoning_tokens", "INTEGER"),
+            ("cached_tokens", "INTEGER"),
+            ("attachments", "TEXT"),
+            ("active_variant", "INTEGER NOT NULL DEFAULT 0"),
+            ("status", "TEXT NOT NULL DEFAULT 'complete'"),
+            ("pinned", "INTEGER NOT NULL DEFAULT 0"),
+        ] {
+            if !self.table_has_column(&conn, "messages", column).await? {
+                conn.execute(
+                    &format!("ALTER TABLE messages ADD COLUMN {column} {ty};"),
+                    (),
+                )
+                .await?;
+            }
+        }
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_messages_created ON messages(created_at_ms);",
+            (),
+        )
+        .await?;
+
+        // Every stored answer of a regenerated assistant turn; `messages` mirrors the active one.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS message_variants (\n  id TEXT PRIMARY KEY NOT NULL,\n  conversation_id TEXT NOT NULL,\n  seq INTEGER NOT NULL,\n  variant INTEGER NOT NULL,\n  content TEXT NOT NULL,\n  reasoning TEXT,\n  tool_calls TEXT,\n  model TEXT,\n  prompt_tokens INTEGER,\n  completion_tokens INTEGER,\n  reasoning_tokens INTEGER,\n  cached_tokens INTEGER,\n  status TEXT NOT NULL DEFAULT 'complete',\n  created_at_ms INTEGER NOT NULL,\n  FOREIGN KEY(conversation_id) REFERENCES conversations(id) ON DELETE CASCADE\n);",
+            (),
+        )
+        .await?;
+
+        // `complete` / `aborted` / `errored` (see `MessageStatus`).
+        if !self
+            .table_has_column(&conn, "message_variants", "status")
+            .await?
+        {
+            conn.execute(
+                "ALTER TABLE message_variants ADD COLUMN status TEXT NOT NULL DEFAULT 'complete';",
+                (),
+            )
+            .await?;
+        }
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_message_variants_turn ON message_variants(conversation_id, seq, variant);",
+            (),
+        )
+        .await?;
+
+        // Append-only: one row per reply that reported usage. Regenerating, truncating or purging
+        // messages never touches it, so spend totals only ever grow.
+        let backfill_usage = !self.table_exists(&conn, "usage_ledger").await?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_ledger (\n  id TEXT PRIMARY KEY NOT NULL,\n  conversation_id TEXT NOT NULL,\n  model TEXT,\n  prompt_tokens INTEGER NOT NULL,\n  completion_tokens INTEGER NOT NULL,\n  reasoning_tokens INTEGER NOT NULL,\n  cached_tokens INTEGER NOT NULL,\n  created_at_ms INTEGER NOT NULL\n);",
+            (),
+        )
+        .await?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_ledger_created ON usage_ledger(created_at_ms);",
+            (),
+        )
+        .await?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_ledger_conversation ON usage_ledger(conversation_id);",
+            (),
+        )
+        .await?;
+
+        if backfill_usage {
+            // Usage recorded before the ledger: active answers plus their inactive variants.
+            conn.execute(
+                "INSERT INTO usage_ledger (id, conversation_id, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, created_at_ms)\nSELECT 'usage_' || id, conversation_id, model, prompt_tokens, COALESCE(completion_tokens, 0), COALESCE(reasoning_tokens, 0), COALESCE(cached_tokens, 0), created_at_ms\n  FROM messages\n WHERE prompt_tokens IS NOT NULL\nUNION ALL\nSELECT 'usage_' || v.id, v.conversation_id, v.model, v.prompt_tokens, COALESCE(v.completion_tokens, 0), COALESCE(v.reasoning_tokens, 0), COALESCE(v.cached_tokens, 0), v.created_at_ms\n  FROM message_variants v\n  JOIN messages m ON m.conversation_id = v.conversation_id AND m.seq = v.seq\n WHERE v.prompt_tokens IS NOT NULL AND v.variant <> m.active_variant;",
+                (),
+            )
+            .await?;
+        }
+
+        // Append-only: rows outlive their conversation and are only removed by `purge_audit`.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS audit_log (\n  id TEXT PRIMARY KEY NOT NULL,\n  created_at_ms INTEGER NOT NULL,\n  kind TEXT NOT NULL,\n  tool TEXT,\n  conversation_id TEXT,\n  request_id TEXT,\n  app_name TEXT,\n  window_title TEXT,\n  chars INTEGER NOT NULL DEFAULT 0,\n  provider TEXT,\n  model TEXT,\n  is_error INTEGER NOT NULL DEFAULT 0\n);",
+            (),
+        )
+        .await?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_audit_log_created ON audit_log(created_at_ms);",
+            (),
+        )
+        .await?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_audit_log_conversation ON audit_log(conversation_id, created_at_ms);",
+            (),
+        )
+        .await?;
+
+        // Optional: without FTS5 (or the trigram tokenizer) `search` falls back to `LIKE` scans.
+        match self.migrate_search(&conn).await {
+            Ok(()) => self.inner.search_fts.store(true, Ordering::Relaxed),
+            Err(err) => log::warn!(
+                "History search index unavailable, using LIKE scans: {}",
+                err
+            ),
+        }
+
         if backfill_counts {
             conn.execute(
                 "UPDATE conversations\n   SET message_count = (\n     SELECT COALESCE(MAX(seq), 0)\n       FROM messages\n      WHERE conversation_id = conversations.id\n   );",
@@ -360,10 +717,105 @@ impl HistoryStore {
         Ok(())
     }
 
+    /// FTS5 indexes over titles and message text, maintained by triggers.
+    ///
+    /// Both tables are keyed by a `TEXT` id, so their implicit rowid is not stable (VACUUM may
+    /// renumber it). The indexes are keyed on a `search_rowid` column instead, assigned once
+    /// when a row is inserted.
+    async fn migrate_search(&self, conn: &libsql::Connection) -> Result<(), HistoryError> {
+        let keyed = self
+            .table_has_column(conn, "messages", "search_rowid")
+            .await?
+            && self
+                .table_has_column(conn, "conversations", "search_rowid")
+                .await?;
+        if !keyed {
+            // Indexes created before `search_rowid` were keyed on the implicit rowid.
+            for sql in [
+                "DROP TRIGGER IF EXISTS messages_fts_insert;",
+                "DROP TRIGGER IF EXISTS messages_fts_delete;",
+                "DROP TRIGGER IF EXISTS messages_fts_update;",
+                "DROP TRIGGER IF EXISTS conversations_fts_insert;",
+                "DROP TRIGGER IF EXISTS conversations_fts_delete;",
+                "DROP TRIGGER IF EXISTS conversations_fts_update;",
+                "DROP TABLE IF EXISTS messages_fts;",
+                "DROP TABLE IF EXISTS conversations_fts;",
+            ] {
+                conn.execute(sql, ()).await?;
+            }
+        }
+
+        let mut backfilled = 0;
+        for table in ["messages", "conversations"] {
+            if !self.table_has_column(conn, table, "search_rowid").await? {
+                conn.execute(
+                    &format!("ALTER TABLE {table} ADD COLUMN search_rowid INTEGER;"),
+                    (),
+                )
+                .await?;
+            }
+            conn.execute(
+                &format!("CREATE UNIQUE INDEX IF NOT EXISTS idx_{table}_search_rowid ON {table}(search_rowid);"),
+                (),
+            )
+            .await?;
+            // Rows written without the insert trigger: offset past every key already handed out.
+            backfilled += conn
+                .execute(
+                    &format!("UPDATE {table}\n   SET search_rowid = (SELECT COALESCE(MAX(search_rowid), 0) FROM {table}) + rowid\n WHERE search_rowid IS NULL;"),
+                    (),
+                )
+                .await?;
+        }
+
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('messages_fts', 'conversations_fts');",
+                (),
+            )
+            .await?;
+        let existing: i64 = match rows.next().await? {
+            Some(row) => row.get(0)?,
+            None => 0,
+        };
+
+        for sql in [
+            "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(content, reasoning, content='messages', content_rowid='search_rowid', tokenize='trigram');",
+            "CREATE VIRTUAL TABLE IF NOT EXISTS conversations_fts USING fts5(title, content='conversations', content_rowid='search_rowid', tokenize='trigram');",
+            "CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN\n  UPDATE messages SET search_rowid = (SELECT COALESCE(MAX(search_rowid), 0) + 1 FROM messages) WHERE rowid = new.rowid;\n  INSERT INTO messages_fts(rowid, content, reasoning) SELECT search_rowid, content, reasoning FROM messages WHERE rowid = new.rowid;\nEND;",
+            "CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN\n  INSERT INTO messages_fts(messages_fts, rowid, content, reasoning) VALUES ('delete', old.search_rowid, old.content, old.reasoning);\nEND;",
+            "CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF content, reasoning ON messages BEGIN\n  INSERT INTO messages_fts(messages_fts, rowid, content, reasoning) VALUES ('delete', old.search_rowid, old.content, old.reasoning);\n  INSERT INTO messages_fts(rowid, content, reasoning) VALUES (new.search_rowid, new.content, new.reasoning);\nEND;",
+            "CREATE TRIGGER IF NOT EXISTS conversations_fts_insert AFTER INSERT ON conversations BEGIN\n  UPDATE conversations SET search_rowid = (SELECT COALESCE(MAX(search_rowid), 0) + 1 FROM conversations) WHERE rowid = new.rowid;\n  INSERT INTO conversations_fts(rowid, title) SELECT search_rowid, title FROM conversations WHERE rowid = new.rowid;\nEND;",
+            "CREATE TRIGGER IF NOT EXISTS conversations_fts_delete AFTER DELETE ON conversations BEGIN\n  INSERT INTO conversations_fts(conversations_fts, rowid, title) VALUES ('delete', old.search_rowid, old.title);\nEND;",
+            "CREATE TRIGGER IF NOT EXISTS conversations_fts_update AFTER UPDATE OF title ON conversations BEGIN\n  INSERT INTO conversations_fts(conversations_fts, rowid, title) VALUES ('delete', old.search_rowid, old.title);\n  INSERT INTO conversations_fts(rowid, title) VALUES (new.search_rowid, new.title);\nEND;",
+        ] {
+            conn.execute(sql, ()).await?;
+        }
+
+        // Index rows written before the search tables existed (or without a key).
+        if existing < 2 || backfilled > 0 {
+            conn.execute(
+                "INSERT INTO messages_fts(messages_fts) VALUES ('rebuild');",
+                (),
+            )
+            .await?;
+            conn.execute(
+                "INSERT INTO conversations_fts(conversations_fts) VALUES ('rebuild');",
+                (),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
     pub(crate) async fn bootstrap(&self) -> Result<HistoryBootstrap, HistoryError> {
         let active_id = match self.get_active_conversation_id().await? {
             Some(id) if self.conversation_exists(&id).await? => id,
-            _ => self.create_conversation(None, true).await?.id,
+            _ => {
+                self.create_conversation(None, true, config::default_persona_id())
+                    .await?
+                    .id
+            }
         };
 
         let conversations = self.list_conversations().await?;
@@ -382,7 +834,7 @@ impl HistoryStore {
 
         let mut rows = conn
             .query(
-                "SELECT id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, message_count, last_message_at_ms, last_role\n   FROM conversations\n  WHERE archived = 0\n  ORDER BY updated_at_ms DESC\n  LIMIT 50;",
+                "SELECT id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, message_count, last_message_at_ms, last_role, persona_id\n   FROM conversations\n  WHERE archived = 0\n  ORDER BY updated_at_ms DESC\n  LIMIT 50;",
                 (),
             )
             .await?;
@@ -398,6 +850,7 @@ impl HistoryStore {
             let message_count: i64 = row.get(6)?;
             let last_message_at_ms: i64 = row.get(7)?;
             let last_role: String = row.get(8)?;
+            let persona_id: Option<String> = row.get(9)?;
 
             let has_unseen = updated_at_ms > last_seen_at_ms;
             let is_active = active_id.as_deref() == Some(id.as_str());
@@ -412,6 +865,7 @@ impl HistoryStore {
                 message_count: message_count.max(0) as u32,
                 last_message_at_ms: last_message_at_ms.max(0) as u64,
                 last_role,
+                persona_id,
                 has_unseen,
                 is_active,
             });
@@ -429,7 +883,7 @@ impl HistoryStore {
 
         let mut conv_rows = conn
             .query(
-                "SELECT id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count, last_message_at_ms, last_role\n   FROM conversations\n  WHERE id = ?1\n  LIMIT 1;",
+                "SELECT id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count, last_message_at_ms, last_role, persona_id\n   FROM conversations\n  WHERE id = ?1\n  LIMIT 1;",
                 params![conversation_id],
             )
             .await?;
@@ -449,35 +903,21 @@ impl HistoryStore {
         let mut message_count: i64 = conv_row.get(7)?;
         let last_message_at_ms: i64 = conv_row.get(8)?;
         let last_role: String = conv_row.get(9)?;
+        let persona_id: Option<String> = conv_row.get(10)?;
         if archived != 0 {
             return Err(HistoryError::archived("Conversation is archived"));
         }
 
         let mut msg_rows = conn
             .query(
-                "SELECT id, seq, role, content, reasoning, created_at_ms\n   FROM messages\n  WHERE conversation_id = ?1\n  ORDER BY seq ASC;",
+                "SELECT id, seq, role, content, reasoning, created_at_ms, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, attachments, active_variant,\n        (SELECT COUNT(*) FROM message_variants v WHERE v.conversation_id = messages.conversation_id AND v.seq = messages.seq), status, pinned\n   FROM messages\n  WHERE conversation_id = ?1\n  ORDER BY seq ASC;",
                 params![conversation_id],
             )
             .await?;
 
         let mut messages = Vec::new();
         while let Some(row) = msg_rows.next().await? {
-            let id: String = row.get(0)?;
-            let seq: i64 = row.get(1)?;
-            let role: String = row.get(2)?;
-            let content: String = row.get(3)?;
-            let reasoning: Option<String> = row.get(4).ok();
-            let created_at_ms: i64 = row.get(5)?;
-
-            messages.push(ConversationMessage {
-                id,
-                conversation_id: conversation_id.to_string(),
-                seq: seq.max(0) as u32,
-                role,
-                content,
-                reasoning,
-                created_at_ms: created_at_ms.max(0) as u64,
-            });
+            messages.push(message_from_row(&row, conversation_id)?);
         }
 
         message_count = message_count.max(messages.len() as i64);
@@ -496,6 +936,7 @@ impl HistoryStore {
                 message_count,
                 last_message_at_ms: last_message_at_ms.max(0) as u64,
                 last_role,
+                persona_id,
                 has_unseen,
                 is_active,
             },
@@ -514,7 +955,7 @@ impl HistoryStore {
 
         let mut conv_rows = conn
             .query(
-                "SELECT id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count, last_message_at_ms, last_role\n   FROM conversations\n  WHERE id = ?1\n  LIMIT 1;",
+                "SELECT id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count, last_message_at_ms, last_role, persona_id\n   FROM conversations\n  WHERE id = ?1\n  LIMIT 1;",
                 params![conversation_id],
             )
             .await?;
@@ -534,6 +975,7 @@ impl HistoryStore {
         let mut message_count: i64 = conv_row.get(7)?;
         let last_message_at_ms: i64 = conv_row.get(8)?;
         let last_role: String = conv_row.get(9)?;
+        let persona_id: Option<String> = conv_row.get(10)?;
         if archived != 0 {
             return Err(HistoryError::archived("Conversation is archived"));
         }
@@ -543,14 +985,14 @@ impl HistoryStore {
         let mut msg_rows = match before_seq {
             Some(before_seq) if before_seq > 0 => {
                 conn.query(
-                    "SELECT id, seq, role, content, reasoning, created_at_ms\n   FROM messages\n  WHERE conversation_id = ?1 AND seq < ?2\n  ORDER BY seq DESC\n  LIMIT ?3;",
+                    "SELECT id, seq, role, content, reasoning, created_at_ms, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, attachments, active_variant,\n        (SELECT COUNT(*) FROM message_variants v WHERE v.conversation_id = messages.conversation_id AND v.seq = messages.seq), status, pinned\n   FROM messages\n  WHERE conversation_id = ?1 AND seq < ?2\n  ORDER BY seq DESC\n  LIMIT ?3;",
                     params![conversation_id, before_seq as i64, page_limit],
                 )
                 .await?
             }
             _ => {
                 conn.query(
-                    "SELECT id, seq, role, content, reasoning, created_at_ms\n   FROM messages\n  WHERE conversation_id = ?1\n  ORDER BY seq DESC\n  LIMIT ?2;",
+                    "SELECT id, seq, role, content, reasoning, created_at_ms, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, attachments, active_variant,\n        (SELECT COUNT(*) FROM message_variants v WHERE v.conversation_id = messages.conversation_id AND v.seq = messages.seq), status, pinned\n   FROM messages\n  WHERE conversation_id = ?1\n  ORDER BY seq DESC\n  LIMIT ?2;",
                     params![conversation_id, page_limit],
                 )
                 .await?
@@ -559,22 +1001,7 @@ impl HistoryStore {
 
         let mut messages_desc = Vec::new();
         while let Some(row) = msg_rows.next().await? {
-            let id: String = row.get(0)?;
-            let seq: i64 = row.get(1)?;
-            let role: String = row.get(2)?;
-            let content: String = row.get(3)?;
-            let reasoning: Option<String> = row.get(4).ok();
-            let created_at_ms: i64 = row.get(5)?;
-
-            messages_desc.push(ConversationMessage {
-                id,
-                conversation_id: conversation_id.to_string(),
-                seq: seq.max(0) as u32,
-                role,
-                content,
-                reasoning,
-                created_at_ms: created_at_ms.max(0) as u64,
-            });
+            messages_desc.push(message_from_row(&row, conversation_id)?);
         }
         messages_desc.reverse();
 
@@ -595,6 +1022,7 @@ impl HistoryStore {
                 message_count,
                 last_message_at_ms: last_message_at_ms.max(0) as u64,
                 last_role,
+                persona_id,
                 has_unseen,
                 is_active,
             },
@@ -602,73 +1030,371 @@ impl HistoryStore {
         })
     }
 
-    pub(crate) async fn create_conversation(
+    /// Search titles and message text (content and reasoning) of conversations not deleted.
+    ///
+    /// Every whitespace-separated term must match. Results are ranked by relevance (BM25, title
+    /// matches weighted up) when the FTS index can serve the query, else newest first.
+    pub(crate) async fn search(
         &self,
-        title: Option<String>,
-        set_active: bool,
-    ) -> Result<ConversationSummary, HistoryError> {
-        let id = new_id("conv");
-        let now = now_ms() as i64;
-        let title = title
-            .as_deref()
-            .map(|t| t.trim().to_string())
-            .filter(|t| !t.is_empty())
-            .unwrap_or_else(|| "新对话".to_string());
-
-        retry_db_locked(|| async {
-            let _write = self.write_permit().await?;
-            let conn = self.connect().await?;
-            let tx = conn.transaction().await?;
-
-            tx.execute(
-                "INSERT INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count)\nVALUES (?1, ?2, 0, ?3, ?3, ?3, 0, 0);",
-                params![id.as_str(), title.as_str(), now],
-            )
-            .await?;
+        query: &str,
+        offset: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<SearchResults, HistoryError> {
+        let terms = search::search_terms(query);
+        if terms.is_empty() {
+            return Err(HistoryError::invalid_input("Search query is required"));
+        }
+        let offset = offset.unwrap_or(0);
+        let limit = limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .clamp(1, MAX_SEARCH_LIMIT);
+        let fts_query =
+            search::fts_query(&terms).filter(|_| self.inner.search_fts.load(Ordering::Relaxed));
 
-            if set_active {
-                tx.execute(
-                    "INSERT INTO app_state (key, value) VALUES (?1, ?2)\nON CONFLICT(key) DO UPDATE SET value = excluded.value;",
-                    params![APP_STATE_ACTIVE_CONVERSATION_ID, id.as_str()],
+        let conn = self.connect().await?;
+        let mut hits = Vec::new();
+        // One extra row tells whether another page follows.
+        let fetch = limit as i64 + 1;
+        if let Some(fts_query) = fts_query {
+            let mut rows = conn
+                .query(
+                    "SELECT conversation_id, title, seq, role, snippet, at_ms FROM (\n  SELECT c.id AS conversation_id, c.title AS title, NULL AS seq, NULL AS role,\n         highlight(conversations_fts, 0, char(2), char(3)) AS snippet,\n         c.updated_at_ms AS at_ms, bm25(conversations_fts) * 2.0 AS score\n    FROM conversations_fts\n    JOIN conversations c ON c.search_rowid = conversations_fts.rowid\n   WHERE conversations_fts MATCH ?1 AND c.archived = 0\n  UNION ALL\n  SELECT m.conversation_id, c.title, m.seq, m.role,\n         snippet(messages_fts, -1, char(2), char(3), '…', 32),\n         m.created_at_ms, bm25(messages_fts, 1.0, 0.5)\n    FROM messages_fts\n    JOIN messages m ON m.search_rowid = messages_fts.rowid\n    JOIN conversations c ON c.id = m.conversation_id\n   WHERE messages_fts MATCH ?1 AND c.archived = 0\n)\nORDER BY score ASC, at_ms DESC\nLIMIT ?2 OFFSET ?3;",
+                    params![fts_query, fetch, offset as i64],
                 )
                 .await?;
+            while let Some(row) = rows.next().await? {
+                hits.push(search_hit_from_row(&row, row.get(4)?)?);
             }
+        } else {
+            // Terms bind from ?3 on; each must appear in the searched text.
+            let filter = |column: &str| {
+                (0..terms.len())
+                    .map(|i| format!("{column} LIKE ?{} ESCAPE '\\'", i + 3))
+                    .collect::<Vec<_>>()
+                    .join(" AND ")
+            };
+            let sql = format!(
+                "SELECT conversation_id, title, seq, role, text, at_ms FROM (\n  SELECT c.id AS conversation_id, c.title AS title, NULL AS seq, NULL AS role,\n         c.title AS text, c.updated_at_ms AS at_ms, 0 AS kind\n    FROM conversations c\n   WHERE c.archived = 0 AND {}\n  UNION ALL\n  SELECT m.conversation_id, c.title, m.seq, m.role,\n         m.content || char(10) || COALESCE(m.reasoning, ''), m.created_at_ms, 1\n    FROM messages m\n    JOIN conversations c ON c.id = m.conversation_id\n   WHERE c.archived = 0 AND {}\n)\nORDER BY kind ASC, at_ms DESC\nLIMIT ?1 OFFSET ?2;",
+                filter("c.title"),
+                filter("(m.content || char(10) || COALESCE(m.reasoning, ''))"),
+            );
+            let mut params: Vec<Value> = vec![Value::from(fetch), Value::from(offset as i64)];
+            params.extend(
+                terms
+                    .iter()
+                    .map(|term| Value::from(search::like_pattern(term))),
+            );
+            let mut rows = conn.query(&sql, params).await?;
+            while let Some(row) = rows.next().await? {
+                let text: String = row.get(4)?;
+                let snippet = search::highlight_snippet(&text, &terms, SEARCH_SNIPPET_CHARS);
+                hits.push(search_hit_from_row(&row, snippet)?);
+            }
+        }
 
-            tx.commit().await?;
-
-            Ok(ConversationSummary {
-                id: id.clone(),
-                title: title.clone(),
-                title_auto: false,
-                created_at_ms: now.max(0) as u64,
-                updated_at_ms: now.max(0) as u64,
-                last_seen_at_ms: now.max(0) as u64,
-                message_count: 0,
-                last_message_at_ms: 0,
-                last_role: String::new(),
-                has_unseen: false,
-                is_active: set_active,
-            })
-        })
-        .await
+        let next_offset = (hits.len() > limit as usize).then(|| {
+            hits.truncate(limit as usize);
+            offset + limit
+        });
+        Ok(SearchResults { hits, next_offset })
     }
 
-    pub(crate) async fn fork_conversation(
+    /// Write one conversation to `path` (a file, or a directory to name the file after it).
+    ///
+    /// Returns the path written.
+    pub(crate) async fn export_conversation(
         &self,
-        source_conversation_id: &str,
-        upto_seq: Option<u32>,
-        set_active: bool,
-    ) -> Result<ConversationSummary, HistoryError> {
-        let source_conversation_id = source_conversation_id.trim().to_string();
-        if source_conversation_id.is_empty() {
-            return Err(HistoryError::invalid_input("conversationId is required"));
+        conversation_id: &str,
+        format: ExportFormat,
+        path: &Path,
+        utc_offset_minutes: i32,
+    ) -> Result<PathBuf, HistoryError> {
+        if !path.is_absolute() {
+            return Err(HistoryError::invalid_input("Export path must be absolute"));
         }
+        let detail = self.get_conversation(conversation_id).await?;
+        let path = if path.is_dir() {
+            path.join(export::file_name(
+                &detail.conversation,
+                format,
+                utc_offset_minutes,
+            ))
+        } else {
+            path.to_path_buf()
+        };
+        let contents = export::render(&detail, format, utc_offset_minutes, now_ms())?;
+        export::write_file(&path, &contents)?;
+        Ok(path)
+    }
 
-        let id = new_id("conv");
-        let now = now_ms() as i64;
-        let upto_seq = upto_seq.map(|v| v as i64);
+    /// Write every conversation (not deleted) into `dir`, one file each; returns the paths written.
+    ///
+    /// Files are named after the conversation, so exporting again overwrites the previous copies.
+    pub(crate) async fn export_all(
+        &self,
+        format: ExportFormat,
+        dir: &Path,
+        utc_offset_minutes: i32,
+    ) -> Result<Vec<PathBuf>, HistoryError> {
+        if !dir.is_absolute() {
+            return Err(HistoryError::invalid_input("Export path must be absolute"));
+        }
+        std::fs::create_dir_all(dir).map_err(|e| {
+            HistoryError::internal(format!("Failed to create {}: {e}", dir.display()))
+        })?;
 
-        retry_db_locked(|| {
+        let conversation_ids = {
+            let conn = self.connect().await?;
+            let mut rows = conn
+                .query(
+                    "SELECT id FROM conversations WHERE archived = 0 ORDER BY created_at_ms ASC;",
+                    (),
+                )
+                .await?;
+            let mut ids = Vec::new();
+            while let Some(row) = rows.next().await? {
+                ids.push(row.get::<String>(0)?);
+            }
+            ids
+        };
+
+        let exported_at_ms = now_ms();
+        let mut written = Vec::with_capacity(conversation_ids.len());
+        for conversation_id in conversation_ids {
+            let detail = match self.get_conversation(&conversation_id).await {
+                Ok(detail) => detail,
+                // Deleted while exporting.
+                Err(HistoryError::NotFound { .. } | HistoryError::Archived { .. }) => continue,
+                Err(err) => return Err(err),
+            };
+            let path = dir.join(export::file_name(
+                &detail.conversation,
+                format,
+                utc_offset_minutes,
+            ));
+            let contents = export::render(&detail, format, utc_offset_minutes, exported_at_ms)?;
+            export::write_file(&path, &contents)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Import the conversations of an export file from another app (see `importer`).
+    ///
+    /// Conversations imported before (same source id, even if deleted since) are skipped.
+    /// `on_progress` runs after each conversation; the final counts are returned.
+    pub(crate) async fn import_file(
+        &self,
+        format: ImportFormat,
+        path: &Path,
+        mut on_progress: impl FnMut(&ImportProgress),
+    ) -> Result<ImportProgress, HistoryError> {
+        let path = path.to_path_buf();
+        let now = now_ms();
+        // Exports can be large; read and parse off the async runtime.
+        let parsed = tokio::task::spawn_blocking(move || {
+            let text = std::fs::read_to_string(&path).map_err(|e| {
+                HistoryError::invalid_input(format!("Failed to read {}: {e}", path.display()))
+            })?;
+            importer::parse(format, &text, now)
+        })
+        .await
+        .map_err(|e| HistoryError::internal(format!("Import task failed: {e}")))??;
+
+        let mut progress = ImportProgress {
+            total: parsed.conversations.len() as u32 + parsed.skipped,
+            processed: parsed.skipped,
+            imported: 0,
+            skipped: parsed.skipped,
+        };
+        on_progress(&progress);
+        for conversation in &parsed.conversations {
+            if self.insert_imported(conversation).await? {
+                progress.imported += 1;
+            } else {
+                progress.skipped += 1;
+            }
+            progress.processed += 1;
+            on_progress(&progress);
+        }
+        Ok(progress)
+    }
+
+    /// Write one imported conversation with its original timestamps; `false` when it exists.
+    async fn insert_imported(
+        &self,
+        conversation: &ImportedConversation,
+    ) -> Result<bool, HistoryError> {
+        let title = if conversation.title.is_empty() {
+            conversation
+                .messages
+                .iter()
+                .find(|m| m.role == "user")
+                .map(|m| truncate_title(&m.content))
+                .unwrap_or_else(|| "导入的对话".to_string())
+        } else {
+            conversation.title.clone()
+        };
+        let (last_message_at_ms, last_role) = conversation
+            .messages
+            .last()
+            .map(|m| (m.created_at_ms as i64, m.role))
+            .unwrap_or((0, ""));
+
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let mut rows = conn
+                .query(
+                    "SELECT 1 FROM conversations WHERE source_id = ?1 LIMIT 1;",
+                    params![conversation.source_id.as_str()],
+                )
+                .await?;
+            if rows.next().await?.is_some() {
+                return Ok(false);
+            }
+
+            let id = new_id("conv");
+            let tx = conn.transaction().await?;
+            // Seen up to its last message, so an import does not mark everything unread.
+            tx.execute(
+                "INSERT INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, last_message_at_ms, last_role, archived, message_count, source_id)\nVALUES (?1, ?2, 0, ?3, ?4, ?4, ?5, ?6, 0, ?7, ?8);",
+                params![
+                    id.as_str(),
+                    title.as_str(),
+                    conversation.created_at_ms as i64,
+                    conversation.updated_at_ms as i64,
+                    last_message_at_ms,
+                    last_role,
+                    conversation.messages.len() as i64,
+                    conversation.source_id.as_str()
+                ],
+            )
+            .await?;
+
+            let chunks = conversation.messages.chunks(IMPORT_CHUNK_SIZE);
+            for (chunk_index, chunk) in chunks.enumerate() {
+                let mut sql = String::from(
+                    "INSERT INTO messages (id, conversation_id, seq, role, content, reasoning, model, created_at_ms)\nVALUES ",
+                );
+                let mut params: Vec<Value> = Vec::with_capacity(chunk.len() * 8);
+                for (offset, message) in chunk.iter().enumerate() {
+                    let seq = (chunk_index * IMPORT_CHUNK_SIZE + offset + 1) as i64;
+                    let base = offset * 8;
+                    if offset > 0 {
+                        sql.push(',');
+                    }
+                    sql.push_str(&format!(
+                        "(?{}, ?{}, ?{}, ?{}, ?{}, ?{}, ?{}, ?{})",
+                        base + 1,
+                        base + 2,
+                        base + 3,
+                        base + 4,
+                        base + 5,
+                        base + 6,
+                        base + 7,
+                        base + 8
+                    ));
+                    params.push(Value::from(format!("{id}:{seq}")));
+                    params.push(Value::from(id.as_str()));
+                    params.push(Value::from(seq));
+                    params.push(Value::from(message.role));
+                    params.push(Value::from(message.content.as_str()));
+                    params.push(
+                        message
+                            .reasoning
+                            .clone()
+                            .map(Value::from)
+                            .unwrap_or(Value::Null),
+                    );
+                    params.push(
+                        message
+                            .model
+                            .clone()
+                            .map(Value::from)
+                            .unwrap_or(Value::Null),
+                    );
+                    params.push(Value::from(message.created_at_ms as i64));
+                }
+                sql.push(';');
+                tx.execute(&sql, params).await?;
+            }
+
+            tx.commit().await?;
+            Ok(true)
+        })
+        .await
+    }
+
+    pub(crate) async fn create_conversation(
+        &self,
+        title: Option<String>,
+        set_active: bool,
+        persona_id: Option<String>,
+    ) -> Result<ConversationSummary, HistoryError> {
+        let id = new_id("conv");
+        let now = now_ms() as i64;
+        let title = title
+            .as_deref()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "新对话".to_string());
+
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let tx = conn.transaction().await?;
+
+            tx.execute(
+                "INSERT INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count, persona_id)\nVALUES (?1, ?2, 0, ?3, ?3, ?3, 0, 0, ?4);",
+                params![id.as_str(), title.as_str(), now, persona_id.clone()],
+            )
+            .await?;
+
+            if set_active {
+                tx.execute(
+                    "INSERT INTO app_state (key, value) VALUES (?1, ?2)\nON CONFLICT(key) DO UPDATE SET value = excluded.value;",
+                    params![APP_STATE_ACTIVE_CONVERSATION_ID, id.as_str()],
+                )
+                .await?;
+            }
+
+            tx.commit().await?;
+
+            Ok(ConversationSummary {
+                id: id.clone(),
+                title: title.clone(),
+                title_auto: false,
+                created_at_ms: now.max(0) as u64,
+                updated_at_ms: now.max(0) as u64,
+                last_seen_at_ms: now.max(0) as u64,
+                message_count: 0,
+                last_message_at_ms: 0,
+                last_role: String::new(),
+                persona_id: persona_id.clone(),
+                has_unseen: false,
+                is_active: set_active,
+            })
+        })
+        .await
+    }
+
+    pub(crate) async fn fork_conversation(
+        &self,
+        source_conversation_id: &str,
+        upto_seq: Option<u32>,
+        set_active: bool,
+    ) -> Result<ConversationSummary, HistoryError> {
+        let source_conversation_id = source_conversation_id.trim().to_string();
+        if source_conversation_id.is_empty() {
+            return Err(HistoryError::invalid_input("conversationId is required"));
+        }
+
+        let id = new_id("conv");
+        let now = now_ms() as i64;
+        let upto_seq = upto_seq.map(|v| v as i64);
+
+        retry_db_locked(|| {
             let source_conversation_id = source_conversation_id.clone();
             let id = id.clone();
             async move {
@@ -677,7 +1403,7 @@ impl HistoryStore {
 
                 let mut src_rows = conn
                     .query(
-                        "SELECT title, archived FROM conversations WHERE id = ?1 LIMIT 1;",
+                        "SELECT title, archived, persona_id FROM conversations WHERE id = ?1 LIMIT 1;",
                         params![source_conversation_id.as_str()],
                     )
                     .await?;
@@ -687,6 +1413,7 @@ impl HistoryStore {
 
                 let src_title: String = row.get(0)?;
                 let archived: i64 = row.get(1)?;
+                let persona_id: Option<String> = row.get(2)?;
                 if archived != 0 {
                     return Err(HistoryError::archived("Conversation is archived"));
                 }
@@ -719,8 +1446,8 @@ impl HistoryStore {
 
                 let tx = conn.transaction().await?;
                 tx.execute(
-                    "INSERT INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count)\nVALUES (?1, ?2, 0, ?3, ?3, ?3, 0, ?4);",
-                    params![id.as_str(), new_title.as_str(), now, seq_limit],
+                    "INSERT INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count, persona_id)\nVALUES (?1, ?2, 0, ?3, ?3, ?3, 0, ?4, ?5);",
+                    params![id.as_str(), new_title.as_str(), now, seq_limit, persona_id.clone()],
                 )
                 .await?;
 
@@ -736,7 +1463,14 @@ impl HistoryStore {
                 let mut last_role = String::new();
                 if seq_limit > 0 {
                     tx.execute(
-                        "INSERT INTO messages (id, conversation_id, seq, role, content, reasoning, created_at_ms)\nSELECT (?1 || ':' || seq) AS id,\n       ?1 AS conversation_id,\n       seq,\n       role,\n       content,\n       reasoning,\n       created_at_ms\n  FROM messages\n WHERE conversation_id = ?2 AND seq <= ?3\n ORDER BY seq ASC;",
+                        "INSERT INTO messages (id, conversation_id, seq, role, content, reasoning, tool_calls, model, attachments, active_variant, status, pinned, created_at_ms)\nSELECT (?1 || ':' || seq) AS id,\n       ?1 AS conversation_id,\n       seq,\n       role,\n       content,\n       reasoning,\n       tool_calls,\n       model,\n       attachments,\n       active_variant,\n       status,\n       pinned,\n       created_at_ms\n  FROM messages\n WHERE conversation_id = ?2 AND seq <= ?3\n ORDER BY seq ASC;",
+                        params![id.as_str(), source_conversation_id.as_str(), seq_limit],
+                    )
+                    .await?;
+
+                    // Like messages, copied variants carry no usage (it was spent in the source).
+                    tx.execute(
+                        "INSERT INTO message_variants (id, conversation_id, seq, variant, content, reasoning, tool_calls, model, status, created_at_ms)\nSELECT (?1 || ':' || seq || ':' || variant) AS id,\n       ?1 AS conversation_id,\n       seq,\n       variant,\n       content,\n       reasoning,\n       tool_calls,\n       model,\n       status,\n       created_at_ms\n  FROM message_variants\n WHERE conversation_id = ?2 AND seq <= ?3;",
                         params![id.as_str(), source_conversation_id.as_str(), seq_limit],
                     )
                     .await?;
@@ -771,6 +1505,7 @@ impl HistoryStore {
                     message_count: message_count.max(0) as u32,
                     last_message_at_ms: last_message_at_ms.max(0) as u64,
                     last_role,
+                    persona_id,
                     has_unseen: false,
                     is_active: set_active,
                 })
@@ -857,6 +1592,58 @@ impl HistoryStore {
         .await
     }
 
+    /// Assign (or clear, with `None`) the conversation's persona.
+    pub(crate) async fn set_conversation_persona(
+        &self,
+        conversation_id: &str,
+        persona_id: Option<&str>,
+    ) -> Result<(), HistoryError> {
+        let conversation_id = conversation_id.trim();
+        if conversation_id.is_empty() {
+            return Err(HistoryError::invalid_input("conversationId is required"));
+        }
+        let persona_id = persona_id.map(str::trim).filter(|id| !id.is_empty());
+        if let Some(id) = persona_id
+            && config::load_persona(id).is_none()
+        {
+            return Err(HistoryError::not_found("Persona not found"));
+        }
+
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let updated = conn
+                .execute(
+                    "UPDATE conversations SET persona_id = ?2 WHERE id = ?1;",
+                    params![conversation_id, persona_id],
+                )
+                .await?;
+            if updated == 0 {
+                return Err(HistoryError::not_found("Conversation not found"));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Persona assigned to the conversation (`None` when unset or the conversation is unknown).
+    pub(crate) async fn conversation_persona_id(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<String>, HistoryError> {
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT persona_id FROM conversations WHERE id = ?1 LIMIT 1;",
+                params![conversation_id],
+            )
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(row.get::<Option<String>>(0)?),
+            None => Ok(None),
+        }
+    }
+
     pub(crate) async fn clear_messages(&self, conversation_id: &str) -> Result<(), HistoryError> {
         retry_db_locked(|| async {
             let _write = self.write_permit().await?;
@@ -866,6 +1653,11 @@ impl HistoryStore {
                 params![conversation_id],
             )
             .await?;
+            conn.execute(
+                "DELETE FROM message_variants WHERE conversation_id = ?1;",
+                params![conversation_id],
+            )
+            .await?;
 
             let now = now_ms() as i64;
             conn.execute(
@@ -927,8 +1719,8 @@ impl HistoryStore {
             let now = now_ms() as i64;
             let tx = conn.transaction().await?;
             tx.execute(
-                "UPDATE conversations SET archived = 1 WHERE id = ?1;",
-                params![conversation_id],
+                "UPDATE conversations SET archived = 1, archived_at_ms = ?2 WHERE id = ?1;",
+                params![conversation_id, now],
             )
             .await?;
 
@@ -951,8 +1743,8 @@ impl HistoryStore {
                 } else {
                     let id = new_id("conv");
                     tx.execute(
-                        "INSERT INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count)\nVALUES (?1, '新对话', 0, ?2, ?2, ?2, 0, 0);",
-                        params![id.as_str(), now],
+                        "INSERT INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count, persona_id)\nVALUES (?1, '新对话', 0, ?2, ?2, ?2, 0, 0, ?3);",
+                        params![id.as_str(), now, config::default_persona_id()],
                     )
                     .await?;
                     id
@@ -983,59 +1775,245 @@ impl HistoryStore {
         })
     }
 
-    pub(crate) async fn sync_from_frontend_messages(
+    /// Deleted conversations still in the trash, most recently deleted first.
+    pub(crate) async fn list_archived(&self) -> Result<Vec<ArchivedConversation>, HistoryError> {
+        let retention_days = config::load_history_trash_retention_days() as u64;
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT id, title, created_at_ms, updated_at_ms, message_count, COALESCE(archived_at_ms, updated_at_ms)\n   FROM conversations\n  WHERE archived = 1\n  ORDER BY archived_at_ms DESC;",
+                (),
+            )
+            .await?;
+
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let created_at_ms: i64 = row.get(2)?;
+            let updated_at_ms: i64 = row.get(3)?;
+            let message_count: i64 = row.get(4)?;
+            let archived_at_ms = row.get::<i64>(5)?.max(0) as u64;
+            out.push(ArchivedConversation {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                created_at_ms: created_at_ms.max(0) as u64,
+                updated_at_ms: updated_at_ms.max(0) as u64,
+                message_count: message_count.max(0) as u32,
+                archived_at_ms,
+                purge_at_ms: (retention_days > 0)
+                    .then(|| archived_at_ms.saturating_add(retention_days * DAY_MS)),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Move a deleted conversation out of the trash.
+    ///
+    /// With `set_active` it becomes the active conversation; it also does when the active id
+    /// no longer points at a live conversation.
+    pub(crate) async fn restore_conversation(
         &self,
         conversation_id: &str,
-        messages: &[ChatMessage],
-        truncate_after_seq: Option<u32>,
-    ) -> Result<(), HistoryError> {
-        retry_db_locked(|| async {
+        set_active: bool,
+    ) -> Result<HistoryBootstrap, HistoryError> {
+        let active_conversation_id = retry_db_locked(|| async {
             let _write = self.write_permit().await?;
             let conn = self.connect().await?;
-            let now = now_ms() as i64;
 
-            let mut meta_rows = conn
+            let mut rows = conn
                 .query(
                     "SELECT archived FROM conversations WHERE id = ?1 LIMIT 1;",
                     params![conversation_id],
                 )
                 .await?;
-            if let Some(row) = meta_rows.next().await? {
-                let archived: i64 = row.get(0)?;
-                if archived != 0 {
-                    return Err(HistoryError::archived("Conversation is archived"));
-                }
+            let Some(row) = rows.next().await? else {
+                return Err(HistoryError::not_found("Conversation not found"));
+            };
+            let archived: i64 = row.get(0)?;
+            if archived == 0 {
+                return Err(HistoryError::invalid_input("Conversation is not deleted"));
             }
 
-            // Ensure the conversation row exists even if the frontend sent an ID we haven't seen yet.
-            conn.execute(
-                "INSERT OR IGNORE INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count)\nVALUES (?1, '新对话', 0, ?2, ?2, ?2, 0, 0);",
-                params![conversation_id, now],
+            let active_id = self.get_active_conversation_id_from_conn(&conn).await?;
+            let active_valid = match active_id.as_deref() {
+                Some(id) => {
+                    let mut valid_rows = conn
+                        .query(
+                            "SELECT 1 FROM conversations WHERE id = ?1 AND archived = 0 LIMIT 1;",
+                            params![id],
+                        )
+                        .await?;
+                    valid_rows.next().await?.is_some()
+                }
+                None => false,
+            };
+
+            let tx = conn.transaction().await?;
+            tx.execute(
+                "UPDATE conversations SET archived = 0, archived_at_ms = NULL WHERE id = ?1;",
+                params![conversation_id],
             )
             .await?;
+            let next_active_id = match active_id {
+                Some(id) if active_valid && !set_active => id,
+                _ => {
+                    tx.execute(
+                        "INSERT INTO app_state (key, value) VALUES (?1, ?2)\nON CONFLICT(key) DO UPDATE SET value = excluded.value;",
+                        params![APP_STATE_ACTIVE_CONVERSATION_ID, conversation_id],
+                    )
+                    .await?;
+                    conversation_id.to_string()
+                }
+            };
+            tx.commit().await?;
+            Ok(next_active_id)
+        })
+        .await?;
 
-            let tx = conn.transaction().await?;
-            let non_system: Vec<&ChatMessage> =
-                messages.iter().filter(|m| m.role != "system").collect();
-            let all_have_seq = non_system.iter().all(|m| m.seq.is_some());
+        let conversations = self.list_conversations().await?;
+        Ok(HistoryBootstrap {
+            active_conversation_id,
+            conversations,
+        })
+    }
 
-            let delete_stmt = tx
-                .prepare("DELETE FROM messages WHERE conversation_id = ?1 AND seq > ?2;")
-                .await?;
-            if all_have_seq {
-                if let Some(truncate_after_seq) = truncate_after_seq {
-                    delete_stmt
-                        .execute(params![conversation_id, truncate_after_seq as i64])
-                        .await?;
+    /// Permanently delete conversations from the trash (all of it when `conversation_ids` is
+    /// `None`) with their messages and answer variants. Returns how many were purged.
+    ///
+    /// Conversations that are not deleted are left alone.
+    pub(crate) async fn purge_conversations(
+        &self,
+        conversation_ids: Option<&[String]>,
+    ) -> Result<u64, HistoryError> {
+        match conversation_ids {
+            None => self.purge_archived("1 = 1", Vec::new()).await,
+            Some([]) => Ok(0),
+            Some(ids) => {
+                let placeholders = (1..=ids.len())
+                    .map(|i| format!("?{i}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let params = ids.iter().map(|id| Value::from(id.trim())).collect();
+                self.purge_archived(&format!("id IN ({placeholders})"), params)
+                    .await
+            }
+        }
+    }
+
+    /// Purge conversations deleted longer ago than the configured retention period.
+    pub(crate) async fn purge_expired_trash(&self) -> Result<u64, HistoryError> {
+        let retention_days = config::load_history_trash_retention_days() as u64;
+        if retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now_ms().saturating_sub(retention_days * DAY_MS);
+        self.purge_archived(
+            "archived_at_ms IS NOT NULL AND archived_at_ms < ?1",
+            vec![Value::from(cutoff as i64)],
+        )
+        .await
+    }
+
+    /// Purge expired trash now and then every `TRASH_PURGE_INTERVAL` while the app runs.
+    pub(crate) fn spawn_trash_purge_task(&self) {
+        let store = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut ticker = tokio::time::interval(TRASH_PURGE_INTERVAL);
+            loop {
+                ticker.tick().await;
+                match store.purge_expired_trash().await {
+                    Ok(0) => {}
+                    Ok(purged) => log::info!("History: purged {} deleted conversations", purged),
+                    Err(err) => log::warn!("History: trash purge failed: {}", err),
                 }
-            } else {
-                let desired_len = non_system.len() as i64;
-                delete_stmt
-                    .execute(params![conversation_id, desired_len])
-                    .await?;
             }
+        });
+    }
 
-            // Upsert messages by stable (conversation_id:seq) key.
+    /// Delete archived conversations matching `filter` (SQL over `conversations`, bound to
+    /// `params`) and everything stored under them.
+    async fn purge_archived(&self, filter: &str, params: Vec<Value>) -> Result<u64, HistoryError> {
+        let selected = format!("SELECT id FROM conversations WHERE archived = 1 AND ({filter})");
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let tx = conn.transaction().await?;
+            // Explicit rather than relying on ON DELETE CASCADE (foreign keys may be off).
+            // `usage_ledger` and `audit_log` are history of their own and are left alone.
+            for table in ["message_variants", "messages"] {
+                tx.execute(
+                    &format!("DELETE FROM {table} WHERE conversation_id IN ({selected});"),
+                    params.clone(),
+                )
+                .await?;
+            }
+            let purged = tx
+                .execute(
+                    &format!("DELETE FROM conversations WHERE archived = 1 AND ({filter});"),
+                    params.clone(),
+                )
+                .await?;
+            tx.commit().await?;
+            Ok(purged)
+        })
+        .await
+    }
+
+    pub(crate) async fn sync_from_frontend_messages(
+        &self,
+        conversation_id: &str,
+        messages: &[ChatMessage],
+        truncate_after_seq: Option<u32>,
+    ) -> Result<(), HistoryError> {
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let now = now_ms() as i64;
+
+            let mut meta_rows = conn
+                .query(
+                    "SELECT archived FROM conversations WHERE id = ?1 LIMIT 1;",
+                    params![conversation_id],
+                )
+                .await?;
+            if let Some(row) = meta_rows.next().await? {
+                let archived: i64 = row.get(0)?;
+                if archived != 0 {
+                    return Err(HistoryError::archived("Conversation is archived"));
+                }
+            }
+
+            // Ensure the conversation row exists even if the frontend sent an ID we haven't seen yet.
+            conn.execute(
+                "INSERT OR IGNORE INTO conversations (id, title, title_auto, created_at_ms, updated_at_ms, last_seen_at_ms, archived, message_count)\nVALUES (?1, '新对话', 0, ?2, ?2, ?2, 0, 0);",
+                params![conversation_id, now],
+            )
+            .await?;
+
+            let tx = conn.transaction().await?;
+            let non_system: Vec<&ChatMessage> =
+                messages.iter().filter(|m| m.role != "system").collect();
+            let all_have_seq = non_system.iter().all(|m| m.seq.is_some());
+
+            let keep_upto = if all_have_seq {
+                truncate_after_seq.map(|seq| seq as i64)
+            } else {
+                Some(non_system.len() as i64)
+            };
+            if let Some(keep_upto) = keep_upto {
+                tx.execute(
+                    "DELETE FROM messages WHERE conversation_id = ?1 AND seq > ?2;",
+                    params![conversation_id, keep_upto],
+                )
+                .await?;
+                // Dropped turns take their stored answers with them.
+                tx.execute(
+                    "DELETE FROM message_variants WHERE conversation_id = ?1 AND seq > ?2;",
+                    params![conversation_id, keep_upto],
+                )
+                .await?;
+            }
+
+            // Upsert messages by stable (conversation_id:seq) key.
             // Batch insert to reduce remote RTT (important for Turso/libSQL remote mode).
             const UPSERT_CHUNK_SIZE: usize = 120;
             let mut to_upsert: Vec<(i64, &ChatMessage)> = if all_have_seq {
@@ -1062,7 +2040,7 @@ impl HistoryStore {
 
             fn build_messages_upsert_sql(row_count: usize) -> String {
                 let mut sql = String::from(
-                    "INSERT INTO messages (id, conversation_id, seq, role, content, reasoning, created_at_ms)\nVALUES ",
+                    "INSERT INTO messages (id, conversation_id, seq, role, content, reasoning, tool_calls, attachments, created_at_ms)\nVALUES ",
                 );
                 let mut param_index = 1;
                 for row in 0..row_count {
@@ -1070,18 +2048,20 @@ impl HistoryStore {
                         sql.push(',');
                     }
                     sql.push_str(&format!(
-                        "(?{}, ?{}, ?{}, ?{}, ?{}, NULL, ?{})",
+                        "(?{}, ?{}, ?{}, ?{}, ?{}, NULL, ?{}, ?{}, ?{})",
                         param_index,
                         param_index + 1,
                         param_index + 2,
                         param_index + 3,
                         param_index + 4,
-                        param_index + 5
+                        param_index + 5,
+                        param_index + 6,
+                        param_index + 7
                     ));
-                    param_index += 6;
+                    param_index += 8;
                 }
                 sql.push_str(
-                    "\nON CONFLICT(id) DO UPDATE SET\n  role = excluded.role,\n  content = excluded.content,\n  reasoning = CASE\n    WHEN excluded.role = 'assistant' THEN COALESCE(messages.reasoning, excluded.reasoning)\n    ELSE NULL\n  END;",
+                    "\nON CONFLICT(id) DO UPDATE SET\n  role = excluded.role,\n  content = excluded.content,\n  reasoning = CASE\n    WHEN excluded.role = 'assistant' THEN COALESCE(messages.reasoning, excluded.reasoning)\n    ELSE NULL\n  END,\n  tool_calls = CASE\n    WHEN excluded.role = 'assistant' THEN COALESCE(messages.tool_calls, excluded.tool_calls)\n    ELSE NULL\n  END,\n  attachments = CASE\n    WHEN excluded.role = 'user' THEN COALESCE(excluded.attachments, messages.attachments)\n    ELSE NULL\n  END;",
                 );
                 sql
             }
@@ -1091,7 +2071,7 @@ impl HistoryStore {
                 let chunk_end = (chunk_start + UPSERT_CHUNK_SIZE).min(to_upsert.len());
                 let chunk = &to_upsert[chunk_start..chunk_end];
 
-                let mut params: Vec<Value> = Vec::with_capacity(chunk.len() * 6);
+                let mut params: Vec<Value> = Vec::with_capacity(chunk.len() * 8);
 
                 for (seq, m) in chunk.iter() {
                     let seq = *seq;
@@ -1102,6 +2082,16 @@ impl HistoryStore {
                     params.push(Value::from(seq));
                     params.push(Value::from(m.role.as_str()));
                     params.push(Value::from(m.content.as_str()));
+                    params.push(
+                        encode_tool_calls(&m.tool_calls)
+                            .map(Value::from)
+                            .unwrap_or(Value::Null),
+                    );
+                    params.push(
+                        encode_attachments(&m.attachments)
+                            .map(Value::from)
+                            .unwrap_or(Value::Null),
+                    );
                     params.push(Value::from(now));
                 }
 
@@ -1147,61 +2137,810 @@ impl HistoryStore {
 
             Ok(())
         })
-        .await
+        .await
+    }
+
+    pub(crate) async fn append_assistant_message(
+        &self,
+        conversation_id: &str,
+        output: ChatOutput,
+        status: MessageStatus,
+    ) -> Result<(), HistoryError> {
+        let conversation_id = conversation_id.to_string();
+        let content = output.text;
+        let reasoning = Some(output.reasoning.trim().to_string()).filter(|r| !r.is_empty());
+        let tool_calls = encode_tool_calls(&output.tool_calls);
+        let model = Some(output.model).filter(|m| !m.trim().is_empty());
+        let usage = output.usage;
+        retry_db_locked(|| {
+            let conversation_id = conversation_id.clone();
+            let content = content.clone();
+            let reasoning = reasoning.clone();
+            let tool_calls = tool_calls.clone();
+            let model = model.clone();
+            async move {
+                let _write = self.write_permit().await?;
+                let conn = self.connect().await?;
+
+                let tx = conn.transaction().await?;
+
+                let mut meta_rows = tx
+                    .query(
+                        "SELECT archived FROM conversations WHERE id = ?1 LIMIT 1;",
+                        params![conversation_id.as_str()],
+                    )
+                    .await?;
+                let Some(meta) = meta_rows.next().await? else {
+                    return Err(HistoryError::not_found("Conversation not found"));
+                };
+                let archived: i64 = meta.get(0)?;
+                if archived != 0 {
+                    return Err(HistoryError::archived("Conversation is archived"));
+                }
+
+                let now = now_ms() as i64;
+                Self::record_usage(
+                    &tx,
+                    &conversation_id,
+                    model.as_deref(),
+                    usage.as_ref(),
+                    now,
+                )
+                .await?;
+                tx.execute(
+                    "WITH next(seq) AS (\n  SELECT COALESCE(MAX(seq), 0) + 1\n    FROM messages\n   WHERE conversation_id = ?1\n)\nINSERT INTO messages (id, conversation_id, seq, role, content, reasoning, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, status, created_at_ms)\nSELECT ?1 || ':' || next.seq, ?1, next.seq, 'assistant', ?2, ?3, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?4\n  FROM next;",
+                    params![
+                        conversation_id.as_str(),
+                        content,
+                        reasoning,
+                        now,
+                        tool_calls,
+                        model,
+                        usage.map(|u| u.prompt_tokens as i64),
+                        usage.map(|u| u.completion_tokens as i64),
+                        usage.map(|u| u.reasoning_tokens as i64),
+                        usage.map(|u| u.cached_tokens as i64),
+                        status.as_str()
+                    ],
+                )
+                .await?;
+
+                tx.execute(
+                    "UPDATE conversations\n   SET updated_at_ms = ?2,\n       message_count = COALESCE(message_count, 0) + 1,\n       last_message_at_ms = ?2,\n       last_role = 'assistant'\n WHERE id = ?1;",
+                    params![conversation_id.as_str(), now],
+                )
+                .await?;
+
+                tx.commit().await?;
+                Ok(())
+            }
+        })
+        .await?;
+
+        self.maybe_spawn_auto_title(&conversation_id).await;
+        Ok(())
+    }
+
+    /// Get ready to answer the assistant turn at `seq` (the last message) again.
+    ///
+    /// Nothing is written: the messages before `seq` are returned as the request context, and
+    /// the new answer is stored with [`Self::append_message_variant`]. A request that fails
+    /// before producing output leaves the turn and its variants untouched.
+    pub(crate) async fn prepare_regenerate(
+        &self,
+        conversation_id: &str,
+        seq: u32,
+    ) -> Result<Vec<ChatMessage>, HistoryError> {
+        let conversation_id = conversation_id.trim();
+        if conversation_id.is_empty() {
+            return Err(HistoryError::invalid_input("conversationId is required"));
+        }
+        if seq < 2 {
+            return Err(HistoryError::invalid_input(
+                "Nothing to regenerate from before this message",
+            ));
+        }
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT c.archived, m.role,\n       (SELECT MAX(seq) FROM messages WHERE conversation_id = ?1)\n  FROM messages m\n  JOIN conversations c ON c.id = m.conversation_id\n WHERE m.conversation_id = ?1 AND m.seq = ?2\n LIMIT 1;",
+                params![conversation_id, seq as i64],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Err(HistoryError::not_found("Message not found"));
+        };
+        let archived: i64 = row.get(0)?;
+        let role: String = row.get(1)?;
+        let last_seq: i64 = row.get(2)?;
+        if archived != 0 {
+            return Err(HistoryError::archived("Conversation is archived"));
+        }
+        if role != "assistant" {
+            return Err(HistoryError::invalid_input(
+                "Only assistant messages can be regenerated",
+            ));
+        }
+        if last_seq != seq as i64 {
+            return Err(HistoryError::invalid_input(
+                "Only the last answer can be regenerated",
+            ));
+        }
+
+        self.context_messages(conversation_id, seq).await
+    }
+
+    /// Get ready to resume the interrupted assistant message at `seq` (the last message).
+    ///
+    /// Returns the conversation up to and including the partial answer; the continuation is
+    /// appended to it with [`Self::extend_assistant_message`].
+    pub(crate) async fn prepare_continue(
+        &self,
+        conversation_id: &str,
+        seq: u32,
+    ) -> Result<Vec<ChatMessage>, HistoryError> {
+        let conversation_id = conversation_id.trim();
+        if conversation_id.is_empty() {
+            return Err(HistoryError::invalid_input("conversationId is required"));
+        }
+
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT c.archived, m.role, m.status,\n       (SELECT MAX(seq) FROM messages WHERE conversation_id = ?1)\n  FROM messages m\n  JOIN conversations c ON c.id = m.conversation_id\n WHERE m.conversation_id = ?1 AND m.seq = ?2\n LIMIT 1;",
+                params![conversation_id, seq as i64],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Err(HistoryError::not_found("Message not found"));
+        };
+        let archived: i64 = row.get(0)?;
+        let role: String = row.get(1)?;
+        let status = MessageStatus::parse(&row.get::<String>(2)?);
+        let last_seq: i64 = row.get(3)?;
+        if archived != 0 {
+            return Err(HistoryError::archived("Conversation is archived"));
+        }
+        if role != "assistant" || status == MessageStatus::Complete {
+            return Err(HistoryError::invalid_input(
+                "Only interrupted answers can be continued",
+            ));
+        }
+        if last_seq != seq as i64 {
+            return Err(HistoryError::invalid_input(
+                "Only the last message can be continued",
+            ));
+        }
+
+        self.context_messages(conversation_id, seq + 1).await
+    }
+
+    /// Messages before `before_seq`, as sent to the model.
+    async fn context_messages(
+        &self,
+        conversation_id: &str,
+        before_seq: u32,
+    ) -> Result<Vec<ChatMessage>, HistoryError> {
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT seq, role, content, tool_calls, attachments, pinned\n   FROM messages\n  WHERE conversation_id = ?1 AND seq < ?2\n  ORDER BY seq ASC;",
+                params![conversation_id, before_seq as i64],
+            )
+            .await?;
+        let mut messages = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let seq: i64 = row.get(0)?;
+            messages.push(ChatMessage {
+                seq: Some(seq.max(0) as u32),
+                role: row.get(1)?,
+                content: row.get(2)?,
+                tool_calls: decode_tool_calls(row.get(3).ok()),
+                pinned: row.get::<i64>(5).unwrap_or(0) != 0,
+                attachments: decode_attachments(row.get(4).ok()),
+            });
+        }
+        Ok(messages)
+    }
+
+    /// Fill in what the frontend does not send back for messages that carry a `seq`: the
+    /// persisted tool rounds of assistant messages (so the model sees them again) and pins.
+    pub(crate) async fn restore_from_history(
+        &self,
+        conversation_id: &str,
+        messages: &mut [ChatMessage],
+    ) -> Result<(), HistoryError> {
+        if !messages.iter().any(|m| m.seq.is_some()) {
+            return Ok(());
+        }
+
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT seq, role, tool_calls, pinned\n   FROM messages\n  WHERE conversation_id = ?1 AND (tool_calls IS NOT NULL OR pinned != 0);",
+                params![conversation_id],
+            )
+            .await?;
+        let mut by_seq = HashMap::new();
+        while let Some(row) = rows.next().await? {
+            let seq: i64 = row.get(0)?;
+            let role: String = row.get(1)?;
+            let pinned: i64 = row.get(3).unwrap_or(0);
+            by_seq.insert(seq, (role, decode_tool_calls(row.get(2).ok()), pinned != 0));
+        }
+        for message in messages.iter_mut() {
+            let Some((role, records, pinned)) =
+                message.seq.and_then(|seq| by_seq.remove(&(seq as i64)))
+            else {
+                continue;
+            };
+            // A seq the frontend reassigned to a different message is not the stored one.
+            if role != message.role {
+                continue;
+            }
+            if message.role == "assistant" && message.tool_calls.is_empty() {
+                message.tool_calls = records;
+            }
+            message.pinned |= pinned;
+        }
+        Ok(())
+    }
+
+    /// Append a continuation to the assistant message at `seq` (and its active variant).
+    pub(crate) async fn extend_assistant_message(
+        &self,
+        conversation_id: &str,
+        seq: u32,
+        output: ChatOutput,
+        status: MessageStatus,
+    ) -> Result<(), HistoryError> {
+        let message_id = format!("{conversation_id}:{seq}");
+        let model = Some(output.model.clone()).filter(|m| !m.trim().is_empty());
+        retry_db_locked(|| {
+            let message_id = message_id.clone();
+            let output = output.clone();
+            let model = model.clone();
+            async move {
+                let _write = self.write_permit().await?;
+                let conn = self.connect().await?;
+                let tx = conn.transaction().await?;
+
+                let mut rows = tx
+                    .query(
+                        "SELECT c.archived, m.role, m.content, m.reasoning, m.tool_calls, m.prompt_tokens, m.completion_tokens, m.reasoning_tokens, m.cached_tokens\n  FROM messages m\n  JOIN conversations c ON c.id = m.conversation_id\n WHERE m.id = ?1\n LIMIT 1;",
+                        params![message_id.as_str()],
+                    )
+                    .await?;
+                let Some(row) = rows.next().await? else {
+                    return Err(HistoryError::not_found("Message not found"));
+                };
+                let archived: i64 = row.get(0)?;
+                let role: String = row.get(1)?;
+                if archived != 0 {
+                    return Err(HistoryError::archived("Conversation is archived"));
+                }
+                if role != "assistant" {
+                    return Err(HistoryError::invalid_input(
+                        "Only assistant messages can be continued",
+                    ));
+                }
+
+                let mut content: String = row.get(2)?;
+                content.push_str(&output.text);
+                let mut reasoning: String = row.get::<String>(3).unwrap_or_default();
+                let more_reasoning = output.reasoning.trim();
+                if !more_reasoning.is_empty() {
+                    if !reasoning.is_empty() {
+                        reasoning.push('\n');
+                    }
+                    reasoning.push_str(more_reasoning);
+                }
+                let reasoning = Some(reasoning).filter(|r| !r.trim().is_empty());
+                let mut tool_calls = decode_tool_calls(row.get(4).ok());
+                tool_calls.extend(output.tool_calls);
+                let tool_calls = encode_tool_calls(&tool_calls);
+                let mut usage = row.get::<i64>(5).ok().map(|prompt_tokens| TokenUsage {
+                    prompt_tokens: prompt_tokens.max(0) as u32,
+                    completion_tokens: row.get::<i64>(6).unwrap_or(0).max(0) as u32,
+                    reasoning_tokens: row.get::<i64>(7).unwrap_or(0).max(0) as u32,
+                    cached_tokens: row.get::<i64>(8).unwrap_or(0).max(0) as u32,
+                });
+                if let Some(more) = output.usage.as_ref() {
+                    usage.get_or_insert_with(TokenUsage::default).add(more);
+                }
+
+                // Mirror into the active variant too (a no-op when the turn has none).
+                for sql in [
+                    "UPDATE messages\n   SET content = ?2, reasoning = ?3, tool_calls = ?4, model = COALESCE(?5, model),\n       prompt_tokens = ?6, completion_tokens = ?7, reasoning_tokens = ?8, cached_tokens = ?9, status = ?10\n WHERE id = ?1;",
+                    "UPDATE message_variants\n   SET content = ?2, reasoning = ?3, tool_calls = ?4, model = COALESCE(?5, model),\n       prompt_tokens = ?6, completion_tokens = ?7, reasoning_tokens = ?8, cached_tokens = ?9, status = ?10\n WHERE id = (SELECT id || ':' || active_variant FROM messages WHERE id = ?1);",
+                ] {
+                    tx.execute(
+                        sql,
+                        params![
+                            message_id.as_str(),
+                            content.as_str(),
+                            reasoning.clone(),
+                            tool_calls.clone(),
+                            model.clone(),
+                            usage.map(|u| u.prompt_tokens as i64),
+                            usage.map(|u| u.completion_tokens as i64),
+                            usage.map(|u| u.reasoning_tokens as i64),
+                            usage.map(|u| u.cached_tokens as i64),
+                            status.as_str()
+                        ],
+                    )
+                    .await?;
+                }
+
+                // Only the continuation's own usage: the earlier part is already in the ledger.
+                let now = now_ms() as i64;
+                Self::record_usage(
+                    &tx,
+                    conversation_id,
+                    model.as_deref(),
+                    output.usage.as_ref(),
+                    now,
+                )
+                .await?;
+
+                tx.execute(
+                    "UPDATE conversations SET updated_at_ms = ?2, last_message_at_ms = ?2 WHERE id = ?1;",
+                    params![conversation_id, now],
+                )
+                .await?;
+
+                tx.commit().await?;
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Store the answer as variant 0 the first time its turn is regenerated (no-op afterwards).
+    async fn snapshot_active_variant(
+        tx: &libsql::Transaction,
+        message_id: &str,
+    ) -> Result<(), HistoryError> {
+        tx.execute(
+            "INSERT OR IGNORE INTO message_variants (id, conversation_id, seq, variant, content, reasoning, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, status, created_at_ms)\nSELECT id || ':' || active_variant, conversation_id, seq, active_variant, content, reasoning, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, status, created_at_ms\n  FROM messages\n WHERE id = ?1;",
+            params![message_id],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Add one reply's reported usage to the ledger (no-op without usage).
+    async fn record_usage(
+        tx: &libsql::Transaction,
+        conversation_id: &str,
+        model: Option<&str>,
+        usage: Option<&TokenUsage>,
+        at_ms: i64,
+    ) -> Result<(), HistoryError> {
+        let Some(usage) = usage else {
+            return Ok(());
+        };
+        tx.execute(
+            "INSERT INTO usage_ledger (id, conversation_id, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, created_at_ms)\nVALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);",
+            params![
+                new_id("usage"),
+                conversation_id,
+                model,
+                usage.prompt_tokens as i64,
+                usage.completion_tokens as i64,
+                usage.reasoning_tokens as i64,
+                usage.cached_tokens as i64,
+                at_ms
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Add a regenerated answer for the assistant turn at `seq` and make it the active variant.
+    pub(crate) async fn append_message_variant(
+        &self,
+        conversation_id: &str,
+        seq: u32,
+        output: ChatOutput,
+        status: MessageStatus,
+    ) -> Result<(), HistoryError> {
+        let message_id = format!("{conversation_id}:{seq}");
+        let content = output.text;
+        let reasoning = Some(output.reasoning.trim().to_string()).filter(|r| !r.is_empty());
+        let tool_calls = encode_tool_calls(&output.tool_calls);
+        let model = Some(output.model).filter(|m| !m.trim().is_empty());
+        let usage = output.usage;
+        retry_db_locked(|| {
+            let message_id = message_id.clone();
+            let content = content.clone();
+            let reasoning = reasoning.clone();
+            let tool_calls = tool_calls.clone();
+            let model = model.clone();
+            async move {
+                let _write = self.write_permit().await?;
+                let conn = self.connect().await?;
+                let tx = conn.transaction().await?;
+
+                let mut rows = tx
+                    .query(
+                        "SELECT c.archived, m.role\n  FROM messages m\n  JOIN conversations c ON c.id = m.conversation_id\n WHERE m.id = ?1\n LIMIT 1;",
+                        params![message_id.as_str()],
+                    )
+                    .await?;
+                // The turn was edited away or its conversation deleted while generating.
+                let Some(row) = rows.next().await? else {
+                    return Err(HistoryError::not_found("Message not found"));
+                };
+                let archived: i64 = row.get(0)?;
+                let role: String = row.get(1)?;
+                if archived != 0 {
+                    return Err(HistoryError::archived("Conversation is archived"));
+                }
+                if role != "assistant" {
+                    return Err(HistoryError::invalid_input(
+                        "Only assistant messages can have variants",
+                    ));
+                }
+
+                Self::snapshot_active_variant(&tx, &message_id).await?;
+                // Only now that a new answer exists: it replaces whatever followed the turn.
+                for table in ["messages", "message_variants"] {
+                    tx.execute(
+                        &format!("DELETE FROM {table} WHERE conversation_id = ?1 AND seq > ?2;"),
+                        params![conversation_id, seq as i64],
+                    )
+                    .await?;
+                }
+                let mut next_rows = tx
+                    .query(
+                        "SELECT COALESCE(MAX(variant), -1) + 1 FROM message_variants WHERE conversation_id = ?1 AND seq = ?2;",
+                        params![conversation_id, seq as i64],
+                    )
+                    .await?;
+                let variant: i64 = match next_rows.next().await? {
+                    Some(row) => row.get(0)?,
+                    None => 0,
+                };
+
+                let now = now_ms() as i64;
+                Self::record_usage(&tx, conversation_id, model.as_deref(), usage.as_ref(), now)
+                    .await?;
+                tx.execute(
+                    "INSERT INTO message_variants (id, conversation_id, seq, variant, content, reasoning, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, created_at_ms, status)\nVALUES (?1 || ':' || ?4, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14);",
+                    params![
+                        message_id.as_str(),
+                        conversation_id,
+                        seq as i64,
+                        variant,
+                        content,
+                        reasoning,
+                        tool_calls,
+                        model,
+                        usage.map(|u| u.prompt_tokens as i64),
+                        usage.map(|u| u.completion_tokens as i64),
+                        usage.map(|u| u.reasoning_tokens as i64),
+                        usage.map(|u| u.cached_tokens as i64),
+                        now,
+                        status.as_str()
+                    ],
+                )
+                .await?;
+                Self::activate_variant(&tx, &message_id, variant).await?;
+
+                tx.execute(
+                    "UPDATE conversations\n   SET updated_at_ms = ?2,\n       message_count = ?3,\n       last_message_at_ms = ?2,\n       last_role = 'assistant'\n WHERE id = ?1;",
+                    params![conversation_id, now, seq as i64],
+                )
+                .await?;
+
+                tx.commit().await?;
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Mirror a stored variant into its message row; `false` when the variant does not exist.
+    async fn activate_variant(
+        tx: &libsql::Transaction,
+        message_id: &str,
+        variant: i64,
+    ) -> Result<bool, HistoryError> {
+        let changed = tx
+            .execute(
+                "UPDATE messages\n   SET (content, reasoning, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, status, created_at_ms) = (\n         SELECT content, reasoning, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, status, created_at_ms\n           FROM message_variants\n          WHERE id = ?1 || ':' || ?2\n       ),\n       active_variant = ?2\n WHERE id = ?1\n   AND EXISTS (SELECT 1 FROM message_variants WHERE id = ?1 || ':' || ?2);",
+                params![message_id, variant],
+            )
+            .await?;
+        Ok(changed > 0)
+    }
+
+    /// Stored answers of the assistant turn at `seq` (just the current one if never regenerated).
+    pub(crate) async fn list_message_variants(
+        &self,
+        conversation_id: &str,
+        seq: u32,
+    ) -> Result<Vec<MessageVariant>, HistoryError> {
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT m.active_variant, v.variant, v.content, v.reasoning, v.tool_calls, v.model, v.prompt_tokens, v.completion_tokens, v.reasoning_tokens, v.cached_tokens, v.created_at_ms, v.status\n   FROM message_variants v\n   JOIN messages m ON m.conversation_id = v.conversation_id AND m.seq = v.seq\n  WHERE v.conversation_id = ?1 AND v.seq = ?2\n  ORDER BY v.variant ASC;",
+                params![conversation_id, seq as i64],
+            )
+            .await?;
+        let mut variants = Vec::new();
+        while let Some(row) = rows.next().await? {
+            variants.push(variant_from_row(&row)?);
+        }
+        if !variants.is_empty() {
+            return Ok(variants);
+        }
+
+        let mut rows = conn
+            .query(
+                "SELECT active_variant, active_variant, content, reasoning, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, created_at_ms, status\n   FROM messages\n  WHERE conversation_id = ?1 AND seq = ?2 AND role = 'assistant';",
+                params![conversation_id, seq as i64],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Err(HistoryError::not_found("Message not found"));
+        };
+        Ok(vec![variant_from_row(&row)?])
+    }
+
+    /// Pin or unpin the message at `seq`; pinned messages survive context trimming.
+    pub(crate) async fn set_message_pinned(
+        &self,
+        conversation_id: &str,
+        seq: u32,
+        pinned: bool,
+    ) -> Result<(), HistoryError> {
+        let message_id = format!("{conversation_id}:{seq}");
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let updated = conn
+                .execute(
+                    "UPDATE messages SET pinned = ?2 WHERE id = ?1;",
+                    params![message_id.as_str(), pinned as i64],
+                )
+                .await?;
+            if updated == 0 {
+                return Err(HistoryError::not_found("Message not found"));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Show another stored answer for the assistant turn at `seq`.
+    pub(crate) async fn select_message_variant(
+        &self,
+        conversation_id: &str,
+        seq: u32,
+        variant: u32,
+    ) -> Result<ConversationMessage, HistoryError> {
+        let message_id = format!("{conversation_id}:{seq}");
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let tx = conn.transaction().await?;
+
+            let mut meta_rows = tx
+                .query(
+                    "SELECT archived FROM conversations WHERE id = ?1 LIMIT 1;",
+                    params![conversation_id],
+                )
+                .await?;
+            let Some(meta) = meta_rows.next().await? else {
+                return Err(HistoryError::not_found("Conversation not found"));
+            };
+            let archived: i64 = meta.get(0)?;
+            if archived != 0 {
+                return Err(HistoryError::archived("Conversation is archived"));
+            }
+
+            if !Self::activate_variant(&tx, &message_id, variant as i64).await? {
+                return Err(HistoryError::not_found("Variant not found"));
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+        .await?;
+
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT id, seq, role, content, reasoning, created_at_ms, tool_calls, model, prompt_tokens, completion_tokens, reasoning_tokens, cached_tokens, attachments, active_variant,\n        (SELECT COUNT(*) FROM message_variants v WHERE v.conversation_id = messages.conversation_id AND v.seq = messages.seq), status, pinned\n   FROM messages\n  WHERE id = ?1;",
+                params![message_id.as_str()],
+            )
+            .await?;
+        let row = rows
+            .next()
+            .await?
+            .ok_or_else(|| HistoryError::not_found("Message not found"))?;
+        message_from_row(&row, conversation_id)
     }
 
-    pub(crate) async fn append_assistant_message(
+    /// Token usage summed over every reply ever generated in one conversation.
+    pub(crate) async fn conversation_usage(
         &self,
         conversation_id: &str,
-        content: String,
-        reasoning: Option<String>,
-    ) -> Result<(), HistoryError> {
-        let conversation_id = conversation_id.to_string();
+    ) -> Result<UsageTotals, HistoryError> {
+        let conversation_id = conversation_id.trim();
+        if conversation_id.is_empty() {
+            return Err(HistoryError::invalid_input("conversationId is required"));
+        }
+
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT ?1,\n       COUNT(*),\n       COALESCE(SUM(prompt_tokens), 0),\n       COALESCE(SUM(completion_tokens), 0),\n       COALESCE(SUM(reasoning_tokens), 0),\n       COALESCE(SUM(cached_tokens), 0)\n  FROM usage_ledger\n WHERE conversation_id = ?1;",
+                params![conversation_id],
+            )
+            .await?;
+        let row = rows
+            .next()
+            .await?
+            .ok_or_else(|| HistoryError::internal("Usage aggregate returned no row"))?;
+        usage_totals_from_row(&row)
+    }
+
+    /// Token usage per local calendar day (`YYYY-MM-DD`), oldest first.
+    ///
+    /// Read from the usage ledger: replaced answers and purged conversations still count.
+    pub(crate) async fn usage_by_day(
+        &self,
+        since_ms: Option<u64>,
+        until_ms: Option<u64>,
+        utc_offset_minutes: Option<i32>,
+    ) -> Result<Vec<UsageTotals>, HistoryError> {
+        let (since_ms, until_ms) = usage_range(since_ms, until_ms);
+        let offset_ms = utc_offset_minutes.unwrap_or(0) as i64 * 60_000;
+
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT strftime('%Y-%m-%d', (created_at_ms + ?3) / 1000, 'unixepoch') AS day,\n       COUNT(*),\n       COALESCE(SUM(prompt_tokens), 0),\n       COALESCE(SUM(completion_tokens), 0),\n       COALESCE(SUM(reasoning_tokens), 0),\n       COALESCE(SUM(cached_tokens), 0)\n  FROM usage_ledger\n WHERE created_at_ms >= ?1 AND created_at_ms < ?2\n GROUP BY day\n ORDER BY day ASC;",
+                params![since_ms, until_ms, offset_ms],
+            )
+            .await?;
+
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().await? {
+            out.push(usage_totals_from_row(&row)?);
+        }
+        Ok(out)
+    }
+
+    /// Token usage per model, highest total first.
+    ///
+    /// Read from the usage ledger: replaced answers and purged conversations still count.
+    pub(crate) async fn usage_by_model(
+        &self,
+        since_ms: Option<u64>,
+        until_ms: Option<u64>,
+    ) -> Result<Vec<UsageTotals>, HistoryError> {
+        let (since_ms, until_ms) = usage_range(since_ms, until_ms);
+
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT COALESCE(model, '') AS model_key,\n       COUNT(*),\n       COALESCE(SUM(prompt_tokens), 0),\n       COALESCE(SUM(completion_tokens), 0),\n       COALESCE(SUM(reasoning_tokens), 0),\n       COALESCE(SUM(cached_tokens), 0)\n  FROM usage_ledger\n WHERE created_at_ms >= ?1 AND created_at_ms < ?2\n GROUP BY model_key\n ORDER BY SUM(prompt_tokens) + SUM(completion_tokens) DESC;",
+                params![since_ms, until_ms],
+            )
+            .await?;
+
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().await? {
+            out.push(usage_totals_from_row(&row)?);
+        }
+        Ok(out)
+    }
+
+    /// Append one entry to the audit log (`id` / `created_at_ms` are assigned here).
+    pub(crate) async fn append_audit(&self, entry: AuditEntry) -> Result<(), HistoryError> {
         retry_db_locked(|| {
-            let conversation_id = conversation_id.clone();
-            let content = content.clone();
-            let reasoning = reasoning.clone();
+            let entry = entry.clone();
             async move {
                 let _write = self.write_permit().await?;
                 let conn = self.connect().await?;
+                conn.execute(
+                    "INSERT INTO audit_log (id, created_at_ms, kind, tool, conversation_id, request_id, app_name, window_title, chars, provider, model, is_error)\nVALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12);",
+                    params![
+                        new_id("audit"),
+                        now_ms() as i64,
+                        entry.kind.as_str(),
+                        entry.tool,
+                        entry.conversation_id,
+                        entry.request_id,
+                        entry.app_name,
+                        entry.window_title,
+                        entry.chars.min(i64::MAX as u64) as i64,
+                        entry.provider,
+                        entry.model,
+                        entry.is_error as i64
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+        })
+        .await
+    }
 
-                let tx = conn.transaction().await?;
+    /// Audit entries in `[since, until)`, newest first, optionally narrowed to one kind or conversation.
+    pub(crate) async fn query_audit(
+        &self,
+        kind: Option<AuditKind>,
+        conversation_id: Option<&str>,
+        since_ms: Option<u64>,
+        until_ms: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<AuditEntry>, HistoryError> {
+        let (since_ms, until_ms) = usage_range(since_ms, until_ms);
+        let conversation_id = conversation_id.map(str::trim).filter(|id| !id.is_empty());
+        let limit = limit
+            .unwrap_or(DEFAULT_AUDIT_LIMIT)
+            .clamp(1, MAX_AUDIT_LIMIT) as i64;
 
-                let mut meta_rows = tx
-                    .query(
-                        "SELECT archived FROM conversations WHERE id = ?1 LIMIT 1;",
-                        params![conversation_id.as_str()],
-                    )
-                    .await?;
-                let Some(meta) = meta_rows.next().await? else {
-                    return Err(HistoryError::not_found("Conversation not found"));
-                };
-                let archived: i64 = meta.get(0)?;
-                if archived != 0 {
-                    return Err(HistoryError::archived("Conversation is archived"));
-                }
+        let conn = self.connect().await?;
+        let mut rows = conn
+            .query(
+                "SELECT id, created_at_ms, kind, tool, conversation_id, request_id, app_name, window_title, chars, provider, model, is_error\n  FROM audit_log\n WHERE created_at_ms >= ?1 AND created_at_ms < ?2\n   AND (?3 IS NULL OR kind = ?3)\n   AND (?4 IS NULL OR conversation_id = ?4)\n ORDER BY created_at_ms DESC\n LIMIT ?5;",
+                params![
+                    since_ms,
+                    until_ms,
+                    kind.map(AuditKind::as_str),
+                    conversation_id,
+                    limit
+                ],
+            )
+            .await?;
 
-                let now = now_ms() as i64;
-                tx.execute(
-                    "WITH next(seq) AS (\n  SELECT COALESCE(MAX(seq), 0) + 1\n    FROM messages\n   WHERE conversation_id = ?1\n)\nINSERT INTO messages (id, conversation_id, seq, role, content, reasoning, created_at_ms)\nSELECT ?1 || ':' || next.seq, ?1, next.seq, 'assistant', ?2, ?3, ?4\n  FROM next;",
-                    params![conversation_id.as_str(), content, reasoning, now],
-                )
-                .await?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let kind: String = row.get(2)?;
+            let Some(kind) = AuditKind::parse(&kind) else {
+                continue;
+            };
+            let created_at_ms: i64 = row.get(1)?;
+            let chars: i64 = row.get(8)?;
+            let is_error: i64 = row.get(11)?;
+            out.push(AuditEntry {
+                id: row.get(0)?,
+                created_at_ms: created_at_ms.max(0) as u64,
+                kind,
+                tool: row.get(3)?,
+                conversation_id: row.get(4)?,
+                request_id: row.get(5)?,
+                app_name: row.get(6)?,
+                window_title: row.get(7)?,
+                chars: chars.max(0) as u64,
+                provider: row.get(9)?,
+                model: row.get(10)?,
+                is_error: is_error != 0,
+            });
+        }
+        Ok(out)
+    }
 
-                tx.execute(
-                    "UPDATE conversations\n   SET updated_at_ms = ?2,\n       message_count = COALESCE(message_count, 0) + 1,\n       last_message_at_ms = ?2,\n       last_role = 'assistant'\n WHERE id = ?1;",
-                    params![conversation_id.as_str(), now],
+    /// Delete audit entries older than `before_ms` (all of them when `None`); returns the count.
+    pub(crate) async fn purge_audit(&self, before_ms: Option<u64>) -> Result<u64, HistoryError> {
+        let before_ms = before_ms.unwrap_or(u64::MAX).min(i64::MAX as u64) as i64;
+        retry_db_locked(|| async {
+            let _write = self.write_permit().await?;
+            let conn = self.connect().await?;
+            let deleted = conn
+                .execute(
+                    "DELETE FROM audit_log WHERE created_at_ms < ?1;",
+                    params![before_ms],
                 )
                 .await?;
-
-                tx.commit().await?;
-                Ok(())
-            }
+            Ok(deleted)
         })
-        .await?;
-
-        self.maybe_spawn_auto_title(&conversation_id).await;
-        Ok(())
+        .await
     }
 
     async fn maybe_set_title_from_first_user_with_conn(
@@ -1359,14 +3098,22 @@ impl HistoryStore {
                     role: row.get(2).unwrap_or_default(),
                     content: row.get(3).unwrap_or_default(),
                     reasoning: row.get(4).ok(),
+                    tool_calls: Vec::new(),
+                    attachments: Vec::new(),
+                    model: None,
+                    usage: None,
+                    status: MessageStatus::Complete,
                     created_at_ms: (row.get::<i64>(5).unwrap_or(0)).max(0) as u64,
+                    active_variant: 0,
+                    variant_count: 0,
+                    pinned: false,
                 });
             }
 
             messages
         };
 
-        let generated = title::generate_title(&messages).await?;
+        let generated = title::generate_title(&messages, &self.inner.rate_limiter).await?;
         let generated = truncate_title(&generated);
 
         if generated.is_empty() {
@@ -1418,3 +3165,708 @@ impl HistoryStore {
         Ok(rows.next().await?.is_some())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::history::AuditContext;
+
+    /// A store on a throwaway database file, removed on drop.
+    struct TestStore {
+        store: HistoryStore,
+        path: PathBuf,
+    }
+
+    impl Deref for TestStore {
+        type Target = HistoryStore;
+
+        fn deref(&self) -> &Self::Target {
+            &self.store
+        }
+    }
+
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{suffix}", self.path.display()));
+            }
+        }
+    }
+
+    async fn test_store() -> TestStore {
+        let path = std::env::temp_dir().join(format!("rcat-history-{}.db", Uuid::new_v4()));
+        let db = Builder::new_local(path.to_string_lossy().to_string())
+            .build()
+            .await
+            .unwrap();
+        let store = HistoryStore::open(db, DbMode::Local, RateLimiter::default())
+            .await
+            .unwrap();
+        TestStore { store, path }
+    }
+
+    fn user_message(seq: u32, content: &str) -> ChatMessage {
+        ChatMessage {
+            seq: Some(seq),
+            role: "user".to_string(),
+            content: content.to_string(),
+            tool_calls: Vec::new(),
+            pinned: false,
+            attachments: Vec::new(),
+        }
+    }
+
+    fn answer(text: &str) -> ChatOutput {
+        ChatOutput {
+            text: text.to_string(),
+            model: "test-model".to_string(),
+            ..ChatOutput::default()
+        }
+    }
+
+    fn answer_with_usage(text: &str, model: &str, prompt_tokens: u32) -> ChatOutput {
+        ChatOutput {
+            model: model.to_string(),
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                completion_tokens: prompt_tokens / 10,
+                ..TokenUsage::default()
+            }),
+            ..answer(text)
+        }
+    }
+
+    /// A conversation of `answers.len()` question / answer turns.
+    async fn conversation_with_turns(store: &HistoryStore, answers: &[ChatOutput]) -> String {
+        let id = store
+            .create_conversation(None, true, None)
+            .await
+            .unwrap()
+            .id;
+        for (turn, output) in answers.iter().enumerate() {
+            let seq = turn as u32 * 2 + 1;
+            store
+                .sync_from_frontend_messages(
+                    &id,
+                    &[user_message(seq, &format!("question {turn}"))],
+                    None,
+                )
+                .await
+                .unwrap();
+            store
+                .append_assistant_message(&id, output.clone(), MessageStatus::Complete)
+                .await
+                .unwrap();
+        }
+        id
+    }
+
+    async fn contents(store: &HistoryStore, conversation_id: &str) -> Vec<String> {
+        store
+            .get_conversation(conversation_id)
+            .await
+            .unwrap()
+            .messages
+            .into_iter()
+            .map(|m| m.content)
+            .collect()
+    }
+
+    async fn search_snippets(store: &HistoryStore, query: &str) -> Vec<String> {
+        let mut snippets: Vec<String> = store
+            .search(query, None, None)
+            .await
+            .unwrap()
+            .hits
+            .into_iter()
+            .map(|hit| {
+                hit.snippet
+                    .replace(search::MATCH_START, "[")
+                    .replace(search::MATCH_END, "]")
+            })
+            .collect();
+        snippets.sort();
+        snippets
+    }
+
+    async fn fts_integrity_check(store: &HistoryStore) {
+        let conn = store.connect().await.unwrap();
+        for table in ["messages_fts", "conversations_fts"] {
+            conn.execute(
+                &format!("INSERT INTO {table}({table}, rank) VALUES ('integrity-check', 1);"),
+                (),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_index_and_like_fallback() {
+        let store = test_store().await;
+        assert!(store.inner.search_fts.load(Ordering::Relaxed));
+        let id = conversation_with_turns(
+            &store,
+            &[answer("明天上海的天气预报：小雨"), answer("今天天气很好")],
+        )
+        .await;
+        let deleted = conversation_with_turns(&store, &[answer("昨天的天气预报")]).await;
+        store.delete_conversation(&deleted).await.unwrap();
+
+        // Served by the trigram index.
+        assert_eq!(
+            search_snippets(&store, "天气预报").await,
+            ["明天上海的[天气预报]：小雨"]
+        );
+        // Two characters: too short for trigrams, answered by `LIKE`.
+        assert_eq!(
+            search_snippets(&store, "天气").await,
+            ["今天[天气]很好", "明天上海的[天气]预报：小雨"]
+        );
+        assert_eq!(
+            search_snippets(&store, "天气 小雨").await,
+            ["明天上海的[天气]预报：[小雨]"]
+        );
+        assert!(search_snippets(&store, "下雪").await.is_empty());
+
+        // Edits and removals keep the external-content index in sync.
+        store
+            .sync_from_frontend_messages(&id, &[user_message(1, "天气预报准吗")], Some(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            search_snippets(&store, "天气预报").await,
+            ["[天气预报]准吗"]
+        );
+        store.purge_conversations(None).await.unwrap();
+        fts_integrity_check(&store).await;
+    }
+
+    #[tokio::test]
+    async fn test_search_index_is_rekeyed_and_survives_vacuum() {
+        let store = test_store().await;
+        let first = conversation_with_turns(&store, &[answer("alpha answer")]).await;
+        conversation_with_turns(&store, &[answer("bravo answer")]).await;
+
+        // Go back to the first index layout: keyed on the implicit rowid.
+        let conn = store.connect().await.unwrap();
+        for sql in [
+            "DROP TRIGGER messages_fts_insert;",
+            "DROP TRIGGER messages_fts_delete;",
+            "DROP TRIGGER messages_fts_update;",
+            "DROP TABLE messages_fts;",
+            "DROP INDEX idx_messages_search_rowid;",
+            "ALTER TABLE messages DROP COLUMN search_rowid;",
+            "CREATE VIRTUAL TABLE messages_fts USING fts5(content, reasoning, content='messages', content_rowid='rowid', tokenize='trigram');",
+            "CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN\n  INSERT INTO messages_fts(rowid, content, reasoning) VALUES (new.rowid, new.content, new.reasoning);\nEND;",
+            "INSERT INTO messages_fts(messages_fts) VALUES ('rebuild');",
+        ] {
+            conn.execute(sql, ()).await.unwrap();
+        }
+        drop(conn);
+        let path = store.path.to_string_lossy().to_string();
+        let db = Builder::new_local(path).build().await.unwrap();
+        let reopened = HistoryStore::open(db, DbMode::Local, RateLimiter::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            search_snippets(&reopened, "answer").await,
+            ["alpha [answer]", "bravo [answer]"]
+        );
+
+        // Rowids may be renumbered by VACUUM; the index must not care.
+        store.delete_conversation(&first).await.unwrap();
+        reopened.purge_conversations(None).await.unwrap();
+        let conn = reopened.connect().await.unwrap();
+        conn.execute("VACUUM;", ()).await.unwrap();
+        drop(conn);
+        conversation_with_turns(&reopened, &[answer("charlie answer")]).await;
+        assert_eq!(
+            search_snippets(&reopened, "answer").await,
+            ["bravo [answer]", "charlie [answer]"]
+        );
+        fts_integrity_check(&reopened).await;
+    }
+
+    #[tokio::test]
+    async fn test_regenerate_and_select_variants() {
+        let store = test_store().await;
+        let id = conversation_with_turns(&store, &[answer("first"), answer("second")]).await;
+
+        // Earlier answers and user messages cannot be regenerated in place.
+        assert!(store.prepare_regenerate(&id, 2).await.is_err());
+        assert!(store.prepare_regenerate(&id, 3).await.is_err());
+
+        let context = store.prepare_regenerate(&id, 4).await.unwrap();
+        let seqs: Vec<_> = context.iter().map(|m| m.seq).collect();
+        assert_eq!(seqs, [Some(1), Some(2), Some(3)]);
+
+        store
+            .append_message_variant(&id, 4, answer("second, again"), MessageStatus::Complete)
+            .await
+            .unwrap();
+        let variants = store.list_message_variants(&id, 4).await.unwrap();
+        let texts: Vec<_> = variants.iter().map(|v| v.content.as_str()).collect();
+        assert_eq!(texts, ["second", "second, again"]);
+        assert!(variants[1].is_active);
+        assert_eq!(
+            contents(&store, &id).await,
+            ["question 0", "first", "question 1", "second, again"]
+        );
+
+        let selected = store.select_message_variant(&id, 4, 0).await.unwrap();
+        assert_eq!(selected.content, "second");
+        assert_eq!((selected.active_variant, selected.variant_count), (0, 2));
+        // The selected answer is what the next request sees.
+        store.prepare_regenerate(&id, 4).await.unwrap();
+        store
+            .append_message_variant(&id, 4, answer("third try"), MessageStatus::Aborted)
+            .await
+            .unwrap();
+        let variants = store.list_message_variants(&id, 4).await.unwrap();
+        assert_eq!(variants.len(), 3);
+        assert_eq!(variants[2].status, MessageStatus::Aborted);
+
+        assert!(store.select_message_variant(&id, 4, 7).await.is_err());
+        let conversation = store.get_conversation(&id).await.unwrap().conversation;
+        assert_eq!(conversation.message_count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_failed_regenerate_keeps_turns_and_variants() {
+        let store = test_store().await;
+        let id = conversation_with_turns(&store, &[answer("first"), answer("second")]).await;
+        store.prepare_regenerate(&id, 4).await.unwrap();
+        store
+            .append_message_variant(&id, 4, answer("retry"), MessageStatus::Complete)
+            .await
+            .unwrap();
+
+        // The request is rejected or the provider fails: nothing is stored.
+        store.prepare_regenerate(&id, 4).await.unwrap();
+
+        assert_eq!(
+            contents(&store, &id).await,
+            ["question 0", "first", "question 1", "retry"]
+        );
+        let variants = store.list_message_variants(&id, 4).await.unwrap();
+        let texts: Vec<_> = variants.iter().map(|v| v.content.as_str()).collect();
+        assert_eq!(texts, ["second", "retry"]);
+        let conversation = store.get_conversation(&id).await.unwrap().conversation;
+        assert_eq!(conversation.message_count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_restore_from_history() {
+        let store = test_store().await;
+        let with_tools = ChatOutput {
+            tool_calls: vec![ToolCallRecord {
+                round: 0,
+                id: "call_1".to_string(),
+                name: "list_windows".to_string(),
+                arguments: "{}".to_string(),
+                result: "Notes".to_string(),
+                is_error: false,
+            }],
+            ..answer("You have Notes open.")
+        };
+        let id = conversation_with_turns(&store, &[with_tools, answer("plain")]).await;
+        store.set_message_pinned(&id, 3, true).await.unwrap();
+        assert!(store.get_conversation(&id).await.unwrap().messages[2].pinned);
+
+        // What the frontend sends back: history with `seq` but without `toolCalls`.
+        let mut messages: Vec<ChatMessage> = store
+            .get_conversation(&id)
+            .await
+            .unwrap()
+            .messages
+            .into_iter()
+            .map(|m| ChatMessage {
+                role: m.role,
+                ..user_message(m.seq, &m.content)
+            })
+            .collect();
+        messages.push(user_message(5, "question 2"));
+        store
+            .restore_from_history(&id, &mut messages)
+            .await
+            .unwrap();
+
+        let restored: Vec<Vec<&str>> = messages
+            .iter()
+            .map(|m| m.tool_calls.iter().map(|r| r.result.as_str()).collect())
+            .collect();
+        assert_eq!(restored, [vec![], vec!["Notes"], vec![], vec![], vec![]]);
+        assert_eq!(messages[1].tool_calls[0].id, "call_1");
+        let pinned: Vec<bool> = messages.iter().map(|m| m.pinned).collect();
+        assert_eq!(pinned, [false, false, true, false, false]);
+
+        assert!(matches!(
+            store.set_message_pinned(&id, 9, true).await,
+            Err(HistoryError::NotFound { .. })
+        ));
+    }
+
+    /// The usage recorded by `test_usage_survives_regenerate_truncate_and_purge`.
+    async fn assert_usage_totals(store: &HistoryStore, conversation_id: &str) {
+        let totals = store.conversation_usage(conversation_id).await.unwrap();
+        assert_eq!(
+            (
+                totals.message_count,
+                totals.prompt_tokens,
+                totals.completion_tokens
+            ),
+            (4, 650, 65)
+        );
+
+        let days = store.usage_by_day(None, None, Some(480)).await.unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].key.len(), "YYYY-MM-DD".len());
+        assert_eq!((days[0].message_count, days[0].prompt_tokens), (4, 650));
+
+        let models = store.usage_by_model(None, None).await.unwrap();
+        let totals: Vec<_> = models
+            .iter()
+            .map(|m| (m.key.as_str(), m.message_count, m.prompt_tokens))
+            .collect();
+        assert_eq!(totals, [("model-b", 2, 350), ("model-a", 2, 300)]);
+    }
+
+    #[tokio::test]
+    async fn test_usage_survives_regenerate_truncate_and_purge() {
+        let store = test_store().await;
+        let id = conversation_with_turns(
+            &store,
+            &[
+                answer_with_usage("first", "model-a", 100),
+                answer_with_usage("second", "model-a", 200),
+            ],
+        )
+        .await;
+        store
+            .append_message_variant(
+                &id,
+                4,
+                answer_with_usage("second, again", "model-b", 300),
+                MessageStatus::Aborted,
+            )
+            .await
+            .unwrap();
+        store
+            .extend_assistant_message(
+                &id,
+                4,
+                answer_with_usage(" and more", "model-b", 50),
+                MessageStatus::Complete,
+            )
+            .await
+            .unwrap();
+        // No usage reported: nothing to record.
+        store
+            .append_message_variant(&id, 4, answer("third"), MessageStatus::Complete)
+            .await
+            .unwrap();
+
+        assert_usage_totals(&store, &id).await;
+
+        // Editing the first question drops every answer after it.
+        store
+            .sync_from_frontend_messages(&id, &[user_message(1, "question 0, edited")], Some(1))
+            .await
+            .unwrap();
+        assert_eq!(contents(&store, &id).await, ["question 0, edited"]);
+        assert_usage_totals(&store, &id).await;
+
+        store.delete_conversation(&id).await.unwrap();
+        assert_eq!(store.purge_conversations(None).await.unwrap(), 1);
+        assert_usage_totals(&store, &id).await;
+
+        // Outside the requested range.
+        let tomorrow = now_ms() + DAY_MS;
+        assert!(
+            store
+                .usage_by_day(Some(tomorrow), None, None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            store
+                .usage_by_model(None, Some(1))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_ledger_backfills_existing_history() {
+        let store = test_store().await;
+        let id =
+            conversation_with_turns(&store, &[answer_with_usage("first", "model-a", 100)]).await;
+        store
+            .append_message_variant(
+                &id,
+                2,
+                answer_with_usage("again", "model-a", 200),
+                MessageStatus::Complete,
+            )
+            .await
+            .unwrap();
+
+        // A database from before the ledger existed.
+        let conn = store.connect().await.unwrap();
+        conn.execute("DROP TABLE usage_ledger;", ()).await.unwrap();
+        drop(conn);
+        store.migrate().await.unwrap();
+
+        let totals = store.conversation_usage(&id).await.unwrap();
+        assert_eq!((totals.message_count, totals.prompt_tokens), (2, 300));
+        // Only once: a second start does not count the history again.
+        store.migrate().await.unwrap();
+        let totals = store.conversation_usage(&id).await.unwrap();
+        assert_eq!((totals.message_count, totals.prompt_tokens), (2, 300));
+    }
+
+    async fn set_archived_at(store: &HistoryStore, conversation_id: &str, archived_at_ms: u64) {
+        let conn = store.connect().await.unwrap();
+        conn.execute(
+            "UPDATE conversations SET archived_at_ms = ?2 WHERE id = ?1;",
+            params![conversation_id, archived_at_ms as i64],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn trash_ids(store: &HistoryStore) -> Vec<String> {
+        store
+            .list_archived()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_trash_list_and_restore() {
+        let store = test_store().await;
+        let first = conversation_with_turns(&store, &[answer("first")]).await;
+        let second = conversation_with_turns(&store, &[answer("second")]).await;
+        assert_eq!(
+            store.get_active_conversation_id().await.unwrap(),
+            Some(second.clone())
+        );
+
+        // Deleting an inactive conversation keeps the active one.
+        let bootstrap = store.delete_conversation(&first).await.unwrap();
+        assert_eq!(bootstrap.active_conversation_id, second);
+        // Deleting the active one falls back to a fresh conversation when nothing else is live.
+        let bootstrap = store.delete_conversation(&second).await.unwrap();
+        let fresh = bootstrap.active_conversation_id;
+        assert!(fresh != first && fresh != second);
+        let live: Vec<_> = bootstrap
+            .conversations
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(live, [fresh.as_str()]);
+
+        let now = now_ms();
+        set_archived_at(&store, &first, now - 2 * DAY_MS).await;
+        set_archived_at(&store, &second, now - DAY_MS).await;
+        let trash = store.list_archived().await.unwrap();
+        let ids: Vec<_> = trash.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, [second.as_str(), first.as_str()]);
+        assert_eq!(trash[1].message_count, 2);
+        assert_eq!(trash[1].archived_at_ms, now - 2 * DAY_MS);
+        // Default retention of 30 days.
+        assert_eq!(trash[1].purge_at_ms, Some(now + 28 * DAY_MS));
+
+        // Restored without `set_active`: the live active conversation stays active.
+        let bootstrap = store.restore_conversation(&first, false).await.unwrap();
+        assert_eq!(bootstrap.active_conversation_id, fresh);
+        assert!(bootstrap.conversations.iter().any(|c| c.id == first));
+        assert_eq!(contents(&store, &first).await, ["question 0", "first"]);
+        assert_eq!(trash_ids(&store).await, [second.as_str()]);
+
+        // With `set_active` it takes over.
+        let bootstrap = store.restore_conversation(&second, true).await.unwrap();
+        assert_eq!(bootstrap.active_conversation_id, second);
+        assert!(trash_ids(&store).await.is_empty());
+
+        assert!(matches!(
+            store.restore_conversation(&second, false).await,
+            Err(HistoryError::InvalidInput { .. })
+        ));
+        assert!(matches!(
+            store.restore_conversation("conv_missing", false).await,
+            Err(HistoryError::NotFound { .. })
+        ));
+
+        // An active id that no longer points at a live conversation is replaced.
+        store.delete_conversation(&first).await.unwrap();
+        let conn = store.connect().await.unwrap();
+        conn.execute(
+            "UPDATE app_state SET value = 'conv_gone' WHERE key = ?1;",
+            params![APP_STATE_ACTIVE_CONVERSATION_ID],
+        )
+        .await
+        .unwrap();
+        let bootstrap = store.restore_conversation(&first, false).await.unwrap();
+        assert_eq!(bootstrap.active_conversation_id, first);
+    }
+
+    #[tokio::test]
+    async fn test_purge_and_retention_cutoff() {
+        let store = test_store().await;
+        let expired =
+            conversation_with_turns(&store, &[answer_with_usage("old", "model-a", 100)]).await;
+        let kept = conversation_with_turns(&store, &[answer("recent")]).await;
+        let live = conversation_with_turns(&store, &[answer("live")]).await;
+        store.delete_conversation(&expired).await.unwrap();
+        store.delete_conversation(&kept).await.unwrap();
+
+        // Default retention of 30 days: only the conversation deleted before the cut-off goes.
+        let now = now_ms();
+        set_archived_at(&store, &expired, now - 31 * DAY_MS).await;
+        set_archived_at(&store, &kept, now - 29 * DAY_MS).await;
+        assert_eq!(store.purge_expired_trash().await.unwrap(), 1);
+        assert_eq!(trash_ids(&store).await, [kept.as_str()]);
+        assert!(matches!(
+            store.get_conversation(&expired).await,
+            Err(HistoryError::NotFound { .. })
+        ));
+        // Its spend is history: purging leaves the usage ledger alone.
+        let totals = store.conversation_usage(&expired).await.unwrap();
+        assert_eq!((totals.message_count, totals.prompt_tokens), (1, 100));
+
+        // Live conversations are never purged, even when named.
+        let ids = [live.clone(), kept.clone()];
+        assert_eq!(store.purge_conversations(Some(&ids)).await.unwrap(), 1);
+        assert_eq!(store.purge_conversations(Some(&[])).await.unwrap(), 0);
+        assert_eq!(store.purge_conversations(None).await.unwrap(), 0);
+        assert!(trash_ids(&store).await.is_empty());
+        assert_eq!(contents(&store, &live).await, ["question 0", "live"]);
+        assert!(matches!(
+            store.get_conversation(&kept).await,
+            Err(HistoryError::NotFound { .. })
+        ));
+    }
+
+    fn audit_context(conversation_id: &str, request_id: &str) -> AuditContext {
+        AuditContext {
+            conversation_id: Some(conversation_id.to_string()),
+            request_id: Some(request_id.to_string()),
+            provider: Some("openai".to_string()),
+            model: Some("gpt-4o".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_entries_carry_their_request() {
+        let store = test_store().await;
+        let conversation = conversation_with_turns(&store, &[answer("Notes is open.")]).await;
+        let audit = audit_context(&conversation, "req_1");
+
+        // A tool call and the capture it made, as `run_chat_generic` and `audit_capture` write them.
+        let mut capture = audit.entry(AuditKind::ScreenCapture);
+        capture.app_name = Some("Notes".to_string());
+        capture.window_title = Some("Groceries".to_string());
+        capture.chars = 42;
+        store.append_audit(capture).await.unwrap();
+        let mut call = audit.entry(AuditKind::ToolCall);
+        call.tool = Some("read_window".to_string());
+        call.app_name = Some("Notes".to_string());
+        call.chars = 50;
+        store.append_audit(call).await.unwrap();
+        let mut other = audit_context("conv_other", "req_2").entry(AuditKind::ToolCall);
+        other.is_error = true;
+        store.append_audit(other).await.unwrap();
+
+        let entries = store
+            .query_audit(None, Some(&conversation), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            assert!(entry.id.starts_with("audit"));
+            assert!(entry.created_at_ms > 0);
+            assert_eq!(
+                entry.conversation_id.as_deref(),
+                Some(conversation.as_str())
+            );
+            assert_eq!(entry.request_id.as_deref(), Some("req_1"));
+            assert_eq!(entry.provider.as_deref(), Some("openai"));
+            assert_eq!(entry.model.as_deref(), Some("gpt-4o"));
+            assert!(!entry.is_error);
+        }
+
+        let calls = store
+            .query_audit(Some(AuditKind::ToolCall), None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(calls.len(), 2);
+        let call = calls
+            .iter()
+            .find(|e| e.request_id.as_deref() == Some("req_1"))
+            .unwrap();
+        assert_eq!(
+            (call.tool.as_deref(), call.app_name.as_deref(), call.chars),
+            (Some("read_window"), Some("Notes"), 50)
+        );
+        let captures = store
+            .query_audit(Some(AuditKind::ScreenCapture), None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures[0].window_title.as_deref(), Some("Groceries"));
+        assert_eq!(captures[0].chars, 42);
+
+        // The log outlives its conversation.
+        store.delete_conversation(&conversation).await.unwrap();
+        store.purge_conversations(None).await.unwrap();
+        let entries = store
+            .query_audit(None, Some(&conversation), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_purge_audit_keeps_newer_rows() {
+        let store = test_store().await;
+        for request_id in ["req_old", "req_new"] {
+            let entry = audit_context("conv_1", request_id).entry(AuditKind::ToolCall);
+            store.append_audit(entry).await.unwrap();
+        }
+        let now = now_ms();
+        let conn = store.connect().await.unwrap();
+        conn.execute(
+            "UPDATE audit_log SET created_at_ms = ?1 WHERE request_id = 'req_old';",
+            params![(now - 10 * DAY_MS) as i64],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(store.purge_audit(Some(now - DAY_MS)).await.unwrap(), 1);
+        let entries = store
+            .query_audit(None, None, None, None, None)
+            .await
+            .unwrap();
+        let request_ids: Vec<_> = entries
+            .iter()
+            .map(|e| e.request_id.as_deref().unwrap())
+            .collect();
+        assert_eq!(request_ids, ["req_new"]);
+
+        assert_eq!(store.purge_audit(None).await.unwrap(), 1);
+        assert!(
+            store
+                .query_audit(None, None, None, None, None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -1,12 +1,14 @@
-use async_openai::{config::OpenAIConfig, Client};
 use serde_json::Value as JsonValue;
+use std::time::Duration;
 
-use crate::services::config::load_ai_config;
+use crate::services::ai::RateLimiter;
+use crate::services::ai::chat_completions;
 use crate::services::config::AiProvider;
+use crate::services::config::load_ai_config;
 use crate::services::prompts;
 
-use super::types::ConversationMessage;
 use super::HistoryError;
+use super::types::ConversationMessage;
 
 fn build_transcript(messages: &[ConversationMessage]) -> String {
     let mut out = String::new();
@@ -27,11 +29,15 @@ fn build_transcript(messages: &[ConversationMessage]) -> String {
     out
 }
 
+/// Longest throttle window worth waiting for; beyond that the title cooldown retries later.
+const MAX_THROTTLE_WAIT: Duration = Duration::from_secs(10);
+
 pub(super) async fn generate_title(
     messages: &[ConversationMessage],
+    rate_limiter: &RateLimiter,
 ) -> Result<String, HistoryError> {
     let config = load_ai_config();
-    if config.api_key.is_empty() {
+    if config.api_key.is_empty() && config.provider.requires_api_key() {
         return Err(HistoryError::internal(
             "AI key missing for title generation",
         ));
@@ -51,10 +57,13 @@ pub(super) async fn generate_title(
         _ => config.model.clone(),
     };
 
-    let openai_config = OpenAIConfig::new()
-        .with_api_base(config.base_url)
-        .with_api_key(config.api_key);
-    let client = Client::with_config(openai_config);
+    // Chat streams share this endpoint: don't add load while it is throttled.
+    if let Err(remaining) = rate_limiter.wait(&config.base_url, MAX_THROTTLE_WAIT).await {
+        return Err(HistoryError::internal(format!(
+            "AI endpoint throttled for {}s",
+            remaining.as_secs()
+        )));
+    }
 
     let transcript = build_transcript(messages);
     let prompt = format!(
@@ -72,11 +81,18 @@ pub(super) async fn generate_title(
         "max_tokens": 64
     });
 
-    let response: JsonValue = client
-        .chat()
-        .create_byot::<_, JsonValue>(&request)
-        .await
-        .map_err(|e| HistoryError::internal(e.to_string()))?;
+    let response = chat_completions::complete(
+        &reqwest::Client::new(),
+        &config.base_url,
+        &config.api_key,
+        &request,
+        rate_limiter,
+    )
+    .await
+    .map_err(|e| {
+        rate_limiter.observe_error(&config.base_url, &e);
+        HistoryError::internal(e.to_string())
+    })?;
 
     let message = response
         .get("choices")
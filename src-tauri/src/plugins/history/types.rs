@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::services::ai::{ChatAttachment, TokenUsage, ToolCallRecord};
+
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
 #[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,10 +16,61 @@ pub struct ConversationSummary {
     pub message_count: u32,
     pub last_message_at_ms: u64,
     pub last_role: String,
+    /// `None` = built-in system prompt.
+    pub persona_id: Option<String>,
     pub has_unseen: bool,
     pub is_active: bool,
 }
 
+/// A deleted conversation in the trash (see `history_list_archived`).
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedConversation {
+    pub id: String,
+    pub title: String,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+    pub message_count: u32,
+    /// When it was deleted.
+    pub archived_at_ms: u64,
+    /// When it will be purged automatically; `None` while retention is off.
+    pub purge_at_ms: Option<u64>,
+}
+
+/// How an assistant message ended.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageStatus {
+    #[default]
+    Complete,
+    /// Stopped by the user; holds what was streamed until then.
+    Aborted,
+    /// The request failed mid-answer; holds what was streamed until then.
+    Errored,
+}
+
+impl MessageStatus {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            MessageStatus::Complete => "complete",
+            MessageStatus::Aborted => "aborted",
+            MessageStatus::Errored => "errored",
+        }
+    }
+
+    /// Unknown values read as `Complete` (rows written before the column existed).
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "aborted" => MessageStatus::Aborted,
+            "errored" => MessageStatus::Errored,
+            _ => MessageStatus::Complete,
+        }
+    }
+}
+
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
 #[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,7 +82,53 @@ pub struct ConversationMessage {
     pub role: String,
     pub content: String,
     pub reasoning: Option<String>,
+    /// Tool calls (and results) made while producing this assistant message.
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// Images sent with this user message (inlined data URLs).
+    #[serde(default)]
+    pub attachments: Vec<ChatAttachment>,
+    /// Model that produced this assistant message (if known).
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Provider-reported token usage for this assistant message (if reported).
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    /// Whether this assistant message was cut short (see `chat_continue`).
+    #[serde(default)]
+    pub status: MessageStatus,
+    pub created_at_ms: u64,
+    /// Variant shown for this assistant turn (see [`MessageVariant`]).
+    #[serde(default)]
+    pub active_variant: u32,
+    /// Stored answers for this turn; 0 until the turn is first regenerated.
+    #[serde(default)]
+    pub variant_count: u32,
+    /// Pinned messages are never dropped when the context is trimmed to fit the model.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// One of several answers kept for an assistant turn (`chat_regenerate`).
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageVariant {
+    pub variant: u32,
+    pub content: String,
+    pub reasoning: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallRecord>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    #[serde(default)]
+    pub status: MessageStatus,
     pub created_at_ms: u64,
+    /// Currently mirrored into the conversation's message row.
+    pub is_active: bool,
 }
 
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
@@ -49,3 +148,184 @@ pub struct ConversationDetail {
     pub conversation: ConversationSummary,
     pub messages: Vec<ConversationMessage>,
 }
+
+/// Token usage totals for one aggregation bucket.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    /// Bucket key: conversation id, day (`YYYY-MM-DD`) or model id (empty when unknown).
+    pub key: String,
+    /// Replies (answers, regenerated answers and continuations) with reported usage in this bucket.
+    pub message_count: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub cached_tokens: u64,
+}
+
+/// What an audit entry records.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditKind {
+    /// A model-requested tool call; its result was sent to `provider`.
+    ToolCall,
+    /// A screen or window capture read with OCR.
+    ScreenCapture,
+    /// A screenshot sent to a vision model.
+    VlmAnalysis,
+}
+
+impl AuditKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            AuditKind::ToolCall => "toolCall",
+            AuditKind::ScreenCapture => "screenCapture",
+            AuditKind::VlmAnalysis => "vlmAnalysis",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "toolCall" => Some(AuditKind::ToolCall),
+            "screenCapture" => Some(AuditKind::ScreenCapture),
+            "vlmAnalysis" => Some(AuditKind::VlmAnalysis),
+            _ => None,
+        }
+    }
+}
+
+/// One row of the append-only audit log.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Assigned on insert.
+    pub id: String,
+    /// Assigned on insert.
+    pub created_at_ms: u64,
+    pub kind: AuditKind,
+    pub tool: Option<String>,
+    pub conversation_id: Option<String>,
+    pub request_id: Option<String>,
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    /// Characters read from the screen (captures) or sent to `provider` (tool calls, VLM prompts).
+    pub chars: u64,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub is_error: bool,
+}
+
+impl AuditEntry {
+    pub(crate) fn new(kind: AuditKind) -> Self {
+        Self {
+            id: String::new(),
+            created_at_ms: 0,
+            kind,
+            tool: None,
+            conversation_id: None,
+            request_id: None,
+            app_name: None,
+            window_title: None,
+            chars: 0,
+            provider: None,
+            model: None,
+            is_error: false,
+        }
+    }
+}
+
+/// The request a capture or tool call ran for; stamped on every audit entry it writes, so tool
+/// calls and the captures they made can be joined on `request_id`.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub conversation_id: Option<String>,
+    pub request_id: Option<String>,
+    /// Provider / model that receive what was read.
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+impl AuditContext {
+    pub(crate) fn entry(&self, kind: AuditKind) -> AuditEntry {
+        AuditEntry {
+            conversation_id: self.conversation_id.clone(),
+            request_id: self.request_id.clone(),
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            ..AuditEntry::new(kind)
+        }
+    }
+}
+
+/// One search match: a conversation title or a message.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub conversation_id: String,
+    pub conversation_title: String,
+    /// Matching message; `None` when the title matched.
+    pub seq: Option<u32>,
+    pub role: Option<String>,
+    /// Text around the match; matched parts are wrapped in `\u{2}` … `\u{3}`.
+    pub snippet: String,
+    /// Message time, or the conversation's last update for title matches.
+    pub created_at_ms: u64,
+}
+
+/// One page of `history_search` results, best matches first.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Offset of the next page; `None` on the last one.
+    pub next_offset: Option<u32>,
+}
+
+/// File format of `history_export_conversation` / `history_export_all`.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Markdown,
+    /// The conversation and its messages as stored (`version`, `exportedAtMs`, `conversation`, `messages`).
+    Json,
+    /// Self-contained page (inline styles and images).
+    Html,
+}
+
+/// Source format of `history_import`.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportFormat {
+    /// `conversations.json` from a ChatGPT data export.
+    ChatGpt,
+    /// `conversations.json` from a DeepSeek data export.
+    DeepSeek,
+    /// One `{ role, content }` object per line, optionally with `conversation_id`, `title`,
+    /// `created_at`, `model` and `reasoning`.
+    Jsonl,
+}
+
+/// Progress of `history_import` (sent as `history-import-progress`, and its final result).
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    /// Conversations found in the file.
+    pub total: u32,
+    pub processed: u32,
+    pub imported: u32,
+    /// Already imported, malformed or empty.
+    pub skipped: u32,
+}
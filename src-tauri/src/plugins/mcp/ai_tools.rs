@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+use crate::plugins::history::AuditContext;
+use crate::services::ai::{ApprovalScope, Tool, ToolFuture};
+use crate::services::config::AiConfig;
+
+use super::manager::McpServer;
+use super::protocol::McpToolInfo;
+
+/// Function names are limited to `^[a-zA-Z0-9_-]{1,64}$` by OpenAI-style APIs.
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Name offered to the model: `mcp__<server>__<tool>`, sanitized and capped at 64 chars.
+///
+/// When sanitizing or truncating loses characters, or `disambiguate` is set because the plain
+/// name is taken, a hash of the original names is appended so distinct tools keep distinct names.
+pub(super) fn exposed_tool_name(server: &str, tool: &str, disambiguate: bool) -> String {
+    let raw = format!("mcp__{server}__{tool}");
+    let sanitized: String = raw
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !disambiguate && sanitized == raw && raw.len() <= MAX_TOOL_NAME_LEN {
+        return raw;
+    }
+    let suffix = format!("_{:08x}", name_hash(server, tool) as u32);
+    let mut name: String = sanitized
+        .chars()
+        .take(MAX_TOOL_NAME_LEN - suffix.len())
+        .collect();
+    name.push_str(&suffix);
+    name
+}
+
+/// FNV-1a over `server \0 tool`: stable across runs, so approval rules keep matching.
+fn name_hash(server: &str, tool: &str) -> u64 {
+    [server.as_bytes(), b"\0", tool.as_bytes()]
+        .concat()
+        .into_iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// A tool served by an MCP server, dispatched with `tools/call`.
+pub(super) struct McpTool {
+    server: Arc<McpServer>,
+    exposed_name: String,
+    info: McpToolInfo,
+}
+
+impl McpTool {
+    pub(super) fn new(server: Arc<McpServer>, info: McpToolInfo, exposed_name: String) -> Self {
+        Self {
+            server,
+            exposed_name,
+            info,
+        }
+    }
+}
+
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.exposed_name
+    }
+
+    fn definition(&self) -> serde_json::Value {
+        let description = if self.info.description.is_empty() {
+            format!(
+                "Tool {} from MCP server {}",
+                self.info.name,
+                self.server.name()
+            )
+        } else {
+            self.info.description.clone()
+        };
+        json!({
+            "name": self.exposed_name,
+            "description": description,
+            "parameters": self.info.input_schema,
+        })
+    }
+
+    fn is_available(&self, _config: &AiConfig) -> bool {
+        self.server.is_running()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(self.server.tool_timeout())
+    }
+
+    fn execute<'a>(
+        &'a self,
+        arguments: &'a serde_json::Value,
+        cancel: &'a CancellationToken,
+        _audit: &'a AuditContext,
+        _approved: Option<&'a ApprovalScope>,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.server
+                .call_tool(&self.info.name, arguments, cancel)
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exposed_tool_name() {
+        assert_eq!(exposed_tool_name("echo", "say", false), "mcp__echo__say");
+
+        // Lossy names get a hash, so tools that sanitize alike stay distinct.
+        let dotted = exposed_tool_name("my-server", "a.b", false);
+        let slashed = exposed_tool_name("my-server", "a/b", false);
+        assert!(dotted.starts_with("mcp__my-server__a_b_"));
+        assert_ne!(dotted, slashed);
+        assert_eq!(dotted, exposed_tool_name("my-server", "a.b", false));
+
+        let long_a = exposed_tool_name("s", &format!("{}a", "x".repeat(100)), false);
+        let long_b = exposed_tool_name("s", &format!("{}b", "x".repeat(100)), false);
+        assert_eq!(long_a.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(long_a, long_b);
+
+        // `a`/`b__c` and `a__b`/`c` are both clean but collide; the registry asks for a hash.
+        assert_eq!(
+            exposed_tool_name("a", "b__c", false),
+            exposed_tool_name("a__b", "c", false)
+        );
+        assert_ne!(
+            exposed_tool_name("a", "b__c", true),
+            exposed_tool_name("a__b", "c", true)
+        );
+    }
+}
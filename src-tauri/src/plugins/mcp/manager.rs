@@ -0,0 +1,660 @@
+//! Server lifecycle: connect + handshake, tool sync, restart with backoff.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{Value, json};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{Notify, mpsc, watch};
+use tokio_util::sync::CancellationToken;
+
+use crate::services::ai::ToolRegistry;
+use crate::services::config::McpServerConfig;
+
+use super::ai_tools::{McpTool, exposed_tool_name};
+use super::protocol::{self, McpToolInfo};
+use super::transport::Transport;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Tool call limit unless the server config sets `toolTimeoutSecs`.
+const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(120);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A connection that lived this long resets the backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+/// Upper bound for `tools/list` pagination.
+const MAX_TOOL_PAGES: usize = 20;
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpServerState {
+    Starting,
+    Running,
+    /// Waiting to reconnect after a failure or exit.
+    Failed,
+    Stopped,
+}
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerStatus {
+    pub name: String,
+    pub state: McpServerState,
+    /// Tool names as offered to the model.
+    pub tools: Vec<String>,
+    pub error: Option<String>,
+    pub restarts: u32,
+}
+
+/// One configured server. The supervisor task owns the connection; tools borrow it per call.
+pub(super) struct McpServer {
+    config: McpServerConfig,
+    status: Mutex<McpServerStatus>,
+    transport: Mutex<Option<Arc<Transport>>>,
+    stop: watch::Sender<bool>,
+    restart: Notify,
+}
+
+impl McpServer {
+    pub(super) fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub(super) fn is_running(&self) -> bool {
+        self.status
+            .lock()
+            .is_ok_and(|s| s.state == McpServerState::Running)
+    }
+
+    pub(super) fn tool_timeout(&self) -> Duration {
+        self.config
+            .tool_timeout_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOOL_TIMEOUT)
+    }
+
+    /// Call a tool; the tool loop bounds the call with [`Self::tool_timeout`].
+    pub(super) async fn call_tool(
+        &self,
+        tool: &str,
+        arguments: &Value,
+        cancel: &CancellationToken,
+    ) -> Result<String, String> {
+        let transport = self
+            .transport
+            .lock()
+            .ok()
+            .and_then(|t| t.clone())
+            .ok_or_else(|| format!("MCP server {} is not running", self.name()))?;
+        let arguments = if arguments.is_object() {
+            arguments.clone()
+        } else {
+            json!({})
+        };
+        let result = transport
+            .request_cancellable(
+                "tools/call",
+                json!({ "name": tool, "arguments": arguments }),
+                cancel,
+            )
+            .await?;
+        protocol::format_call_result(&result)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut McpServerStatus)) {
+        if let Ok(mut status) = self.status.lock() {
+            f(&mut status);
+        }
+    }
+
+    fn set_transport(&self, transport: Option<Arc<Transport>>) {
+        if let Ok(mut current) = self.transport.lock() {
+            *current = transport;
+        }
+    }
+
+    /// Stop the supervisor and take the server's tools off the registry right away; the
+    /// connection itself is shut down by the supervisor in the background.
+    fn retire(&self, app: &AppHandle) {
+        // Under the status lock: `publish_tools` checks `stop` while holding it.
+        let status = self.status.lock();
+        let _ = self.stop.send(true);
+        if let Ok(mut status) = status {
+            unregister_tools(app, &std::mem::take(&mut status.tools));
+            status.state = McpServerState::Stopped;
+        }
+        self.set_transport(None);
+    }
+}
+
+/// Tauri state: the running MCP servers by name.
+pub struct McpManager {
+    servers: Mutex<HashMap<String, Arc<McpServer>>>,
+    http_client: reqwest::Client,
+}
+
+impl Default for McpManager {
+    fn default() -> Self {
+        let http_client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Self {
+            servers: Mutex::new(HashMap::new()),
+            http_client,
+        }
+    }
+}
+
+impl McpManager {
+    /// Bring the running servers in line with `configs`: unchanged servers keep running,
+    /// removed / edited / disabled ones are stopped, new ones are started.
+    pub(crate) fn apply(&self, app: &AppHandle, configs: Vec<McpServerConfig>) {
+        let Ok(mut servers) = self.servers.lock() else {
+            return;
+        };
+
+        // Retired before the replacements start, so they get the plain tool names back.
+        servers.retain(|name, server| {
+            let keep = configs
+                .iter()
+                .any(|c| &c.name == name && !c.disabled && *c == server.config);
+            if !keep {
+                server.retire(app);
+            }
+            keep
+        });
+
+        for config in configs.into_iter().filter(|c| !c.disabled) {
+            if servers.contains_key(&config.name) {
+                continue;
+            }
+            let (stop, stop_rx) = watch::channel(false);
+            let server = Arc::new(McpServer {
+                status: Mutex::new(McpServerStatus {
+                    name: config.name.clone(),
+                    state: McpServerState::Starting,
+                    tools: Vec::new(),
+                    error: None,
+                    restarts: 0,
+                }),
+                config,
+                transport: Mutex::new(None),
+                stop,
+                restart: Notify::new(),
+            });
+            servers.insert(server.config.name.clone(), server.clone());
+            tauri::async_runtime::spawn(supervise(
+                app.clone(),
+                server,
+                stop_rx,
+                self.http_client.clone(),
+            ));
+        }
+    }
+
+    pub(crate) fn status(&self) -> Vec<McpServerStatus> {
+        let Ok(servers) = self.servers.lock() else {
+            return Vec::new();
+        };
+        let mut out: Vec<McpServerStatus> = servers
+            .values()
+            .filter_map(|s| s.status.lock().ok().map(|s| s.clone()))
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+
+    /// Reconnect now (skipping any pending backoff).
+    pub(crate) fn restart(&self, name: &str) -> Result<(), String> {
+        let servers = self
+            .servers
+            .lock()
+            .map_err(|_| "MCP manager lock poisoned".to_string())?;
+        let server = servers
+            .get(name)
+            .ok_or_else(|| format!("MCP server not running: {name}"))?;
+        server.restart.notify_one();
+        Ok(())
+    }
+}
+
+/// Connect, run the handshake and list the server's tools.
+async fn connect(
+    server: &McpServer,
+    http_client: reqwest::Client,
+    events: mpsc::UnboundedSender<String>,
+) -> Result<(Arc<Transport>, Vec<McpToolInfo>), String> {
+    let transport = Arc::new(Transport::connect(
+        server.name(),
+        &server.config.transport,
+        http_client,
+        events,
+    )?);
+
+    let handshake = async {
+        let init = transport
+            .request(
+                "initialize",
+                protocol::initialize_params(),
+                HANDSHAKE_TIMEOUT,
+            )
+            .await?;
+        if let Some(version) = init.get("protocolVersion").and_then(|v| v.as_str()) {
+            transport.set_protocol_version(version);
+        }
+        transport.notify("notifications/initialized").await?;
+        list_tools(&transport).await
+    };
+    match handshake.await {
+        Ok(tools) => Ok((transport, tools)),
+        Err(err) => {
+            transport.shutdown().await;
+            Err(err)
+        }
+    }
+}
+
+async fn list_tools(transport: &Transport) -> Result<Vec<McpToolInfo>, String> {
+    let mut tools = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_TOOL_PAGES {
+        let params = match &cursor {
+            Some(cursor) => json!({ "cursor": cursor }),
+            None => json!({}),
+        };
+        let result = transport
+            .request("tools/list", params, HANDSHAKE_TIMEOUT)
+            .await?;
+        let (page, next) = protocol::parse_tools(&result);
+        tools.extend(page);
+        cursor = next;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(tools)
+}
+
+/// Replace the server's tools on the registry; returns the exposed names.
+///
+/// A name already taken (by another server, a built-in tool or an earlier tool of this server)
+/// is disambiguated with a hash rather than replacing the other tool.
+fn register_tools(
+    app: &AppHandle,
+    server: &Arc<McpServer>,
+    previous: &[String],
+    tools: Vec<McpToolInfo>,
+) -> Vec<String> {
+    let registry = app.state::<ToolRegistry>();
+    for name in previous {
+        registry.unregister(name);
+    }
+    let mut names: Vec<String> = Vec::new();
+    for info in tools {
+        let taken = |name: &String| names.contains(name) || registry.get(name).is_some();
+        let mut name = exposed_tool_name(server.name(), &info.name, false);
+        if taken(&name) {
+            let unique = exposed_tool_name(server.name(), &info.name, true);
+            if taken(&unique) {
+                log::warn!(
+                    "MCP server {}: skipping tool {}, name {} is already registered",
+                    server.name(),
+                    info.name,
+                    unique
+                );
+                continue;
+            }
+            log::warn!(
+                "MCP server {}: tool name {} is taken, offering {} as {}",
+                server.name(),
+                name,
+                info.name,
+                unique
+            );
+            name = unique;
+        }
+        registry.register(Arc::new(McpTool::new(server.clone(), info, name.clone())));
+        names.push(name);
+    }
+    names
+}
+
+fn unregister_tools(app: &AppHandle, names: &[String]) {
+    let registry = app.state::<ToolRegistry>();
+    for name in names {
+        registry.unregister(name);
+    }
+}
+
+/// Replace the server's tools on the registry and in its status; returns how many were
+/// registered, or `None` (registering nothing) once the server was retired.
+fn publish_tools(
+    app: &AppHandle,
+    server: &Arc<McpServer>,
+    stop: &watch::Receiver<bool>,
+    tools: Vec<McpToolInfo>,
+) -> Option<usize> {
+    let mut status = server.status.lock().ok()?;
+    if *stop.borrow() {
+        return None;
+    }
+    let previous = std::mem::take(&mut status.tools);
+    status.tools = register_tools(app, server, &previous, tools);
+    Some(status.tools.len())
+}
+
+/// Take the server's tools off the registry (a no-op after [`McpServer::retire`]).
+fn withdraw_tools(app: &AppHandle, server: &McpServer) {
+    if let Ok(mut status) = server.status.lock() {
+        unregister_tools(app, &std::mem::take(&mut status.tools));
+    }
+}
+
+async fn supervise(
+    app: AppHandle,
+    server: Arc<McpServer>,
+    mut stop: watch::Receiver<bool>,
+    http_client: reqwest::Client,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        server.update(|s| s.state = McpServerState::Starting);
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel::<String>();
+
+        let connected = tokio::select! {
+            result = connect(&server, http_client.clone(), events_tx) => result,
+            _ = stop.changed() => break,
+        };
+
+        let mut restart_now = false;
+        match connected {
+            Ok((transport, tools)) => {
+                let started = Instant::now();
+                let Some(count) = publish_tools(&app, &server, &stop, tools) else {
+                    transport.shutdown().await;
+                    break;
+                };
+                server.set_transport(Some(transport.clone()));
+                server.update(|s| {
+                    s.state = McpServerState::Running;
+                    s.error = None;
+                });
+                log::info!("MCP server {} ready ({} tools)", server.name(), count);
+
+                let error = loop {
+                    tokio::select! {
+                        _ = transport.closed() => break Some("Server exited".to_string()),
+                        Some(method) = events_rx.recv() => {
+                            if method != "notifications/tools/list_changed" {
+                                continue;
+                            }
+                            match list_tools(&transport).await {
+                                Ok(tools) => {
+                                    publish_tools(&app, &server, &stop, tools);
+                                }
+                                Err(err) => log::warn!(
+                                    "MCP server {} tools/list failed: {}",
+                                    server.name(),
+                                    err
+                                ),
+                            }
+                        }
+                        _ = server.restart.notified() => {
+                            restart_now = true;
+                            break None;
+                        }
+                        // `retire` already took the tools off the registry.
+                        _ = stop.changed() => {
+                            transport.shutdown().await;
+                            return;
+                        }
+                    }
+                };
+
+                server.set_transport(None);
+                withdraw_tools(&app, &server);
+                transport.shutdown().await;
+                if started.elapsed() >= STABLE_AFTER {
+                    backoff = INITIAL_BACKOFF;
+                }
+                server.update(|s| {
+                    s.state = McpServerState::Failed;
+                    s.error = error;
+                });
+            }
+            Err(err) => {
+                log::warn!("MCP server {} failed to start: {}", server.name(), err);
+                server.update(|s| {
+                    s.state = McpServerState::Failed;
+                    s.error = Some(err);
+                });
+            }
+        }
+
+        if !restart_now {
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = server.restart.notified() => {}
+                _ = stop.changed() => break,
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+        server.update(|s| s.restarts += 1);
+    }
+    server.update(|s| s.state = McpServerState::Stopped);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    use crate::services::config::McpTransport;
+
+    use super::*;
+
+    /// Newline-delimited JSON-RPC echo server: tools `echo` (returns `text`) and `exit` (quits
+    /// without answering).
+    #[cfg(unix)]
+    const STDIO_ECHO_SERVER: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-06-18","capabilities":{}}}\n' "$id" ;;
+    *'"method":"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo"},{"name":"exit"}]}}\n' "$id" ;;
+    *'"name":"exit"'*)
+      exit 0 ;;
+    *'"method":"tools/call"'*)
+      text=$(printf '%s' "$line" | sed -n 's/.*"text":"\([^"]*\)".*/\1/p')
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"%s"}]}}\n' "$id" "$text" ;;
+  esac
+done
+"#;
+
+    fn test_server(transport: McpTransport) -> McpServer {
+        let (stop, _) = watch::channel(false);
+        McpServer {
+            status: Mutex::new(McpServerStatus {
+                name: "echo".to_string(),
+                state: McpServerState::Running,
+                tools: Vec::new(),
+                error: None,
+                restarts: 0,
+            }),
+            config: McpServerConfig {
+                name: "echo".to_string(),
+                disabled: false,
+                tool_timeout_secs: None,
+                transport,
+            },
+            transport: Mutex::new(None),
+            stop,
+            restart: Notify::new(),
+        }
+    }
+
+    /// Handshake like the supervisor and hand the connection to the server's tools.
+    async fn start(server: &McpServer) -> (Arc<Transport>, Vec<String>) {
+        let (events, _) = mpsc::unbounded_channel();
+        let (transport, tools) = connect(server, reqwest::Client::new(), events)
+            .await
+            .expect("connect");
+        server.set_transport(Some(transport.clone()));
+        (transport, tools.into_iter().map(|t| t.name).collect())
+    }
+
+    async fn call(server: &McpServer, tool: &str, text: &str) -> Result<String, String> {
+        server
+            .call_tool(tool, &json!({ "text": text }), &CancellationToken::new())
+            .await
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdio_server_round_trip_and_exit() {
+        let server = test_server(McpTransport::Stdio {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), STDIO_ECHO_SERVER.to_string()],
+            env: BTreeMap::new(),
+            cwd: None,
+        });
+        let (transport, tools) = start(&server).await;
+        assert_eq!(tools, ["echo", "exit"]);
+        assert_eq!(call(&server, "echo", "hi").await, Ok("hi".to_string()));
+
+        // Unanswered (the server ignores `ping`): the request times out and is forgotten.
+        assert_eq!(
+            transport
+                .request("ping", json!({}), Duration::from_millis(200))
+                .await,
+            Err("ping timed out after 0s".to_string())
+        );
+        assert_eq!(transport.pending_requests(), 0);
+
+        // The server quits mid-call: the pending call fails and the connection reports closed.
+        assert_eq!(
+            call(&server, "exit", "").await,
+            Err("MCP server exited".to_string())
+        );
+        tokio::time::timeout(Duration::from_secs(5), transport.closed())
+            .await
+            .expect("closed");
+        assert!(call(&server, "echo", "again").await.is_err());
+    }
+
+    /// Read one HTTP request; returns its JSON body (`Null` when there is none).
+    fn read_request(socket: &TcpStream) -> Option<Value> {
+        let mut reader = BufReader::new(socket);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).ok()?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                content_length = value.trim().parse().ok()?;
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).ok()?;
+        Some(serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn write_json(socket: &mut TcpStream, body: Value) {
+        let body = body.to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nmcp-session-id: s1\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).expect("write");
+    }
+
+    /// Streamable HTTP echo server (one request per connection). `echo` answers over SSE, split
+    /// inside a multi-byte character; `exit` opens the stream and hangs up.
+    fn spawn_http_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        std::thread::spawn(move || {
+            for socket in listener.incoming() {
+                let Ok(mut socket) = socket else {
+                    break;
+                };
+                let Some(message) = read_request(&socket) else {
+                    continue;
+                };
+                let id = message["id"].clone();
+                let method = message["method"].as_str().unwrap_or_default();
+                let tool = message["params"]["name"].as_str().unwrap_or_default();
+                match (method, tool) {
+                    (_, _) if id.is_null() => {
+                        let _ = socket.write_all(
+                            b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                        );
+                    }
+                    ("initialize", _) => write_json(
+                        &mut socket,
+                        json!({ "jsonrpc": "2.0", "id": id, "result": { "protocolVersion": "2025-06-18" } }),
+                    ),
+                    ("tools/list", _) => write_json(
+                        &mut socket,
+                        json!({ "jsonrpc": "2.0", "id": id, "result": { "tools": [{ "name": "echo" }, { "name": "exit" }] } }),
+                    ),
+                    ("tools/call", "echo") => {
+                        let result = json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "result": { "content": [{ "type": "text", "text": message["params"]["arguments"]["text"] }] }
+                        });
+                        let event = format!(": ping\n\nevent: message\ndata: {result}\n\n");
+                        let split = event.find('好').expect("multi-byte text") + 1;
+                        let _ = socket.write_all(
+                            b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n",
+                        );
+                        for part in [&event.as_bytes()[..split], &event.as_bytes()[split..]] {
+                            let _ = socket.write_all(part);
+                            let _ = socket.flush();
+                            std::thread::sleep(Duration::from_millis(20));
+                        }
+                    }
+                    _ => {
+                        let _ = socket.write_all(
+                            b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n",
+                        );
+                    }
+                }
+            }
+        });
+        format!("http://{addr}/mcp")
+    }
+
+    #[tokio::test]
+    async fn test_http_server_round_trip_and_hang_up() {
+        let server = test_server(McpTransport::Http {
+            url: spawn_http_server(),
+            headers: BTreeMap::new(),
+        });
+        let (transport, tools) = start(&server).await;
+        assert_eq!(tools, ["echo", "exit"]);
+        assert_eq!(call(&server, "echo", "你好").await, Ok("你好".to_string()));
+        assert_eq!(
+            call(&server, "exit", "").await,
+            Err("No response to tools/call".to_string())
+        );
+        transport.shutdown().await;
+    }
+}
@@ -0,0 +1,16 @@
+//! MCP (Model Context Protocol) client "plugin" (crate-local module).
+//!
+//! - Runs the servers configured in settings (`mcpServers`): stdio child processes or
+//!   streamable HTTP endpoints.
+//! - Performs the `initialize` handshake and registers each server's `tools/list` on
+//!   `services::ai::ToolRegistry`, so the chat tool loop calls them like built-in tools.
+//! - Restarts servers that exit or fail with exponential backoff.
+//!
+//! The Tauri command surface lives in `crate::services::mcp`.
+
+mod ai_tools;
+mod manager;
+mod protocol;
+mod transport;
+
+pub use manager::{McpManager, McpServerState, McpServerStatus};
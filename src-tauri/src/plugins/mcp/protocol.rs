@@ -0,0 +1,245 @@
+//! JSON-RPC 2.0 framing and the MCP messages rcat uses (`initialize`, `tools/list`, `tools/call`).
+
+use serde_json::{Value, json};
+
+/// Protocol revision we ask for; the server answers with the one it speaks.
+pub(super) const PROTOCOL_VERSION: &str = "2025-06-18";
+
+pub(super) fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+pub(super) fn notification(method: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method })
+}
+
+/// Tell the server we gave up on a request (`notifications/cancelled`).
+pub(super) fn cancelled_notification(request_id: u64, reason: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/cancelled",
+        "params": { "requestId": request_id, "reason": reason }
+    })
+}
+
+/// Reply to a server -> client request. Only `ping` is supported.
+pub(super) fn reply_to_server_request(id: &Value, method: &str) -> Value {
+    if method == "ping" {
+        json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+    } else {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("Method not found: {method}") }
+        })
+    }
+}
+
+pub(super) fn initialize_params() -> Value {
+    json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "rcat", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Incoming {
+    Response {
+        id: u64,
+        result: Result<Value, String>,
+    },
+    Request {
+        id: Value,
+        method: String,
+    },
+    Notification {
+        method: String,
+    },
+}
+
+pub(super) fn parse_incoming(message: &Value) -> Option<Incoming> {
+    let method = message.get("method").and_then(|m| m.as_str());
+    let id = message.get("id").filter(|id| !id.is_null());
+    match (method, id) {
+        (Some(method), Some(id)) => Some(Incoming::Request {
+            id: id.clone(),
+            method: method.to_string(),
+        }),
+        (Some(method), None) => Some(Incoming::Notification {
+            method: method.to_string(),
+        }),
+        (None, Some(id)) => {
+            let id = id.as_u64()?;
+            let result = match message.get("error") {
+                Some(error) => Err(error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string())),
+                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+            };
+            Some(Incoming::Response { id, result })
+        }
+        (None, None) => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct McpToolInfo {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+/// One `tools/list` page: the tools and the cursor of the next page.
+pub(super) fn parse_tools(result: &Value) -> (Vec<McpToolInfo>, Option<String>) {
+    let tools = result
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|tool| {
+                    Some(McpToolInfo {
+                        name: tool.get("name")?.as_str()?.to_string(),
+                        description: tool
+                            .get("description")
+                            .and_then(|d| d.as_str())
+                            .unwrap_or("")
+                            .to_string(),
+                        input_schema: tool
+                            .get("inputSchema")
+                            .filter(|s| s.is_object())
+                            .cloned()
+                            .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let next_cursor = result
+        .get("nextCursor")
+        .and_then(|c| c.as_str())
+        .filter(|c| !c.is_empty())
+        .map(str::to_string);
+    (tools, next_cursor)
+}
+
+/// Flatten a `tools/call` result into the text fed back to the model.
+///
+/// Non-text content is summarized; `isError: true` becomes an `Err` with the same text.
+pub(super) fn format_call_result(result: &Value) -> Result<String, String> {
+    let mut parts: Vec<String> = Vec::new();
+    for item in result
+        .get("content")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let kind = item.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        match kind {
+            "text" => parts.push(
+                item.get("text")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            ),
+            "resource" => {
+                let resource = item.get("resource");
+                match resource
+                    .and_then(|r| r.get("text"))
+                    .and_then(|t| t.as_str())
+                {
+                    Some(text) => parts.push(text.to_string()),
+                    None => parts.push(format!(
+                        "[resource: {}]",
+                        resource
+                            .and_then(|r| r.get("uri"))
+                            .and_then(|u| u.as_str())
+                            .unwrap_or("?")
+                    )),
+                }
+            }
+            "resource_link" => parts.push(format!(
+                "[resource: {}]",
+                item.get("uri").and_then(|u| u.as_str()).unwrap_or("?")
+            )),
+            other => parts.push(format!("[{other} content omitted]")),
+        }
+    }
+    if parts.is_empty()
+        && let Some(structured) = result.get("structuredContent")
+    {
+        parts.push(structured.to_string());
+    }
+
+    let text = parts.join("\n");
+    if result.get("isError").and_then(|e| e.as_bool()) == Some(true) {
+        Err(if text.is_empty() {
+            "Tool reported an error".to_string()
+        } else {
+            text
+        })
+    } else {
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_messages_and_results() {
+        assert_eq!(
+            parse_incoming(&json!({ "jsonrpc": "2.0", "id": 3, "result": { "ok": true } })),
+            Some(Incoming::Response {
+                id: 3,
+                result: Ok(json!({ "ok": true }))
+            })
+        );
+        assert_eq!(
+            parse_incoming(
+                &json!({ "jsonrpc": "2.0", "id": 4, "error": { "code": -32602, "message": "bad" } })
+            ),
+            Some(Incoming::Response {
+                id: 4,
+                result: Err("bad".to_string())
+            })
+        );
+        assert_eq!(
+            parse_incoming(
+                &json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })
+            ),
+            Some(Incoming::Notification {
+                method: "notifications/tools/list_changed".to_string()
+            })
+        );
+        assert!(matches!(
+            parse_incoming(&json!({ "jsonrpc": "2.0", "id": "a", "method": "ping" })),
+            Some(Incoming::Request { .. })
+        ));
+
+        let (tools, cursor) = parse_tools(&json!({
+            "tools": [{ "name": "echo", "inputSchema": { "type": "object", "properties": { "text": { "type": "string" } } } }],
+            "nextCursor": "2"
+        }));
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "echo");
+        assert_eq!(cursor.as_deref(), Some("2"));
+
+        assert_eq!(
+            format_call_result(&json!({
+                "content": [{ "type": "text", "text": "hi" }, { "type": "image", "data": "..." }]
+            })),
+            Ok("hi\n[image content omitted]".to_string())
+        );
+        assert_eq!(
+            format_call_result(
+                &json!({ "content": [{ "type": "text", "text": "boom" }], "isError": true })
+            ),
+            Err("boom".to_string())
+        );
+    }
+}
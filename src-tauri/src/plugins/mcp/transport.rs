@@ -0,0 +1,503 @@
+//! MCP transports: stdio child processes and streamable HTTP.
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_util::sync::CancellationToken;
+
+use crate::services::config::McpTransport;
+use crate::services::sse::SseDecoder;
+
+use super::protocol::{self, Incoming};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+pub(super) enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+impl Transport {
+    /// Connect (spawn the process / prepare the HTTP client).
+    ///
+    /// Server notifications (method names) are forwarded to `events`.
+    pub(super) fn connect(
+        server: &str,
+        config: &McpTransport,
+        http_client: reqwest::Client,
+        events: mpsc::UnboundedSender<String>,
+    ) -> Result<Self, String> {
+        match config {
+            McpTransport::Stdio {
+                command,
+                args,
+                env,
+                cwd,
+            } => StdioTransport::spawn(server, command, args, env, cwd.as_deref(), events)
+                .map(Transport::Stdio),
+            McpTransport::Http { url, headers } => {
+                HttpTransport::new(url, headers, http_client, events).map(Transport::Http)
+            }
+        }
+    }
+
+    fn next_request_id(&self) -> u64 {
+        match self {
+            Transport::Stdio(t) => t.next_id.fetch_add(1, Ordering::Relaxed),
+            Transport::Http(t) => t.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    async fn send_request(&self, id: u64, method: &str, params: Value) -> Result<Value, String> {
+        match self {
+            Transport::Stdio(t) => t.request(id, method, params).await,
+            Transport::Http(t) => t.request(id, method, params).await,
+        }
+    }
+
+    pub(super) async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let id = self.next_request_id();
+        tokio::time::timeout(timeout, self.send_request(id, method, params))
+            .await
+            .map_err(|_| format!("{method} timed out after {}s", timeout.as_secs()))?
+    }
+
+    /// A request the caller may give up on: when `cancel` fires, or the future is dropped
+    /// (tool timeout, aborted chat), the server gets `notifications/cancelled`.
+    pub(super) async fn request_cancellable(
+        self: &Arc<Self>,
+        method: &str,
+        params: Value,
+        cancel: &CancellationToken,
+    ) -> Result<Value, String> {
+        let id = self.next_request_id();
+        let guard = CancelOnDrop {
+            transport: Some(self.clone()),
+            id,
+        };
+        tokio::select! {
+            result = self.send_request(id, method, params) => {
+                guard.disarm();
+                result
+            }
+            _ = cancel.cancelled() => Err("Cancelled".to_string()),
+        }
+    }
+
+    pub(super) async fn notify(&self, method: &str) -> Result<(), String> {
+        self.send_notification(&protocol::notification(method))
+            .await
+    }
+
+    async fn send_notification(&self, message: &Value) -> Result<(), String> {
+        match self {
+            Transport::Stdio(t) => t.send(message).await,
+            Transport::Http(t) => t.post(message, None).await.map(|_| ()),
+        }
+    }
+
+    /// Resolves when the connection is gone (the child exited). HTTP connections never close.
+    pub(super) async fn closed(&self) {
+        match self {
+            Transport::Stdio(t) => {
+                let mut closed = t.closed.clone();
+                let _ = closed.wait_for(|closed| *closed).await;
+            }
+            Transport::Http(_) => std::future::pending().await,
+        }
+    }
+
+    /// Requests still waiting for an answer (always 0 over HTTP).
+    #[cfg(test)]
+    pub(super) fn pending_requests(&self) -> usize {
+        match self {
+            Transport::Stdio(t) => t.pending.lock().map(|p| p.len()).unwrap_or(0),
+            Transport::Http(_) => 0,
+        }
+    }
+
+    pub(super) fn set_protocol_version(&self, version: &str) {
+        if let Transport::Http(t) = self
+            && let Ok(mut current) = t.protocol_version.lock()
+        {
+            *current = Some(version.to_string());
+        }
+    }
+
+    pub(super) async fn shutdown(&self) {
+        match self {
+            Transport::Stdio(t) => t.shutdown().await,
+            Transport::Http(t) => t.shutdown().await,
+        }
+    }
+}
+
+/// Sends `notifications/cancelled` for an unanswered request when dropped.
+struct CancelOnDrop {
+    transport: Option<Arc<Transport>>,
+    id: u64,
+}
+
+impl CancelOnDrop {
+    fn disarm(mut self) {
+        self.transport = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(transport) = self.transport.take() else {
+            return;
+        };
+        let id = self.id;
+        tauri::async_runtime::spawn(async move {
+            let message = protocol::cancelled_notification(id, "Cancelled by client");
+            let _ = transport.send_notification(&message).await;
+        });
+    }
+}
+
+pub(super) struct StdioTransport {
+    /// `None` once shut down (dropping the handle closes the pipe).
+    stdin: Arc<tokio::sync::Mutex<Option<ChildStdin>>>,
+    child: tokio::sync::Mutex<Child>,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: watch::Receiver<bool>,
+}
+
+impl StdioTransport {
+    fn spawn(
+        server: &str,
+        command: &str,
+        args: &[String],
+        env: &std::collections::BTreeMap<String, String>,
+        cwd: Option<&str>,
+        events: mpsc::UnboundedSender<String>,
+    ) -> Result<Self, String> {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = cwd.filter(|c| !c.trim().is_empty()) {
+            cmd.current_dir(cwd);
+        }
+        #[cfg(target_os = "windows")]
+        {
+            // CREATE_NO_WINDOW: don't flash a console for each server.
+            cmd.creation_flags(0x0800_0000);
+        }
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to start {command}: {e}"))?;
+        let stdin = child.stdin.take().ok_or("Child stdin unavailable")?;
+        let stdout = child.stdout.take().ok_or("Child stdout unavailable")?;
+        let stderr = child.stderr.take().ok_or("Child stderr unavailable")?;
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(Some(stdin)));
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let (closed_tx, closed_rx) = watch::channel(false);
+
+        let server_name = server.to_string();
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log::info!("[mcp:{}] {}", server_name, line);
+            }
+        });
+
+        let server_name = server.to_string();
+        let reader_pending = pending.clone();
+        let reader_stdin = stdin.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let Ok(message) = serde_json::from_str::<Value>(line) else {
+                    log::warn!("[mcp:{}] ignoring non-JSON output: {}", server_name, line);
+                    continue;
+                };
+                match protocol::parse_incoming(&message) {
+                    Some(Incoming::Response { id, result }) => {
+                        let sender = reader_pending.lock().ok().and_then(|mut p| p.remove(&id));
+                        if let Some(sender) = sender {
+                            let _ = sender.send(result);
+                        }
+                    }
+                    Some(Incoming::Request { id, method }) => {
+                        let reply = protocol::reply_to_server_request(&id, &method);
+                        let _ = write_line(&reader_stdin, &reply).await;
+                    }
+                    Some(Incoming::Notification { method }) => {
+                        let _ = events.send(method);
+                    }
+                    None => {}
+                }
+            }
+
+            // EOF: fail whatever is still waiting.
+            if let Ok(mut pending) = reader_pending.lock() {
+                for (_, sender) in pending.drain() {
+                    let _ = sender.send(Err("MCP server exited".to_string()));
+                }
+            }
+            let _ = closed_tx.send(true);
+        });
+
+        Ok(Self {
+            stdin,
+            child: tokio::sync::Mutex::new(child),
+            pending,
+            next_id: AtomicU64::new(1),
+            closed: closed_rx,
+        })
+    }
+
+    async fn request(&self, id: u64, method: &str, params: Value) -> Result<Value, String> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|_| "MCP pending lock poisoned".to_string())?
+            .insert(id, tx);
+        // Also when the caller drops this future (timeout, cancellation).
+        let _entry = PendingEntry {
+            pending: &self.pending,
+            id,
+        };
+
+        self.send(&protocol::request(id, method, params)).await?;
+        rx.await
+            .unwrap_or_else(|_| Err("MCP server exited".to_string()))
+    }
+
+    async fn send(&self, message: &Value) -> Result<(), String> {
+        if *self.closed.borrow() {
+            return Err("MCP server exited".to_string());
+        }
+        write_line(&self.stdin, message).await
+    }
+
+    async fn shutdown(&self) {
+        // Closing stdin asks the server to exit; kill it if it does not.
+        self.stdin.lock().await.take();
+        let mut child = self.child.lock().await;
+        if tokio::time::timeout(Duration::from_secs(2), child.wait())
+            .await
+            .is_err()
+        {
+            let _ = child.kill().await;
+        }
+    }
+}
+
+/// Removes an unanswered request from the pending map when its caller is done with it.
+struct PendingEntry<'a> {
+    pending: &'a Pending,
+    id: u64,
+}
+
+impl Drop for PendingEntry<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+async fn write_line(
+    stdin: &tokio::sync::Mutex<Option<ChildStdin>>,
+    message: &Value,
+) -> Result<(), String> {
+    let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    line.push('\n');
+    let mut stdin = stdin.lock().await;
+    let stdin = stdin
+        .as_mut()
+        .ok_or_else(|| "MCP server is shut down".to_string())?;
+    stdin
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to MCP server: {e}"))?;
+    stdin
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to write to MCP server: {e}"))
+}
+
+pub(super) struct HttpTransport {
+    url: String,
+    headers: HeaderMap,
+    client: reqwest::Client,
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+    next_id: AtomicU64,
+    events: mpsc::UnboundedSender<String>,
+}
+
+impl HttpTransport {
+    fn new(
+        url: &str,
+        headers: &std::collections::BTreeMap<String, String>,
+        client: reqwest::Client,
+        events: mpsc::UnboundedSender<String>,
+    ) -> Result<Self, String> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|e| format!("Invalid header name {name}: {e}"))?;
+            let value = HeaderValue::from_str(value.trim())
+                .map_err(|e| format!("Invalid header value for {name}: {e}"))?;
+            header_map.insert(name, value);
+        }
+        Ok(Self {
+            url: url.trim().to_string(),
+            headers: header_map,
+            client,
+            session_id: Mutex::new(None),
+            protocol_version: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+            events,
+        })
+    }
+
+    async fn request(&self, id: u64, method: &str, params: Value) -> Result<Value, String> {
+        self.post(&protocol::request(id, method, params), Some(id))
+            .await?
+            .ok_or_else(|| format!("No response to {method}"))?
+    }
+
+    fn session_headers(&self) -> HeaderMap {
+        let mut headers = self.headers.clone();
+        let session = self.session_id.lock().ok().and_then(|s| s.clone());
+        if let Some(value) = session.and_then(|s| HeaderValue::from_str(&s).ok()) {
+            headers.insert(SESSION_HEADER, value);
+        }
+        let version = self.protocol_version.lock().ok().and_then(|v| v.clone());
+        if let Some(value) = version.and_then(|v| HeaderValue::from_str(&v).ok()) {
+            headers.insert(PROTOCOL_VERSION_HEADER, value);
+        }
+        headers
+    }
+
+    /// POST one message; returns the response matching `expect_id` (JSON body or SSE stream).
+    async fn post(
+        &self,
+        message: &Value,
+        expect_id: Option<u64>,
+    ) -> Result<Option<Result<Value, String>>, String> {
+        let response = self
+            .client
+            .post(&self.url)
+            .headers(self.session_headers())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| format!("MCP request failed: {e}"))?;
+
+        let status = response.status();
+        if let Some(session) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            && let Ok(mut current) = self.session_id.lock()
+        {
+            *current = Some(session.to_string());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "MCP server returned HTTP {status}: {}",
+                body.trim()
+            ));
+        }
+        let Some(expect_id) = expect_id else {
+            return Ok(None);
+        };
+
+        let is_sse = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if !is_sse {
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|e| format!("Invalid MCP response: {e}"))?;
+            let messages = match body {
+                Value::Array(items) => items,
+                other => vec![other],
+            };
+            for message in &messages {
+                if let Some(result) = self.dispatch(message, expect_id) {
+                    return Ok(Some(result));
+                }
+            }
+            return Ok(None);
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut decoder = SseDecoder::default();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| format!("MCP stream failed: {e}"))?;
+            decoder.push(&chunk);
+            while let Some(event) = decoder.next_event() {
+                if let Ok(message) = serde_json::from_str::<Value>(&event.data)
+                    && let Some(result) = self.dispatch(&message, expect_id)
+                {
+                    return Ok(Some(result));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Route an incoming message; returns the result when it answers `expect_id`.
+    fn dispatch(&self, message: &Value, expect_id: u64) -> Option<Result<Value, String>> {
+        match protocol::parse_incoming(message)? {
+            Incoming::Response { id, result } if id == expect_id => Some(result),
+            Incoming::Notification { method } => {
+                let _ = self.events.send(method);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    async fn shutdown(&self) {
+        let has_session = self.session_id.lock().is_ok_and(|s| s.is_some());
+        if has_session {
+            let _ = self
+                .client
+                .delete(&self.url)
+                .headers(self.session_headers())
+                .send()
+                .await;
+        }
+    }
+}
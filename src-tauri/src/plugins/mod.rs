@@ -4,5 +4,6 @@
 //! boundary so other parts of the app can depend on them without tight coupling.
 
 pub(crate) mod history;
+pub(crate) mod mcp;
 #[cfg(feature = "vision")]
 pub(crate) mod vision;
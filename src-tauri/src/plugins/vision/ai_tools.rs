@@ -1,58 +1,273 @@
-use crate::services::config::{AiConfig, AiProvider};
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::plugins::history::AuditContext;
+use crate::services::ai::{ApprovalScope, Tool, ToolFuture, ToolRegistry};
+use crate::services::config::AiConfig;
 use crate::services::prompts;
 
-use super::{capture_screen_text, capture_smart, list_capturable_windows};
-
-pub(super) fn tools_schema(config: &AiConfig) -> serde_json::Value {
-    // DeepSeek strict Tool Calls are enabled under `/beta` + `strict: true` schemas.
-    // Allow an explicit env override for other providers during testing.
-    let strict_from_env = std::env::var("AI_TOOL_STRICT")
-        .ok()
-        .map(|v| {
-            matches!(
-                v.trim().to_ascii_lowercase().as_str(),
-                "1" | "true" | "yes" | "on"
-            )
-        })
-        .unwrap_or(false);
+use super::{
+    capture_approved_window, describe_window, get_smart_window, has_vision_model,
+    list_capturable_windows, runtime_enabled,
+};
+
+fn scope_of(window: Option<super::WindowInfo>, fallback_title: Option<&str>) -> ApprovalScope {
+    match window {
+        Some(window) => ApprovalScope {
+            app: Some(window.app_name).filter(|a| !a.is_empty()),
+            window: Some(window.title),
+            pid: Some(window.pid),
+        },
+        None => ApprovalScope {
+            app: None,
+            window: fallback_title.map(str::to_string),
+            pid: None,
+        },
+    }
+}
 
-    let base = config.base_url.trim().trim_end_matches('/');
-    let strict_from_base =
-        matches!(config.provider, AiProvider::DeepSeek) && base.ends_with("/beta");
+/// Capture tools act only on the window the user approved, never on a fresh lookup.
+fn approved_scope(approved: Option<&ApprovalScope>) -> Result<&ApprovalScope, String> {
+    approved.ok_or_else(|| "Window capture was not approved".to_string())
+}
 
-    prompts::build_vision_tools_schema(strict_from_env || strict_from_base)
+/// Same lookup as the capture: first window whose title contains the pattern.
+fn find_window(pattern: &str) -> Option<super::WindowInfo> {
+    let pattern_lower = pattern.to_lowercase();
+    list_capturable_windows().ok().and_then(|windows| {
+        windows
+            .into_iter()
+            .find(|w| w.title.to_lowercase().contains(&pattern_lower))
+    })
 }
 
-pub(super) async fn execute_tool_call(
-    name: &str,
-    arguments: &serde_json::Value,
-) -> Result<String, String> {
-    match name {
-        name if name == prompts::tool_list_windows::NAME => {
+struct ListWindowsTool;
+
+impl Tool for ListWindowsTool {
+    fn name(&self) -> &str {
+        prompts::tool_list_windows::NAME
+    }
+
+    fn definition(&self) -> serde_json::Value {
+        prompts::list_windows_function()
+    }
+
+    fn is_available(&self, _config: &AiConfig) -> bool {
+        runtime_enabled()
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _arguments: &'a serde_json::Value,
+        _cancel: &'a CancellationToken,
+        _audit: &'a AuditContext,
+        _approved: Option<&'a ApprovalScope>,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
             let windows = list_capturable_windows()?;
             let formatted: Vec<(String, String, bool)> = windows
                 .iter()
                 .map(|w| (w.title.clone(), w.app_name.clone(), w.is_focused))
                 .collect();
             Ok(prompts::format_window_list(&formatted))
-        }
-        name if name == prompts::tool_capture_window::NAME => {
+        })
+    }
+}
+
+struct CaptureWindowTool {
+    app: tauri::AppHandle,
+}
+
+impl Tool for CaptureWindowTool {
+    fn name(&self) -> &str {
+        prompts::tool_capture_window::NAME
+    }
+
+    fn definition(&self) -> serde_json::Value {
+        prompts::capture_window_function()
+    }
+
+    fn is_available(&self, _config: &AiConfig) -> bool {
+        runtime_enabled()
+    }
+
+    fn approval_scope(&self, arguments: &serde_json::Value) -> Option<ApprovalScope> {
+        let pattern = arguments
+            .get(prompts::tool_capture_window::PARAM_WINDOW_TITLE)
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        Some(scope_of(find_window(pattern), Some(pattern)))
+    }
+
+    fn execute<'a>(
+        &'a self,
+        arguments: &'a serde_json::Value,
+        _cancel: &'a CancellationToken,
+        audit: &'a AuditContext,
+        approved: Option<&'a ApprovalScope>,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
             let window_title = arguments
                 .get(prompts::tool_capture_window::PARAM_WINDOW_TITLE)
                 .and_then(|v| v.as_str())
                 .ok_or_else(|| "Missing window_title argument".to_string())?;
 
-            let result = capture_screen_text(Some(window_title.to_string())).await?;
+            let result =
+                capture_approved_window(&self.app, approved_scope(approved)?, audit).await?;
             let window_name = result
                 .window_name
                 .unwrap_or_else(|| window_title.to_string());
             Ok(prompts::format_window_capture(&window_name, &result.text))
-        }
-        name if name == prompts::tool_capture_focused::NAME => {
-            let result = capture_smart().await?;
+        })
+    }
+}
+
+struct CaptureFocusedTool {
+    app: tauri::AppHandle,
+}
+
+impl Tool for CaptureFocusedTool {
+    fn name(&self) -> &str {
+        prompts::tool_capture_focused::NAME
+    }
+
+    fn definition(&self) -> serde_json::Value {
+        prompts::capture_focused_function()
+    }
+
+    fn is_available(&self, _config: &AiConfig) -> bool {
+        runtime_enabled()
+    }
+
+    fn approval_scope(&self, _arguments: &serde_json::Value) -> Option<ApprovalScope> {
+        Some(scope_of(get_smart_window().ok().flatten(), None))
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _arguments: &'a serde_json::Value,
+        _cancel: &'a CancellationToken,
+        audit: &'a AuditContext,
+        approved: Option<&'a ApprovalScope>,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let result =
+                capture_approved_window(&self.app, approved_scope(approved)?, audit).await?;
             let window_name = result.window_name.unwrap_or_else(|| "未知".to_string());
             Ok(prompts::format_focused_capture(&window_name, &result.text))
+        })
+    }
+}
+
+struct DescribeScreenTool {
+    app: tauri::AppHandle,
+}
+
+impl DescribeScreenTool {
+    /// Window title pattern; empty means the focused window.
+    fn window_title(arguments: &serde_json::Value) -> Option<&str> {
+        arguments
+            .get(prompts::tool_describe_screen::PARAM_WINDOW_TITLE)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Offered only when some model can look at the screenshot.
+    fn available(config: &AiConfig) -> bool {
+        runtime_enabled() && has_vision_model(config)
+    }
+}
+
+impl Tool for DescribeScreenTool {
+    fn name(&self) -> &str {
+        prompts::tool_describe_screen::NAME
+    }
+
+    fn definition(&self) -> serde_json::Value {
+        prompts::describe_screen_function()
+    }
+
+    fn is_available(&self, config: &AiConfig) -> bool {
+        Self::available(config)
+    }
+
+    fn approval_scope(&self, arguments: &serde_json::Value) -> Option<ApprovalScope> {
+        match Self::window_title(arguments) {
+            Some(pattern) => Some(scope_of(find_window(pattern), Some(pattern))),
+            None => Some(scope_of(get_smart_window().ok().flatten(), None)),
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        arguments: &'a serde_json::Value,
+        _cancel: &'a CancellationToken,
+        audit: &'a AuditContext,
+        approved: Option<&'a ApprovalScope>,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let question = arguments
+                .get(prompts::tool_describe_screen::PARAM_QUESTION)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|q| !q.is_empty())
+                .unwrap_or(prompts::tool_describe_screen::DEFAULT_QUESTION);
+
+            let (window_name, description) =
+                describe_window(&self.app, question, approved_scope(approved)?, audit).await?;
+            Ok(prompts::format_screen_description(
+                &window_name,
+                &description,
+            ))
+        })
+    }
+}
+
+pub(super) fn register(app: &tauri::AppHandle, registry: &ToolRegistry) {
+    registry.register(Arc::new(ListWindowsTool));
+    registry.register(Arc::new(CaptureWindowTool { app: app.clone() }));
+    registry.register(Arc::new(CaptureFocusedTool { app: app.clone() }));
+    registry.register(Arc::new(DescribeScreenTool { app: app.clone() }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_screen_window_title() {
+        let title = |arguments: serde_json::Value| {
+            DescribeScreenTool::window_title(&arguments).map(str::to_string)
+        };
+        assert_eq!(
+            title(serde_json::json!({ "window_title": "  Notes \n" })),
+            Some("Notes".to_string())
+        );
+        // Empty, blank, missing or non-string titles fall back to the focused window.
+        assert_eq!(title(serde_json::json!({ "window_title": "" })), None);
+        assert_eq!(title(serde_json::json!({ "window_title": "   " })), None);
+        assert_eq!(
+            title(serde_json::json!({ "question": "What is this?" })),
+            None
+        );
+        assert_eq!(title(serde_json::json!({ "window_title": 3 })), None);
+    }
+
+    #[test]
+    fn test_describe_screen_needs_vision_model() {
+        // The default models (gpt-4o-mini / gpt-4o) both support vision.
+        let mut config = AiConfig::default();
+        assert!(DescribeScreenTool::available(&config));
+
+        // A text-only selection still finds the provider's vision model.
+        config.models[0].supports_vision = false;
+        assert!(DescribeScreenTool::available(&config));
+
+        for model in &mut config.models {
+            model.supports_vision = false;
         }
-        _ => Err(format!("Unknown tool: {}", name)),
+        assert!(!DescribeScreenTool::available(&config));
     }
 }
@@ -147,25 +147,27 @@ pub(crate) fn get_smart_window() -> Result<Option<WindowInfo>, String> {
     Ok(windows.into_iter().next())
 }
 
-pub(crate) fn capture_smart_image() -> Result<(DynamicImage, String), String> {
-    use xcap::Window;
-
+pub(crate) fn capture_smart_image() -> Result<(DynamicImage, WindowInfo), String> {
     let target_info =
         get_smart_window()?.ok_or_else(|| "No suitable window found to capture".to_string())?;
+    let image = capture_exact_window(target_info.pid, &target_info.title)?;
+    Ok((image, target_info))
+}
+
+/// Capture the window of process `pid` whose title is exactly `title`.
+pub(crate) fn capture_exact_window(pid: u32, title: &str) -> Result<DynamicImage, String> {
+    use xcap::Window;
 
     let windows = Window::all().map_err(|e| format!("Failed to enumerate windows: {}", e))?;
 
     let target = windows
         .into_iter()
-        .find(|w| {
-            w.pid().unwrap_or(0) == target_info.pid
-                && w.title().unwrap_or_default() == target_info.title
-        })
-        .ok_or_else(|| format!("Window '{}' no longer exists", target_info.title))?;
+        .find(|w| w.pid().unwrap_or(0) == pid && w.title().unwrap_or_default() == title)
+        .ok_or_else(|| format!("Window '{}' no longer exists", title))?;
 
     let buffer = target
         .capture_image()
         .map_err(|e| format!("Failed to capture window: {}", e))?;
 
-    Ok((DynamicImage::ImageRgba8(buffer), target_info.title))
+    Ok(DynamicImage::ImageRgba8(buffer))
 }
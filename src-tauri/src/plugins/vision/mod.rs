@@ -1,8 +1,8 @@
 //! Vision "plugin" (crate-local module).
 //!
 //! - Provides screen/window capture + OCR + optional VLM analysis.
-//! - Exposes helper entrypoints for AI tool-calls without coupling AI code to
-//!   Tauri command modules.
+//! - Registers its AI tools on `services::ai::ToolRegistry` so the chat tool loop
+//!   does not need to know about vision specifics.
 
 mod ai_tools;
 mod capture;
@@ -12,19 +12,50 @@ mod vlm;
 
 pub use types::{ScreenCaptureResult, VlmAnalysisResult, WindowInfo};
 
-pub(crate) fn ai_tools_schema(config: &crate::services::config::AiConfig) -> serde_json::Value {
-    ai_tools::tools_schema(config)
+use crate::plugins::history::{AuditContext, AuditKind, record_audit};
+use crate::services::ai::ApprovalScope;
+
+/// Runtime kill-switch (`RCAT_VISION` / `VISION_ENABLED`), on by default.
+pub(crate) fn runtime_enabled() -> bool {
+    std::env::var("RCAT_VISION")
+        .or_else(|_| std::env::var("VISION_ENABLED"))
+        .ok()
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "y" | "on"
+            )
+        })
+        .unwrap_or(true)
+}
+
+/// Register the vision tools (window list / capture) on the AI tool registry.
+pub(crate) fn register_ai_tools(
+    app: &tauri::AppHandle,
+    registry: &crate::services::ai::ToolRegistry,
+) {
+    ai_tools::register(app, registry);
 }
 
-pub(crate) async fn execute_ai_tool_call(
-    name: &str,
-    arguments: &serde_json::Value,
-) -> Result<String, String> {
-    ai_tools::execute_tool_call(name, arguments).await
+/// Log an OCR read in the history audit log.
+async fn audit_capture(
+    app: &tauri::AppHandle,
+    audit: &AuditContext,
+    app_name: Option<&str>,
+    window_title: Option<&str>,
+    text: &str,
+) {
+    let mut entry = audit.entry(AuditKind::ScreenCapture);
+    entry.app_name = app_name.filter(|a| !a.is_empty()).map(str::to_string);
+    entry.window_title = window_title.map(str::to_string);
+    entry.chars = text.chars().count() as u64;
+    record_audit(app, entry).await;
 }
 
 pub(crate) async fn capture_screen_text(
+    app: &tauri::AppHandle,
     window_name: Option<String>,
+    audit: &AuditContext,
 ) -> Result<ScreenCaptureResult, String> {
     let (image, captured_window) = if let Some(ref pattern) = window_name {
         let (img, name) = capture::capture_window(pattern)?;
@@ -34,6 +65,7 @@ pub(crate) async fn capture_screen_text(
     };
 
     let (text, confidence) = ocr::perform_ocr(&image).await?;
+    audit_capture(app, audit, None, captured_window.as_deref(), &text).await;
 
     Ok(ScreenCaptureResult {
         text,
@@ -44,10 +76,64 @@ pub(crate) async fn capture_screen_text(
 }
 
 pub(crate) async fn analyze_screen_vlm(
+    app: &tauri::AppHandle,
     prompt: String,
     window_name: Option<String>,
+    audit: &AuditContext,
 ) -> Result<VlmAnalysisResult, String> {
-    vlm::analyze_screen_vlm(prompt, window_name).await
+    vlm::analyze_screen_vlm(app, prompt, window_name, audit).await
+}
+
+/// The window an approval pinned, as `(pid, title)`.
+fn approved_window(scope: &ApprovalScope) -> Result<(u32, &str), String> {
+    match (scope.pid, scope.window.as_deref()) {
+        (Some(pid), Some(title)) => Ok((pid, title)),
+        (_, Some(pattern)) => Err(format!("No window matching '{}' found", pattern)),
+        (_, None) => Err("No suitable window found to capture".to_string()),
+    }
+}
+
+/// OCR exactly the window the user approved; fails if it closed or changed its title since.
+pub(crate) async fn capture_approved_window(
+    app: &tauri::AppHandle,
+    scope: &ApprovalScope,
+    audit: &AuditContext,
+) -> Result<ScreenCaptureResult, String> {
+    let (pid, title) = approved_window(scope)?;
+    let image = capture::capture_exact_window(pid, title)?;
+    let (text, confidence) = ocr::perform_ocr(&image).await?;
+    audit_capture(app, audit, scope.app.as_deref(), Some(title), &text).await;
+
+    Ok(ScreenCaptureResult {
+        text,
+        confidence,
+        timestamp: types::timestamp_ms(),
+        window_name: Some(title.to_string()),
+    })
+}
+
+/// Describe the approved window with the vision model; returns `(title, description)`.
+pub(crate) async fn describe_window(
+    app: &tauri::AppHandle,
+    prompt: &str,
+    scope: &ApprovalScope,
+    audit: &AuditContext,
+) -> Result<(String, String), String> {
+    vlm::describe_window(app, prompt, scope, audit).await
+}
+
+/// Whether screen analysis has a model to use with this config.
+pub(crate) fn has_vision_model(config: &crate::services::config::AiConfig) -> bool {
+    vlm::vision_model(config).is_some()
+}
+
+/// Chat image attachments, encoded within the same limits as VLM screenshots.
+pub(crate) fn normalize_image_data_url(url: &str) -> Result<String, String> {
+    vlm::normalize_image_data_url(url)
+}
+
+pub(crate) fn image_file_data_url(path: &str) -> Result<String, String> {
+    vlm::image_file_data_url(path)
 }
 
 pub(crate) fn list_capturable_windows() -> Result<Vec<WindowInfo>, String> {
@@ -58,14 +144,25 @@ pub(crate) fn get_smart_window() -> Result<Option<WindowInfo>, String> {
     capture::get_smart_window()
 }
 
-pub(crate) async fn capture_smart() -> Result<ScreenCaptureResult, String> {
-    let (image, window_name) = capture::capture_smart_image()?;
+pub(crate) async fn capture_smart(
+    app: &tauri::AppHandle,
+    audit: &AuditContext,
+) -> Result<ScreenCaptureResult, String> {
+    let (image, window) = capture::capture_smart_image()?;
     let (text, confidence) = ocr::perform_ocr(&image).await?;
+    audit_capture(
+        app,
+        audit,
+        Some(&window.app_name),
+        Some(&window.title),
+        &text,
+    )
+    .await;
 
     Ok(ScreenCaptureResult {
         text,
         confidence,
         timestamp: types::timestamp_ms(),
-        window_name: Some(window_name),
+        window_name: Some(window.title),
     })
 }
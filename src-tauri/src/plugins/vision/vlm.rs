@@ -1,21 +1,32 @@
 use image::DynamicImage;
 
-use crate::services::config;
+use crate::plugins::history::{AuditContext, AuditEntry, AuditKind, record_audit};
+use crate::services::ai::ApprovalScope;
+use crate::services::config::{self, AiConfig};
 use crate::services::retry::RetryConfig;
 
 use super::capture;
-use super::types::{timestamp_ms, VlmAnalysisResult};
+use super::types::{VlmAnalysisResult, timestamp_ms};
+
+const JPEG_DATA_URL_HEADER: &str = "data:image/jpeg;base64";
+/// Upper bound for image files attached from disk.
+const MAX_ATTACHMENT_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Longest image side sent to vision models (`VLM_IMAGE_MAX_DIM`, 0 = no limit).
+fn max_image_dim() -> u32 {
+    std::env::var("VLM_IMAGE_MAX_DIM")
+        .ok()
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .unwrap_or(1280)
+}
 
 pub(crate) fn image_to_base64(image: &DynamicImage) -> Result<String, String> {
-    use base64::{engine::general_purpose, Engine as _};
+    use base64::{Engine as _, engine::general_purpose};
     use image::codecs::jpeg::JpegEncoder;
     use image::imageops::FilterType;
     use image::{ColorType, GenericImageView};
 
-    let max_dim = std::env::var("VLM_IMAGE_MAX_DIM")
-        .ok()
-        .and_then(|v| v.trim().parse::<u32>().ok())
-        .unwrap_or(1280);
+    let max_dim = max_image_dim();
 
     let quality = std::env::var("VLM_JPEG_QUALITY")
         .ok()
@@ -54,29 +65,144 @@ pub(crate) fn image_to_base64(image: &DynamicImage) -> Result<String, String> {
     Ok(general_purpose::STANDARD.encode(buffer))
 }
 
+/// Decode PNG/JPEG bytes and re-encode them as a JPEG data URL within the VLM limits.
+fn encode_data_url(bytes: &[u8]) -> Result<String, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| format!("Unsupported image: {}", e))?;
+    let data = image_to_base64(&image)?;
+    Ok(format!("{},{}", JPEG_DATA_URL_HEADER, data))
+}
+
+/// Normalize a `data:image/...;base64,` attachment; JPEGs already within the limits are kept
+/// as-is so stored attachments are not re-compressed on every request.
+pub(crate) fn normalize_image_data_url(url: &str) -> Result<String, String> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let (header, data) = url
+        .split_once(',')
+        .filter(|(header, _)| header.starts_with("data:image/") && header.ends_with(";base64"))
+        .ok_or_else(|| "Image attachment must be a base64 data URL".to_string())?;
+    let bytes = general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|e| format!("Invalid image data: {}", e))?;
+
+    if header == JPEG_DATA_URL_HEADER {
+        let max_dim = max_image_dim();
+        let fits = image::ImageReader::new(std::io::Cursor::new(&bytes))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+            .is_some_and(|(w, h)| max_dim == 0 || w.max(h) <= max_dim);
+        if fits {
+            return Ok(url.to_string());
+        }
+    }
+    encode_data_url(&bytes)
+}
+
+/// Read a local image file and encode it like [`normalize_image_data_url`].
+pub(crate) fn image_file_data_url(path: &str) -> Result<String, String> {
+    let path = std::path::Path::new(path.trim());
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read image '{}': {}", path.display(), e))?
+        .len();
+    if size > MAX_ATTACHMENT_FILE_BYTES {
+        return Err(format!(
+            "Image '{}' is too large ({} MB max)",
+            path.display(),
+            MAX_ATTACHMENT_FILE_BYTES / (1024 * 1024)
+        ));
+    }
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read image '{}': {}", path.display(), e))?;
+    encode_data_url(&bytes)
+}
+
+/// Explicit vision model (`AI_VISION_MODEL`, `LLM_VISION_MODEL`, `VLM_MODEL`).
+fn vision_model_override() -> Option<String> {
+    ["AI_VISION_MODEL", "LLM_VISION_MODEL", "VLM_MODEL"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .map(|v| v.trim().to_string())
+        .find(|v| !v.is_empty())
+}
+
+/// Model used for screen analysis: the explicit override, else the selected model when it
+/// supports vision, else the provider's first vision-capable model.
+pub(crate) fn vision_model(config: &AiConfig) -> Option<String> {
+    vision_model_override().or_else(|| {
+        let mut vision_models = config.models.iter().filter(|m| m.supports_vision);
+        let selected = config
+            .models
+            .iter()
+            .find(|m| m.supports_vision && m.id == config.model);
+        selected
+            .or_else(|| vision_models.next())
+            .map(|m| m.id.clone())
+    })
+}
+
 pub(crate) async fn analyze_screen_vlm(
+    app: &tauri::AppHandle,
     prompt: String,
     window_name: Option<String>,
+    audit: &AuditContext,
 ) -> Result<VlmAnalysisResult, String> {
-    let image = if let Some(ref pattern) = window_name {
-        let (img, _) = capture::capture_window(pattern)?;
-        img
+    let (image, captured_window) = if let Some(ref pattern) = window_name {
+        let (img, name) = capture::capture_window(pattern)?;
+        (img, Some(name))
     } else {
-        capture::capture_screen()?
+        (capture::capture_screen()?, None)
     };
 
-    let base64_image = image_to_base64(&image)?;
     let config = config::load_ai_config();
+    let model = vision_model(&config)
+        .or_else(|| std::env::var("LLM_MODEL").ok())
+        .unwrap_or_else(|| config.model.clone());
+
+    let mut entry = audit.entry(AuditKind::VlmAnalysis);
+    entry.window_title = captured_window;
+    let content = describe_image(app, &config, model, &prompt, &image, entry).await?;
+
+    Ok(VlmAnalysisResult {
+        content,
+        timestamp: timestamp_ms(),
+    })
+}
+
+/// Capture the window `scope` was approved for and describe it with the vision model. Returns
+/// the captured window's title and the description.
+pub(crate) async fn describe_window(
+    app: &tauri::AppHandle,
+    prompt: &str,
+    scope: &ApprovalScope,
+    audit: &AuditContext,
+) -> Result<(String, String), String> {
+    let config = config::load_ai_config();
+    let model = vision_model(&config).ok_or_else(|| {
+        "No vision model configured (set AI_VISION_MODEL or enable supportsVision on a model)"
+            .to_string()
+    })?;
 
-    let model = std::env::var("AI_VISION_MODEL")
-        .or_else(|_| std::env::var("LLM_VISION_MODEL"))
-        .or_else(|_| std::env::var("VLM_MODEL"))
-        .or_else(|_| std::env::var("LLM_MODEL"))
-        .unwrap_or_else(|_| config.model.clone());
+    let (pid, title) = super::approved_window(scope)?;
+    let image = capture::capture_exact_window(pid, title)?;
+    let mut entry = audit.entry(AuditKind::VlmAnalysis);
+    entry.app_name = scope.app.clone().filter(|a| !a.is_empty());
+    entry.window_title = Some(title.to_string());
 
-    let api_key = config.api_key;
-    let base_url = config.base_url;
+    let description = describe_image(app, &config, model, prompt, &image, entry).await?;
+    Ok((title.to_string(), description))
+}
 
+/// Send one screenshot plus `prompt` to `model` and record the call in the audit log.
+async fn describe_image(
+    app: &tauri::AppHandle,
+    config: &AiConfig,
+    model: String,
+    prompt: &str,
+    image: &DynamicImage,
+    mut entry: AuditEntry,
+) -> Result<String, String> {
+    let base64_image = image_to_base64(image)?;
     let client = reqwest::Client::new();
 
     let payload = serde_json::json!({
@@ -91,7 +217,7 @@ pub(crate) async fn analyze_screen_vlm(
                 {
                     "type": "image_url",
                     "image_url": {
-                        "url": format!("data:image/jpeg;base64,{}", base64_image),
+                        "url": format!("{},{}", JPEG_DATA_URL_HEADER, base64_image),
                         "detail": "auto"
                     }
                 }
@@ -100,16 +226,34 @@ pub(crate) async fn analyze_screen_vlm(
         "max_tokens": 4096
     });
 
-    let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
+    let url = format!("{}/chat/completions", config.base_url.trim_end_matches('/'));
+    let result = post_analysis(&client, &url, &config.api_key, &payload).await;
+
+    entry.chars = prompt.chars().count() as u64;
+    entry.provider = Some(config::provider_key(config.provider).to_string());
+    entry.model = Some(model);
+    entry.is_error = result.is_err();
+    record_audit(app, entry).await;
+
+    result
+}
+
+/// POST the analysis request (retrying timeouts, 429 and 5xx) and return the reply text.
+async fn post_analysis(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: &str,
+    payload: &serde_json::Value,
+) -> Result<String, String> {
     let retry = RetryConfig::from_env();
     let mut last_error: Option<String> = None;
 
     for attempt in 1..=retry.max_attempts {
         let response = client
-            .post(&url)
+            .post(url)
             .header("Authorization", format!("Bearer {}", api_key))
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await;
 
@@ -164,10 +308,7 @@ pub(crate) async fn analyze_screen_vlm(
             .unwrap_or("")
             .to_string();
 
-        return Ok(VlmAnalysisResult {
-            content,
-            timestamp: timestamp_ms(),
-        });
+        return Ok(content);
     }
 
     Err(last_error.unwrap_or_else(|| "VLM API request failed".to_string()))
@@ -0,0 +1,750 @@
+//! Native Anthropic Messages API (`POST /v1/messages`, streaming).
+//!
+//! The tool loop in `tools.rs` keeps its transcript in OpenAI chat-completions shape. This module
+//! converts that transcript to a Messages API request and maps the SSE stream
+//! (`content_block_start` / `content_block_delta` / `message_delta` ...) back to provider-neutral
+//! [`RoundEvent`]s. Errors are reported as [`OpenAIError`] so the shared retry policy applies.
+
+use std::collections::{BTreeMap, HashMap};
+
+use async_openai::error::{ApiError, OpenAIError, StreamError};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::services::config::AiConfig;
+use crate::services::sse::{SseDecoder, SseEvent};
+
+use super::generation_params::GenerationParams;
+use super::rate_limit::RateLimiter;
+use super::types::{RoundEvent, RoundStream, TokenUsage};
+
+pub(super) const MESSAGES_PATH: &str = "/messages";
+/// Key on OpenAI-shaped assistant messages carrying Anthropic thinking blocks for replay.
+pub(super) const THINKING_BLOCKS_KEY: &str = "thinking_blocks";
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// `max_tokens` is mandatory on the Messages API; used when the model has no `max_output`.
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+const MIN_THINKING_BUDGET: u32 = 1024;
+const MAX_THINKING_BUDGET: u32 = 16_384;
+
+/// Build a streaming Messages API request from the OpenAI-shaped transcript and `tools` schema.
+///
+/// Penalties, `seed` and `reasoning_effort` have no Messages API equivalent and are dropped.
+pub(super) fn build_request(
+    config: &AiConfig,
+    api_messages: &[Value],
+    tools: Option<&Value>,
+    params: &GenerationParams,
+) -> Value {
+    let model = config.models.iter().find(|m| m.id == config.model);
+    let max_tokens = params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+    let (system, messages) = convert_messages(api_messages);
+
+    let mut request = json!({
+        "model": config.model,
+        "max_tokens": max_tokens,
+        "messages": messages,
+        "stream": true
+    });
+    let Some(obj) = request.as_object_mut() else {
+        return request;
+    };
+    if !system.is_empty() {
+        obj.insert("system".to_string(), Value::String(system));
+    }
+    if let Some(tools) = tools.map(convert_tools).filter(|t| !t.is_empty()) {
+        obj.insert("tools".to_string(), Value::Array(tools));
+    }
+    if !params.stop.is_empty() {
+        obj.insert("stop_sequences".to_string(), json!(params.stop));
+    }
+    // Extended thinking needs `budget_tokens >= 1024` and `< max_tokens`.
+    let thinking_budget = (max_tokens / 2).min(MAX_THINKING_BUDGET);
+    if model.is_some_and(|m| m.supports_think) && thinking_budget >= MIN_THINKING_BUDGET {
+        obj.insert(
+            "thinking".to_string(),
+            json!({ "type": "enabled", "budget_tokens": thinking_budget }),
+        );
+    } else {
+        // Not allowed together with thinking. Anthropic's temperature range is 0..=1.
+        if let Some(temperature) = params.temperature {
+            obj.insert("temperature".to_string(), json!(temperature.min(1.0)));
+        }
+        if let Some(top_p) = params.top_p {
+            obj.insert("top_p".to_string(), json!(top_p));
+        }
+    }
+    request
+}
+
+/// Split out the system prompt and convert messages to Messages API content blocks.
+fn convert_messages(api_messages: &[Value]) -> (String, Vec<Value>) {
+    let mut system_parts: Vec<&str> = Vec::new();
+    let mut out: Vec<Value> = Vec::new();
+
+    for m in api_messages {
+        let role = m.get("role").and_then(Value::as_str).unwrap_or("");
+        let text = m.get("content").and_then(Value::as_str).unwrap_or("");
+        match role {
+            "system" => {
+                if !text.trim().is_empty() {
+                    system_parts.push(text);
+                }
+            }
+            "tool" => push_block(
+                &mut out,
+                "user",
+                json!({
+                    "type": "tool_result",
+                    "tool_use_id": m.get("tool_call_id").cloned().unwrap_or(Value::Null),
+                    "content": text
+                }),
+            ),
+            "assistant" => {
+                if let Some(blocks) = m.get(THINKING_BLOCKS_KEY).and_then(Value::as_array) {
+                    for block in blocks {
+                        push_block(&mut out, "assistant", block.clone());
+                    }
+                }
+                if !text.is_empty() {
+                    push_block(
+                        &mut out,
+                        "assistant",
+                        json!({ "type": "text", "text": text }),
+                    );
+                }
+                let calls = m.get("tool_calls").and_then(Value::as_array);
+                for call in calls.into_iter().flatten() {
+                    let function = call.get("function");
+                    let name = function
+                        .and_then(|f| f.get("name"))
+                        .and_then(Value::as_str)
+                        .unwrap_or("");
+                    let input = function
+                        .and_then(|f| f.get("arguments"))
+                        .and_then(Value::as_str)
+                        .and_then(|args| serde_json::from_str::<Value>(args).ok())
+                        .filter(Value::is_object)
+                        .unwrap_or_else(|| json!({}));
+                    push_block(
+                        &mut out,
+                        "assistant",
+                        json!({
+                            "type": "tool_use",
+                            "id": call.get("id").cloned().unwrap_or(Value::Null),
+                            "name": name,
+                            "input": input
+                        }),
+                    );
+                }
+            }
+            _ => {
+                if let Some(parts) = m.get("content").and_then(Value::as_array) {
+                    for block in parts.iter().filter_map(user_part_block) {
+                        push_block(&mut out, "user", block);
+                    }
+                } else if !text.is_empty() {
+                    push_block(&mut out, "user", json!({ "type": "text", "text": text }));
+                }
+            }
+        }
+    }
+
+    (system_parts.join("\n\n"), out)
+}
+
+/// OpenAI user content part (`text` / `image_url`) -> Messages API content block.
+fn user_part_block(part: &Value) -> Option<Value> {
+    match part.get("type").and_then(Value::as_str)? {
+        "text" => {
+            let text = part.get("text").and_then(Value::as_str)?;
+            (!text.is_empty()).then(|| json!({ "type": "text", "text": text }))
+        }
+        "image_url" => {
+            let url = part.pointer("/image_url/url").and_then(Value::as_str)?;
+            let source = match url
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(";base64,"))
+            {
+                Some((media_type, data)) => {
+                    json!({ "type": "base64", "media_type": media_type, "data": data })
+                }
+                None => json!({ "type": "url", "url": url }),
+            };
+            Some(json!({ "type": "image", "source": source }))
+        }
+        _ => None,
+    }
+}
+
+/// Append a content block, merging into the previous message when the role repeats
+/// (the Messages API expects alternating user / assistant turns).
+fn push_block(out: &mut Vec<Value>, role: &str, block: Value) {
+    if let Some(last) = out.last_mut()
+        && last["role"] == role
+        && let Some(content) = last["content"].as_array_mut()
+    {
+        content.push(block);
+        return;
+    }
+    out.push(json!({ "role": role, "content": [block] }));
+}
+
+/// OpenAI `{"type":"function","function":{..}}` tools -> Messages API tools.
+fn convert_tools(tools: &Value) -> Vec<Value> {
+    tools
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| {
+            let function = tool.get("function")?;
+            Some(json!({
+                "name": function.get("name")?,
+                "description": function
+                    .get("description")
+                    .cloned()
+                    .unwrap_or_else(|| Value::String(String::new())),
+                "input_schema": function
+                    .get("parameters")
+                    .cloned()
+                    .unwrap_or_else(|| json!({ "type": "object", "properties": {} }))
+            }))
+        })
+        .collect()
+}
+
+/// Send the request and stream parsed events until `message_stop`.
+pub(super) async fn open_stream(
+    request: reqwest::RequestBuilder,
+    api_key: &str,
+    body: &Value,
+    rate_limiter: &RateLimiter,
+    endpoint: &str,
+) -> Result<RoundStream, OpenAIError> {
+    let response = request
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .header(reqwest::header::ACCEPT, "text/event-stream")
+        .json(body)
+        .send()
+        .await?;
+
+    let status = response.status();
+    rate_limiter.observe_response(endpoint, status, response.headers());
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(api_error(status, &text));
+    }
+
+    let state = (
+        response.bytes_stream().boxed(),
+        SseDecoder::default(),
+        MessageParser::default(),
+        false,
+    );
+    let stream = futures_util::stream::unfold(
+        state,
+        |(mut bytes, mut decoder, mut parser, finished)| async move {
+            if finished {
+                return None;
+            }
+            loop {
+                if let Some(event) = decoder.next_event() {
+                    let result = parser.handle(&event);
+                    let finished = result.is_err();
+                    return Some((result, (bytes, decoder, parser, finished)));
+                }
+                match bytes.next().await {
+                    Some(Ok(chunk)) => decoder.push(&chunk),
+                    Some(Err(err)) => {
+                        let err = stream_error(&err.to_string());
+                        return Some((Err(err), (bytes, decoder, parser, true)));
+                    }
+                    None if parser.stopped => return None,
+                    None => {
+                        let err = stream_error("stream closed before message_stop");
+                        return Some((Err(err), (bytes, decoder, parser, true)));
+                    }
+                }
+            }
+        },
+    );
+    Ok(stream.boxed())
+}
+
+/// Non-streaming 1-token request used by `test_ai_profile`.
+pub(crate) async fn ping(base_url: &str, api_key: &str, model: &str) -> Result<(), String> {
+    let url = format!("{}{}", base_url.trim_end_matches('/'), MESSAGES_PATH);
+    let response = reqwest::Client::new()
+        .post(url)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&json!({
+            "model": model,
+            "max_tokens": 1,
+            "messages": [{ "role": "user", "content": "ping" }]
+        }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let status = response.status();
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(api_error(status, &text).to_string());
+    }
+
+    let body: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    if body.get("content").and_then(Value::as_array).is_none() {
+        return Err("No content returned".to_string());
+    }
+    Ok(())
+}
+
+fn stream_error(message: &str) -> OpenAIError {
+    OpenAIError::StreamError(Box::new(StreamError::EventStream(message.to_string())))
+}
+
+fn api_error(status: reqwest::StatusCode, body: &str) -> OpenAIError {
+    #[derive(Deserialize)]
+    struct Envelope {
+        error: ErrorBody,
+    }
+
+    let (kind, message) = match serde_json::from_str::<Envelope>(body) {
+        Ok(envelope) => (envelope.error.kind, envelope.error.message),
+        Err(_) if body.trim().is_empty() => (None, status.to_string()),
+        Err(_) => (None, body.trim().to_string()),
+    };
+    OpenAIError::ApiError(ApiError {
+        message,
+        r#type: kind,
+        param: None,
+        code: Some(status.as_u16().to_string()),
+    })
+}
+
+// ============================================================================
+// Messages API stream events
+// ============================================================================
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage {
+    MessageStart {
+        message: MessageStartBody,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: BlockDelta,
+    },
+    MessageDelta {
+        delta: MessageDeltaBody,
+        #[serde(default)]
+        usage: Option<UsageBody>,
+    },
+    MessageStop,
+    Error {
+        error: ErrorBody,
+    },
+    /// `ping`, `content_block_stop` and event types added later.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct MessageStartBody {
+    #[serde(default)]
+    usage: Option<UsageBody>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        #[serde(default)]
+        text: String,
+    },
+    Thinking {
+        #[serde(default)]
+        thinking: String,
+    },
+    RedactedThinking {
+        data: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlockDelta {
+    TextDelta {
+        text: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct MessageDeltaBody {
+    #[serde(default)]
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UsageBody {
+    #[serde(default)]
+    input_tokens: Option<u32>,
+    #[serde(default)]
+    output_tokens: Option<u32>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    message: String,
+}
+
+/// Per-response state: maps content block indices to tool call slots and collects thinking
+/// blocks (with signatures) that must be sent back alongside `tool_result`s.
+#[derive(Default)]
+struct MessageParser {
+    tool_slots: HashMap<usize, usize>,
+    thinking_blocks: BTreeMap<usize, Value>,
+    input_tokens: u32,
+    cached_tokens: u32,
+    stopped: bool,
+}
+
+impl MessageParser {
+    fn handle(&mut self, event: &SseEvent) -> Result<Vec<RoundEvent>, OpenAIError> {
+        let message: StreamMessage = serde_json::from_str(&event.data)
+            .map_err(|e| OpenAIError::JSONDeserialize(e, event.data.clone()))?;
+
+        let mut out = Vec::new();
+        match message {
+            StreamMessage::MessageStart { message } => {
+                if let Some(usage) = message.usage {
+                    self.record_input_usage(&usage);
+                }
+            }
+            StreamMessage::ContentBlockStart {
+                index,
+                content_block,
+            } => match content_block {
+                ContentBlock::Text { text } if !text.is_empty() => out.push(RoundEvent::Text(text)),
+                ContentBlock::Thinking { thinking } => {
+                    self.thinking_blocks.insert(
+                        index,
+                        json!({ "type": "thinking", "thinking": thinking, "signature": "" }),
+                    );
+                    if !thinking.is_empty() {
+                        out.push(RoundEvent::Reasoning(thinking));
+                    }
+                }
+                ContentBlock::RedactedThinking { data } => {
+                    self.thinking_blocks
+                        .insert(index, json!({ "type": "redacted_thinking", "data": data }));
+                }
+                ContentBlock::ToolUse { id, name } => {
+                    let slot = self.tool_slots.len();
+                    self.tool_slots.insert(index, slot);
+                    out.push(RoundEvent::ToolCall {
+                        index: slot,
+                        id: Some(id),
+                        name: Some(name),
+                        arguments: None,
+                    });
+                }
+                _ => {}
+            },
+            StreamMessage::ContentBlockDelta { index, delta } => match delta {
+                BlockDelta::TextDelta { text } => out.push(RoundEvent::Text(text)),
+                BlockDelta::ThinkingDelta { thinking } => {
+                    if let Some(Value::String(acc)) = self
+                        .thinking_blocks
+                        .get_mut(&index)
+                        .and_then(|b| b.get_mut("thinking"))
+                    {
+                        acc.push_str(&thinking);
+                    }
+                    out.push(RoundEvent::Reasoning(thinking));
+                }
+                BlockDelta::SignatureDelta { signature } => {
+                    if let Some(Value::String(acc)) = self
+                        .thinking_blocks
+                        .get_mut(&index)
+                        .and_then(|b| b.get_mut("signature"))
+                    {
+                        acc.push_str(&signature);
+                    }
+                }
+                BlockDelta::InputJsonDelta { partial_json } => {
+                    if let Some(slot) = self.tool_slots.get(&index) {
+                        out.push(RoundEvent::ToolCall {
+                            index: *slot,
+                            id: None,
+                            name: None,
+                            arguments: Some(partial_json),
+                        });
+                    }
+                }
+                BlockDelta::Other => {}
+            },
+            StreamMessage::MessageDelta { delta, usage } => {
+                if let Some(reason) = delta.stop_reason {
+                    out.push(RoundEvent::Finish(
+                        normalize_stop_reason(&reason).to_string(),
+                    ));
+                }
+                if let Some(usage) = usage {
+                    self.record_input_usage(&usage);
+                    out.push(RoundEvent::Usage(TokenUsage {
+                        prompt_tokens: self.input_tokens,
+                        completion_tokens: usage.output_tokens.unwrap_or(0),
+                        reasoning_tokens: 0,
+                        cached_tokens: self.cached_tokens,
+                    }));
+                }
+            }
+            StreamMessage::MessageStop => {
+                self.stopped = true;
+                if !self.thinking_blocks.is_empty() {
+                    let blocks = std::mem::take(&mut self.thinking_blocks);
+                    out.push(RoundEvent::ReplayBlocks(blocks.into_values().collect()));
+                }
+            }
+            StreamMessage::Error { error } => {
+                return Err(OpenAIError::ApiError(ApiError {
+                    message: error.message,
+                    r#type: error.kind,
+                    param: None,
+                    code: None,
+                }));
+            }
+            StreamMessage::Other => {}
+        }
+        Ok(out)
+    }
+
+    fn record_input_usage(&mut self, usage: &UsageBody) {
+        // `input_tokens` excludes cache reads / writes; report the full prompt size.
+        let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
+        let cache_write = usage.cache_creation_input_tokens.unwrap_or(0);
+        if let Some(input) = usage.input_tokens {
+            self.input_tokens = input + cache_read + cache_write;
+            self.cached_tokens = cache_read;
+        }
+    }
+}
+
+/// Map Anthropic `stop_reason` to the chat-completions `finish_reason` the tool loop expects.
+fn normalize_stop_reason(reason: &str) -> &str {
+    match reason {
+        "tool_use" => "tool_calls",
+        "max_tokens" => "length",
+        "end_turn" | "stop_sequence" => "stop",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    const SSE_BODY: &str = concat!(
+        "event: message_start\n",
+        "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"usage\":{\"input_tokens\":12,\"cache_read_input_tokens\":3,\"output_tokens\":1}}}\n\n",
+        "event: content_block_start\n",
+        "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\"}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"look\"}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"signature_delta\",\"signature\":\"sig\"}}\n\n",
+        ": keep-alive\n\n",
+        "event: content_block_start\n",
+        "data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+        "event: content_block_delta\r\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\r\n\r\n",
+        "event: content_block_start\n",
+        "data: {\"type\":\"content_block_start\",\"index\":2,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"capture_focused_window\",\"input\":{}}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":2,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{}\"}}\n\n",
+        "event: message_delta\n",
+        "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":40}}\n\n",
+        "event: message_stop\n",
+        "data: {\"type\":\"message_stop\"}\n\n",
+    );
+
+    /// Serve one canned SSE response on a local port; returns the base URL.
+    fn spawn_mock_server(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().expect("accept");
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read the full request (headers + content-length body) before answering.
+            loop {
+                let n = socket.read(&mut buf).expect("read");
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text[..header_end]
+                        .lines()
+                        .find_map(|l| {
+                            let (k, v) = l.split_once(':')?;
+                            k.eq_ignore_ascii_case("content-length")
+                                .then(|| v.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).expect("write");
+        });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_convert_messages_maps_tool_rounds() {
+        let api_messages = vec![
+            json!({ "role": "system", "content": "sys" }),
+            json!({ "role": "user", "content": "look" }),
+            json!({
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "toolu_1",
+                    "type": "function",
+                    "function": { "name": "list_visible_windows", "arguments": "" }
+                }]
+            }),
+            json!({ "role": "tool", "tool_call_id": "toolu_1", "content": "QQ" }),
+        ];
+
+        let (system, messages) = convert_messages(&api_messages);
+        assert_eq!(system, "sys");
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"], json!({}));
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["type"], "tool_result");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "toolu_1");
+    }
+
+    #[test]
+    fn test_convert_messages_maps_image_parts() {
+        let api_messages = vec![json!({
+            "role": "user",
+            "content": [
+                { "type": "text", "text": "what is this?" },
+                { "type": "image_url", "image_url": { "url": "data:image/jpeg;base64,AAAA" } }
+            ]
+        })];
+
+        let (_, messages) = convert_messages(&api_messages);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["content"][0]["text"], "what is this?");
+        let source = &messages[0]["content"][1]["source"];
+        assert_eq!(source["type"], "base64");
+        assert_eq!(source["media_type"], "image/jpeg");
+        assert_eq!(source["data"], "AAAA");
+    }
+
+    #[tokio::test]
+    async fn test_open_stream_against_mock_server() {
+        let base = spawn_mock_server(SSE_BODY);
+        let request = reqwest::Client::new().post(format!("{base}{MESSAGES_PATH}"));
+        let stream = open_stream(
+            request,
+            "test-key",
+            &json!({ "stream": true }),
+            &RateLimiter::default(),
+            "test",
+        )
+        .await
+        .expect("open stream");
+
+        let events: Vec<RoundEvent> = stream
+            .map(|item| item.expect("event"))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let reasoning: String = events
+            .iter()
+            .filter_map(|e| match e {
+                RoundEvent::Reasoning(r) => Some(r.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasoning, "look");
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, RoundEvent::Text(t) if t == "Hi"))
+        );
+        assert!(events.iter().any(|e| matches!(
+            e,
+            RoundEvent::ToolCall { index: 0, id: Some(id), .. } if id == "toolu_1"
+        )));
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, RoundEvent::Finish(r) if r == "tool_calls"))
+        );
+        assert!(events.iter().any(|e| matches!(
+            e,
+            RoundEvent::Usage(u) if u.prompt_tokens == 15 && u.cached_tokens == 3 && u.completion_tokens == 40
+        )));
+        let replay = events.iter().find_map(|e| match e {
+            RoundEvent::ReplayBlocks(blocks) => Some(blocks),
+            _ => None,
+        });
+        assert_eq!(
+            replay.expect("thinking blocks")[0],
+            json!({ "type": "thinking", "thinking": "look", "signature": "sig" })
+        );
+    }
+}
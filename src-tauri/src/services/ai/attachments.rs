@@ -0,0 +1,194 @@
+//! Image attachments on user messages.
+//!
+//! Attachments arrive as data URLs or local file paths. Before a request starts they are inlined
+//! as JPEG data URLs within the VLM image limits (`VLM_IMAGE_MAX_DIM` / `VLM_JPEG_QUALITY`), which
+//! is also the form history stores. Requests carry them as OpenAI `image_url` parts; a profile
+//! whose model lacks `supports_vision` gets a text note in their place.
+
+use std::borrow::Cow;
+
+use serde_json::{Value, json};
+
+use crate::services::config::AiConfig;
+use crate::services::prompts;
+
+use super::types::{ChatAttachment, ChatMessage};
+
+/// Inline every attachment as [`ChatAttachment::Image`]; attachments on non-user messages are
+/// dropped.
+pub(super) async fn inline_attachments(
+    mut messages: Vec<ChatMessage>,
+) -> Result<Vec<ChatMessage>, String> {
+    if messages.iter().all(|m| m.attachments.is_empty()) {
+        return Ok(messages);
+    }
+
+    // Decoding / resizing is CPU-bound.
+    tauri::async_runtime::spawn_blocking(move || {
+        for message in &mut messages {
+            if message.role != "user" {
+                message.attachments.clear();
+                continue;
+            }
+            message.attachments = std::mem::take(&mut message.attachments)
+                .into_iter()
+                .map(inline)
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(messages)
+    })
+    .await
+    .map_err(|e| format!("Attachment task failed: {}", e))?
+}
+
+#[cfg(feature = "vision")]
+fn inline(attachment: ChatAttachment) -> Result<ChatAttachment, String> {
+    let url = match attachment {
+        ChatAttachment::Image { url } => crate::plugins::vision::normalize_image_data_url(&url)?,
+        ChatAttachment::ImageFile { path } => crate::plugins::vision::image_file_data_url(&path)?,
+    };
+    Ok(ChatAttachment::Image { url })
+}
+
+/// Without the vision feature images cannot be re-encoded: data URLs pass through unchanged.
+#[cfg(not(feature = "vision"))]
+fn inline(attachment: ChatAttachment) -> Result<ChatAttachment, String> {
+    match attachment {
+        ChatAttachment::Image { url } if url.starts_with("data:image/") => {
+            Ok(ChatAttachment::Image { url })
+        }
+        ChatAttachment::Image { .. } => Err("Image attachment must be a data URL".to_string()),
+        ChatAttachment::ImageFile { .. } => {
+            Err("Image files require the vision feature".to_string())
+        }
+    }
+}
+
+/// API `content` of a message: its text, as parts followed by `image_url` parts when it has
+/// attachments.
+pub(super) fn message_content(message: &ChatMessage) -> Value {
+    let images: Vec<Value> = message
+        .attachments
+        .iter()
+        .filter_map(|attachment| match attachment {
+            ChatAttachment::Image { url } => {
+                Some(json!({ "type": "image_url", "image_url": { "url": url } }))
+            }
+            ChatAttachment::ImageFile { .. } => None,
+        })
+        .collect();
+    if images.is_empty() {
+        return Value::String(message.content.clone());
+    }
+
+    let mut parts = Vec::with_capacity(images.len() + 1);
+    if !message.content.is_empty() {
+        parts.push(json!({ "type": "text", "text": message.content }));
+    }
+    parts.extend(images);
+    Value::Array(parts)
+}
+
+fn supports_vision(profile: &AiConfig) -> bool {
+    profile
+        .models
+        .iter()
+        .any(|m| m.id == profile.model && m.supports_vision)
+}
+
+/// `api_messages` as `profile` should receive them: without vision, content parts collapse to
+/// their text plus a note about the omitted images.
+pub(super) fn for_profile<'a>(profile: &AiConfig, api_messages: &'a [Value]) -> Cow<'a, [Value]> {
+    if supports_vision(profile) || !api_messages.iter().any(|m| m["content"].is_array()) {
+        return Cow::Borrowed(api_messages);
+    }
+
+    let downgraded = api_messages
+        .iter()
+        .map(|message| {
+            let Some(parts) = message["content"].as_array() else {
+                return message.clone();
+            };
+            let mut text = parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let image_count = parts.iter().filter(|p| p["type"] == "image_url").count();
+            if image_count > 0 {
+                if !text.is_empty() {
+                    text.push_str("\n\n");
+                }
+                text.push_str(&prompts::format_images_omitted_notice(image_count));
+            }
+            let mut message = message.clone();
+            message["content"] = Value::String(text);
+            message
+        })
+        .collect();
+    Cow::Owned(downgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::config::{AiModel, AiProvider};
+
+    fn profile(supports_vision: bool) -> AiConfig {
+        AiConfig {
+            provider: AiProvider::OpenAI,
+            base_url: String::new(),
+            api_key: String::new(),
+            model: "m".to_string(),
+            models: vec![AiModel {
+                id: "m".to_string(),
+                max_context: None,
+                max_output: None,
+                supports_vision,
+                supports_think: false,
+                special: None,
+                params: Default::default(),
+            }],
+            fallbacks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_message_content_and_profile_downgrade() {
+        let message = ChatMessage {
+            seq: None,
+            role: "user".to_string(),
+            content: "what is this?".to_string(),
+            tool_calls: Vec::new(),
+            pinned: false,
+            attachments: vec![ChatAttachment::Image {
+                url: "data:image/jpeg;base64,AAAA".to_string(),
+            }],
+        };
+        let content = message_content(&message);
+        assert_eq!(content[0]["text"], "what is this?");
+        assert_eq!(
+            content[1]["image_url"]["url"],
+            "data:image/jpeg;base64,AAAA"
+        );
+
+        let api_messages = vec![
+            json!({ "role": "system", "content": "sys" }),
+            json!({ "role": "user", "content": content }),
+        ];
+        assert!(matches!(
+            for_profile(&profile(true), &api_messages),
+            Cow::Borrowed(_)
+        ));
+
+        let downgraded = for_profile(&profile(false), &api_messages);
+        assert_eq!(downgraded[0], api_messages[0]);
+        assert_eq!(
+            downgraded[1]["content"],
+            format!(
+                "what is this?\n\n{}",
+                prompts::format_images_omitted_notice(1)
+            )
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! OpenAI-compatible chat completions (`POST /chat/completions`) over plain `reqwest`.
+//!
+//! OpenAI, DeepSeek, Ollama and other compatible endpoints are called directly rather than
+//! through the `async-openai` client so the response headers (`x-ratelimit-*`, `Retry-After`)
+//! reach the shared [`RateLimiter`]. Errors are still reported as [`OpenAIError`] so the retry
+//! policy treats every provider alike.
+
+use async_openai::error::{ApiError, OpenAIError, StreamError};
+use futures_util::StreamExt;
+use serde_json::Value;
+
+use crate::services::sse::SseDecoder;
+
+use super::rate_limit::RateLimiter;
+use super::types::{ByotChatCompletionStreamResponse, RoundEvent, RoundStream};
+
+pub(super) const COMPLETIONS_PATH: &str = "/chat/completions";
+
+/// Send `body` (with `"stream": true`) and stream the parsed chunks until `[DONE]`.
+pub(super) async fn open_stream(
+    request: reqwest::RequestBuilder,
+    api_key: &str,
+    body: &Value,
+    rate_limiter: &RateLimiter,
+    endpoint: &str,
+) -> Result<RoundStream, OpenAIError> {
+    let response = send(
+        request.header(reqwest::header::ACCEPT, "text/event-stream"),
+        api_key,
+        body,
+        rate_limiter,
+        endpoint,
+    )
+    .await?;
+
+    let state = (
+        response.bytes_stream().boxed(),
+        SseDecoder::default(),
+        false,
+    );
+    let stream =
+        futures_util::stream::unfold(state, |(mut bytes, mut decoder, finished)| async move {
+            if finished {
+                return None;
+            }
+            loop {
+                if let Some(event) = decoder.next_event() {
+                    if event.data == "[DONE]" {
+                        return None;
+                    }
+                    let result = parse_chunk(&event.data);
+                    let finished = result.is_err();
+                    return Some((result, (bytes, decoder, finished)));
+                }
+                match bytes.next().await {
+                    Some(Ok(chunk)) => decoder.push(&chunk),
+                    // A connection dropped mid-body is transient, like any broken stream.
+                    Some(Err(err)) => {
+                        let err = OpenAIError::StreamError(Box::new(StreamError::EventStream(
+                            err.to_string(),
+                        )));
+                        return Some((Err(err), (bytes, decoder, true)));
+                    }
+                    // Some compatible servers close the stream without `[DONE]`.
+                    None => return None,
+                }
+            }
+        });
+    Ok(stream.boxed())
+}
+
+/// Non-streaming request; returns the response body.
+pub(crate) async fn complete(
+    http_client: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+    body: &Value,
+    rate_limiter: &RateLimiter,
+) -> Result<Value, OpenAIError> {
+    let url = format!("{}{}", base_url.trim_end_matches('/'), COMPLETIONS_PATH);
+    let response = send(http_client.post(url), api_key, body, rate_limiter, base_url).await?;
+    let text = response.text().await?;
+    serde_json::from_str(&text).map_err(|e| OpenAIError::JSONDeserialize(e, text))
+}
+
+/// Send the request, record its rate-limit headers and turn error statuses into `ApiError`.
+async fn send(
+    request: reqwest::RequestBuilder,
+    api_key: &str,
+    body: &Value,
+    rate_limiter: &RateLimiter,
+    endpoint: &str,
+) -> Result<reqwest::Response, OpenAIError> {
+    let request = if api_key.is_empty() {
+        request
+    } else {
+        request.bearer_auth(api_key)
+    };
+    let response = request.json(body).send().await?;
+
+    let status = response.status();
+    rate_limiter.observe_response(endpoint, status, response.headers());
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(api_error(status, &text));
+    }
+    Ok(response)
+}
+
+fn parse_chunk(data: &str) -> Result<Vec<RoundEvent>, OpenAIError> {
+    let value: Value = serde_json::from_str(data)
+        .map_err(|e| OpenAIError::JSONDeserialize(e, data.to_string()))?;
+    // Errors that happen after the stream started arrive as a regular event.
+    if let Some(error) = value.get("error").filter(|e| e.is_object()) {
+        return Err(OpenAIError::ApiError(error_body(error)));
+    }
+    serde_json::from_value::<ByotChatCompletionStreamResponse>(value)
+        .map(RoundEvent::from_openai_chunk)
+        .map_err(|e| OpenAIError::JSONDeserialize(e, data.to_string()))
+}
+
+/// `{"error": {...}}` bodies become `ApiError`; anything else keeps the raw text.
+fn api_error(status: reqwest::StatusCode, body: &str) -> OpenAIError {
+    let parsed = serde_json::from_str::<Value>(body).ok();
+    let mut error = match parsed.as_ref().and_then(|v| v.get("error")) {
+        Some(error) if error.is_object() => error_body(error),
+        Some(Value::String(message)) => ApiError {
+            message: message.clone(),
+            r#type: None,
+            param: None,
+            code: None,
+        },
+        _ => ApiError {
+            message: if body.trim().is_empty() {
+                status.to_string()
+            } else {
+                body.trim().to_string()
+            },
+            r#type: None,
+            param: None,
+            code: None,
+        },
+    };
+    error
+        .code
+        .get_or_insert_with(|| status.as_u16().to_string());
+    OpenAIError::ApiError(error)
+}
+
+fn error_body(error: &Value) -> ApiError {
+    // Compatible servers disagree on whether `code` is a string or a number.
+    let text = |key: &str| match error.get(key) {
+        Some(Value::String(s)) => Some(s.clone()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+    ApiError {
+        message: text("message").unwrap_or_else(|| error.to_string()),
+        r#type: text("type"),
+        param: text("param"),
+        code: text("code"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::super::retry_policy::should_retry_openai_error;
+    use super::*;
+
+    /// Serve one canned HTTP response (`head` without the trailing blank line) to one request.
+    fn spawn_mock_server(head: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().expect("accept");
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf);
+            let response = format!(
+                "{head}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).expect("write");
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_open_stream_parses_chunks_and_records_quota() {
+        let base = spawn_mock_server(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nx-ratelimit-remaining-requests: 0\r\nx-ratelimit-reset-requests: 30s",
+            concat!(
+                "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"hm\"},\"finish_reason\":null}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\n",
+                "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2,\"total_tokens\":7}}\n\n",
+                "data: [DONE]\n\n",
+            ),
+        );
+        let limiter = RateLimiter::default();
+        let request = reqwest::Client::new().post(format!("{base}{COMPLETIONS_PATH}"));
+        let stream = open_stream(request, "key", &json!({ "stream": true }), &limiter, &base)
+            .await
+            .expect("open stream");
+        let events: Vec<RoundEvent> = stream
+            .map(|item| item.expect("event"))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+        assert!(matches!(&events[0], RoundEvent::Reasoning(r) if r == "hm"));
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, RoundEvent::Text(t) if t == "Hi"))
+        );
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, RoundEvent::Usage(u) if u.prompt_tokens == 5))
+        );
+        // The exhausted request quota throttles the endpoint until the reset.
+        let remaining = limiter.remaining(&base).expect("throttled");
+        assert!(remaining > Duration::from_secs(25) && remaining <= Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_connection_dropped_mid_stream_is_retryable() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let base = format!("http://{}", listener.local_addr().expect("addr"));
+        std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().expect("accept");
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf);
+            // Promises more body than it sends, then hangs up.
+            let chunk = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: 4096\r\n\r\n{chunk}"
+            );
+            socket.write_all(response.as_bytes()).expect("write");
+        });
+        let limiter = RateLimiter::default();
+        let request = reqwest::Client::new().post(format!("{base}{COMPLETIONS_PATH}"));
+        let mut stream = open_stream(request, "key", &json!({ "stream": true }), &limiter, &base)
+            .await
+            .expect("open stream");
+
+        assert!(stream.next().await.expect("first chunk").is_ok());
+        let err = stream
+            .next()
+            .await
+            .expect("body error")
+            .expect_err("truncated body");
+        assert!(matches!(err, OpenAIError::StreamError(_)));
+        assert!(should_retry_openai_error(&err));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_error_status_is_api_error_and_honors_retry_after() {
+        let base = spawn_mock_server(
+            "HTTP/1.1 429 Too Many Requests\r\ncontent-type: application/json\r\nretry-after: 12",
+            r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#,
+        );
+        let limiter = RateLimiter::default();
+        let err = complete(&reqwest::Client::new(), &base, "key", &json!({}), &limiter)
+            .await
+            .expect_err("429");
+
+        match err {
+            OpenAIError::ApiError(api) => {
+                assert_eq!(api.message, "Rate limit reached");
+                assert_eq!(api.code.as_deref(), Some("rate_limit_exceeded"));
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+        let remaining = limiter.remaining(&base).expect("throttled");
+        assert!(remaining > Duration::from_secs(10) && remaining <= Duration::from_secs(12));
+    }
+
+    #[test]
+    fn test_error_bodies() {
+        let status = reqwest::StatusCode::SERVICE_UNAVAILABLE;
+        let OpenAIError::ApiError(plain) = api_error(status, "upstream overloaded") else {
+            panic!("expected ApiError");
+        };
+        assert_eq!(plain.message, "upstream overloaded");
+        assert_eq!(plain.code.as_deref(), Some("503"));
+
+        let OpenAIError::ApiError(numeric) =
+            api_error(status, r#"{"error":{"message":"busy","code":1302}}"#)
+        else {
+            panic!("expected ApiError");
+        };
+        assert_eq!(numeric.code.as_deref(), Some("1302"));
+
+        assert!(matches!(
+            parse_chunk(r#"{"error":{"message":"stream broke"}}"#),
+            Err(OpenAIError::ApiError(api)) if api.message == "stream broke"
+        ));
+    }
+}
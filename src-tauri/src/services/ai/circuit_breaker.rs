@@ -0,0 +1,152 @@
+//! Per-provider circuit breaker shared by all chat requests.
+//!
+//! Retryable failures (see `retry_policy::should_retry_openai_error`) are counted per provider.
+//! Once `AI_BREAKER_FAILURES` happen within `AI_BREAKER_WINDOW_MS`, the provider is skipped for
+//! `AI_BREAKER_COOLDOWN_MS` and requests go straight to the next profile of the fallback chain.
+//! After the cooldown a single failure re-opens the breaker; a success closes it.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::services::config::AiProvider;
+
+#[derive(Debug, Clone, Copy)]
+struct BreakerSettings {
+    failure_threshold: usize,
+    window: Duration,
+    cooldown: Duration,
+}
+
+impl BreakerSettings {
+    fn from_env() -> Self {
+        Self {
+            failure_threshold: env_u64("AI_BREAKER_FAILURES", 3).clamp(1, 100) as usize,
+            window: Duration::from_millis(
+                env_u64("AI_BREAKER_WINDOW_MS", 60_000).clamp(1_000, 3_600_000),
+            ),
+            cooldown: Duration::from_millis(
+                env_u64("AI_BREAKER_COOLDOWN_MS", 30_000).clamp(1_000, 3_600_000),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    recent_failures: VecDeque<Instant>,
+    open_until: Option<Instant>,
+    /// Tripped since the last success: the next failure re-opens immediately.
+    half_open: bool,
+}
+
+#[derive(Clone)]
+pub(super) struct CircuitBreakers {
+    settings: BreakerSettings,
+    states: Arc<Mutex<HashMap<AiProvider, BreakerState>>>,
+}
+
+impl Default for CircuitBreakers {
+    fn default() -> Self {
+        Self {
+            settings: BreakerSettings::from_env(),
+            states: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl CircuitBreakers {
+    /// `false` while the provider's breaker is open.
+    pub(super) fn allows(&self, provider: AiProvider) -> bool {
+        self.allows_at(provider, Instant::now())
+    }
+
+    pub(super) fn record_failure(&self, provider: AiProvider) {
+        self.record_failure_at(provider, Instant::now());
+    }
+
+    pub(super) fn record_success(&self, provider: AiProvider) {
+        let Ok(mut states) = self.states.lock() else {
+            return;
+        };
+        states.remove(&provider);
+    }
+
+    fn allows_at(&self, provider: AiProvider, now: Instant) -> bool {
+        let Ok(states) = self.states.lock() else {
+            return true;
+        };
+        states
+            .get(&provider)
+            .and_then(|state| state.open_until)
+            .is_none_or(|until| now >= until)
+    }
+
+    fn record_failure_at(&self, provider: AiProvider, now: Instant) {
+        let Ok(mut states) = self.states.lock() else {
+            return;
+        };
+        let state = states.entry(provider).or_default();
+        while state
+            .recent_failures
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > self.settings.window)
+        {
+            state.recent_failures.pop_front();
+        }
+        state.recent_failures.push_back(now);
+
+        if state.half_open || state.recent_failures.len() >= self.settings.failure_threshold {
+            log::warn!(
+                "Circuit breaker open for {:?} ({} recent failures), cooling down for {:?}",
+                provider,
+                state.recent_failures.len(),
+                self.settings.cooldown
+            );
+            state.open_until = Some(now + self.settings.cooldown);
+            state.recent_failures.clear();
+            state.half_open = true;
+        }
+    }
+}
+
+fn env_u64(key: &str, default: u64) -> u64 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_after_threshold_and_reopens_when_half_open() {
+        let breakers = CircuitBreakers {
+            settings: BreakerSettings {
+                failure_threshold: 2,
+                window: Duration::from_secs(60),
+                cooldown: Duration::from_secs(30),
+            },
+            states: Arc::new(Mutex::new(HashMap::new())),
+        };
+        let provider = AiProvider::DeepSeek;
+        let t0 = Instant::now();
+
+        breakers.record_failure_at(provider, t0);
+        assert!(breakers.allows_at(provider, t0));
+        breakers.record_failure_at(provider, t0 + Duration::from_secs(1));
+        assert!(!breakers.allows_at(provider, t0 + Duration::from_secs(2)));
+        assert!(breakers.allows_at(AiProvider::OpenAI, t0));
+
+        // Cooldown over: one more failure trips it again.
+        let later = t0 + Duration::from_secs(40);
+        assert!(breakers.allows_at(provider, later));
+        breakers.record_failure_at(provider, later);
+        assert!(!breakers.allows_at(provider, later));
+
+        breakers.record_success(provider);
+        assert!(breakers.allows_at(provider, later));
+    }
+}
@@ -1,15 +1,19 @@
 use std::future::Future;
+use std::sync::{Arc, Mutex};
 
 use tauri::{Emitter, Manager};
+use tokio_util::sync::CancellationToken;
 
-use crate::plugins::history::HistoryStore;
-use crate::services::config::load_ai_config;
+use crate::plugins::history::{HistoryError, HistoryStore, MessageStatus};
+use crate::services::config::{Persona, load_ai_config, load_persona};
+use crate::services::prompts;
 
-use super::manager::AiStreamManager;
+use super::attachments;
+use super::manager::{ActiveStream, AiStreamManager, StreamRegistry};
 use super::tools::run_chat_generic;
 use super::types::{
-    ChatDeltaKind, ChatDonePayload, ChatErrorPayload, ChatMessage, ChatRequestOptions,
-    ChatStreamPayload, EVT_CHAT_DONE, EVT_CHAT_ERROR, EVT_CHAT_STREAM,
+    ChatDeltaKind, ChatDonePayload, ChatErrorPayload, ChatMessage, ChatOutput, ChatRequestOptions,
+    ChatStreamPayload, EVT_CHAT_DONE, EVT_CHAT_ERROR, EVT_CHAT_STREAM, ReplyBuffer,
 };
 
 fn stop_voice_async(app: &tauri::AppHandle) {
@@ -25,6 +29,167 @@ fn stop_voice_async(app: &tauri::AppHandle) {
     });
 }
 
+/// Persona assigned to the conversation (`None` for ad-hoc requests or when it was deleted).
+async fn conversation_persona(
+    history: &HistoryStore,
+    conversation_id: Option<&str>,
+) -> Option<Persona> {
+    let conversation_id = conversation_id?;
+    match history.conversation_persona_id(conversation_id).await {
+        Ok(persona_id) => load_persona(persona_id.as_deref()?),
+        Err(err) => {
+            log::warn!(
+                "Persona lookup failed (conversation_id={}): {}",
+                conversation_id,
+                err
+            );
+            None
+        }
+    }
+}
+
+/// How a finished answer is written to the conversation's history.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum HistoryWrite {
+    /// Sync the request's messages (dropping rows after `truncate_after_seq`), then append.
+    Append { truncate_after_seq: Option<u32> },
+    /// Store the answer as a new variant of the assistant turn at `seq` (no sync).
+    Variant { seq: u32 },
+    /// Append the answer to the interrupted assistant message at `seq` (no sync).
+    Continue { seq: u32 },
+}
+
+/// Write a reply (finished or cut short) to the conversation's history.
+async fn save_reply(
+    history: &HistoryStore,
+    conversation_id: &str,
+    history_write: HistoryWrite,
+    output: ChatOutput,
+    status: MessageStatus,
+) -> Result<(), HistoryError> {
+    match history_write {
+        HistoryWrite::Append { .. } => {
+            history
+                .append_assistant_message(conversation_id, output, status)
+                .await
+        }
+        HistoryWrite::Variant { seq } => {
+            history
+                .append_message_variant(conversation_id, seq, output, status)
+                .await
+        }
+        HistoryWrite::Continue { seq } => {
+            history
+                .extend_assistant_message(conversation_id, seq, output, status)
+                .await
+        }
+    }
+}
+
+/// Announce that a request is over (the stream is closed and history can be reloaded).
+fn emit_chat_done(app: &tauri::AppHandle, request_id: &str, conversation_id: Option<&str>) {
+    let _ = app.emit(
+        EVT_CHAT_STREAM,
+        ChatStreamPayload {
+            request_id: request_id.to_string(),
+            delta: String::new(),
+            kind: ChatDeltaKind::Text,
+            done: true,
+        },
+    );
+    let _ = app.emit(
+        EVT_CHAT_DONE,
+        ChatDonePayload {
+            request_id: request_id.to_string(),
+            conversation_id: conversation_id.map(str::to_string),
+        },
+    );
+}
+
+/// How far a request task got before it ended or was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskStage {
+    Streaming,
+    Saving,
+    Finished,
+}
+
+/// Owned by a request task from the moment it is spawned; frees its registry entry when the
+/// task ends. When the task is dropped early (`chat_abort`) it saves what was streamed first
+/// and announces `chat-done`, so the conversation stays busy until the partial answer is stored.
+struct RequestTaskGuard {
+    app: tauri::AppHandle,
+    history: HistoryStore,
+    registry: Arc<Mutex<StreamRegistry>>,
+    request_id: String,
+    conversation_id: Option<String>,
+    history_write: HistoryWrite,
+    reply: ReplyBuffer,
+    stage: TaskStage,
+}
+
+impl RequestTaskGuard {
+    fn release(&self) {
+        if let Ok(mut registry) = self.registry.lock() {
+            registry.release(&self.request_id, self.conversation_id.as_deref());
+        }
+    }
+}
+
+impl Drop for RequestTaskGuard {
+    fn drop(&mut self) {
+        match self.stage {
+            TaskStage::Finished => self.release(),
+            // Aborted while the finished answer was being written.
+            TaskStage::Saving => {
+                self.release();
+                emit_chat_done(&self.app, &self.request_id, self.conversation_id.as_deref());
+            }
+            TaskStage::Streaming => {
+                let output = self.reply.take();
+                let Some(conversation_id) =
+                    self.conversation_id.clone().filter(|_| !output.is_empty())
+                else {
+                    self.release();
+                    emit_chat_done(&self.app, &self.request_id, self.conversation_id.as_deref());
+                    return;
+                };
+                // Frees the conversation even if the save below never completes.
+                let guard = RequestTaskGuard {
+                    app: self.app.clone(),
+                    history: self.history.clone(),
+                    registry: self.registry.clone(),
+                    request_id: self.request_id.clone(),
+                    conversation_id: Some(conversation_id.clone()),
+                    history_write: self.history_write,
+                    reply: ReplyBuffer::default(),
+                    stage: TaskStage::Finished,
+                };
+                tauri::async_runtime::spawn(async move {
+                    if let Err(err) = save_reply(
+                        &guard.history,
+                        &conversation_id,
+                        guard.history_write,
+                        output,
+                        MessageStatus::Aborted,
+                    )
+                    .await
+                    {
+                        log::warn!(
+                            "Saving aborted reply failed (request_id={}, conversation_id={}): {}",
+                            guard.request_id,
+                            conversation_id,
+                            err
+                        );
+                    }
+                    guard.release();
+                    emit_chat_done(&guard.app, &guard.request_id, Some(&conversation_id));
+                });
+            }
+        }
+    }
+}
+
 fn start_stream_task<F, Fut>(
     app: tauri::AppHandle,
     streams: &AiStreamManager,
@@ -32,7 +197,7 @@ fn start_stream_task<F, Fut>(
     request_id: String,
     conversation_id: Option<String>,
     messages: Vec<ChatMessage>,
-    truncate_after_seq: Option<u32>,
+    history_write: HistoryWrite,
     config: crate::services::config::AiConfig,
     request_options: ChatRequestOptions,
     stream_fn: F,
@@ -45,18 +210,32 @@ where
             crate::services::config::AiConfig,
             ChatRequestOptions,
             reqwest::Client,
+            CancellationToken,
+            ReplyBuffer,
         ) -> Fut
         + Send
         + 'static,
-    Fut: Future<Output = Result<(String, String), String>> + Send + 'static,
+    Fut: Future<Output = Result<ChatOutput, String>> + Send + 'static,
 {
     let request_id_for_task = request_id.clone();
     let conversation_id_for_task = conversation_id.clone();
     let app_for_task = app.clone();
     let http_client = streams.http_client.clone();
-    let registry_for_task = streams.registry.clone();
     let history_for_task = history.clone();
-    let truncate_after_seq_for_task = truncate_after_seq;
+    let reply = ReplyBuffer::default();
+    // Moved into the task up front: it is dropped even if the task is aborted before it runs.
+    let mut guard = RequestTaskGuard {
+        app: app.clone(),
+        history: history.clone(),
+        registry: streams.registry.clone(),
+        request_id: request_id.clone(),
+        conversation_id: conversation_id.clone(),
+        history_write,
+        reply: reply.clone(),
+        stage: TaskStage::Streaming,
+    };
+    let cancel = CancellationToken::new();
+    let cancel_for_task = cancel.clone();
 
     let mut registry = streams
         .registry
@@ -72,22 +251,18 @@ where
     }
 
     let handle = tauri::async_runtime::spawn(async move {
-        if let Some(conversation_id) = conversation_id_for_task.as_deref() {
-            if let Err(err) = history_for_task
-                .sync_from_frontend_messages(
-                    conversation_id,
-                    &messages,
-                    truncate_after_seq_for_task,
-                )
+        if let Some(conversation_id) = conversation_id_for_task.as_deref()
+            && let HistoryWrite::Append { truncate_after_seq } = history_write
+            && let Err(err) = history_for_task
+                .sync_from_frontend_messages(conversation_id, &messages, truncate_after_seq)
                 .await
-            {
-                log::warn!(
-                    "History sync failed (request_id={}, conversation_id={:?}): {}",
-                    request_id_for_task,
-                    conversation_id_for_task,
-                    err
-                );
-            }
+        {
+            log::warn!(
+                "History sync failed (request_id={}, conversation_id={:?}): {}",
+                request_id_for_task,
+                conversation_id_for_task,
+                err
+            );
         }
 
         let result = stream_fn(
@@ -97,75 +272,54 @@ where
             config,
             request_options,
             http_client,
+            cancel_for_task,
+            reply.clone(),
         )
         .await;
+        // The task saves the reply itself from here on.
+        guard.stage = TaskStage::Saving;
 
-        match result {
-            Ok((text, reasoning)) => {
-                if let Some(conversation_id) = conversation_id_for_task.as_deref() {
-                    let reasoning = reasoning.trim();
-                    if let Err(err) = history_for_task
-                        .append_assistant_message(
-                            conversation_id,
-                            text,
-                            if reasoning.is_empty() {
-                                None
-                            } else {
-                                Some(reasoning.to_string())
-                            },
-                        )
-                        .await
-                    {
-                        log::warn!(
-                            "History append failed (request_id={}, conversation_id={:?}): {}",
-                            request_id_for_task,
-                            conversation_id_for_task,
-                            err
-                        );
-                    }
-                }
-            }
-            Err(error) => {
-                let _ = app_for_task.emit(
-                    EVT_CHAT_ERROR,
-                    ChatErrorPayload {
-                        request_id: request_id_for_task.clone(),
-                        error,
-                    },
-                );
-            }
+        // A failed request keeps what was already streamed, marked as errored.
+        let (output, status) = match &result {
+            Ok(output) => (output.clone(), MessageStatus::Complete),
+            Err(_) => (reply.take(), MessageStatus::Errored),
+        };
+        if let Some(conversation_id) = conversation_id_for_task.as_deref()
+            && (result.is_ok() || !output.is_empty())
+            && let Err(err) = save_reply(
+                &history_for_task,
+                conversation_id,
+                history_write,
+                output,
+                status,
+            )
+            .await
+        {
+            log::warn!(
+                "History append failed (request_id={}, conversation_id={:?}): {}",
+                request_id_for_task,
+                conversation_id_for_task,
+                err
+            );
         }
 
-        let _ = app_for_task.emit(
-            EVT_CHAT_STREAM,
-            ChatStreamPayload {
-                request_id: request_id_for_task.clone(),
-                delta: String::new(),
-                kind: ChatDeltaKind::Text,
-                done: true,
-            },
-        );
-        let _ = app_for_task.emit(
-            EVT_CHAT_DONE,
-            ChatDonePayload {
-                request_id: request_id_for_task.clone(),
-                conversation_id: conversation_id_for_task.clone(),
-            },
-        );
-
-        if let Ok(mut registry) = registry_for_task.lock() {
-            registry.handles.remove(&request_id_for_task);
-            if let Some(conversation_id) = conversation_id_for_task.as_deref() {
-                if registry
-                    .by_conversation
-                    .get(conversation_id)
-                    .map(|rid| rid.as_str())
-                    == Some(request_id_for_task.as_str())
-                {
-                    registry.by_conversation.remove(conversation_id);
-                }
-            }
+        if let Err(error) = result {
+            let _ = app_for_task.emit(
+                EVT_CHAT_ERROR,
+                ChatErrorPayload {
+                    request_id: request_id_for_task.clone(),
+                    error,
+                },
+            );
         }
+
+        guard.stage = TaskStage::Finished;
+        drop(guard);
+        emit_chat_done(
+            &app_for_task,
+            &request_id_for_task,
+            conversation_id_for_task.as_deref(),
+        );
     });
 
     if let Some(conversation_id) = conversation_id {
@@ -173,25 +327,30 @@ where
             .by_conversation
             .insert(conversation_id, request_id.clone());
     }
-    registry.handles.insert(request_id, handle);
+    registry.handles.insert(
+        request_id,
+        ActiveStream {
+            task: handle,
+            cancel,
+        },
+    );
     Ok(())
 }
 
-/// Start a streaming chat request (standard).
-///
-/// Emits chunks via `chat-stream` event and completion via `chat-done`.
-#[tauri::command]
-pub async fn chat_stream(
+/// Validate a chat request, resolve the conversation's persona and start streaming.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn start_chat(
     app: tauri::AppHandle,
-    streams: tauri::State<'_, AiStreamManager>,
-    history: tauri::State<'_, HistoryStore>,
+    streams: &AiStreamManager,
+    history: &HistoryStore,
     request_id: String,
     conversation_id: Option<String>,
     messages: Vec<ChatMessage>,
-    truncate_after_seq: Option<u32>,
+    history_write: HistoryWrite,
     model: Option<String>,
     request_options: Option<ChatRequestOptions>,
     voice: Option<bool>,
+    tools_enabled: bool,
 ) -> Result<(), String> {
     if request_id.trim().is_empty() {
         return Err("requestId is required".to_string());
@@ -199,29 +358,49 @@ pub async fn chat_stream(
     if messages.is_empty() {
         return Err("No messages provided".to_string());
     }
+    if let Some(format) = request_options
+        .as_ref()
+        .and_then(|o| o.response_format.as_ref())
+    {
+        format.validate()?;
+    }
+    let mut messages = messages;
+    if let Some(conversation_id) = conversation_id.as_deref()
+        && let Err(err) = history
+            .restore_from_history(conversation_id, &mut messages)
+            .await
+    {
+        log::warn!("Failed to restore history context for {conversation_id}: {err}");
+    }
+    let messages = attachments::inline_attachments(messages).await?;
 
+    let persona = conversation_persona(history, conversation_id.as_deref()).await;
     let mut config = load_ai_config();
+    let model = model
+        .filter(|model| !model.trim().is_empty())
+        .or_else(|| persona.as_ref().and_then(|p| p.model.clone()));
     if let Some(model) = model {
-        if !model.trim().is_empty() {
-            config.model = model;
-        }
+        config.model = model;
     }
-    if config.api_key.is_empty() {
+    if config.api_key.is_empty() && config.provider.requires_api_key() {
         return Err("API key is required".to_string());
     }
 
+    let persona_prompt = persona
+        .map(|p| p.system_prompt)
+        .filter(|prompt| !prompt.trim().is_empty());
     let voice_enabled = voice.unwrap_or(false);
     start_stream_task(
         app,
-        streams.inner(),
-        history.inner().clone(),
+        streams,
+        history.clone(),
         request_id,
         conversation_id,
         messages,
-        truncate_after_seq,
+        history_write,
         config,
         request_options.unwrap_or_default(),
-        move |app, request_id, messages, config, request_options, http_client| async move {
+        move |app, request_id, messages, config, request_options, http_client, cancel, reply| async move {
             run_chat_generic(
                 &app,
                 &request_id,
@@ -229,14 +408,144 @@ pub async fn chat_stream(
                 config,
                 request_options,
                 http_client,
-                false, // tools_enabled
+                cancel,
+                tools_enabled,
                 voice_enabled,
+                persona_prompt,
+                reply,
             )
             .await
         },
     )
 }
 
+/// Start a streaming chat request (standard).
+///
+/// Emits chunks via `chat-stream` event and completion via `chat-done`.
+#[tauri::command]
+pub async fn chat_stream(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, AiStreamManager>,
+    history: tauri::State<'_, HistoryStore>,
+    request_id: String,
+    conversation_id: Option<String>,
+    messages: Vec<ChatMessage>,
+    truncate_after_seq: Option<u32>,
+    model: Option<String>,
+    request_options: Option<ChatRequestOptions>,
+    voice: Option<bool>,
+) -> Result<(), String> {
+    start_chat(
+        app,
+        streams.inner(),
+        history.inner(),
+        request_id,
+        conversation_id,
+        messages,
+        HistoryWrite::Append { truncate_after_seq },
+        model,
+        request_options,
+        voice,
+        false, // tools_enabled
+    )
+    .await
+}
+
+/// Answer the last assistant turn (`seq`) again, keeping earlier answers as variants.
+///
+/// The context is read from history. The new answer becomes the active variant once it
+/// completes (see `history_list_message_variants`); a request that fails first changes nothing.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn chat_regenerate(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, AiStreamManager>,
+    history: tauri::State<'_, HistoryStore>,
+    request_id: String,
+    conversation_id: String,
+    seq: u32,
+    model: Option<String>,
+    request_options: Option<ChatRequestOptions>,
+    voice: Option<bool>,
+    tools: Option<bool>,
+) -> Result<(), String> {
+    if streams.conversation_busy(&conversation_id) {
+        return Err("Conversation is busy".to_string());
+    }
+    let messages = history
+        .prepare_regenerate(&conversation_id, seq)
+        .await
+        .map_err(|e| e.to_string())?;
+    start_chat(
+        app,
+        streams.inner(),
+        history.inner(),
+        request_id,
+        Some(conversation_id),
+        messages,
+        HistoryWrite::Variant { seq },
+        model,
+        request_options,
+        voice,
+        tools.unwrap_or(false),
+    )
+    .await
+}
+
+/// Finish the aborted or failed assistant answer at `seq`.
+///
+/// Only the last message of the conversation can be continued. The new text is appended to the
+/// same message, which is marked complete once the request succeeds.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn chat_continue(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, AiStreamManager>,
+    history: tauri::State<'_, HistoryStore>,
+    request_id: String,
+    conversation_id: String,
+    seq: u32,
+    model: Option<String>,
+    request_options: Option<ChatRequestOptions>,
+    voice: Option<bool>,
+    tools: Option<bool>,
+) -> Result<(), String> {
+    if streams.conversation_busy(&conversation_id) {
+        return Err("Conversation is busy".to_string());
+    }
+    let mut messages = history
+        .prepare_continue(&conversation_id, seq)
+        .await
+        .map_err(|e| e.to_string())?;
+    messages.push(ChatMessage {
+        seq: None,
+        role: "user".to_string(),
+        content: prompts::CONTINUE_GENERATION_PROMPT.to_string(),
+        tool_calls: Vec::new(),
+        pinned: false,
+        attachments: Vec::new(),
+    });
+    // The tail of a JSON answer would never validate on its own.
+    let request_options = request_options.map(|options| ChatRequestOptions {
+        response_format: None,
+        ..options
+    });
+    start_chat(
+        app,
+        streams.inner(),
+        history.inner(),
+        request_id,
+        Some(conversation_id),
+        messages,
+        HistoryWrite::Continue { seq },
+        model,
+        request_options,
+        voice,
+        tools.unwrap_or(false),
+    )
+    .await
+}
+
 #[tauri::command]
 pub fn chat_abort(
     app: tauri::AppHandle,
@@ -247,42 +556,23 @@ pub fn chat_abort(
         return Err("requestId is required".to_string());
     }
 
-    let (conversation_id, handle) = match streams.take_request(&request_id)? {
-        Some((cid, h)) => (cid, h),
-        None => {
-            let _ = app.emit(
-                EVT_CHAT_DONE,
-                ChatDonePayload {
-                    request_id,
-                    conversation_id: None,
-                },
-            );
-            return Ok(());
-        }
+    let Some(handle) = streams.take_request(&request_id)? else {
+        let _ = app.emit(
+            EVT_CHAT_DONE,
+            ChatDonePayload {
+                request_id,
+                conversation_id: None,
+            },
+        );
+        return Ok(());
     };
 
+    // The task announces `chat-done` once its partial answer is saved.
     handle.abort();
 
     // Stop any voice playback associated with this chat session
     stop_voice_async(&app);
 
-    let _ = app.emit(
-        EVT_CHAT_STREAM,
-        ChatStreamPayload {
-            request_id: request_id.clone(),
-            delta: String::new(),
-            kind: ChatDeltaKind::Text,
-            done: true,
-        },
-    );
-    let _ = app.emit(
-        EVT_CHAT_DONE,
-        ChatDonePayload {
-            request_id,
-            conversation_id,
-        },
-    );
-
     Ok(())
 }
 
@@ -298,32 +588,16 @@ pub fn chat_abort_conversation(
         return Err("conversationId is required".to_string());
     }
 
-    let Some((request_id, handle)) = streams.take_conversation(&conversation_id)? else {
+    let Some(handle) = streams.take_conversation(&conversation_id)? else {
         return Ok(());
     };
 
+    // The task announces `chat-done` once its partial answer is saved.
     handle.abort();
 
     // Stop any voice playback associated with this chat session
     stop_voice_async(&app);
 
-    let _ = app.emit(
-        EVT_CHAT_STREAM,
-        ChatStreamPayload {
-            request_id: request_id.clone(),
-            delta: String::new(),
-            kind: ChatDeltaKind::Text,
-            done: true,
-        },
-    );
-    let _ = app.emit(
-        EVT_CHAT_DONE,
-        ChatDonePayload {
-            request_id,
-            conversation_id: Some(conversation_id),
-        },
-    );
-
     Ok(())
 }
 
@@ -343,46 +617,18 @@ pub async fn chat_stream_with_tools(
     request_options: Option<ChatRequestOptions>,
     voice: Option<bool>,
 ) -> Result<(), String> {
-    if request_id.trim().is_empty() {
-        return Err("requestId is required".to_string());
-    }
-    if messages.is_empty() {
-        return Err("No messages provided".to_string());
-    }
-
-    let mut config = load_ai_config();
-    if let Some(model) = model {
-        if !model.trim().is_empty() {
-            config.model = model;
-        }
-    }
-    if config.api_key.is_empty() {
-        return Err("API key is required".to_string());
-    }
-
-    let voice_enabled = voice.unwrap_or(false);
-    start_stream_task(
+    start_chat(
         app,
         streams.inner(),
-        history.inner().clone(),
+        history.inner(),
         request_id,
         conversation_id,
         messages,
-        truncate_after_seq,
-        config,
-        request_options.unwrap_or_default(),
-        move |app, request_id, messages, config, request_options, http_client| async move {
-            run_chat_generic(
-                &app,
-                &request_id,
-                messages,
-                config,
-                request_options,
-                http_client,
-                true, // tools_enabled
-                voice_enabled,
-            )
-            .await
-        },
+        HistoryWrite::Append { truncate_after_seq },
+        model,
+        request_options,
+        voice,
+        true, // tools_enabled
     )
+    .await
 }
@@ -0,0 +1,290 @@
+//! Context window budgeting.
+//!
+//! The frontend sends the whole (paged-in) conversation. Before building the API request we
+//! estimate its size with a per-provider heuristic and drop the oldest turns until it fits in
+//! `max_context - max_output`. System messages, turns holding a pinned message and the current
+//! turn are never dropped.
+
+use serde::Serialize;
+
+use crate::services::config::{AiConfig, AiProvider};
+
+use super::types::ChatMessage;
+
+/// Output reserve used when the model has a context size but no `max_output` configured.
+const DEFAULT_OUTPUT_RESERVE: u32 = 4096;
+/// Per-message framing overhead (role, separators) added by chat templates.
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+/// Rough cost of one attached image (≈ a 1280px image at OpenAI "auto" detail).
+const IMAGE_TOKENS: u32 = 1_100;
+
+/// Cheap per-provider tokenizer estimate (no vocab files shipped).
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TokenEstimator {
+    /// Tokens per CJK character.
+    cjk_tokens_per_char: f32,
+    /// Characters per token for everything else.
+    other_chars_per_token: f32,
+}
+
+impl TokenEstimator {
+    pub(super) fn for_provider(provider: AiProvider) -> Self {
+        match provider {
+            // o200k/cl100k: roughly one token per CJK char, ~4 chars per token otherwise.
+            AiProvider::OpenAI => Self {
+                cjk_tokens_per_char: 1.0,
+                other_chars_per_token: 4.0,
+            },
+            // DeepSeek docs: 1 Chinese char ≈ 0.6 token, 1 English char ≈ 0.3 token.
+            AiProvider::DeepSeek => Self {
+                cjk_tokens_per_char: 0.6,
+                other_chars_per_token: 3.3,
+            },
+            // Claude tokenizer: ~3.5 English chars per token, CJK slightly above 1 per char.
+            AiProvider::Anthropic => Self {
+                cjk_tokens_per_char: 1.2,
+                other_chars_per_token: 3.5,
+            },
+            // Unknown tokenizer: stay on the conservative side.
+            AiProvider::Compatible | AiProvider::Ollama => Self {
+                cjk_tokens_per_char: 1.0,
+                other_chars_per_token: 3.0,
+            },
+        }
+    }
+
+    pub(super) fn estimate(&self, text: &str) -> u32 {
+        let mut cjk = 0u32;
+        let mut other = 0u32;
+        for ch in text.chars() {
+            if is_cjk(ch) {
+                cjk += 1;
+            } else {
+                other += 1;
+            }
+        }
+        let tokens =
+            cjk as f32 * self.cjk_tokens_per_char + other as f32 / self.other_chars_per_token;
+        tokens.ceil() as u32
+    }
+
+    fn estimate_message(&self, message: &ChatMessage) -> u32 {
+        let tool_tokens: u32 = message
+            .tool_calls
+            .iter()
+            .map(|r| {
+                self.estimate(&r.name)
+                    + self.estimate(&r.arguments)
+                    + self.estimate(&r.result)
+                    + 2 * MESSAGE_OVERHEAD_TOKENS
+            })
+            .sum();
+        let image_tokens = message.attachments.len() as u32 * IMAGE_TOKENS;
+        MESSAGE_OVERHEAD_TOKENS + self.estimate(&message.content) + tool_tokens + image_tokens
+    }
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x3040..=0x30FF     // Hiragana / Katakana
+            | 0x3400..=0x4DBF   // CJK Extension A
+            | 0x4E00..=0x9FFF   // CJK Unified Ideographs
+            | 0xAC00..=0xD7AF   // Hangul syllables
+            | 0xF900..=0xFAFF   // CJK Compatibility Ideographs
+            | 0xFF00..=0xFFEF   // Full-width forms
+            | 0x3000..=0x303F   // CJK punctuation
+            | 0x20000..=0x2FFFF // Extensions B+
+    )
+}
+
+/// Prompt token budget for one request.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ContextBudget {
+    /// `max_context - max_output`.
+    pub(super) limit: u32,
+    pub(super) estimator: TokenEstimator,
+}
+
+impl ContextBudget {
+    /// `None` when the selected model has no known `max_context` (budgeting disabled).
+    pub(super) fn for_config(config: &AiConfig) -> Option<Self> {
+        let model = config.models.iter().find(|m| m.id == config.model)?;
+        let max_context = model.max_context?;
+        let reserve = model
+            .max_output
+            .unwrap_or(DEFAULT_OUTPUT_RESERVE.min(max_context / 4));
+        Some(Self {
+            limit: max_context.saturating_sub(reserve),
+            estimator: TokenEstimator::for_provider(config.provider),
+        })
+    }
+}
+
+/// What the budgeter removed from a request.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextTrimReport {
+    pub request_id: String,
+    pub dropped_count: u32,
+    /// History `seq` of dropped messages (messages without a seq are only counted).
+    pub dropped_seqs: Vec<u32>,
+    /// Estimated prompt tokens before / after trimming (including system prompt and tools).
+    pub estimated_tokens_before: u32,
+    pub estimated_tokens_after: u32,
+    pub budget_tokens: u32,
+    /// `false` when protected messages alone exceed the budget.
+    pub fits: bool,
+}
+
+/// Drop the oldest turns until the request fits `budget`.
+///
+/// `messages` must already be the list as sent (resolved system prompt included);
+/// `reserved_tokens` covers everything else (the tool schema). A turn is a user message plus
+/// everything up to the next user message, so assistant replies (and their tool rounds) are never
+/// orphaned; a pinned message keeps its whole turn. Returns the kept messages and, when the
+/// request was over budget, a report (with an empty `request_id` for the caller to fill in).
+pub(super) fn fit_messages(
+    messages: Vec<ChatMessage>,
+    budget: &ContextBudget,
+    reserved_tokens: u32,
+) -> (Vec<ChatMessage>, Option<ContextTrimReport>) {
+    let costs: Vec<u32> = messages
+        .iter()
+        .map(|m| budget.estimator.estimate_message(m))
+        .collect();
+    let before = reserved_tokens.saturating_add(costs.iter().sum());
+    if before <= budget.limit {
+        return (messages, None);
+    }
+
+    // Everything from the last user message on is the turn being answered.
+    let protected_from = messages
+        .iter()
+        .rposition(|m| m.role == "user")
+        .unwrap_or(messages.len().saturating_sub(1));
+
+    let mut dropped = vec![false; messages.len()];
+    let mut total = before;
+    let mut idx = 0;
+    while idx < protected_from && total > budget.limit {
+        let turn_end = messages[idx + 1..protected_from]
+            .iter()
+            .position(|m| m.role == "user")
+            .map(|offset| idx + 1 + offset)
+            .unwrap_or(protected_from);
+        if messages[idx..turn_end].iter().any(|m| m.pinned) {
+            idx = turn_end;
+            continue;
+        }
+        for i in idx..turn_end {
+            if messages[i].role == "system" {
+                continue;
+            }
+            dropped[i] = true;
+            total = total.saturating_sub(costs[i]);
+        }
+        idx = turn_end;
+    }
+
+    let mut report = ContextTrimReport {
+        request_id: String::new(),
+        dropped_count: 0,
+        dropped_seqs: Vec::new(),
+        estimated_tokens_before: before,
+        estimated_tokens_after: total,
+        budget_tokens: budget.limit,
+        fits: total <= budget.limit,
+    };
+    let kept = messages
+        .into_iter()
+        .zip(dropped)
+        .filter_map(|(m, dropped)| {
+            if !dropped {
+                return Some(m);
+            }
+            report.dropped_count += 1;
+            report.dropped_seqs.extend(m.seq);
+            None
+        })
+        .collect();
+    (kept, Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(seq: u32, role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            seq: Some(seq),
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: Vec::new(),
+            pinned: false,
+            attachments: Vec::new(),
+        }
+    }
+
+    fn budget(limit: u32) -> ContextBudget {
+        ContextBudget {
+            limit,
+            estimator: TokenEstimator::for_provider(AiProvider::OpenAI),
+        }
+    }
+
+    #[test]
+    fn test_fit_messages_drops_oldest_turns_only() {
+        let long = "x".repeat(400); // ~100 tokens
+        let messages = vec![
+            msg(1, "user", &long),
+            msg(2, "assistant", &long),
+            msg(3, "user", &long),
+            msg(4, "assistant", &long),
+            msg(5, "user", "hi"),
+        ];
+
+        let (kept, report) = fit_messages(messages.clone(), &budget(10_000), 0);
+        assert_eq!(kept.len(), 5);
+        assert!(report.is_none());
+
+        let (kept, report) = fit_messages(messages, &budget(250), 0);
+        let report = report.expect("trimmed");
+        assert_eq!(report.dropped_seqs, vec![1, 2]);
+        assert!(report.fits);
+        assert_eq!(
+            kept.iter().filter_map(|m| m.seq).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_fit_messages_keeps_pinned_turns_and_current_turn() {
+        let long = "x".repeat(400);
+        let mut pinned_question = msg(1, "user", &long);
+        pinned_question.pinned = true;
+        let mut pinned_answer = msg(6, "assistant", &long);
+        pinned_answer.pinned = true;
+        let messages = vec![
+            pinned_question,
+            msg(2, "assistant", &long),
+            msg(3, "user", &long),
+            msg(4, "assistant", &long),
+            msg(5, "user", &long),
+            pinned_answer,
+            msg(7, "user", &long),
+        ];
+
+        let (kept, report) = fit_messages(messages, &budget(10), 0);
+        let report = report.expect("trimmed");
+        // Pinning either side of a turn keeps the question and its answer together.
+        assert_eq!(report.dropped_seqs, vec![3, 4]);
+        assert!(!report.fits);
+        assert_eq!(
+            kept.iter().filter_map(|m| m.seq).collect::<Vec<_>>(),
+            vec![1, 2, 5, 6, 7]
+        );
+    }
+}
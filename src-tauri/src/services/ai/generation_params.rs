@@ -0,0 +1,207 @@
+//! Sampling / generation parameters.
+//!
+//! Set per model in settings (`AiModel::params`) and overridden per request
+//! (`ChatRequestOptions::params`). The merged block is written into the provider request body;
+//! parameters the provider (or the model family) rejects are left out instead of failing the call.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::services::config::{AiConfig, AiProvider};
+
+/// OpenAI rejects more than 4 stop sequences.
+const MAX_STOP_SEQUENCES: usize = 4;
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GenerationParams {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    /// Falls back to the model's `max_output`.
+    pub max_tokens: Option<u32>,
+    pub presence_penalty: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+    pub seed: Option<i64>,
+    pub stop: Vec<String>,
+    /// Only sent to reasoning models (`supports_think`).
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+impl GenerationParams {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        check_range("temperature", self.temperature, 0.0, 2.0)?;
+        check_range("topP", self.top_p, 0.0, 1.0)?;
+        check_range("presencePenalty", self.presence_penalty, -2.0, 2.0)?;
+        check_range("frequencyPenalty", self.frequency_penalty, -2.0, 2.0)?;
+        if self.max_tokens == Some(0) {
+            return Err("maxTokens must be a positive integer".to_string());
+        }
+        if self.stop.len() > MAX_STOP_SEQUENCES {
+            return Err(format!(
+                "At most {MAX_STOP_SEQUENCES} stop sequences are allowed"
+            ));
+        }
+        if self.stop.iter().any(|s| s.is_empty()) {
+            return Err("Stop sequences must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// `overrides` wins field by field (a non-empty `stop` list replaces the base list).
+    pub(crate) fn merged(&self, overrides: &GenerationParams) -> GenerationParams {
+        GenerationParams {
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            presence_penalty: overrides.presence_penalty.or(self.presence_penalty),
+            frequency_penalty: overrides.frequency_penalty.or(self.frequency_penalty),
+            seed: overrides.seed.or(self.seed),
+            stop: if overrides.stop.is_empty() {
+                self.stop.clone()
+            } else {
+                overrides.stop.clone()
+            },
+            reasoning_effort: overrides.reasoning_effort.or(self.reasoning_effort),
+        }
+    }
+}
+
+/// Parameters for the profile's selected model, with the per-request overrides applied.
+pub(super) fn resolve_params(
+    config: &AiConfig,
+    overrides: Option<&GenerationParams>,
+) -> Result<GenerationParams, String> {
+    let model = config.models.iter().find(|m| m.id == config.model);
+    let base = model.map(|m| m.params.clone()).unwrap_or_default();
+    let mut params = match overrides {
+        Some(overrides) => base.merged(overrides),
+        None => base,
+    };
+    params.max_tokens = params.max_tokens.or(model.and_then(|m| m.max_output));
+    params.validate()?;
+    Ok(params)
+}
+
+/// Write `params` into an OpenAI-shaped chat completions body.
+pub(super) fn apply_openai_params(
+    provider: AiProvider,
+    reasoning_model: bool,
+    params: &GenerationParams,
+    request: &mut Map<String, Value>,
+) {
+    // OpenAI reasoning models (o-series, gpt-5) reject sampling knobs; DeepSeek's reasoner ignores
+    // them. Seed and reasoning effort are OpenAI extensions that DeepSeek does not accept.
+    let sampling =
+        !(reasoning_model && matches!(provider, AiProvider::OpenAI | AiProvider::DeepSeek));
+    let seed = provider != AiProvider::DeepSeek;
+    let effort = reasoning_model && provider != AiProvider::DeepSeek;
+
+    if sampling {
+        insert(request, "temperature", params.temperature.map(|v| json!(v)));
+        insert(request, "top_p", params.top_p.map(|v| json!(v)));
+        insert(
+            request,
+            "presence_penalty",
+            params.presence_penalty.map(|v| json!(v)),
+        );
+        insert(
+            request,
+            "frequency_penalty",
+            params.frequency_penalty.map(|v| json!(v)),
+        );
+        if !params.stop.is_empty() {
+            insert(request, "stop", Some(json!(params.stop)));
+        }
+    }
+    if seed {
+        insert(request, "seed", params.seed.map(|v| json!(v)));
+    }
+    if effort {
+        insert(
+            request,
+            "reasoning_effort",
+            params.reasoning_effort.map(|v| json!(v)),
+        );
+    }
+    // OpenAI deprecated `max_tokens` (and rejects it for reasoning models).
+    let max_tokens_key = match provider {
+        AiProvider::OpenAI => "max_completion_tokens",
+        _ => "max_tokens",
+    };
+    insert(request, max_tokens_key, params.max_tokens.map(|v| json!(v)));
+}
+
+fn insert(request: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    if let Some(value) = value {
+        request.insert(key.to_string(), value);
+    }
+}
+
+fn check_range(name: &str, value: Option<f64>, min: f64, max: f64) -> Result<(), String> {
+    match value {
+        Some(v) if !(min..=max).contains(&v) => {
+            Err(format!("{name} must be between {min} and {max}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_params_omit_rejected_fields() {
+        let params = GenerationParams {
+            temperature: Some(0.7),
+            max_tokens: Some(1024),
+            seed: Some(42),
+            stop: vec!["###".to_string()],
+            reasoning_effort: Some(ReasoningEffort::Low),
+            ..Default::default()
+        };
+        assert!(params.validate().is_ok());
+
+        let mut body = Map::new();
+        apply_openai_params(AiProvider::OpenAI, true, &params, &mut body);
+        assert_eq!(body.get("max_completion_tokens"), Some(&json!(1024)));
+        assert_eq!(body.get("reasoning_effort"), Some(&json!("low")));
+        assert!(!body.contains_key("temperature"));
+        assert!(!body.contains_key("stop"));
+
+        let mut body = Map::new();
+        apply_openai_params(AiProvider::DeepSeek, false, &params, &mut body);
+        assert_eq!(body.get("max_tokens"), Some(&json!(1024)));
+        assert_eq!(body.get("stop"), Some(&json!(["###"])));
+        assert!(body.contains_key("temperature"));
+        assert!(!body.contains_key("seed"));
+        assert!(!body.contains_key("reasoning_effort"));
+
+        let overrides = GenerationParams {
+            temperature: Some(0.2),
+            ..Default::default()
+        };
+        let merged = params.merged(&overrides);
+        assert_eq!(merged.temperature, Some(0.2));
+        assert_eq!(merged.seed, Some(42));
+        assert_eq!(merged.stop, params.stop);
+
+        let invalid = GenerationParams {
+            top_p: Some(1.5),
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
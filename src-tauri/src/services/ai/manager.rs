@@ -3,17 +3,59 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use tokio_util::sync::CancellationToken;
+
+use super::circuit_breaker::CircuitBreakers;
+use super::rate_limit::RateLimiter;
+use super::tool_approval::PendingApprovals;
+
+/// A running chat request: its task and the token handed to tool executors.
+pub(super) struct ActiveStream {
+    pub(super) task: tauri::async_runtime::JoinHandle<()>,
+    pub(super) cancel: CancellationToken,
+}
+
+impl ActiveStream {
+    /// Signal cancellation to in-flight tools and hand back the task for the caller to abort.
+    fn cancel(self) -> tauri::async_runtime::JoinHandle<()> {
+        self.cancel.cancel();
+        self.task
+    }
+}
+
 #[derive(Default)]
 pub(super) struct StreamRegistry {
-    pub(super) handles: HashMap<String, tauri::async_runtime::JoinHandle<()>>,
+    pub(super) handles: HashMap<String, ActiveStream>,
     pub(super) by_conversation: HashMap<String, String>,
 }
 
+impl StreamRegistry {
+    /// Forget a request once its task has ended (and saved what it streamed).
+    pub(super) fn release(&mut self, request_id: &str, conversation_id: Option<&str>) {
+        self.handles.remove(request_id);
+        if let Some(conversation_id) = conversation_id
+            && self
+                .by_conversation
+                .get(conversation_id)
+                .map(String::as_str)
+                == Some(request_id)
+        {
+            self.by_conversation.remove(conversation_id);
+        }
+    }
+}
+
 pub struct AiStreamManager {
     pub(super) http_client: reqwest::Client,
     // NOTE: Using std::sync::Mutex since lock is never held across .await.
     // If future logic requires holding lock across await points, switch to tokio::sync::Mutex.
     pub(super) registry: Arc<Mutex<StreamRegistry>>,
+    /// Provider health shared across requests (fallback chain selection).
+    pub(super) breakers: CircuitBreakers,
+    /// Per-endpoint throttle windows (also used by title generation).
+    pub(super) rate_limiter: RateLimiter,
+    /// Tool calls waiting for the user's approval.
+    pub(super) approvals: PendingApprovals,
 }
 
 impl Default for AiStreamManager {
@@ -26,53 +68,70 @@ impl Default for AiStreamManager {
         Self {
             http_client,
             registry: Arc::new(Mutex::new(StreamRegistry::default())),
+            breakers: CircuitBreakers::default(),
+            rate_limiter: RateLimiter::default(),
+            approvals: PendingApprovals::default(),
         }
     }
 }
 
 impl AiStreamManager {
+    pub(crate) fn rate_limiter(&self) -> RateLimiter {
+        self.rate_limiter.clone()
+    }
+
+    /// Cancel a request's token and hand back its task for the caller to abort.
+    ///
+    /// Its conversation stays busy until the task has saved the partial answer and announced
+    /// `chat-done`, so a new request cannot race that save.
     pub(crate) fn take_request(
         &self,
         request_id: &str,
-    ) -> Result<Option<(Option<String>, tauri::async_runtime::JoinHandle<()>)>, String> {
+    ) -> Result<Option<tauri::async_runtime::JoinHandle<()>>, String> {
         let mut registry = self
             .registry
             .lock()
             .map_err(|_| "AI stream manager lock poisoned".to_string())?;
-        let handle = registry.handles.remove(request_id);
-        let Some(handle) = handle else {
-            return Ok(None);
-        };
+        Ok(registry
+            .handles
+            .remove(request_id)
+            .map(ActiveStream::cancel))
+    }
 
-        let conversation_id = registry
+    /// Conversation a running request belongs to (`None` for conversation-less requests).
+    pub(crate) fn conversation_of(&self, request_id: &str) -> Option<String> {
+        let registry = self.registry.lock().ok()?;
+        registry
             .by_conversation
             .iter()
             .find(|(_, rid)| rid.as_str() == request_id)
-            .map(|(cid, _)| cid.clone());
-
-        if let Some(cid) = conversation_id.as_deref() {
-            registry.by_conversation.remove(cid);
-        }
+            .map(|(cid, _)| cid.clone())
+    }
 
-        Ok(Some((conversation_id, handle)))
+    /// Whether a request is already running for the conversation.
+    pub(crate) fn conversation_busy(&self, conversation_id: &str) -> bool {
+        self.registry
+            .lock()
+            .map(|registry| registry.by_conversation.contains_key(conversation_id))
+            .unwrap_or(false)
     }
 
+    /// Like [`Self::take_request`], looked up by conversation.
     pub(crate) fn take_conversation(
         &self,
         conversation_id: &str,
-    ) -> Result<Option<(String, tauri::async_runtime::JoinHandle<()>)>, String> {
+    ) -> Result<Option<tauri::async_runtime::JoinHandle<()>>, String> {
         let mut registry = self
             .registry
             .lock()
             .map_err(|_| "AI stream manager lock poisoned".to_string())?;
 
-        let Some(request_id) = registry.by_conversation.remove(conversation_id) else {
+        let Some(request_id) = registry.by_conversation.get(conversation_id).cloned() else {
             return Ok(None);
         };
-        let Some(handle) = registry.handles.remove(&request_id) else {
-            return Ok(None);
-        };
-
-        Ok(Some((request_id, handle)))
+        Ok(registry
+            .handles
+            .remove(&request_id)
+            .map(ActiveStream::cancel))
     }
 }
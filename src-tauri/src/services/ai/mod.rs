@@ -1,21 +1,49 @@
 //! AI Service module for OpenAI-compatible API integration.
 //!
 //! Notes:
-//! - We use `async-openai` for its HTTP client/backoff/stream handling.
+//! - Chat completions are sent with plain `reqwest` (`chat_completions`, `anthropic`) so response
+//!   headers feed the shared rate limiter; `async-openai` supplies the error types.
 //! - For OpenAI-compatible vendors (DeepSeek/OpenRouter/etc.) that include extra
-//!   fields like `reasoning_content` in streaming deltas, we use async-openai's
-//!   `byot` ("bring your own types") methods to deserialize those fields.
+//!   fields like `reasoning_content` in streaming deltas, chunks are parsed into our own
+//!   `Byot*` types.
 
+pub(crate) mod anthropic;
+mod attachments;
+pub(crate) mod chat_completions;
+mod circuit_breaker;
 pub(crate) mod commands;
+mod context_budget;
+mod generation_params;
 mod manager;
+pub(crate) mod ollama;
+mod rate_limit;
 mod request_options;
+mod response_format;
 mod retry_policy;
+pub(crate) mod tool_approval;
+mod tool_registry;
 mod tools;
 mod types;
 
-pub use commands::{chat_abort, chat_abort_conversation, chat_stream, chat_stream_with_tools};
+pub use commands::{
+    chat_abort, chat_abort_conversation, chat_continue, chat_regenerate, chat_stream,
+    chat_stream_with_tools,
+};
+pub use context_budget::ContextTrimReport;
+pub use generation_params::{GenerationParams, ReasoningEffort};
 pub use manager::AiStreamManager;
+pub use ollama::LocalModelStatus;
+pub(crate) use rate_limit::RateLimiter;
+pub use response_format::ResponseFormat;
+pub(crate) use retry_policy::parse_rfc3339_ms;
+pub use tool_approval::{
+    ApprovalScope, EVT_TOOL_APPROVAL_REQUEST, ToolApprovalDecision, ToolApprovalRequest,
+};
+pub use tool_registry::{Tool, ToolFuture, ToolRegistry};
+pub(crate) use types::ChatOutput;
 pub use types::{
-    ChatDeltaKind, ChatDonePayload, ChatErrorPayload, ChatMessage, ChatRequestOptions,
-    ChatStreamPayload, EVT_CHAT_DONE, EVT_CHAT_ERROR, EVT_CHAT_STREAM,
+    ChatAttachment, ChatDeltaKind, ChatDonePayload, ChatErrorPayload, ChatMessage,
+    ChatProfilePayload, ChatRequestOptions, ChatStreamPayload, ChatToolCallPayload,
+    EVT_CHAT_CONTEXT_TRIMMED, EVT_CHAT_DONE, EVT_CHAT_ERROR, EVT_CHAT_PROFILE, EVT_CHAT_STREAM,
+    EVT_CHAT_TOOL_CALL, TokenUsage, ToolCallFinished, ToolCallRecord, ToolCallStarted,
 };
@@ -0,0 +1,285 @@
+//! Local Ollama / llama.cpp servers.
+//!
+//! Chat goes through the OpenAI-compatible `/v1` endpoint like the other BYOT providers. This
+//! module talks to the native Ollama API for model discovery (`/api/tags` + `/api/show`) and load
+//! status (`/api/ps`). llama.cpp's server has no native API, so discovery falls back to
+//! `GET /v1/models` there.
+
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::services::config::AiModel;
+
+use super::generation_params::GenerationParams;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Ollama's default `num_ctx` when neither the Modelfile nor `OLLAMA_CONTEXT_LENGTH` set one.
+/// The OpenAI-compatible endpoint cannot raise it per request, so budgeting must not assume the
+/// trained context length.
+const DEFAULT_NUM_CTX: u32 = 4096;
+
+/// Load status of a local model, reported by `test_ai_profile`.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalModelStatus {
+    /// Model is resident in memory after the test request.
+    pub loaded: bool,
+    /// RFC 3339 time at which the server unloads the model (`keep_alive`).
+    pub keep_alive_until: Option<String>,
+    /// Bytes of the model held in VRAM (0 = CPU only).
+    pub size_vram: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagEntry {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShowResponse {
+    /// e.g. `["completion", "tools", "vision", "thinking"]` (Ollama 0.6.4+).
+    #[serde(default)]
+    capabilities: Vec<String>,
+    /// GGUF metadata; the context size lives under `<arch>.context_length`.
+    #[serde(default)]
+    model_info: Map<String, Value>,
+    /// Modelfile `PARAMETER` lines, one `name value` pair per line.
+    #[serde(default)]
+    parameters: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PsResponse {
+    #[serde(default)]
+    models: Vec<PsEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PsEntry {
+    name: String,
+    #[serde(default)]
+    expires_at: Option<String>,
+    #[serde(default)]
+    size_vram: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiModelList {
+    #[serde(default)]
+    data: Vec<OpenAiModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiModelEntry {
+    id: String,
+}
+
+/// List the models available on the server, with capabilities from their metadata.
+pub(crate) async fn discover_models(base_url: &str, api_key: &str) -> Result<Vec<AiModel>, String> {
+    let client = reqwest::Client::new();
+    let root = native_root(base_url);
+
+    let Some(tags) = call::<TagsResponse>(client.get(format!("{root}/api/tags"))).await? else {
+        return list_openai_models(&client, root, api_key).await;
+    };
+    let models = join_all(
+        tags.models
+            .into_iter()
+            .map(|tag| describe_model(&client, root, tag.name)),
+    )
+    .await;
+    Ok(models)
+}
+
+/// Check that `model` has been pulled.
+///
+/// Returns `Ok(false)` when the server is not Ollama (no native API to ask).
+pub(crate) async fn ensure_pulled(base_url: &str, model: &str) -> Result<bool, String> {
+    let client = reqwest::Client::new();
+    let root = native_root(base_url);
+
+    let Some(tags) = call::<TagsResponse>(client.get(format!("{root}/api/tags"))).await? else {
+        return Ok(false);
+    };
+    if tags.models.iter().any(|tag| same_model(&tag.name, model)) {
+        return Ok(true);
+    }
+    Err(format!(
+        "Model `{model}` is not pulled on this server (run `ollama pull {model}`)"
+    ))
+}
+
+/// Report whether `model` is loaded and when Ollama will unload it.
+pub(crate) async fn model_status(base_url: &str, model: &str) -> Result<LocalModelStatus, String> {
+    let client = reqwest::Client::new();
+    let root = native_root(base_url);
+
+    let running = call::<PsResponse>(client.get(format!("{root}/api/ps")))
+        .await?
+        .map(|ps| ps.models)
+        .unwrap_or_default();
+    let entry = running.into_iter().find(|m| same_model(&m.name, model));
+    Ok(LocalModelStatus {
+        loaded: entry.is_some(),
+        keep_alive_until: entry.as_ref().and_then(|m| m.expires_at.clone()),
+        size_vram: entry.and_then(|m| m.size_vram),
+    })
+}
+
+async fn describe_model(client: &reqwest::Client, root: &str, name: String) -> AiModel {
+    let mut model = AiModel {
+        id: short_name(&name).to_string(),
+        max_context: None,
+        max_output: None,
+        supports_vision: false,
+        supports_think: false,
+        special: None,
+        params: GenerationParams::default(),
+    };
+
+    let request = client
+        .post(format!("{root}/api/show"))
+        .json(&json!({ "model": name }));
+    let show = match call::<ShowResponse>(request).await {
+        Ok(Some(show)) => show,
+        Ok(None) => return model,
+        Err(err) => {
+            log::warn!(
+                "Ollama model metadata unavailable (model={}): {}",
+                name,
+                err
+            );
+            return model;
+        }
+    };
+
+    model.supports_vision = show.capabilities.iter().any(|c| c == "vision");
+    model.supports_think = show.capabilities.iter().any(|c| c == "thinking");
+    let trained = show
+        .model_info
+        .iter()
+        .find(|(key, _)| key.ends_with(".context_length"))
+        .and_then(|(_, value)| value.as_u64())
+        .map(|n| u32::try_from(n).unwrap_or(u32::MAX));
+    model.max_context =
+        parse_num_ctx(&show.parameters).or_else(|| trained.map(|n| n.min(DEFAULT_NUM_CTX)));
+    model
+}
+
+async fn list_openai_models(
+    client: &reqwest::Client,
+    root: &str,
+    api_key: &str,
+) -> Result<Vec<AiModel>, String> {
+    let mut request = client.get(format!("{root}/v1/models"));
+    if !api_key.is_empty() {
+        request = request.bearer_auth(api_key);
+    }
+    let Some(list) = call::<OpenAiModelList>(request).await? else {
+        return Err("Server exposes neither `/api/tags` nor `/v1/models`".to_string());
+    };
+    Ok(list
+        .data
+        .into_iter()
+        .map(|entry| AiModel {
+            id: entry.id,
+            max_context: None,
+            max_output: None,
+            supports_vision: false,
+            supports_think: false,
+            special: None,
+            params: GenerationParams::default(),
+        })
+        .collect())
+}
+
+/// Send a request to the server; `Ok(None)` on 404 (endpoint or model not found).
+async fn call<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<Option<T>, String> {
+    let response = request
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Local model server unreachable: {e}"))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            error: String,
+        }
+        return Err(match serde_json::from_str::<ErrorBody>(&text) {
+            Ok(body) => body.error,
+            Err(_) if text.trim().is_empty() => status.to_string(),
+            Err(_) => text.trim().to_string(),
+        });
+    }
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// The profile stores the OpenAI-compatible base (`.../v1`); the native API lives at the root.
+fn native_root(base_url: &str) -> &str {
+    let base = base_url.trim().trim_end_matches('/');
+    base.strip_suffix("/v1").unwrap_or(base)
+}
+
+/// `llama3.2:latest` -> `llama3.2` (Ollama resolves the untagged name to `:latest`).
+fn short_name(name: &str) -> &str {
+    name.strip_suffix(":latest").unwrap_or(name)
+}
+
+fn same_model(a: &str, b: &str) -> bool {
+    short_name(a.trim()) == short_name(b.trim())
+}
+
+fn parse_num_ctx(parameters: &str) -> Option<u32> {
+    parameters.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some("num_ctx"), Some(value)) => value.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_name_helpers() {
+        assert_eq!(
+            native_root("http://127.0.0.1:11434/v1/"),
+            "http://127.0.0.1:11434"
+        );
+        assert_eq!(
+            native_root("http://127.0.0.1:8080"),
+            "http://127.0.0.1:8080"
+        );
+        assert!(same_model("llama3.2:latest", "llama3.2"));
+        assert!(!same_model("qwen3:8b", "qwen3"));
+        assert_eq!(
+            parse_num_ctx(
+                "stop                           \"<|im_end|>\"\nnum_ctx                        8192"
+            ),
+            Some(8192)
+        );
+        assert_eq!(parse_num_ctx(""), None);
+    }
+}
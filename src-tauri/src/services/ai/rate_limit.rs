@@ -0,0 +1,173 @@
+//! Throttle windows shared by every request that talks to the same endpoint.
+//!
+//! When a provider says "come back later" (`Retry-After`, exhausted `x-ratelimit-*` quotas, or a
+//! hint in the error body), the endpoint is held until then. Chat streams and background title
+//! generation both check this before sending, so concurrent callers don't keep hammering a
+//! throttled API.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use async_openai::error::OpenAIError;
+
+use super::retry_policy::{retry_after_from_error, retry_after_from_headers};
+
+#[derive(Clone, Default)]
+pub(crate) struct RateLimiter {
+    /// Endpoint (API base URL) -> earliest time the next request may be sent.
+    blocked_until: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl RateLimiter {
+    /// Time left before `endpoint` may be called again (`None` when not throttled).
+    pub(crate) fn remaining(&self, endpoint: &str) -> Option<Duration> {
+        let Ok(blocked) = self.blocked_until.lock() else {
+            return None;
+        };
+        let until = blocked.get(endpoint_key(endpoint))?;
+        until
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Hold `endpoint` for `delay` (extends, never shortens, an existing window).
+    pub(crate) fn defer(&self, endpoint: &str, delay: Duration) {
+        let Ok(mut blocked) = self.blocked_until.lock() else {
+            return;
+        };
+        let until = Instant::now() + delay;
+        let entry = blocked
+            .entry(endpoint_key(endpoint).to_string())
+            .or_insert(until);
+        if *entry < until {
+            *entry = until;
+        }
+        log::info!("Endpoint {} throttled for {:?}", endpoint, delay);
+    }
+
+    /// Record the throttle window announced by a response's headers.
+    pub(crate) fn observe_response(
+        &self,
+        endpoint: &str,
+        status: reqwest::StatusCode,
+        headers: &reqwest::header::HeaderMap,
+    ) {
+        if let Some(delay) = retry_after_from_headers(status, headers, SystemTime::now()) {
+            self.defer(endpoint, delay);
+        }
+    }
+
+    /// Record a retry hint carried in an error body; returns it.
+    pub(crate) fn observe_error(&self, endpoint: &str, err: &OpenAIError) -> Option<Duration> {
+        let delay = retry_after_from_error(err)?;
+        self.defer(endpoint, delay);
+        Some(delay)
+    }
+
+    /// Wait out the endpoint's throttle window, unless it is longer than `max_wait`.
+    pub(crate) async fn wait(&self, endpoint: &str, max_wait: Duration) -> Result<(), Duration> {
+        let Some(delay) = self.remaining(endpoint) else {
+            return Ok(());
+        };
+        if delay > max_wait {
+            return Err(delay);
+        }
+        tokio::time::sleep(delay).await;
+        Ok(())
+    }
+}
+
+fn endpoint_key(endpoint: &str) -> &str {
+    endpoint.trim().trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use async_openai::error::ApiError;
+    use reqwest::StatusCode;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use super::*;
+
+    const ENDPOINT: &str = "https://api.example.com/v1";
+
+    fn between(delay: Option<Duration>, min_secs: u64, max_secs: u64) -> bool {
+        delay.is_some_and(|d| {
+            d > Duration::from_secs(min_secs) && d <= Duration::from_secs(max_secs)
+        })
+    }
+
+    #[test]
+    fn test_defer_extends_and_keys_by_endpoint() {
+        let limiter = RateLimiter::default();
+        assert_eq!(limiter.remaining(ENDPOINT), None);
+
+        limiter.defer(ENDPOINT, Duration::from_secs(30));
+        // A shorter window never cuts an existing one short.
+        limiter.defer(ENDPOINT, Duration::from_secs(5));
+        assert!(between(limiter.remaining(ENDPOINT), 25, 30));
+        // Trailing slashes and whitespace name the same endpoint; other endpoints are unaffected.
+        assert!(between(
+            limiter.remaining(" https://api.example.com/v1/"),
+            25,
+            30
+        ));
+        assert_eq!(limiter.remaining("https://other.example.com/v1"), None);
+
+        // Clones share the windows (one limiter per app, handed to every request).
+        limiter.clone().defer(ENDPOINT, Duration::from_secs(60));
+        assert!(between(limiter.remaining(ENDPOINT), 55, 60));
+    }
+
+    #[test]
+    fn test_observe_response_and_error() {
+        let limiter = RateLimiter::default();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from_static("3"),
+        );
+        headers.insert(
+            "x-ratelimit-reset-requests",
+            HeaderValue::from_static("20s"),
+        );
+        limiter.observe_response(ENDPOINT, StatusCode::OK, &headers);
+        assert_eq!(limiter.remaining(ENDPOINT), None);
+
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from_static("0"),
+        );
+        limiter.observe_response(ENDPOINT, StatusCode::OK, &headers);
+        assert!(between(limiter.remaining(ENDPOINT), 15, 20));
+
+        let other = "https://other.example.com";
+        let err = OpenAIError::ApiError(ApiError {
+            message: "Rate limit reached. Please try again in 40s.".to_string(),
+            r#type: None,
+            param: None,
+            code: None,
+        });
+        assert_eq!(
+            limiter.observe_error(other, &err),
+            Some(Duration::from_secs(40))
+        );
+        assert!(between(limiter.remaining(other), 35, 40));
+    }
+
+    #[tokio::test]
+    async fn test_wait() {
+        let limiter = RateLimiter::default();
+        assert_eq!(limiter.wait(ENDPOINT, Duration::ZERO).await, Ok(()));
+
+        limiter.defer(ENDPOINT, Duration::from_millis(20));
+        assert_eq!(limiter.wait(ENDPOINT, Duration::from_secs(1)).await, Ok(()));
+        assert_eq!(limiter.remaining(ENDPOINT), None);
+
+        // Windows longer than the caller will wait are returned instead of slept through.
+        limiter.defer(ENDPOINT, Duration::from_secs(30));
+        let err = limiter.wait(ENDPOINT, Duration::from_secs(1)).await;
+        assert!(between(err.err(), 25, 30));
+    }
+}
@@ -1,4 +1,3 @@
-use async_openai::traits::RequestOptionsBuilder;
 use std::collections::HashMap;
 
 use super::types::ChatRequestOptions;
@@ -42,17 +41,24 @@ fn build_header_map(
     Ok(header_map)
 }
 
-pub(super) fn apply_request_options<T: RequestOptionsBuilder>(
-    mut builder: T,
+/// POST to `base_url` + `default_path` with the request's path / query / header overrides.
+pub(super) fn build_http_request(
+    http_client: &reqwest::Client,
+    base_url: &str,
+    default_path: &str,
     request_options: &ChatRequestOptions,
-) -> Result<T, String> {
-    if let Some(path) = request_options.path.as_deref() {
-        validate_path_override(path)?;
-        builder = builder.path(path).map_err(|e| e.to_string())?;
-    }
+) -> Result<reqwest::RequestBuilder, String> {
+    let path = match request_options.path.as_deref() {
+        Some(path) => {
+            validate_path_override(path)?;
+            path.trim()
+        }
+        None => default_path,
+    };
+    let mut builder = http_client.post(format!("{}{}", base_url.trim_end_matches('/'), path));
 
     if let Some(query) = request_options.query.as_ref() {
-        builder = builder.query(query).map_err(|e| e.to_string())?;
+        builder = builder.query(query);
     }
 
     if let Some(headers) = request_options.headers.as_ref() {
@@ -0,0 +1,217 @@
+//! Structured output (`response_format`).
+//!
+//! Requested per call (`ChatRequestOptions::response_format`) or per prompt template.
+//! OpenAI-style providers get the native `response_format` field (DeepSeek only knows
+//! `json_object`); every provider also gets a system note describing the expected JSON, which is
+//! all Anthropic sees. The final answer is validated here; the tool loop re-asks once on failure.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::services::config::AiProvider;
+use crate::services::prompts;
+
+/// Validation errors listed in one re-ask / error message.
+const MAX_REPORTED_ERRORS: usize = 5;
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Any JSON object.
+    JsonObject,
+    /// JSON matching `schema` (JSON Schema).
+    JsonSchema {
+        /// Schema name sent to the provider (default `response`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        schema: Value,
+        /// Ask the provider for strict schema adherence (OpenAI structured outputs).
+        #[serde(default)]
+        strict: bool,
+    },
+}
+
+impl ResponseFormat {
+    /// Reject formats the provider (or our validator) could not use.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let ResponseFormat::JsonSchema { name, schema, .. } = self else {
+            return Ok(());
+        };
+        if let Some(name) = name {
+            let valid = !name.is_empty()
+                && name.len() <= 64
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid {
+                return Err(format!(
+                    "Invalid response schema name \"{name}\" (1-64 chars of a-z, A-Z, 0-9, _ or -)"
+                ));
+            }
+        }
+        jsonschema::validator_for(schema)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid response schema: {e}"))
+    }
+
+    /// System note telling the model what to answer with.
+    pub(super) fn instructions(&self) -> String {
+        match self {
+            ResponseFormat::JsonObject => prompts::format_response_format_instructions(None),
+            ResponseFormat::JsonSchema { schema, .. } => {
+                let schema = serde_json::to_string_pretty(schema).unwrap_or_default();
+                prompts::format_response_format_instructions(Some(&schema))
+            }
+        }
+    }
+
+    /// `response_format` request field for an OpenAI-style provider.
+    fn request_field(&self, provider: AiProvider) -> Value {
+        match self {
+            ResponseFormat::JsonSchema {
+                name,
+                schema,
+                strict,
+            } if provider != AiProvider::DeepSeek => json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": name.as_deref().unwrap_or("response"),
+                    "schema": schema,
+                    "strict": strict,
+                }
+            }),
+            _ => json!({ "type": "json_object" }),
+        }
+    }
+
+    /// Check the final answer; `Err` lists what is wrong (fed back to the model on re-ask).
+    pub(super) fn check(&self, text: &str) -> Result<Value, String> {
+        let value: Value = serde_json::from_str(strip_code_fence(text))
+            .map_err(|e| format!("not valid JSON: {e}"))?;
+        match self {
+            ResponseFormat::JsonObject if !value.is_object() => {
+                Err("expected a JSON object".to_string())
+            }
+            ResponseFormat::JsonObject => Ok(value),
+            ResponseFormat::JsonSchema { schema, .. } => {
+                let validator = jsonschema::validator_for(schema)
+                    .map_err(|e| format!("invalid response schema: {e}"))?;
+                let errors: Vec<String> = validator
+                    .iter_errors(&value)
+                    .take(MAX_REPORTED_ERRORS)
+                    .map(|e| match e.instance_path.to_string() {
+                        path if path.is_empty() => e.to_string(),
+                        path => format!("{path}: {e}"),
+                    })
+                    .collect();
+                if errors.is_empty() {
+                    Ok(value)
+                } else {
+                    Err(errors.join("; "))
+                }
+            }
+        }
+    }
+}
+
+/// Models often wrap JSON in a Markdown code block even when told not to.
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let Some(body) = rest.strip_suffix("```") else {
+        return trimmed;
+    };
+    // Drop the info string (e.g. `json`) on the opening line.
+    body.split_once('\n').map_or(body, |(_, body)| body).trim()
+}
+
+/// Write `response_format` into an OpenAI-style request body (Anthropic has no such field).
+pub(super) fn apply_openai(
+    format: &ResponseFormat,
+    provider: AiProvider,
+    obj: &mut Map<String, Value>,
+) {
+    if provider != AiProvider::Anthropic {
+        obj.insert(
+            "response_format".to_string(),
+            format.request_field(provider),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person_schema() -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            name: Some("person".to_string()),
+            schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "age": { "type": "integer" }
+                },
+                "required": ["name", "age"]
+            }),
+            strict: false,
+        }
+    }
+
+    #[test]
+    fn test_check_against_schema() {
+        let format = person_schema();
+        assert!(format.validate().is_ok());
+        assert_eq!(format.check(r#"{"name":"Ann","age":3}"#).unwrap()["age"], 3);
+        assert!(
+            format
+                .check("```json\n{\"name\":\"Ann\",\"age\":3}\n```")
+                .is_ok()
+        );
+
+        let err = format.check(r#"{"name":"Ann","age":"3"}"#).unwrap_err();
+        assert!(err.starts_with("/age: "), "{err}");
+        assert!(format.check(r#"{"name":"Ann""#).is_err());
+
+        let object = ResponseFormat::JsonObject;
+        assert!(object.check("{}").is_ok());
+        assert!(object.check("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_definitions() {
+        let bad_schema = ResponseFormat::JsonSchema {
+            name: None,
+            schema: json!({ "type": 12 }),
+            strict: false,
+        };
+        assert!(bad_schema.validate().is_err());
+
+        let bad_name = ResponseFormat::JsonSchema {
+            name: Some("has space".to_string()),
+            schema: json!({ "type": "object" }),
+            strict: false,
+        };
+        assert!(bad_name.validate().is_err());
+    }
+
+    #[test]
+    fn test_request_field_per_provider() {
+        let format = person_schema();
+        let mut obj = Map::new();
+        apply_openai(&format, AiProvider::OpenAI, &mut obj);
+        assert_eq!(obj["response_format"]["type"], "json_schema");
+        assert_eq!(obj["response_format"]["json_schema"]["name"], "person");
+
+        let mut obj = Map::new();
+        apply_openai(&format, AiProvider::DeepSeek, &mut obj);
+        assert_eq!(obj["response_format"], json!({ "type": "json_object" }));
+
+        let mut obj = Map::new();
+        apply_openai(&format, AiProvider::Anthropic, &mut obj);
+        assert!(obj.is_empty());
+    }
+}
@@ -1,4 +1,11 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use async_openai::error::OpenAIError;
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+
+/// Upper bound for server-provided retry hints (guards against bogus headers).
+const MAX_RETRY_HINT: Duration = Duration::from_secs(600);
 
 pub(super) fn should_retry_openai_error(err: &OpenAIError) -> bool {
     match err {
@@ -21,7 +28,292 @@ pub(super) fn should_retry_openai_error(err: &OpenAIError) -> bool {
                 || code.contains("overload")
                 || ty.contains("rate")
                 || ty.contains("timeout")
+                || ty.contains("overload")
+                || retry_after_from_message(&msg).is_some()
         }
         _ => false,
     }
 }
+
+/// Retry delay suggested inside an error body, e.g. OpenAI's "Please try again in 20s." or
+/// Azure's "Please retry after 7 seconds.".
+pub(super) fn retry_after_from_error(err: &OpenAIError) -> Option<Duration> {
+    match err {
+        OpenAIError::ApiError(api) => retry_after_from_message(&api.message.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// Throttle window announced by response headers.
+///
+/// Error responses honor `retry-after-ms` / `retry-after` first. Any response whose
+/// `x-ratelimit-remaining-*` (OpenAI, DeepSeek, most proxies) or `anthropic-ratelimit-*-remaining`
+/// quota hit 0 is held until the matching reset.
+pub(super) fn retry_after_from_headers(
+    status: StatusCode,
+    headers: &HeaderMap,
+    now: SystemTime,
+) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+    };
+
+    if !status.is_success() {
+        // `Retry-After` is either delay-seconds or an HTTP-date.
+        let retry_after = |value: &str| {
+            parse_duration(value).or_else(|| {
+                let at = UNIX_EPOCH + Duration::from_millis(parse_http_date_ms(value)?);
+                Some(at.duration_since(now).unwrap_or_default())
+            })
+        };
+        let explicit = header("retry-after-ms")
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|ms| Duration::from_secs_f64(ms.max(0.0) / 1000.0))
+            .or_else(|| header("retry-after").and_then(retry_after));
+        if let Some(delay) = explicit {
+            return Some(delay.min(MAX_RETRY_HINT));
+        }
+    }
+
+    let exhausted = |remaining: &str| {
+        status == StatusCode::TOO_MANY_REQUESTS
+            || header(remaining).and_then(|v| v.parse::<u64>().ok()) == Some(0)
+    };
+    let mut delay: Option<Duration> = None;
+    for kind in ["requests", "tokens"] {
+        if exhausted(&format!("x-ratelimit-remaining-{kind}"))
+            && let Some(reset) =
+                header(&format!("x-ratelimit-reset-{kind}")).and_then(parse_duration)
+        {
+            delay = delay.max(Some(reset));
+        }
+    }
+    for kind in ["requests", "tokens", "input-tokens", "output-tokens"] {
+        if exhausted(&format!("anthropic-ratelimit-{kind}-remaining"))
+            && let Some(reset) = header(&format!("anthropic-ratelimit-{kind}-reset"))
+                .and_then(parse_rfc3339_ms)
+                .map(|ms| UNIX_EPOCH + Duration::from_millis(ms))
+                .and_then(|at| at.duration_since(now).ok())
+        {
+            delay = delay.max(Some(reset));
+        }
+    }
+    delay.map(|d| d.min(MAX_RETRY_HINT))
+}
+
+fn retry_after_from_message(msg: &str) -> Option<Duration> {
+    ["try again in ", "retry after ", "retry in "]
+        .iter()
+        .find_map(|marker| {
+            let rest = &msg[msg.find(marker)? + marker.len()..];
+            let mut words = rest.split_whitespace();
+            let value = words.next()?.trim_end_matches(['.', ',', ';']);
+            let unit = words.next().unwrap_or("").trim_end_matches(['.', ',', ';']);
+            match unit {
+                "ms" | "millisecond" | "milliseconds" => value
+                    .parse::<f64>()
+                    .ok()
+                    .map(|ms| Duration::from_secs_f64(ms / 1000.0)),
+                "s" | "sec" | "secs" | "second" | "seconds" => {
+                    value.parse::<f64>().ok().map(Duration::from_secs_f64)
+                }
+                "min" | "minute" | "minutes" => value
+                    .parse::<f64>()
+                    .ok()
+                    .map(|m| Duration::from_secs_f64(m * 60.0)),
+                _ => parse_duration(value),
+            }
+        })
+        .filter(|d| !d.is_zero())
+        .map(|d| d.min(MAX_RETRY_HINT))
+}
+
+/// Plain seconds (`"20"`, `"1.5"`) or Go-style durations (`"6m0s"`, `"1.2s"`, `"250ms"`).
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs));
+    }
+
+    let mut total = 0.0_f64;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|&i| i > 0)?;
+        let number: f64 = rest[..split].parse().ok()?;
+        rest = &rest[split..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
+        };
+        total += number * scale;
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+/// Milliseconds since the epoch of an HTTP-date (`Wed, 21 Oct 2015 07:28:00 GMT`).
+fn parse_http_date_ms(s: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let mut parts = s.split_whitespace();
+    parts.next()?.strip_suffix(',')?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? + 1;
+    let year: u32 = parts.next()?.parse().ok()?;
+    let time = parts.next()?;
+    if parts.next()? != "GMT" || parts.next().is_some() {
+        return None;
+    }
+    parse_rfc3339_ms(&format!("{year:04}-{month:02}-{day:02}T{time}Z"))
+}
+
+/// Milliseconds since the epoch of `YYYY-MM-DD[T ]HH:MM:SS[.fff][Z|±HH:MM]` (no offset means
+/// UTC). Shared by rate-limit reset headers and history import.
+pub(crate) fn parse_rfc3339_ms(s: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| s.get(range)?.parse::<i64>().ok();
+    let bytes = s.as_bytes();
+    if bytes.len() < 19
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut millis = 0i64;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.chars().take_while(char::is_ascii_digit).count();
+        let padded = format!("{:0<3}", &fraction[..digits.min(3)]);
+        millis = padded.parse().ok()?;
+        rest = &fraction[digits..];
+    }
+    let offset_minutes = match rest {
+        "" | "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let (h, m) = rest[1..].split_once(':').unwrap_or((rest.get(1..3)?, "00"));
+            sign * (h.parse::<i64>().ok()? * 60 + m.parse::<i64>().ok()?)
+        }
+    };
+
+    // Days since 1970-01-01 (Howard Hinnant's `days_from_civil`).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second - offset_minutes * 60;
+    u64::try_from(seconds * 1000 + millis).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_retry_hints_from_messages_and_headers() {
+        assert_eq!(
+            retry_after_from_message("rate limit reached ... please try again in 6m0s. visit"),
+            Some(Duration::from_secs(360))
+        );
+        assert_eq!(
+            retry_after_from_message("please retry after 7 seconds."),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            retry_after_from_message("try again in 250ms"),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(retry_after_from_message("server overloaded"), None);
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_735_689_600); // 2025-01-01T00:00:00Z
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        assert_eq!(
+            retry_after_from_headers(StatusCode::TOO_MANY_REQUESTS, &headers, now),
+            Some(Duration::from_secs(3))
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Wed, 01 Jan 2025 00:00:45 GMT"),
+        );
+        assert_eq!(
+            retry_after_from_headers(StatusCode::SERVICE_UNAVAILABLE, &headers, now),
+            Some(Duration::from_secs(45))
+        );
+        // Already passed: retry right away.
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Tue, 31 Dec 2024 23:59:00 GMT"),
+        );
+        assert_eq!(
+            retry_after_from_headers(StatusCode::SERVICE_UNAVAILABLE, &headers, now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_http_date_ms("Wed, 01 Jan 2025 00:00:45 UTC"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from_static("0"),
+        );
+        headers.insert(
+            "x-ratelimit-reset-requests",
+            HeaderValue::from_static("1.5s"),
+        );
+        headers.insert(
+            "x-ratelimit-remaining-tokens",
+            HeaderValue::from_static("900"),
+        );
+        headers.insert("x-ratelimit-reset-tokens", HeaderValue::from_static("1m"));
+        assert_eq!(
+            retry_after_from_headers(StatusCode::OK, &headers, now),
+            Some(Duration::from_millis(1500))
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "anthropic-ratelimit-requests-remaining",
+            HeaderValue::from_static("0"),
+        );
+        headers.insert(
+            "anthropic-ratelimit-requests-reset",
+            HeaderValue::from_static("2025-01-01T00:00:30Z"),
+        );
+        assert_eq!(
+            retry_after_from_headers(StatusCode::OK, &headers, now),
+            Some(Duration::from_secs(30))
+        );
+    }
+}
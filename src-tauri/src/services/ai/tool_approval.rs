@@ -0,0 +1,351 @@
+//! User consent for sensitive tool calls.
+//!
+//! Tools opt in via [`Tool::approval_scope`]. Before such a call runs, the tool loop emits
+//! `tool-approval-request` and waits (bounded by `AI_TOOL_APPROVAL_TIMEOUT_MS`) for
+//! `tool_approval_respond`. "Always allow" is remembered as a [`ToolApprovalRule`] in settings.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+use crate::services::config::{self, ToolApprovalRule};
+use crate::services::prompts;
+
+use super::manager::AiStreamManager;
+use super::tool_registry::Tool;
+
+/// Event name for a tool call waiting on the user's decision
+pub const EVT_TOOL_APPROVAL_REQUEST: &str = "tool-approval-request";
+
+/// What a call touches; remembered rules are matched against it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApprovalScope {
+    pub app: Option<String>,
+    pub window: Option<String>,
+    /// Process owning `window` when a window was resolved; together with the title it pins the
+    /// exact window the call may touch.
+    pub pid: Option<u32>,
+}
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolApprovalDecision {
+    AllowOnce,
+    AlwaysAllow,
+    Deny,
+}
+
+/// Payload of `tool-approval-request`.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolApprovalRequest {
+    pub approval_id: String,
+    pub request_id: String,
+    pub call_id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+    pub app: Option<String>,
+    pub window: Option<String>,
+    /// The call is denied when no answer arrives in time.
+    pub timeout_ms: u64,
+}
+
+/// Calls waiting for `tool_approval_respond`, by approval id.
+#[derive(Default)]
+pub(super) struct PendingApprovals {
+    // NOTE: std Mutex is fine: never held across .await.
+    waiting: Mutex<HashMap<String, oneshot::Sender<ToolApprovalDecision>>>,
+}
+
+impl PendingApprovals {
+    fn insert(&self, approval_id: &str) -> oneshot::Receiver<ToolApprovalDecision> {
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut waiting) = self.waiting.lock() {
+            waiting.insert(approval_id.to_string(), tx);
+        }
+        rx
+    }
+
+    fn remove(&self, approval_id: &str) -> Option<oneshot::Sender<ToolApprovalDecision>> {
+        self.waiting.lock().ok()?.remove(approval_id)
+    }
+}
+
+fn approval_timeout() -> Duration {
+    let ms = std::env::var("AI_TOOL_APPROVAL_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(60_000)
+        .clamp(5_000, 600_000);
+    Duration::from_millis(ms)
+}
+
+/// Case-insensitive match where `*` stands for any run of characters.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    let value = value.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !value.starts_with(first) || !value[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+fn rule_matches(rule: &ToolApprovalRule, tool: &str, scope: &ApprovalScope) -> bool {
+    let field_matches = |pattern: &Option<String>, value: &Option<String>| match pattern {
+        None => true,
+        Some(pattern) => value
+            .as_deref()
+            .is_some_and(|v| matches_pattern(pattern, v)),
+    };
+    rule.tool == tool
+        && field_matches(&rule.app, &scope.app)
+        && field_matches(&rule.window, &scope.window)
+}
+
+/// Rule stored for "always allow": the app when known (window titles change), else the window.
+///
+/// `None` when the scope names neither: such a rule would allow the tool for every window.
+fn remembered_rule(tool: &str, scope: &ApprovalScope) -> Option<ToolApprovalRule> {
+    let app = scope.app.clone().filter(|a| !a.trim().is_empty());
+    let window = if app.is_none() {
+        scope.window.clone().filter(|w| !w.trim().is_empty())
+    } else {
+        None
+    };
+    if app.is_none() && window.is_none() {
+        return None;
+    }
+    Some(ToolApprovalRule {
+        tool: tool.to_string(),
+        app,
+        window,
+    })
+}
+
+/// Ask the user before running `tool`, unless it needs no consent or a rule already allows it.
+///
+/// `Ok` carries the scope the call was approved for (`None` when the tool needs no consent);
+/// `Err` is the reason fed back to the model (denied, timed out, request cancelled).
+pub(super) async fn authorize(
+    app: &tauri::AppHandle,
+    pending: &PendingApprovals,
+    tool: &dyn Tool,
+    request_id: &str,
+    call_id: &str,
+    arguments: &serde_json::Value,
+    cancel: &CancellationToken,
+) -> Result<Option<ApprovalScope>, String> {
+    let Some(scope) = tool.approval_scope(arguments) else {
+        return Ok(None);
+    };
+    let name = tool.name();
+    if config::load_tool_approval_rules()
+        .iter()
+        .any(|rule| rule_matches(rule, name, &scope))
+    {
+        return Ok(Some(scope));
+    }
+
+    let timeout = approval_timeout();
+    let approval_id = uuid::Uuid::new_v4().to_string();
+    let rx = pending.insert(&approval_id);
+    let _ = app.emit(
+        EVT_TOOL_APPROVAL_REQUEST,
+        ToolApprovalRequest {
+            approval_id: approval_id.clone(),
+            request_id: request_id.to_string(),
+            call_id: call_id.to_string(),
+            name: name.to_string(),
+            arguments: arguments.clone(),
+            app: scope.app.clone(),
+            window: scope.window.clone(),
+            timeout_ms: timeout.as_millis() as u64,
+        },
+    );
+
+    let decision = tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(prompts::TOOL_CANCELLED_ERROR.to_string()),
+        answer = tokio::time::timeout(timeout, rx) => match answer {
+            Ok(Ok(decision)) => Ok(decision),
+            _ => Err(prompts::TOOL_APPROVAL_TIMEOUT_ERROR.to_string()),
+        },
+    };
+    pending.remove(&approval_id);
+
+    match decision? {
+        ToolApprovalDecision::AllowOnce => Ok(Some(scope)),
+        ToolApprovalDecision::AlwaysAllow => {
+            // Without an app or window to pin it to, "always" only covers this call.
+            let Some(rule) = remembered_rule(name, &scope) else {
+                log::info!("Not remembering approval for {}: no app or window", name);
+                return Ok(Some(scope));
+            };
+            let mut rules = config::load_tool_approval_rules();
+            if !rules.contains(&rule) {
+                rules.push(rule);
+                if let Err(err) = config::save_tool_approval_rules(rules) {
+                    log::warn!("Failed to save tool approval rule for {}: {}", name, err);
+                }
+            }
+            Ok(Some(scope))
+        }
+        ToolApprovalDecision::Deny => Err(prompts::TOOL_DENIED_ERROR.to_string()),
+    }
+}
+
+/// Answer a `tool-approval-request`.
+#[tauri::command]
+pub fn tool_approval_respond(
+    streams: tauri::State<'_, AiStreamManager>,
+    approval_id: String,
+    decision: ToolApprovalDecision,
+) -> Result<(), String> {
+    let sender = streams
+        .approvals
+        .remove(approval_id.trim())
+        .ok_or_else(|| "Approval request expired or unknown".to_string())?;
+    sender
+        .send(decision)
+        .map_err(|_| "Approval request expired or unknown".to_string())
+}
+
+#[tauri::command]
+pub fn get_tool_approval_rules() -> Vec<ToolApprovalRule> {
+    config::load_tool_approval_rules()
+}
+
+/// Replace the remembered rules (e.g. to revoke one or widen a pattern).
+#[tauri::command]
+pub fn set_tool_approval_rules(
+    app: tauri::AppHandle,
+    rules: Vec<ToolApprovalRule>,
+) -> Result<Vec<ToolApprovalRule>, String> {
+    // Ensure data dir exists (and is cached) before writing settings.
+    let _ = crate::services::paths::data_dir(&app)?;
+
+    let normalize = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let mut next: Vec<ToolApprovalRule> = Vec::new();
+    for rule in rules {
+        let rule = ToolApprovalRule {
+            tool: rule.tool.trim().to_string(),
+            app: normalize(rule.app),
+            window: normalize(rule.window),
+        };
+        if rule.tool.is_empty() {
+            return Err("Tool approval rule needs a tool name".to_string());
+        }
+        if !next.contains(&rule) {
+            next.push(rule);
+        }
+    }
+
+    config::save_tool_approval_rules(next.clone())?;
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("Code", "code"));
+        assert!(!matches_pattern("Code", "Code - Insiders"));
+        assert!(matches_pattern(
+            "*visual studio code",
+            "main.rs - Visual Studio Code"
+        ));
+        assert!(matches_pattern("chrome*", "Chrome"));
+        assert!(matches_pattern("*a*c*", "xxabbcxx"));
+        assert!(!matches_pattern("*a*c", "ca"));
+        assert!(matches_pattern("*", ""));
+    }
+
+    #[test]
+    fn test_rules() {
+        let scope = ApprovalScope {
+            app: Some("Code".to_string()),
+            window: Some("main.rs - Visual Studio Code".to_string()),
+            pid: Some(42),
+        };
+        let rule = remembered_rule("capture_window_content", &scope).expect("app rule");
+        assert_eq!(rule.app.as_deref(), Some("Code"));
+        assert_eq!(rule.window, None);
+        assert!(rule_matches(&rule, "capture_window_content", &scope));
+        assert!(!rule_matches(&rule, "capture_focused_window", &scope));
+
+        let other_app = ApprovalScope {
+            app: Some("Slack".to_string()),
+            ..Default::default()
+        };
+        assert!(!rule_matches(&rule, "capture_window_content", &other_app));
+
+        let window_only = remembered_rule(
+            "capture_window_content",
+            &ApprovalScope {
+                window: Some("Notes".to_string()),
+                ..Default::default()
+            },
+        )
+        .expect("window rule");
+        assert_eq!(window_only.window.as_deref(), Some("Notes"));
+        assert!(!rule_matches(
+            &window_only,
+            "capture_window_content",
+            &other_app
+        ));
+    }
+
+    #[test]
+    fn test_no_rule_without_app_or_window() {
+        // Nothing was resolved (e.g. no focused window): "always allow" must not become a
+        // rule that matches every window.
+        for scope in [
+            ApprovalScope::default(),
+            ApprovalScope {
+                app: Some(" ".to_string()),
+                window: Some("".to_string()),
+                pid: None,
+            },
+        ] {
+            assert_eq!(remembered_rule("capture_focused_window", &scope), None);
+        }
+
+        let unpinned = ToolApprovalRule {
+            tool: "capture_focused_window".to_string(),
+            app: None,
+            window: None,
+        };
+        let anywhere = ApprovalScope {
+            app: Some("Slack".to_string()),
+            ..Default::default()
+        };
+        assert!(rule_matches(&unpinned, "capture_focused_window", &anywhere));
+    }
+}
@@ -0,0 +1,405 @@
+//! Pluggable registry of model-callable tools.
+//!
+//! Any subsystem can register a [`Tool`] (schema + executor + availability check) on the
+//! [`ToolRegistry`] managed as Tauri state. The chat tool loop builds the `tools` schema from
+//! everything currently available and dispatches tool calls by name.
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio_util::sync::CancellationToken;
+
+use crate::plugins::history::AuditContext;
+use crate::services::config::{AiConfig, AiProvider};
+use crate::services::prompts;
+
+use super::tool_approval::ApprovalScope;
+
+/// Future returned by [`Tool::execute`]: the text result fed back to the model, or an error.
+pub type ToolFuture<'a> = BoxFuture<'a, Result<String, String>>;
+
+pub trait Tool: Send + Sync {
+    /// Function name exposed to the model (unique within the registry).
+    fn name(&self) -> &str;
+
+    /// OpenAI-style `function` definition (`name` / `description` / `parameters`).
+    fn definition(&self) -> serde_json::Value;
+
+    /// Whether the tool should be offered for the current request.
+    fn is_available(&self, _config: &AiConfig) -> bool {
+        true
+    }
+
+    /// Calls that need the user's consent return what they touch (window / app); `None` runs
+    /// without asking. See `tool_approval`.
+    fn approval_scope(&self, _arguments: &serde_json::Value) -> Option<ApprovalScope> {
+        None
+    }
+
+    /// Per-call time limit; `None` uses the tool loop default (`AI_TOOL_TIMEOUT_MS`).
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Run the tool. `cancel` fires when the chat request is aborted; the registry also drops the
+    /// future on timeout / cancellation, so only work that outlives it needs to watch the token.
+    /// Tools that write to the audit log stamp their entries with `audit`. `approved` is the scope
+    /// the user consented to (see [`Tool::approval_scope`]); tools must act on exactly that target.
+    fn execute<'a>(
+        &'a self,
+        arguments: &'a serde_json::Value,
+        cancel: &'a CancellationToken,
+        audit: &'a AuditContext,
+        approved: Option<&'a ApprovalScope>,
+    ) -> ToolFuture<'a>;
+}
+
+#[derive(Default)]
+pub struct ToolRegistry {
+    // NOTE: std RwLock is fine: we only clone `Arc`s out and never hold the lock across .await.
+    tools: RwLock<Vec<Arc<dyn Tool>>>,
+}
+
+impl ToolRegistry {
+    /// Register a tool. A tool with the same name replaces the previous registration.
+    pub fn register(&self, tool: Arc<dyn Tool>) {
+        let Ok(mut tools) = self.tools.write() else {
+            log::warn!("Tool registry lock poisoned; dropping tool {}", tool.name());
+            return;
+        };
+        if let Some(existing) = tools.iter_mut().find(|t| t.name() == tool.name()) {
+            *existing = tool;
+        } else {
+            tools.push(tool);
+        }
+    }
+
+    pub fn unregister(&self, name: &str) {
+        if let Ok(mut tools) = self.tools.write() {
+            tools.retain(|t| t.name() != name);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools
+            .read()
+            .ok()?
+            .iter()
+            .find(|t| t.name() == name)
+            .cloned()
+    }
+
+    /// Tools that are available for this request, in registration order.
+    pub fn available(&self, config: &AiConfig) -> Vec<Arc<dyn Tool>> {
+        let Ok(tools) = self.tools.read() else {
+            return Vec::new();
+        };
+        tools
+            .iter()
+            .filter(|t| t.is_available(config))
+            .cloned()
+            .collect()
+    }
+
+    /// Build the `tools` request field from every available tool.
+    pub fn schema(&self, config: &AiConfig) -> serde_json::Value {
+        let functions = self
+            .available(config)
+            .iter()
+            .map(|t| t.definition())
+            .collect::<Vec<_>>();
+        prompts::build_tools_schema(functions, strict_tool_calls(config))
+    }
+
+    /// Dispatch a tool call, bounded by the tool's timeout (else `default_timeout`) and `cancel`.
+    /// Tools not available under `config` are refused, whatever the model asked for.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn execute(
+        &self,
+        name: &str,
+        arguments: &serde_json::Value,
+        config: &AiConfig,
+        cancel: &CancellationToken,
+        audit: &AuditContext,
+        approved: Option<&ApprovalScope>,
+        default_timeout: Duration,
+    ) -> Result<String, String> {
+        let tool = self
+            .get(name)
+            .ok_or_else(|| format!("Unknown tool: {}", name))?;
+        if !tool.is_available(config) {
+            return Err(prompts::tool_unavailable_error(name));
+        }
+        let timeout = tool.timeout().unwrap_or(default_timeout);
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(prompts::TOOL_CANCELLED_ERROR.to_string()),
+            result = tokio::time::timeout(timeout, tool.execute(arguments, cancel, audit, approved)) => {
+                result.unwrap_or_else(|_| Err(prompts::tool_timeout_error(timeout.as_secs_f64())))
+            }
+        }
+    }
+}
+
+fn strict_tool_calls(config: &AiConfig) -> bool {
+    // DeepSeek strict Tool Calls are enabled under `/beta` + `strict: true` schemas.
+    // Allow an explicit env override for other providers during testing.
+    let strict_from_env = std::env::var("AI_TOOL_STRICT")
+        .ok()
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false);
+
+    let base = config.base_url.trim().trim_end_matches('/');
+    let strict_from_base =
+        matches!(config.provider, AiProvider::DeepSeek) && base.ends_with("/beta");
+
+    strict_from_env || strict_from_base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SleepTool;
+
+    impl Tool for SleepTool {
+        fn name(&self) -> &str {
+            "sleep"
+        }
+
+        fn definition(&self) -> serde_json::Value {
+            serde_json::json!({ "name": "sleep", "parameters": { "type": "object" } })
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            Some(Duration::from_millis(50))
+        }
+
+        fn execute<'a>(
+            &'a self,
+            arguments: &'a serde_json::Value,
+            _cancel: &'a CancellationToken,
+            _audit: &'a AuditContext,
+            _approved: Option<&'a ApprovalScope>,
+        ) -> ToolFuture<'a> {
+            Box::pin(async move {
+                let ms = arguments.get("ms").and_then(|v| v.as_u64()).unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                Ok(format!("slept {ms}"))
+            })
+        }
+    }
+
+    /// Offers a fixed function definition; never executed.
+    struct DefinitionTool(serde_json::Value);
+
+    impl Tool for DefinitionTool {
+        fn name(&self) -> &str {
+            self.0["name"].as_str().unwrap_or_default()
+        }
+
+        fn definition(&self) -> serde_json::Value {
+            self.0.clone()
+        }
+
+        fn execute<'a>(
+            &'a self,
+            _arguments: &'a serde_json::Value,
+            _cancel: &'a CancellationToken,
+            _audit: &'a AuditContext,
+            _approved: Option<&'a ApprovalScope>,
+        ) -> ToolFuture<'a> {
+            Box::pin(async { Ok(String::new()) })
+        }
+    }
+
+    /// Stands in for `describe_screen`: only offered when some model supports vision.
+    struct VisionTool;
+
+    impl Tool for VisionTool {
+        fn name(&self) -> &str {
+            prompts::tool_describe_screen::NAME
+        }
+
+        fn definition(&self) -> serde_json::Value {
+            prompts::describe_screen_function()
+        }
+
+        fn is_available(&self, config: &AiConfig) -> bool {
+            config.models.iter().any(|m| m.supports_vision)
+        }
+
+        fn execute<'a>(
+            &'a self,
+            _arguments: &'a serde_json::Value,
+            _cancel: &'a CancellationToken,
+            _audit: &'a AuditContext,
+            _approved: Option<&'a ApprovalScope>,
+        ) -> ToolFuture<'a> {
+            Box::pin(async { Ok("a chart".to_string()) })
+        }
+    }
+
+    fn vision_registry() -> ToolRegistry {
+        let registry = ToolRegistry::default();
+        for definition in [
+            prompts::list_windows_function(),
+            prompts::capture_window_function(),
+            prompts::capture_focused_function(),
+            prompts::describe_screen_function(),
+        ] {
+            registry.register(Arc::new(DefinitionTool(definition)));
+        }
+        registry
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = vision_registry().schema(&AiConfig::default());
+        let tools = schema.as_array().unwrap();
+        assert_eq!(tools.len(), 4);
+        for tool in tools {
+            assert_eq!(tool["type"], "function");
+            assert!(tool["function"]["name"].is_string());
+        }
+    }
+
+    #[test]
+    fn test_schema_strict() {
+        let config = AiConfig {
+            provider: AiProvider::DeepSeek,
+            base_url: "https://api.deepseek.com/beta".to_string(),
+            ..AiConfig::default()
+        };
+        let schema = vision_registry().schema(&config);
+        let tools = schema.as_array().unwrap();
+        for tool in tools {
+            assert_eq!(
+                tool.get("function")
+                    .and_then(|f| f.get("strict"))
+                    .and_then(|s| s.as_bool()),
+                Some(true)
+            );
+            assert_eq!(
+                tool.get("function")
+                    .and_then(|f| f.get("parameters"))
+                    .and_then(|p| p.get("additionalProperties"))
+                    .and_then(|v| v.as_bool()),
+                Some(false)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_timeout_and_cancel() {
+        let registry = ToolRegistry::default();
+        registry.register(Arc::new(SleepTool));
+        let cancel = CancellationToken::new();
+        let audit = AuditContext::default();
+        let config = AiConfig::default();
+        let fallback = Duration::from_secs(60);
+
+        let ok = registry
+            .execute(
+                "sleep",
+                &serde_json::json!({ "ms": 1 }),
+                &config,
+                &cancel,
+                &audit,
+                None,
+                fallback,
+            )
+            .await;
+        assert_eq!(ok, Ok("slept 1".to_string()));
+
+        // The tool's own timeout wins over the loop default.
+        let timed_out = registry
+            .execute(
+                "sleep",
+                &serde_json::json!({ "ms": 5_000 }),
+                &config,
+                &cancel,
+                &audit,
+                None,
+                fallback,
+            )
+            .await;
+        assert_eq!(timed_out, Err(prompts::tool_timeout_error(0.05)));
+
+        assert!(
+            registry
+                .execute(
+                    "missing",
+                    &serde_json::json!({}),
+                    &config,
+                    &cancel,
+                    &audit,
+                    None,
+                    fallback,
+                )
+                .await
+                .is_err()
+        );
+
+        cancel.cancel();
+        let cancelled = registry
+            .execute(
+                "sleep",
+                &serde_json::json!({ "ms": 1 }),
+                &config,
+                &cancel,
+                &audit,
+                None,
+                fallback,
+            )
+            .await;
+        assert_eq!(cancelled, Err(prompts::TOOL_CANCELLED_ERROR.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_execute_refuses_unavailable_tool() {
+        let registry = ToolRegistry::default();
+        registry.register(Arc::new(VisionTool));
+        let cancel = CancellationToken::new();
+        let audit = AuditContext::default();
+        let name = prompts::tool_describe_screen::NAME;
+        let execute = |config: AiConfig| {
+            let registry = &registry;
+            let cancel = &cancel;
+            let audit = &audit;
+            async move {
+                let arguments = serde_json::json!({});
+                registry
+                    .execute(
+                        name,
+                        &arguments,
+                        &config,
+                        cancel,
+                        audit,
+                        None,
+                        Duration::from_secs(1),
+                    )
+                    .await
+            }
+        };
+
+        let mut config = AiConfig::default();
+        assert_eq!(execute(config.clone()).await, Ok("a chart".to_string()));
+
+        // Not offered without a vision model, so a call the model makes anyway is refused.
+        for model in &mut config.models {
+            model.supports_vision = false;
+        }
+        assert!(registry.available(&config).is_empty());
+        assert_eq!(
+            execute(config).await,
+            Err(prompts::tool_unavailable_error(name))
+        );
+    }
+}
@@ -1,48 +1,351 @@
+use std::time::Duration;
+
 use async_openai::error::OpenAIError;
-use async_openai::{Client, config::OpenAIConfig};
 use futures_util::StreamExt;
 use tauri::{Emitter, Manager};
+use tokio_util::sync::CancellationToken;
 
-use crate::services::config::AiConfig;
+use crate::plugins::history::{AuditContext, AuditKind, record_audit};
+use crate::services::config::{AiConfig, AiProvider, load_ai_fallback_configs, provider_key};
 use crate::services::prompts;
 use crate::services::retry::RetryConfig;
 
-use super::request_options::apply_request_options;
+use super::anthropic;
+use super::attachments;
+use super::chat_completions;
+use super::circuit_breaker::CircuitBreakers;
+use super::context_budget::{ContextBudget, fit_messages};
+use super::generation_params::{apply_openai_params, resolve_params};
+use super::manager::AiStreamManager;
+use super::rate_limit::RateLimiter;
+use super::request_options::build_http_request;
+use super::response_format;
 use super::retry_policy::should_retry_openai_error;
+use super::tool_approval;
+use super::tool_registry::ToolRegistry;
 use super::types::{
-    ByotChatCompletionStreamResponse, ChatDeltaKind, ChatMessage, ChatRequestOptions,
-    ChatStreamPayload, EVT_CHAT_STREAM,
+    ChatDeltaKind, ChatMessage, ChatOutput, ChatProfilePayload, ChatRequestOptions,
+    ChatStreamPayload, ChatToolCallPayload, EVT_CHAT_CONTEXT_TRIMMED, EVT_CHAT_PROFILE,
+    EVT_CHAT_STREAM, EVT_CHAT_TOOL_CALL, ReplyBuffer, RoundEvent, TokenUsage, ToolCallFinished,
+    ToolCallRecord, ToolCallStarted,
 };
-#[cfg(feature = "vision")]
-use crate::plugins::vision as vision_plugin;
 
-#[cfg(not(feature = "vision"))]
-mod vision_plugin {
-    use crate::services::config::AiConfig;
+/// Max chars of tool output included in `chat-tool-call` finished events.
+const TOOL_RESULT_PREVIEW_CHARS: usize = 280;
+
+/// Per-call limit for tools that don't declare their own (`AI_TOOL_TIMEOUT_MS`).
+fn default_tool_timeout() -> std::time::Duration {
+    let ms = std::env::var("AI_TOOL_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(60_000)
+        .clamp(1_000, 3_600_000);
+    std::time::Duration::from_millis(ms)
+}
 
-    pub fn ai_tools_schema(_config: &AiConfig) -> serde_json::Value {
-        serde_json::json!([])
+fn tool_result_preview(text: &str) -> String {
+    if text.chars().count() <= TOOL_RESULT_PREVIEW_CHARS {
+        return text.to_string();
     }
+    text.chars()
+        .take(TOOL_RESULT_PREVIEW_CHARS)
+        .collect::<String>()
+        + "…"
+}
+
+/// Expand persisted tool calls into the `assistant(tool_calls)` + `tool` message pairs the API
+/// expects, one pair group per tool round, ahead of the final assistant message.
+fn push_replayed_tool_rounds(
+    api_messages: &mut Vec<serde_json::Value>,
+    records: &[ToolCallRecord],
+) {
+    let mut start = 0;
+    while start < records.len() {
+        let round = records[start].round;
+        let end = records[start..]
+            .iter()
+            .position(|r| r.round != round)
+            .map(|offset| start + offset)
+            .unwrap_or(records.len());
+        let group = &records[start..end];
 
-    pub async fn execute_ai_tool_call(
-        _name: &str,
-        _arguments: &serde_json::Value,
-    ) -> Result<String, String> {
-        Err("Vision disabled".to_string())
+        let tool_calls_json: Vec<serde_json::Value> = group
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "id": r.id,
+                    "type": "function",
+                    "function": {
+                        "name": r.name,
+                        "arguments": r.arguments
+                    }
+                })
+            })
+            .collect();
+        api_messages.push(serde_json::json!({
+            "role": "assistant",
+            "content": serde_json::Value::Null,
+            "tool_calls": tool_calls_json
+        }));
+        for r in group {
+            api_messages.push(serde_json::json!({
+                "role": "tool",
+                "tool_call_id": r.id,
+                "content": r.result
+            }));
+        }
+
+        start = end;
     }
 }
 
-fn vision_runtime_enabled() -> bool {
-    std::env::var("RCAT_VISION")
-        .or_else(|_| std::env::var("VISION_ENABLED"))
-        .ok()
-        .map(|v| {
-            matches!(
-                v.trim().to_ascii_lowercase().as_str(),
-                "1" | "true" | "yes" | "y" | "on"
-            )
-        })
-        .unwrap_or(true)
+/// Initial API messages: the resolved system prompt (replacing the frontend's, else first), then
+/// every turn, with persisted tool rounds replayed before the answer they led to.
+fn conversation_api_messages(
+    messages: Vec<ChatMessage>,
+    system_prompt: &str,
+    mut trimmed_notice: Option<String>,
+) -> Vec<serde_json::Value> {
+    let mut api_messages = Vec::new();
+    if messages.first().is_none_or(|m| m.role != "system") {
+        api_messages.push(serde_json::json!({ "role": "system", "content": system_prompt }));
+    }
+    for m in messages {
+        if m.role != "system"
+            && let Some(notice) = trimmed_notice.take()
+        {
+            api_messages.push(serde_json::json!({ "role": "system", "content": notice }));
+        }
+        if m.role == "system" {
+            // Replace existing system prompt with the resolved one (keeps tool rules consistent)
+            api_messages.push(serde_json::json!({ "role": "system", "content": system_prompt }));
+        } else if m.role == "assistant" && !m.tool_calls.is_empty() {
+            push_replayed_tool_rounds(&mut api_messages, &m.tool_calls);
+            api_messages.push(serde_json::json!({ "role": m.role, "content": m.content }));
+        } else {
+            let content = attachments::message_content(&m);
+            api_messages.push(serde_json::json!({ "role": m.role, "content": content }));
+        }
+    }
+    api_messages
+}
+
+/// `messages` as they will be sent: the resolved system prompt in place of the frontend's, else
+/// first, so the budget counts it exactly once.
+fn with_system_prompt(messages: &[ChatMessage], system_prompt: &str) -> Vec<ChatMessage> {
+    let system = || ChatMessage {
+        seq: None,
+        role: "system".to_string(),
+        content: system_prompt.to_string(),
+        tool_calls: Vec::new(),
+        pinned: false,
+        attachments: Vec::new(),
+    };
+    let mut resolved = Vec::with_capacity(messages.len() + 1);
+    if messages.first().is_none_or(|m| m.role != "system") {
+        resolved.push(system());
+    }
+    resolved.extend(messages.iter().map(|m| {
+        if m.role == "system" {
+            system()
+        } else {
+            m.clone()
+        }
+    }));
+    resolved
+}
+
+/// API messages fitted to `profile`'s context window: the oldest turns are dropped when the
+/// conversation no longer fits, and `chat-context-trimmed` reports what went.
+fn fit_to_profile(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    profile: &AiConfig,
+    messages: &[ChatMessage],
+    system_prompt: &str,
+    tools_schema: &serde_json::Value,
+) -> Vec<serde_json::Value> {
+    let messages = with_system_prompt(messages, system_prompt);
+    let Some(budget) = ContextBudget::for_config(profile) else {
+        return conversation_api_messages(messages, system_prompt, None);
+    };
+    let reserved = budget.estimator.estimate(&tools_schema.to_string());
+    let (kept, report) = fit_messages(messages, &budget, reserved);
+    let mut trimmed_notice = None;
+    if let Some(mut report) = report {
+        log::info!(
+            "Context budget exceeded (request_id={}, model={}): dropped {} messages, ~{} -> ~{} / {} tokens",
+            request_id,
+            profile.model,
+            report.dropped_count,
+            report.estimated_tokens_before,
+            report.estimated_tokens_after,
+            report.budget_tokens
+        );
+        if report.dropped_count > 0 {
+            trimmed_notice = Some(prompts::format_context_trimmed_notice(report.dropped_count));
+        }
+        report.request_id = request_id.to_string();
+        let _ = app.emit(EVT_CHAT_CONTEXT_TRIMMED, report);
+    }
+    conversation_api_messages(kept, system_prompt, trimmed_notice)
+}
+
+enum Recovery {
+    Retry(std::time::Duration),
+    Switch(usize),
+    Fail,
+}
+
+/// The active profile followed by its fallback chain.
+///
+/// The request may move down the chain until the first delta reaches the UI ("committed").
+struct ProfileChain {
+    profiles: Vec<AiConfig>,
+    active: usize,
+    committed: bool,
+    breakers: CircuitBreakers,
+    rate_limiter: RateLimiter,
+}
+
+impl ProfileChain {
+    fn new(config: AiConfig, breakers: CircuitBreakers, rate_limiter: RateLimiter) -> Self {
+        let fallbacks = load_ai_fallback_configs(&config.fallbacks);
+        let mut profiles = vec![config];
+        for candidate in fallbacks {
+            if candidate.api_key.is_empty() && candidate.provider.requires_api_key() {
+                log::warn!(
+                    "Skipping fallback {:?}/{}: no API key configured",
+                    candidate.provider,
+                    candidate.model
+                );
+                continue;
+            }
+            if profiles
+                .iter()
+                .any(|p| p.provider == candidate.provider && p.model == candidate.model)
+            {
+                continue;
+            }
+            profiles.push(candidate);
+        }
+
+        let mut chain = Self {
+            profiles,
+            active: 0,
+            committed: false,
+            breakers,
+            rate_limiter,
+        };
+        // Every breaker open: try the active profile anyway.
+        chain.active = chain.next_available(0).unwrap_or(0);
+        chain
+    }
+
+    fn current(&self) -> &AiConfig {
+        &self.profiles[self.active]
+    }
+
+    /// First profile at or after `from` whose circuit breaker is closed.
+    fn next_available(&self, from: usize) -> Option<usize> {
+        (from..self.profiles.len()).find(|&idx| self.breakers.allows(self.profiles[idx].provider))
+    }
+
+    /// Decide how to continue after a failed attempt (`can_retry` is false once the attempt
+    /// streamed something).
+    fn recover(
+        &self,
+        err: &OpenAIError,
+        can_retry: bool,
+        attempt: usize,
+        retry: &RetryConfig,
+    ) -> Recovery {
+        let profile = self.current();
+        self.rate_limiter.observe_error(&profile.base_url, err);
+        let retryable = should_retry_openai_error(err);
+        if retryable {
+            self.breakers.record_failure(profile.provider);
+        }
+        if can_retry
+            && retryable
+            && attempt < retry.max_attempts
+            && self.breakers.allows(profile.provider)
+        {
+            // Wait for the server-announced window when longer than our own backoff.
+            let throttled = self.rate_limiter.remaining(&profile.base_url);
+            let delay = throttled.unwrap_or_default().max(retry.backoff(attempt));
+            if delay <= retry.max_delay {
+                return Recovery::Retry(delay);
+            }
+            if let Some(next) = self.switch_target() {
+                return Recovery::Switch(next);
+            }
+            // Nowhere else to go: retry after our own limit rather than the full window.
+            return Recovery::Retry(retry.max_delay);
+        }
+        match self.switch_target() {
+            Some(next) => Recovery::Switch(next),
+            None => Recovery::Fail,
+        }
+    }
+
+    /// Before sending: wait out (or route around) a throttle window set by another request.
+    ///
+    /// Without a fallback the wait is capped at `max_delay`.
+    fn throttle(&self, retry: &RetryConfig) -> Option<Recovery> {
+        let delay = self.rate_limiter.remaining(&self.current().base_url)?;
+        if delay > retry.max_delay
+            && let Some(next) = self.switch_target()
+        {
+            return Some(Recovery::Switch(next));
+        }
+        Some(Recovery::Retry(delay.min(retry.max_delay)))
+    }
+
+    fn switch_target(&self) -> Option<usize> {
+        if self.committed {
+            return None;
+        }
+        self.next_available(self.active + 1)
+    }
+
+    /// Lock in the current profile and tell the UI which one answers (first call only).
+    fn commit(&mut self, app: &tauri::AppHandle, request_id: &str, reply: &ReplyBuffer) {
+        if self.committed {
+            return;
+        }
+        self.committed = true;
+        let profile = self.current();
+        reply.update(|output| output.model = profile.model.clone());
+        let _ = app.emit(
+            EVT_CHAT_PROFILE,
+            ChatProfilePayload {
+                request_id: request_id.to_string(),
+                provider: profile.provider,
+                model: profile.model.clone(),
+                fallback_index: self.active as u32,
+            },
+        );
+    }
+}
+
+/// Wait before the next attempt; gives up with the cancellation error if the request is
+/// cancelled meanwhile.
+async fn sleep_before_retry(
+    app: &tauri::AppHandle,
+    delay: Duration,
+    cancel: &CancellationToken,
+    voice_enabled: bool,
+) -> Result<(), String> {
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => Ok(()),
+        _ = cancel.cancelled() => {
+            if voice_enabled {
+                clear_voice_stream_handle(app).await;
+            }
+            Err(prompts::TOOL_CANCELLED_ERROR.to_string())
+        }
+    }
 }
 
 async fn clear_voice_stream_handle(app: &tauri::AppHandle) {
@@ -52,6 +355,29 @@ async fn clear_voice_stream_handle(app: &tauri::AppHandle) {
     voice_state.set_stream_handle(None).await;
 }
 
+/// Hand answer text to the reply, the voice stream and the UI.
+async fn emit_text(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    reply: &ReplyBuffer,
+    voice_handle: Option<&rcat_voice::streaming::StreamHandle>,
+    content: String,
+) {
+    reply.update(|output| output.text.push_str(&content));
+    if let Some(handle) = voice_handle {
+        let _ = handle.push_delta(content.clone()).await;
+    }
+    let _ = app.emit(
+        EVT_CHAT_STREAM,
+        ChatStreamPayload {
+            request_id: request_id.to_string(),
+            delta: content,
+            kind: ChatDeltaKind::Text,
+            done: false,
+        },
+    );
+}
+
 pub(super) async fn run_chat_generic(
     app: &tauri::AppHandle,
     request_id: &str,
@@ -59,9 +385,12 @@ pub(super) async fn run_chat_generic(
     config: AiConfig,
     request_options: ChatRequestOptions,
     http_client: reqwest::Client,
+    cancel: CancellationToken,
     tools_enabled: bool,
     voice_enabled: bool,
-) -> Result<(String, String), String> {
+    persona_prompt: Option<String>,
+    reply: ReplyBuffer,
+) -> Result<ChatOutput, String> {
     let request_id = request_id.to_string();
 
     let mut voice_session: Option<rcat_voice::streaming::StreamSession> = None;
@@ -95,60 +424,33 @@ pub(super) async fn run_chat_generic(
         }
     }
 
-    let openai_config = OpenAIConfig::new()
-        .with_api_base(config.base_url.clone())
-        .with_api_key(config.api_key.clone());
-    let client = Client::with_config(openai_config).with_http_client(http_client);
-
-    // Build initial messages
-    let mut api_messages: Vec<serde_json::Value> = Vec::new();
-
-    // 1. Inject or replace system prompt
-    let has_system = messages
-        .first()
-        .map(|m| m.role == "system")
-        .unwrap_or(false);
-
-    let tools_available = tools_enabled && cfg!(feature = "vision") && vision_runtime_enabled();
-    let tools_schema = if tools_available {
-        vision_plugin::ai_tools_schema(&config)
-    } else {
-        serde_json::json!([])
-    };
-    let tools_active = tools_available
-        && tools_schema
-            .as_array()
-            .map(|items| !items.is_empty())
-            .unwrap_or(true);
-
-    let system_prompt = if tools_active {
-        prompts::SYSTEM_PROMPT_WITH_TOOLS
-    } else {
-        prompts::SYSTEM_PROMPT_DEFAULT
+    // System prompt: persona prompt, else the frontend's, else the default; tool rules are
+    // appended in tool mode.
+    let registry = app.try_state::<ToolRegistry>();
+    let tools_schema = match registry.as_ref() {
+        Some(registry) if tools_enabled => registry.schema(&config),
+        _ => serde_json::json!([]),
     };
+    let tools_active = tools_schema
+        .as_array()
+        .map(|items| !items.is_empty())
+        .unwrap_or(false);
 
-    if !has_system {
-        api_messages.push(serde_json::json!({
-            "role": "system",
-            "content": system_prompt
-        }));
-    }
-
-    // 2. Add user messages
-    for m in messages {
-        if m.role == "system" {
-            // Replace existing system prompt with ours (force consistent rules)
-            api_messages.push(serde_json::json!({
-                "role": "system",
-                "content": system_prompt
-            }));
-        } else {
-            api_messages.push(serde_json::json!({ "role": m.role, "content": m.content }));
-        }
+    let base_prompt = persona_prompt
+        .or_else(|| {
+            messages
+                .iter()
+                .find(|m| m.role == "system" && !m.content.trim().is_empty())
+                .map(|m| m.content.clone())
+        })
+        .unwrap_or_else(|| prompts::SYSTEM_PROMPT_DEFAULT.to_string());
+    let mut system_prompt = prompts::build_system_prompt(&base_prompt, tools_active);
+    if let Some(format) = request_options.response_format.as_ref() {
+        system_prompt = format!("{}\n\n{}", system_prompt, format.instructions());
     }
 
     let tools = if tools_active {
-        Some(tools_schema)
+        Some(tools_schema.clone())
     } else {
         None
     };
@@ -163,85 +465,220 @@ pub(super) async fn run_chat_generic(
     } else {
         1 // Non-tool chats only need 1 round
     };
+    // One extra round for re-asking after a `response_format` validation failure.
+    let max_tool_rounds = max_tool_rounds + usize::from(request_options.response_format.is_some());
+    let mut format_reasked = false;
+    // Structured answers are held back until they validate, so a rejected attempt never reaches
+    // the UI or the voice.
+    let hold_text = request_options.response_format.is_some();
+
+    // Start with the first healthy profile; switching is only allowed until the first delta.
+    let (breakers, rate_limiter) = app
+        .try_state::<AiStreamManager>()
+        .map(|streams| (streams.breakers.clone(), streams.rate_limiter()))
+        .unwrap_or_default();
+    let mut chain = ProfileChain::new(config, breakers, rate_limiter);
+
+    // Fallback profiles may have a smaller context window, so the untrimmed conversation is
+    // fitted again whenever the first round moves to another profile.
+    let mut api_messages = fit_to_profile(
+        app,
+        &request_id,
+        chain.current(),
+        &messages,
+        &system_prompt,
+        &tools_schema,
+    );
+    let mut fitted_for = chain.active;
 
     // Accumulate what the UI receives across tool rounds.
-    let mut all_text = String::new();
-    let mut all_reasoning = String::new();
-
-    'rounds: for _round in 0..max_tool_rounds {
-        // Use streaming API
-        let mut request_json = serde_json::json!({
-            "model": config.model,
-            "messages": api_messages,
-            "stream": true
-        });
-
-        if let Some(t) = &tools {
-            request_json
-                .as_object_mut()
-                .unwrap()
-                .insert("tools".to_string(), t.clone());
-        }
+    reply.update(|output| output.model = chain.current().model.clone());
+
+    'rounds: for round in 0..max_tool_rounds {
+        let mut attempt = 0;
+        'attempts: loop {
+            attempt += 1;
+
+            // Another request may have been told to back off from this endpoint.
+            match chain.throttle(&retry) {
+                Some(Recovery::Switch(next)) => {
+                    log::warn!(
+                        "Endpoint {} throttled, falling back to {:?}/{}",
+                        chain.current().base_url,
+                        chain.profiles[next].provider,
+                        chain.profiles[next].model
+                    );
+                    chain.active = next;
+                    attempt = 0;
+                    continue 'attempts;
+                }
+                Some(Recovery::Retry(delay)) => {
+                    log::info!(
+                        "Waiting {:?} for throttled endpoint {}",
+                        delay,
+                        chain.current().base_url
+                    );
+                    sleep_before_retry(app, delay, &cancel, voice_enabled).await?;
+                }
+                _ => {}
+            }
 
-        let request = request_json;
+            let profile = chain.current().clone();
+            if round == 0 && fitted_for != chain.active {
+                api_messages = fit_to_profile(
+                    app,
+                    &request_id,
+                    &profile,
+                    &messages,
+                    &system_prompt,
+                    &tools_schema,
+                );
+                fitted_for = chain.active;
+            }
+            let params = resolve_params(&profile, request_options.params.as_ref())?;
+            let profile_messages = attachments::for_profile(&profile, &api_messages);
 
-        let mut last_error: Option<String> = None;
+            // Use streaming API
+            let request = match profile.provider {
+                AiProvider::Anthropic => {
+                    anthropic::build_request(&profile, &profile_messages, tools.as_ref(), &params)
+                }
+                _ => {
+                    let mut request_json = serde_json::json!({
+                        "model": profile.model,
+                        "messages": profile_messages,
+                        "stream": true,
+                        // Ask for a final usage chunk so token spend can be recorded per message.
+                        "stream_options": { "include_usage": true }
+                    });
+                    let obj = request_json.as_object_mut().unwrap();
+                    if let Some(t) = &tools {
+                        obj.insert("tools".to_string(), t.clone());
+                    }
+                    let reasoning_model = profile
+                        .models
+                        .iter()
+                        .any(|m| m.id == profile.model && m.supports_think);
+                    apply_openai_params(profile.provider, reasoning_model, &params, obj);
+                    if let Some(format) = request_options.response_format.as_ref() {
+                        response_format::apply_openai(format, profile.provider, obj);
+                    }
+                    request_json
+                }
+            };
 
-        'attempts: for attempt in 1..=retry.max_attempts {
-            let chat = apply_request_options(client.chat(), &request_options)?;
+            let opened = match profile.provider {
+                AiProvider::Anthropic => {
+                    let http = build_http_request(
+                        &http_client,
+                        &profile.base_url,
+                        anthropic::MESSAGES_PATH,
+                        &request_options,
+                    )?;
+                    anthropic::open_stream(
+                        http,
+                        &profile.api_key,
+                        &request,
+                        &chain.rate_limiter,
+                        &profile.base_url,
+                    )
+                    .await
+                }
+                _ => {
+                    let http = build_http_request(
+                        &http_client,
+                        &profile.base_url,
+                        chat_completions::COMPLETIONS_PATH,
+                        &request_options,
+                    )?;
+                    chat_completions::open_stream(
+                        http,
+                        &profile.api_key,
+                        &request,
+                        &chain.rate_limiter,
+                        &profile.base_url,
+                    )
+                    .await
+                }
+            };
 
-            let mut stream = match chat
-                .create_stream_byot::<_, ByotChatCompletionStreamResponse>(&request)
-                .await
-            {
+            let mut stream = match opened {
                 Ok(stream) => stream,
                 Err(err) => {
                     let msg = err.to_string();
-                    last_error = Some(msg.clone());
-                    if attempt < retry.max_attempts && should_retry_openai_error(&err) {
-                        log::warn!(
-                            "Retry attempt {}/{} after error: {}",
-                            attempt + 1,
-                            retry.max_attempts,
-                            msg
-                        );
-                        tokio::time::sleep(retry.backoff(attempt)).await;
-                        continue 'attempts;
+                    match chain.recover(&err, true, attempt, &retry) {
+                        Recovery::Retry(delay) => {
+                            log::warn!(
+                                "Retry attempt {}/{} after error: {}",
+                                attempt + 1,
+                                retry.max_attempts,
+                                msg
+                            );
+                            sleep_before_retry(app, delay, &cancel, voice_enabled).await?;
+                        }
+                        Recovery::Switch(next) => {
+                            log::warn!(
+                                "Falling back from {:?}/{} to {:?}/{} after error: {}",
+                                profile.provider,
+                                profile.model,
+                                chain.profiles[next].provider,
+                                chain.profiles[next].model,
+                                msg
+                            );
+                            chain.active = next;
+                            attempt = 0;
+                        }
+                        Recovery::Fail => {
+                            if voice_enabled {
+                                clear_voice_stream_handle(app).await;
+                            }
+                            return Err(msg);
+                        }
                     }
-                    return Err(msg);
+                    continue 'attempts;
                 }
             };
 
             // Accumulators for this round
             let mut accumulated_content = String::new();
             let mut accumulated_reasoning = String::new();
-            let mut accumulated_tool_calls: Vec<(String, String, String, String)> = Vec::new(); // (id, type, name, arguments)
+            let mut accumulated_tool_calls: Vec<(String, String, String)> = Vec::new(); // (id, name, arguments)
+            let mut replay_blocks: Vec<serde_json::Value> = Vec::new();
             let mut finish_reason: Option<String> = None;
+            let mut round_usage: Option<TokenUsage> = None;
             let mut emitted_any = false;
             let mut stream_error: Option<OpenAIError> = None;
 
             // Process stream
-            while let Some(chunk) = stream.next().await {
-                let chunk = match chunk {
-                    Ok(chunk) => chunk,
+            while let Some(events) = stream.next().await {
+                let events = match events {
+                    Ok(events) => events,
                     Err(err) => {
                         stream_error = Some(err);
                         break;
                     }
                 };
 
-                for choice in chunk.choices {
-                    // Track finish reason
-                    if choice.finish_reason.is_some() {
-                        finish_reason = choice.finish_reason;
+                for event in events {
+                    let emits = matches!(
+                        &event,
+                        RoundEvent::Text(delta) | RoundEvent::Reasoning(delta) if !delta.is_empty()
+                    );
+                    if emits {
+                        chain.commit(app, &request_id, &reply);
                     }
-
-                    // Stream reasoning content
-                    if let Some(reasoning) = choice.delta.reasoning_content {
-                        if !reasoning.is_empty() {
+                    match event {
+                        RoundEvent::Finish(reason) => finish_reason = Some(reason),
+                        RoundEvent::Usage(usage) => round_usage = Some(usage),
+                        RoundEvent::ReplayBlocks(blocks) => replay_blocks.extend(blocks),
+                        // Stream reasoning content
+                        RoundEvent::Reasoning(reasoning) => {
+                            if reasoning.is_empty() {
+                                continue;
+                            }
                             emitted_any = true;
                             accumulated_reasoning.push_str(&reasoning);
-                            all_reasoning.push_str(&reasoning);
+                            reply.update(|output| output.reasoning.push_str(&reasoning));
                             let _ = app.emit(
                                 EVT_CHAT_STREAM,
                                 ChatStreamPayload {
@@ -252,56 +689,43 @@ pub(super) async fn run_chat_generic(
                                 },
                             );
                         }
-                    }
-
-                    // Stream text content
-                    if let Some(content) = choice.delta.content {
-                        if !content.is_empty() {
-                            emitted_any = true;
+                        // Stream text content
+                        RoundEvent::Text(content) => {
+                            if content.is_empty() {
+                                continue;
+                            }
                             accumulated_content.push_str(&content);
-                            all_text.push_str(&content);
-                            if let Some(handle) = voice_handle.as_ref() {
-                                let _ = handle.push_delta(content.clone()).await;
+                            if hold_text {
+                                continue;
                             }
-                            let _ = app.emit(
-                                EVT_CHAT_STREAM,
-                                ChatStreamPayload {
-                                    request_id: request_id.clone(),
-                                    delta: content,
-                                    kind: ChatDeltaKind::Text,
-                                    done: false,
-                                },
-                            );
+                            emitted_any = true;
+                            emit_text(app, &request_id, &reply, voice_handle.as_ref(), content)
+                                .await;
                         }
-                    }
-
-                    // Accumulate tool calls (they come in chunks)
-                    if let Some(tool_calls) = choice.delta.tool_calls {
-                        for tc in tool_calls {
-                            let idx = tc.index;
+                        // Accumulate tool calls (they come in chunks)
+                        RoundEvent::ToolCall {
+                            index,
+                            id,
+                            name,
+                            arguments,
+                        } => {
                             // Ensure we have enough slots
-                            while accumulated_tool_calls.len() <= idx {
+                            while accumulated_tool_calls.len() <= index {
                                 accumulated_tool_calls.push((
                                     String::new(),
                                     String::new(),
                                     String::new(),
-                                    String::new(),
                                 ));
                             }
                             // Accumulate parts
-                            if let Some(id) = tc.id {
-                                accumulated_tool_calls[idx].0 = id;
+                            if let Some(id) = id {
+                                accumulated_tool_calls[index].0 = id;
                             }
-                            if let Some(call_type) = tc.call_type {
-                                accumulated_tool_calls[idx].1 = call_type;
+                            if let Some(name) = name {
+                                accumulated_tool_calls[index].1 = name;
                             }
-                            if let Some(func) = tc.function {
-                                if let Some(name) = func.name {
-                                    accumulated_tool_calls[idx].2 = name;
-                                }
-                                if let Some(args) = func.arguments {
-                                    accumulated_tool_calls[idx].3.push_str(&args);
-                                }
+                            if let Some(args) = arguments {
+                                accumulated_tool_calls[index].2.push_str(&args);
                             }
                         }
                     }
@@ -310,34 +734,69 @@ pub(super) async fn run_chat_generic(
 
             if let Some(err) = stream_error {
                 let msg = err.to_string();
-                last_error = Some(msg.clone());
-                if attempt < retry.max_attempts && !emitted_any && should_retry_openai_error(&err) {
-                    log::warn!(
-                        "Retry attempt {}/{} after stream error: {}",
-                        attempt + 1,
-                        retry.max_attempts,
-                        msg
-                    );
-                    tokio::time::sleep(retry.backoff(attempt)).await;
-                    continue 'attempts;
+                match chain.recover(&err, !emitted_any, attempt, &retry) {
+                    Recovery::Retry(delay) => {
+                        log::warn!(
+                            "Retry attempt {}/{} after stream error: {}",
+                            attempt + 1,
+                            retry.max_attempts,
+                            msg
+                        );
+                        sleep_before_retry(app, delay, &cancel, voice_enabled).await?;
+                    }
+                    Recovery::Switch(next) => {
+                        log::warn!(
+                            "Falling back from {:?}/{} to {:?}/{} after stream error: {}",
+                            profile.provider,
+                            profile.model,
+                            chain.profiles[next].provider,
+                            chain.profiles[next].model,
+                            msg
+                        );
+                        chain.active = next;
+                        attempt = 0;
+                    }
+                    Recovery::Fail => {
+                        if voice_enabled {
+                            clear_voice_stream_handle(app).await;
+                        }
+                        return Err(msg);
+                    }
                 }
-                return Err(msg);
+                continue 'attempts;
+            }
+
+            chain.breakers.record_success(profile.provider);
+            chain.commit(app, &request_id, &reply);
+
+            if let Some(usage) = round_usage.as_ref() {
+                reply.update(|output| {
+                    output
+                        .usage
+                        .get_or_insert_with(TokenUsage::default)
+                        .add(usage)
+                });
             }
 
             // Check if we have tool calls to execute
-            let has_tool_calls = tools_enabled
+            let has_tool_calls = tools_active
                 && !accumulated_tool_calls.is_empty()
                 && finish_reason.as_deref() == Some("tool_calls");
 
             if has_tool_calls {
+                if hold_text && !accumulated_content.is_empty() {
+                    let content = accumulated_content.clone();
+                    emit_text(app, &request_id, &reply, voice_handle.as_ref(), content).await;
+                }
+
                 // Build assistant message with tool_calls AND reasoning_content
                 let tool_calls_json: Vec<serde_json::Value> = accumulated_tool_calls
                     .iter()
-                    .filter(|(id, _, name, _)| !id.is_empty() && !name.is_empty())
-                    .map(|(id, call_type, name, args)| {
+                    .filter(|(id, name, _)| !id.is_empty() && !name.is_empty())
+                    .map(|(id, name, args)| {
                         serde_json::json!({
                             "id": id,
-                            "type": if call_type.is_empty() { "function" } else { call_type.as_str() },
+                            "type": "function",
                             "function": {
                                 "name": name,
                                 "arguments": args
@@ -352,13 +811,28 @@ pub(super) async fn run_chat_generic(
                     "reasoning_content": if accumulated_reasoning.is_empty() { serde_json::Value::Null } else { serde_json::Value::String(accumulated_reasoning.clone()) },
                     "tool_calls": tool_calls_json
                 }));
+                if !replay_blocks.is_empty()
+                    && let Some(assistant) = api_messages.last_mut().and_then(|m| m.as_object_mut())
+                {
+                    assistant.insert(
+                        anthropic::THINKING_BLOCKS_KEY.to_string(),
+                        serde_json::Value::Array(replay_blocks),
+                    );
+                }
 
-                // Emit tool call info and execute
-                for (id, _, name, args) in &accumulated_tool_calls {
-                    if id.is_empty() || name.is_empty() {
-                        continue;
-                    }
+                // Emit tool call info, then run the calls concurrently
+                let calls: Vec<(&String, &String, &String, serde_json::Value)> =
+                    accumulated_tool_calls
+                        .iter()
+                        .filter(|(id, name, _)| !id.is_empty() && !name.is_empty())
+                        .map(|(id, name, args)| {
+                            let arguments: serde_json::Value =
+                                serde_json::from_str(args).unwrap_or(serde_json::json!({}));
+                            (id, name, args, arguments)
+                        })
+                        .collect();
 
+                for (id, name, _, arguments) in &calls {
                     let indicator = prompts::tool_call_indicator(name);
                     let _ = app.emit(
                         EVT_CHAT_STREAM,
@@ -369,18 +843,131 @@ pub(super) async fn run_chat_generic(
                             done: false,
                         },
                     );
-                    all_reasoning.push_str(&indicator);
+                    reply.update(|output| output.reasoning.push_str(&indicator));
 
-                    let arguments: serde_json::Value =
-                        serde_json::from_str(args).unwrap_or(serde_json::json!({}));
+                    let _ = app.emit(
+                        EVT_CHAT_TOOL_CALL,
+                        ChatToolCallPayload::Started(ToolCallStarted {
+                            request_id: request_id.clone(),
+                            call_id: (*id).clone(),
+                            name: (*name).clone(),
+                            arguments: arguments.clone(),
+                        }),
+                    );
+                }
 
-                    let tool_result = if vision_runtime_enabled() {
-                        vision_plugin::execute_ai_tool_call(name, &arguments)
-                            .await
-                            .unwrap_or_else(|e| format!("工具执行失败: {}", e))
-                    } else {
-                        "Vision disabled".to_string()
-                    };
+                let default_timeout = default_tool_timeout();
+                let streams = app.try_state::<AiStreamManager>();
+                // Results are sent to the profile that asked for the calls; what the tools read
+                // is logged against it.
+                let profile = chain.current();
+                let audit = AuditContext {
+                    conversation_id: streams
+                        .as_ref()
+                        .and_then(|s| s.conversation_of(&request_id)),
+                    request_id: Some(request_id.clone()),
+                    provider: Some(provider_key(profile.provider).to_string()),
+                    model: Some(profile.model.clone()),
+                };
+                let pending = calls.iter().map(|(id, name, _, arguments)| {
+                    let registry = registry.as_ref();
+                    let approvals = streams.as_ref().map(|s| &s.approvals);
+                    let cancel = &cancel;
+                    let request_id = &request_id;
+                    let audit = &audit;
+                    async move {
+                        let started_at = std::time::Instant::now();
+                        let mut scope = None;
+                        let outcome = match (registry, approvals) {
+                            (Some(registry), Some(approvals)) => {
+                                // Sensitive tools wait for the user before running; tools this
+                                // profile cannot use are refused by `execute` without asking.
+                                let tool = registry.get(name).filter(|t| t.is_available(profile));
+                                let approved = match tool {
+                                    Some(tool) => {
+                                        tool_approval::authorize(
+                                            app,
+                                            approvals,
+                                            tool.as_ref(),
+                                            request_id,
+                                            id,
+                                            arguments,
+                                            cancel,
+                                        )
+                                        .await
+                                    }
+                                    None => Ok(None),
+                                };
+                                match approved {
+                                    Ok(approved_scope) => {
+                                        scope = approved_scope;
+                                        registry
+                                            .execute(
+                                                name,
+                                                arguments,
+                                                profile,
+                                                cancel,
+                                                audit,
+                                                scope.as_ref(),
+                                                default_timeout,
+                                            )
+                                            .await
+                                    }
+                                    Err(reason) => Err(reason),
+                                }
+                            }
+                            _ => Err("Tool registry unavailable".to_string()),
+                        };
+                        let is_error = outcome.is_err();
+                        let tool_result =
+                            outcome.unwrap_or_else(|e| prompts::format_tool_error(&e));
+
+                        // Finished events go out as each call completes.
+                        let _ = app.emit(
+                            EVT_CHAT_TOOL_CALL,
+                            ChatToolCallPayload::Finished(ToolCallFinished {
+                                request_id: request_id.clone(),
+                                call_id: (*id).clone(),
+                                name: (*name).clone(),
+                                duration_ms: started_at.elapsed().as_millis() as u64,
+                                result_preview: tool_result_preview(&tool_result),
+                                is_error,
+                            }),
+                        );
+
+                        let scope = scope.unwrap_or_default();
+                        let mut entry = audit.entry(AuditKind::ToolCall);
+                        entry.tool = Some((*name).clone());
+                        entry.app_name = scope.app;
+                        entry.window_title = scope.window;
+                        entry.chars = tool_result.chars().count() as u64;
+                        entry.is_error = is_error;
+                        record_audit(app, entry).await;
+
+                        (tool_result, is_error)
+                    }
+                });
+                let results = futures_util::future::join_all(pending).await;
+
+                if cancel.is_cancelled() {
+                    if voice_enabled {
+                        clear_voice_stream_handle(app).await;
+                    }
+                    return Err(prompts::TOOL_CANCELLED_ERROR.to_string());
+                }
+
+                // Results go back in call order, whatever order they finished in.
+                for ((id, name, args, _), (tool_result, is_error)) in calls.iter().zip(results) {
+                    reply.update(|output| {
+                        output.tool_calls.push(ToolCallRecord {
+                            round: round as u32,
+                            id: (*id).clone(),
+                            name: (*name).clone(),
+                            arguments: (*args).clone(),
+                            result: tool_result.clone(),
+                            is_error,
+                        })
+                    });
 
                     // Add tool result to conversation
                     api_messages.push(serde_json::json!({
@@ -393,19 +980,62 @@ pub(super) async fn run_chat_generic(
                 continue 'rounds;
             }
 
+            // Structured output: re-ask once with the validation error, then give up.
+            if let Some(format) = request_options.response_format.as_ref()
+                && let Err(err) = format.check(&accumulated_content)
+            {
+                if format_reasked || round + 1 >= max_tool_rounds {
+                    if voice_enabled {
+                        clear_voice_stream_handle(app).await;
+                    }
+                    return Err(format!("Response does not match response_format: {err}"));
+                }
+                log::warn!(
+                    "Response failed response_format validation (request_id={}), re-asking: {}",
+                    request_id,
+                    err
+                );
+                format_reasked = true;
+                api_messages.push(serde_json::json!({
+                    "role": "assistant",
+                    "content": accumulated_content
+                }));
+                api_messages.push(serde_json::json!({
+                    "role": "user",
+                    "content": prompts::format_response_format_retry(&err)
+                }));
+                let indicator = prompts::RESPONSE_FORMAT_RETRY_INDICATOR;
+                let _ = app.emit(
+                    EVT_CHAT_STREAM,
+                    ChatStreamPayload {
+                        request_id: request_id.clone(),
+                        delta: indicator.to_string(),
+                        kind: ChatDeltaKind::Reasoning,
+                        done: false,
+                    },
+                );
+                reply.update(|output| output.reasoning.push_str(indicator));
+                continue 'rounds;
+            }
+
             // No tool calls - we're done
+            if hold_text && !accumulated_content.is_empty() {
+                emit_text(
+                    app,
+                    &request_id,
+                    &reply,
+                    voice_handle.as_ref(),
+                    accumulated_content,
+                )
+                .await;
+            }
             if voice_enabled {
                 clear_voice_stream_handle(app).await;
             }
             drop(voice_handle);
             drop(voice_session);
-            return Ok((all_text, all_reasoning));
-        }
-
-        if voice_enabled {
-            clear_voice_stream_handle(app).await;
+            return Ok(reply.take());
         }
-        return Err(last_error.unwrap_or_else(|| "Retry limit exceeded".to_string()));
     }
 
     if voice_enabled {
@@ -413,3 +1043,107 @@ pub(super) async fn run_chat_generic(
     }
     Err(format!("Tool round limit reached ({max_tool_rounds})"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::context_budget::TokenEstimator;
+    use super::*;
+
+    fn message(role: &str, content: &str, tool_calls: Vec<ToolCallRecord>) -> ChatMessage {
+        ChatMessage {
+            seq: None,
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls,
+            pinned: false,
+            attachments: Vec::new(),
+        }
+    }
+
+    fn record(round: u32, id: &str, result: &str) -> ToolCallRecord {
+        ToolCallRecord {
+            round,
+            id: id.to_string(),
+            name: "list_windows".to_string(),
+            arguments: "{}".to_string(),
+            result: result.to_string(),
+            is_error: false,
+        }
+    }
+
+    #[test]
+    fn test_conversation_api_messages_replays_tool_rounds() {
+        let messages = vec![
+            message("user", "What is open?", Vec::new()),
+            message(
+                "assistant",
+                "Notes and Mail.",
+                vec![
+                    record(0, "call_1", "Notes"),
+                    record(0, "call_2", "Mail"),
+                    record(1, "call_3", "Mail (2 unread)"),
+                ],
+            ),
+            message("user", "Thanks", Vec::new()),
+        ];
+        let api = conversation_api_messages(messages, "system prompt", None);
+
+        let roles: Vec<_> = api.iter().map(|m| m["role"].as_str().unwrap()).collect();
+        assert_eq!(
+            roles,
+            [
+                "system",
+                "user",
+                "assistant",
+                "tool",
+                "tool",
+                "assistant",
+                "tool",
+                "assistant",
+                "user"
+            ]
+        );
+        assert_eq!(api[0]["content"], "system prompt");
+        assert_eq!(api[2]["tool_calls"][1]["id"], "call_2");
+        assert_eq!(api[4]["tool_call_id"], "call_2");
+        assert_eq!(api[6]["content"], "Mail (2 unread)");
+        assert_eq!(api[7]["content"], "Notes and Mail.");
+    }
+
+    #[test]
+    fn test_conversation_api_messages_replaces_system_and_adds_notice() {
+        let messages = vec![
+            message("system", "frontend prompt", Vec::new()),
+            message("user", "Hi", Vec::new()),
+        ];
+        let api = conversation_api_messages(messages, "resolved", Some("trimmed".to_string()));
+        let contents: Vec<_> = api.iter().map(|m| m["content"].as_str().unwrap()).collect();
+        assert_eq!(contents, ["resolved", "trimmed", "Hi"]);
+    }
+
+    #[test]
+    fn test_system_prompt_counted_once_against_budget() {
+        let budget = ContextBudget {
+            limit: 1,
+            estimator: TokenEstimator::for_provider(AiProvider::OpenAI),
+        };
+        let estimate = |messages: &[ChatMessage]| {
+            let (_, report) = fit_messages(with_system_prompt(messages, "resolved"), &budget, 0);
+            report.unwrap().estimated_tokens_before
+        };
+        let turn = message("user", "Hi", Vec::new());
+        let long_frontend = message("system", &"x".repeat(4000), Vec::new());
+
+        let without_system = estimate(std::slice::from_ref(&turn));
+        assert_eq!(estimate(&[long_frontend, turn.clone()]), without_system);
+        assert_eq!(
+            estimate(&[message("system", "", Vec::new()), turn]),
+            without_system
+        );
+
+        let sent = with_system_prompt(&[message("user", "Hi", Vec::new())], "resolved");
+        let api = conversation_api_messages(sent, "resolved", None);
+        let contents: Vec<_> = api.iter().map(|m| m["content"].as_str().unwrap()).collect();
+        assert_eq!(contents, ["resolved", "Hi"]);
+    }
+}
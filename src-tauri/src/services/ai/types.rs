@@ -1,5 +1,13 @@
+use async_openai::error::OpenAIError;
+use futures_util::stream::BoxStream;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::services::config::AiProvider;
+
+use super::generation_params::GenerationParams;
+use super::response_format::ResponseFormat;
 
 /// Event name for streaming chat chunks
 pub const EVT_CHAT_STREAM: &str = "chat-stream";
@@ -7,6 +15,12 @@ pub const EVT_CHAT_STREAM: &str = "chat-stream";
 pub const EVT_CHAT_DONE: &str = "chat-done";
 /// Event name for stream error
 pub const EVT_CHAT_ERROR: &str = "chat-error";
+/// Event name for structured tool-call activity (started / finished)
+pub const EVT_CHAT_TOOL_CALL: &str = "chat-tool-call";
+/// Event name for context budgeting (older messages dropped to fit the model window)
+pub const EVT_CHAT_CONTEXT_TRIMMED: &str = "chat-context-trimmed";
+/// Event name for the profile (provider + model) that answers a request
+pub const EVT_CHAT_PROFILE: &str = "chat-profile";
 
 /// Stream completion payload (used for history refresh / notifications).
 #[derive(Clone, Serialize)]
@@ -16,9 +30,24 @@ pub struct ChatDonePayload {
     pub conversation_id: Option<String>,
 }
 
+/// Emitted once per request, before the first delta, naming the profile that answers.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatProfilePayload {
+    pub request_id: String,
+    pub provider: AiProvider,
+    pub model: String,
+    /// 0 = active profile, `n` = n-th entry of the fallback chain.
+    pub fallback_index: u32,
+}
+
 /// Message format received from frontend
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
 #[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ChatMessage {
     /// Optional history sequence number (1-based, non-system messages only).
     ///
@@ -26,6 +55,112 @@ pub struct ChatMessage {
     pub seq: Option<u32>,
     pub role: String,
     pub content: String,
+    /// Tool calls the assistant made while producing this message (replayed to the API).
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// Pinned messages are never dropped by context budgeting.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Images sent with a user message.
+    #[serde(default)]
+    pub attachments: Vec<ChatAttachment>,
+}
+
+/// Image attached to a user message.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ChatAttachment {
+    /// Inline `data:image/...;base64,...` URL (the form stored in history).
+    Image { url: String },
+    /// Local image file; inlined as [`ChatAttachment::Image`] when the request starts.
+    ImageFile { path: String },
+}
+
+/// A tool call made by the assistant while producing a message, with its result.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallRecord {
+    /// Tool round (0-based) within the assistant turn.
+    pub round: u32,
+    /// Provider tool call id (`tool_call_id`).
+    pub id: String,
+    pub name: String,
+    /// Raw JSON arguments as sent by the model.
+    pub arguments: String,
+    /// Tool output as fed back to the model.
+    pub result: String,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// Provider-reported token usage (summed across tool rounds for one assistant message).
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Subset of `completion_tokens` spent on reasoning (if reported).
+    pub reasoning_tokens: u32,
+    /// Subset of `prompt_tokens` served from the provider's prompt cache (if reported).
+    pub cached_tokens: u32,
+}
+
+impl TokenUsage {
+    pub(crate) fn add(&mut self, other: &TokenUsage) {
+        self.prompt_tokens = self.prompt_tokens.saturating_add(other.prompt_tokens);
+        self.completion_tokens = self
+            .completion_tokens
+            .saturating_add(other.completion_tokens);
+        self.reasoning_tokens = self.reasoning_tokens.saturating_add(other.reasoning_tokens);
+        self.cached_tokens = self.cached_tokens.saturating_add(other.cached_tokens);
+    }
+}
+
+/// Final output of a chat request (accumulated across tool rounds).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChatOutput {
+    pub(crate) text: String,
+    pub(crate) reasoning: String,
+    pub(crate) tool_calls: Vec<ToolCallRecord>,
+    /// Model that produced the answer.
+    pub(crate) model: String,
+    /// `None` when the provider did not report usage for any round.
+    pub(crate) usage: Option<TokenUsage>,
+}
+
+impl ChatOutput {
+    /// Nothing was streamed (no text, reasoning or tool calls).
+    pub(crate) fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+            && self.reasoning.trim().is_empty()
+            && self.tool_calls.is_empty()
+    }
+}
+
+/// The reply as streamed so far, shared with `start_stream_task` so an aborted or failed
+/// request can still persist what the user already saw.
+#[derive(Clone, Default)]
+pub(crate) struct ReplyBuffer(Arc<Mutex<ChatOutput>>);
+
+impl ReplyBuffer {
+    pub(crate) fn update(&self, f: impl FnOnce(&mut ChatOutput)) {
+        if let Ok(mut output) = self.0.lock() {
+            f(&mut output);
+        }
+    }
+
+    /// Take the accumulated reply, leaving the buffer empty.
+    pub(crate) fn take(&self) -> ChatOutput {
+        self.0
+            .lock()
+            .map(|mut output| std::mem::take(&mut *output))
+            .unwrap_or_default()
+    }
 }
 
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
@@ -35,6 +170,10 @@ pub struct ChatRequestOptions {
     pub path: Option<String>,
     pub headers: Option<HashMap<String, String>>,
     pub query: Option<HashMap<String, String>>,
+    /// Overrides the selected model's `params` for this request.
+    pub params: Option<GenerationParams>,
+    /// Require a JSON answer; validated before the reply is accepted (see `response_format`).
+    pub response_format: Option<ResponseFormat>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
@@ -54,6 +193,44 @@ pub struct ChatStreamPayload {
     pub done: bool,
 }
 
+/// Emitted when the model asks for a tool and before it runs.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallStarted {
+    pub request_id: String,
+    pub call_id: String,
+    pub name: String,
+    /// Parsed JSON arguments (`{}` when the model sent invalid JSON).
+    pub arguments: serde_json::Value,
+}
+
+/// Emitted once a tool call has produced its result (or failed).
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallFinished {
+    pub request_id: String,
+    pub call_id: String,
+    pub name: String,
+    pub duration_ms: u64,
+    /// Truncated tool output (the model receives the full text).
+    pub result_preview: String,
+    pub is_error: bool,
+}
+
+/// Payload of `chat-tool-call`.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(tag = "type", rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ChatToolCallPayload {
+    Started(ToolCallStarted),
+    Finished(ToolCallFinished),
+}
+
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChatErrorPayload {
@@ -64,7 +241,58 @@ pub struct ChatErrorPayload {
 /// BYOT stream chunk type that keeps DeepSeek-style `reasoning_content`.
 #[derive(Debug, Deserialize)]
 pub(super) struct ByotChatCompletionStreamResponse {
+    // The final `include_usage` chunk has an empty (or missing) `choices` array.
+    #[serde(default)]
     pub(super) choices: Vec<ByotChatChoiceStream>,
+    #[serde(default)]
+    pub(super) usage: Option<ByotUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ByotUsage {
+    #[serde(default)]
+    pub(super) prompt_tokens: u32,
+    #[serde(default)]
+    pub(super) completion_tokens: u32,
+    #[serde(default)]
+    pub(super) prompt_tokens_details: Option<ByotPromptTokensDetails>,
+    #[serde(default)]
+    pub(super) completion_tokens_details: Option<ByotCompletionTokensDetails>,
+    /// DeepSeek reports cache hits here instead of `prompt_tokens_details`.
+    #[serde(default)]
+    pub(super) prompt_cache_hit_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ByotPromptTokensDetails {
+    #[serde(default)]
+    pub(super) cached_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ByotCompletionTokensDetails {
+    #[serde(default)]
+    pub(super) reasoning_tokens: Option<u32>,
+}
+
+impl From<ByotUsage> for TokenUsage {
+    fn from(usage: ByotUsage) -> Self {
+        let cached_tokens = usage
+            .prompt_tokens_details
+            .and_then(|d| d.cached_tokens)
+            .or(usage.prompt_cache_hit_tokens)
+            .unwrap_or(0);
+        let reasoning_tokens = usage
+            .completion_tokens_details
+            .and_then(|d| d.reasoning_tokens)
+            .unwrap_or(0);
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            reasoning_tokens,
+            cached_tokens,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,8 +314,6 @@ pub(super) struct ByotChatCompletionStreamDelta {
 pub(super) struct StreamToolCallDelta {
     pub(super) index: usize,
     pub(super) id: Option<String>,
-    #[serde(rename = "type")]
-    pub(super) call_type: Option<String>,
     pub(super) function: Option<StreamFunctionDelta>,
 }
 
@@ -96,3 +322,58 @@ pub(super) struct StreamFunctionDelta {
     pub(super) name: Option<String>,
     pub(super) arguments: Option<String>,
 }
+
+/// Parsed stream of one tool round, as opened by each provider module.
+pub(super) type RoundStream = BoxStream<'static, Result<Vec<RoundEvent>, OpenAIError>>;
+
+/// Provider-neutral streaming event within one tool round.
+#[derive(Debug)]
+pub(super) enum RoundEvent {
+    Text(String),
+    Reasoning(String),
+    /// Tool call fragment; `index` is the call's slot within the round.
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: Option<String>,
+    },
+    /// Chat-completions style finish reason (`stop` / `tool_calls` / `length`).
+    Finish(String),
+    Usage(TokenUsage),
+    /// Provider blocks that must be echoed back on the assistant turn (Anthropic thinking).
+    ReplayBlocks(Vec<serde_json::Value>),
+}
+
+impl RoundEvent {
+    pub(super) fn from_openai_chunk(chunk: ByotChatCompletionStreamResponse) -> Vec<RoundEvent> {
+        let mut out = Vec::new();
+        if let Some(usage) = chunk.usage {
+            out.push(RoundEvent::Usage(usage.into()));
+        }
+        for choice in chunk.choices {
+            if let Some(reason) = choice.finish_reason {
+                out.push(RoundEvent::Finish(reason));
+            }
+            if let Some(reasoning) = choice.delta.reasoning_content {
+                out.push(RoundEvent::Reasoning(reasoning));
+            }
+            if let Some(content) = choice.delta.content {
+                out.push(RoundEvent::Text(content));
+            }
+            for tc in choice.delta.tool_calls.into_iter().flatten() {
+                let (name, arguments) = match tc.function {
+                    Some(f) => (f.name, f.arguments),
+                    None => (None, None),
+                };
+                out.push(RoundEvent::ToolCall {
+                    index: tc.index,
+                    id: tc.id,
+                    name,
+                    arguments,
+                });
+            }
+        }
+        out
+    }
+}
@@ -8,13 +8,20 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+use crate::services::ai::ollama;
+use crate::services::ai::{GenerationParams, LocalModelStatus, ResponseFormat};
+
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AiProvider {
     OpenAI,
     DeepSeek,
     Compatible,
+    /// Native Anthropic Messages API (not OpenAI chat completions).
+    Anthropic,
+    /// Local Ollama / llama.cpp server (OpenAI-compatible `/v1`, no API key).
+    Ollama,
 }
 
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
@@ -30,6 +37,9 @@ pub struct AiModel {
     #[serde(default)]
     pub supports_think: bool,
     pub special: Option<String>,
+    /// Default generation parameters for this model (requests may override them).
+    #[serde(default)]
+    pub params: GenerationParams,
 }
 
 impl AiModel {
@@ -42,11 +52,30 @@ impl AiModel {
             supports_vision: false,
             supports_think: false,
             special: None,
+            params: GenerationParams::default(),
         };
 
         match id {
-            "deepseek-reasoner" => model.supports_think = true,
-            "gpt-4o" | "gpt-4o-mini" => model.supports_vision = true,
+            "deepseek-chat" => {
+                model.max_context = Some(131_072);
+                model.max_output = Some(8_192);
+            }
+            "deepseek-reasoner" => {
+                model.max_context = Some(131_072);
+                model.max_output = Some(65_536);
+                model.supports_think = true;
+            }
+            "gpt-4o" | "gpt-4o-mini" => {
+                model.max_context = Some(128_000);
+                model.max_output = Some(16_384);
+                model.supports_vision = true;
+            }
+            "claude-sonnet-4-5" | "claude-haiku-4-5" => {
+                model.max_context = Some(200_000);
+                model.max_output = Some(64_000);
+                model.supports_vision = true;
+                model.supports_think = true;
+            }
             _ => {}
         }
 
@@ -54,6 +83,16 @@ impl AiModel {
     }
 }
 
+/// One entry of the fallback chain; base URL and API key come from that provider's profile.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiFallbackTarget {
+    pub provider: AiProvider,
+    pub model: String,
+}
+
 /// AI configuration for OpenAI-compatible endpoints.
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
 #[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
@@ -65,6 +104,107 @@ pub struct AiConfig {
     pub api_key: String,
     pub model: String,
     pub models: Vec<AiModel>,
+    /// Profiles tried in order when this one fails before the first streamed delta.
+    #[serde(default)]
+    pub fallbacks: Vec<AiFallbackTarget>,
+}
+
+/// Character profile; a conversation keeps its persona via `conversations.persona_id`.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Persona {
+    pub id: String,
+    pub name: String,
+    /// Replaces the built-in system prompt (tool rules are appended in tool mode).
+    pub system_prompt: String,
+    /// Model of the active provider used for this persona's chats (`None` = selected model).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// TTS voice identifier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    /// Emotion -> motion overrides on top of the VRM's own emotion profile.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub emotion_profile: BTreeMap<String, VrmEmotionMotion>,
+}
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonaSettings {
+    pub personas: Vec<Persona>,
+    /// Persona assigned to new conversations.
+    pub default_persona_id: Option<String>,
+}
+
+/// Named prompt with `{{placeholder}}` variables (see `services::prompt_templates`).
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub content: String,
+    /// Structured output for runs of this template (a request's own format wins).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// External MCP tool server (see `plugins::mcp`).
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerConfig {
+    /// Unique name; its tools are offered to the model as `mcp__<name>__<tool>`.
+    pub name: String,
+    #[serde(default)]
+    pub disabled: bool,
+    /// Per-call timeout for this server's tools (default 120s).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_timeout_secs: Option<u64>,
+    pub transport: McpTransport,
+}
+
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum McpTransport {
+    /// Child process speaking newline-delimited JSON-RPC over stdin/stdout.
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+        #[serde(default)]
+        cwd: Option<String>,
+    },
+    /// Streamable HTTP endpoint.
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+/// Remembered "always allow" for a tool that asks for consent (see `services::ai::tool_approval`).
+///
+/// Patterns are case-insensitive and may use `*`; `None` matches anything.
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolApprovalRule {
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<String>,
 }
 
 #[cfg_attr(feature = "typegen", derive(specta::Type))]
@@ -158,6 +298,8 @@ pub struct VrmMouseTrackingSettings {
     pub eyes: VrmMouseTrackingPart,
 }
 
+#[cfg_attr(feature = "typegen", derive(specta::Type))]
+#[cfg_attr(feature = "typegen", specta(rename_all = "camelCase"))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VrmEmotionMotion {
@@ -281,11 +423,14 @@ impl Default for AiConfig {
             api_key: String::new(),
             model: "gpt-4o-mini".to_string(),
             models: vec![AiModel::from_id("gpt-4o-mini"), AiModel::from_id("gpt-4o")],
+            fallbacks: Vec::new(),
         }
     }
 }
 
 const DEFAULT_PROVIDER: AiProvider = AiProvider::DeepSeek;
+const DEFAULT_HISTORY_TRASH_RETENTION_DAYS: u32 = 30;
+pub(crate) const MAX_HISTORY_TRASH_RETENTION_DAYS: u32 = 3650;
 
 // ============================================================================
 // Provider Configuration Table (Single Source of Truth)
@@ -298,9 +443,19 @@ struct ProviderSpec {
     default_models: &'static [&'static str],
     /// URL normalization: Some(true) = ensure `/v1`, Some(false) = strip `/v1`, None = no change
     url_suffix_v1: Option<bool>,
+    /// Local servers accept unauthenticated requests.
+    requires_api_key: bool,
 }
 
 impl AiProvider {
+    const ALL: [AiProvider; 5] = [
+        AiProvider::OpenAI,
+        AiProvider::DeepSeek,
+        AiProvider::Compatible,
+        AiProvider::Anthropic,
+        AiProvider::Ollama,
+    ];
+
     const fn spec(self) -> &'static ProviderSpec {
         match self {
             AiProvider::OpenAI => &ProviderSpec {
@@ -308,21 +463,42 @@ impl AiProvider {
                 default_model: "gpt-4o-mini",
                 default_models: &["gpt-4o-mini", "gpt-4o"],
                 url_suffix_v1: Some(true), // OpenAI requires /v1
+                requires_api_key: true,
             },
             AiProvider::DeepSeek => &ProviderSpec {
                 base_url: "https://api.deepseek.com",
                 default_model: "deepseek-reasoner",
                 default_models: &["deepseek-chat", "deepseek-reasoner"],
                 url_suffix_v1: Some(false), // DeepSeek doesn't want /v1
+                requires_api_key: true,
             },
             AiProvider::Compatible => &ProviderSpec {
                 base_url: "https://api.openai.com/v1",
                 default_model: "gpt-4o-mini",
                 default_models: &["gpt-4o-mini"],
                 url_suffix_v1: None, // User controls URL exactly
+                requires_api_key: true,
+            },
+            AiProvider::Anthropic => &ProviderSpec {
+                base_url: "https://api.anthropic.com/v1",
+                default_model: "claude-sonnet-4-5",
+                default_models: &["claude-sonnet-4-5", "claude-haiku-4-5"],
+                url_suffix_v1: Some(true), // `/v1/messages`
+                requires_api_key: true,
+            },
+            AiProvider::Ollama => &ProviderSpec {
+                base_url: "http://127.0.0.1:11434",
+                default_model: "llama3.2",
+                default_models: &["llama3.2"],
+                url_suffix_v1: Some(true), // Chat goes through the OpenAI-compatible `/v1`
+                requires_api_key: false,
             },
         }
     }
+
+    pub(crate) const fn requires_api_key(self) -> bool {
+        self.spec().requires_api_key
+    }
 }
 
 fn default_base_url(provider: AiProvider) -> &'static str {
@@ -367,8 +543,23 @@ fn default_models(provider: AiProvider) -> Vec<AiModel> {
 struct PersistedSettings {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     ai_provider: Option<AiProvider>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ai_fallbacks: Vec<AiFallbackTarget>,
     #[serde(default)]
     ai: PersistedAiSettings,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    personas: Vec<Persona>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_persona_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    prompt_templates: Vec<PromptTemplate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mcp_servers: Vec<McpServerConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_approval_rules: Vec<ToolApprovalRule>,
+    /// Days deleted conversations stay restorable; `0` keeps them until purged by hand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    history_trash_retention_days: Option<u32>,
     #[serde(default)]
     vrm: PersistedVrmSettings,
 }
@@ -416,12 +607,14 @@ struct PersistedVrmSettings {
     mouse_tracking: VrmMouseTrackingSettings,
 }
 
-/// Get provider key for HashMap lookup
-fn provider_key(provider: AiProvider) -> &'static str {
+/// Get provider key for HashMap lookup (also the provider's name in the audit log)
+pub(crate) fn provider_key(provider: AiProvider) -> &'static str {
     match provider {
         AiProvider::OpenAI => "openai",
         AiProvider::DeepSeek => "deepseek",
         AiProvider::Compatible => "compatible",
+        AiProvider::Anthropic => "anthropic",
+        AiProvider::Ollama => "ollama",
     }
 }
 
@@ -450,11 +643,7 @@ fn default_settings() -> PersistedSettings {
     let mut settings = PersistedSettings::default();
     settings.ai_provider = Some(DEFAULT_PROVIDER);
 
-    for provider in [
-        AiProvider::OpenAI,
-        AiProvider::DeepSeek,
-        AiProvider::Compatible,
-    ] {
+    for provider in AiProvider::ALL {
         let p = profile_mut(&mut settings, provider);
         p.base_url = Some(default_base_url(provider).to_string());
         p.model = Some(default_model(provider).to_string());
@@ -488,6 +677,38 @@ where
     })
 }
 
+/// Drop blank emotions and entries without a motion.
+fn normalize_emotion_profile(
+    profile: BTreeMap<String, VrmEmotionMotion>,
+) -> BTreeMap<String, VrmEmotionMotion> {
+    let mut next: BTreeMap<String, VrmEmotionMotion> = BTreeMap::new();
+    for (emotion, mapping) in profile {
+        let emotion = emotion.trim();
+        if emotion.is_empty() {
+            continue;
+        }
+        let motion_id = mapping.motion_id.and_then(|value| {
+            let trimmed = value.trim();
+            if trimmed.is_empty() {
+                None
+            } else {
+                Some(trimmed.to_string())
+            }
+        });
+        if motion_id.is_none() {
+            continue;
+        }
+        next.insert(
+            emotion.to_string(),
+            VrmEmotionMotion {
+                motion_id,
+                loop_motion: mapping.loop_motion,
+            },
+        );
+    }
+    next
+}
+
 fn normalize_models(models: Vec<AiModel>) -> Vec<AiModel> {
     let mut out: Vec<AiModel> = Vec::new();
     for mut m in models {
@@ -533,11 +754,7 @@ fn normalize_settings(settings: &mut PersistedSettings) -> bool {
         changed = true;
     }
 
-    for provider in [
-        AiProvider::OpenAI,
-        AiProvider::DeepSeek,
-        AiProvider::Compatible,
-    ] {
+    for provider in AiProvider::ALL {
         let p = profile_mut(settings, provider);
 
         let base = p.base_url.as_deref().unwrap_or("").trim();
@@ -664,14 +881,96 @@ pub fn load_ai_config() -> AiConfig {
     let settings = load_settings();
 
     let provider = settings.ai_provider.unwrap_or(DEFAULT_PROVIDER);
+    let mut config = profile_config(&settings, provider);
+    config.fallbacks = settings.ai_fallbacks.clone();
+    config
+}
+
+/// Look up a persona by id.
+pub(crate) fn load_persona(id: &str) -> Option<Persona> {
+    let id = id.trim();
+    if id.is_empty() {
+        return None;
+    }
+    load_settings().personas.into_iter().find(|p| p.id == id)
+}
+
+/// Persona assigned to new conversations, if it still exists.
+pub(crate) fn default_persona_id() -> Option<String> {
+    let settings = load_settings();
+    let id = settings.default_persona_id?;
+    settings.personas.iter().any(|p| p.id == id).then_some(id)
+}
+
+pub(crate) fn load_prompt_templates() -> Vec<PromptTemplate> {
+    load_settings().prompt_templates
+}
+
+pub(crate) fn save_prompt_templates(templates: Vec<PromptTemplate>) -> Result<(), String> {
+    let mut settings = load_settings();
+    settings.prompt_templates = templates;
+    save_settings(&settings)
+}
+
+pub(crate) fn load_mcp_servers() -> Vec<McpServerConfig> {
+    load_settings().mcp_servers
+}
+
+pub(crate) fn save_mcp_servers(servers: Vec<McpServerConfig>) -> Result<(), String> {
+    let mut settings = load_settings();
+    settings.mcp_servers = servers;
+    save_settings(&settings)
+}
+
+pub(crate) fn load_tool_approval_rules() -> Vec<ToolApprovalRule> {
+    load_settings().tool_approval_rules
+}
 
+pub(crate) fn save_tool_approval_rules(rules: Vec<ToolApprovalRule>) -> Result<(), String> {
+    let mut settings = load_settings();
+    settings.tool_approval_rules = rules;
+    save_settings(&settings)
+}
+
+/// Days deleted conversations stay in the trash (`0` = until purged by hand).
+pub(crate) fn load_history_trash_retention_days() -> u32 {
+    load_settings()
+        .history_trash_retention_days
+        .unwrap_or(DEFAULT_HISTORY_TRASH_RETENTION_DAYS)
+        .min(MAX_HISTORY_TRASH_RETENTION_DAYS)
+}
+
+pub(crate) fn save_history_trash_retention_days(days: u32) -> Result<(), String> {
+    let mut settings = load_settings();
+    settings.history_trash_retention_days = Some(days.min(MAX_HISTORY_TRASH_RETENTION_DAYS));
+    save_settings(&settings)
+}
+
+/// Resolve fallback targets against their providers' saved profiles (in order).
+pub fn load_ai_fallback_configs(targets: &[AiFallbackTarget]) -> Vec<AiConfig> {
+    if targets.is_empty() {
+        return Vec::new();
+    }
+
+    let settings = load_settings();
+    targets
+        .iter()
+        .map(|target| {
+            let mut config = profile_config(&settings, target.provider);
+            config.model = target.model.clone();
+            config
+        })
+        .collect()
+}
+
+fn profile_config(settings: &PersistedSettings, provider: AiProvider) -> AiConfig {
     static EMPTY_PROFILE: PersistedAiProfile = PersistedAiProfile {
         base_url: None,
         api_key: None,
         model: None,
         models: Vec::new(),
     };
-    let p = profile(&settings, provider).unwrap_or(&EMPTY_PROFILE);
+    let p = profile(settings, provider).unwrap_or(&EMPTY_PROFILE);
 
     let base_url = p.base_url.as_deref().unwrap_or(default_base_url(provider));
 
@@ -690,6 +989,7 @@ pub fn load_ai_config() -> AiConfig {
         api_key,
         model: model.to_string(),
         models,
+        fallbacks: Vec::new(),
     }
 }
 
@@ -768,6 +1068,11 @@ pub fn set_ai_profile(
         Some(key.to_string())
     };
 
+    for m in &models {
+        m.params
+            .validate()
+            .map_err(|e| format!("Invalid parameters for model `{}`: {e}", m.id.trim()))?;
+    }
     let mut models = normalize_models(models);
     if models.is_empty() {
         models = default_models(provider);
@@ -782,19 +1087,109 @@ pub fn set_ai_profile(
     Ok(get_ai_config())
 }
 
+/// Persist the ordered fallback chain (tried when the active profile fails before answering).
+///
+/// Entries with an empty model and duplicates are dropped.
+#[tauri::command]
+pub fn set_ai_fallbacks(
+    app: tauri::AppHandle,
+    fallbacks: Vec<AiFallbackTarget>,
+) -> Result<AiConfig, String> {
+    // Ensure data dir exists (and is cached) before writing settings.
+    let _ = crate::services::paths::data_dir(&app)?;
+
+    let mut chain: Vec<AiFallbackTarget> = Vec::new();
+    for target in fallbacks {
+        let model = target.model.trim();
+        if model.is_empty() {
+            continue;
+        }
+        let target = AiFallbackTarget {
+            provider: target.provider,
+            model: model.to_string(),
+        };
+        if !chain.contains(&target) {
+            chain.push(target);
+        }
+    }
+
+    let mut settings = load_settings();
+    settings.ai_fallbacks = chain;
+    save_settings(&settings)?;
+    Ok(get_ai_config())
+}
+
+#[tauri::command]
+pub fn get_personas() -> PersonaSettings {
+    let settings = load_settings();
+    PersonaSettings {
+        personas: settings.personas,
+        default_persona_id: default_persona_id(),
+    }
+}
+
+/// Replace the persona list.
+///
+/// Personas without an id get a new one; a `default_persona_id` that matches no persona is cleared.
+#[tauri::command]
+pub fn set_personas(
+    app: tauri::AppHandle,
+    personas: Vec<Persona>,
+    default_persona_id: Option<String>,
+) -> Result<PersonaSettings, String> {
+    // Ensure data dir exists (and is cached) before writing settings.
+    let _ = crate::services::paths::data_dir(&app)?;
+
+    let mut next: Vec<Persona> = Vec::new();
+    for persona in personas {
+        let name = persona.name.trim();
+        if name.is_empty() {
+            return Err("Persona name is required".to_string());
+        }
+        let id = match persona.id.trim() {
+            "" => uuid::Uuid::new_v4().to_string(),
+            id => id.to_string(),
+        };
+        if next.iter().any(|p| p.id == id) {
+            return Err(format!("Duplicate persona id: {id}"));
+        }
+        let non_empty = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        next.push(Persona {
+            id,
+            name: name.to_string(),
+            system_prompt: persona.system_prompt.trim().to_string(),
+            model: non_empty(persona.model),
+            voice: non_empty(persona.voice),
+            emotion_profile: normalize_emotion_profile(persona.emotion_profile),
+        });
+    }
+
+    let mut settings = load_settings();
+    settings.default_persona_id = default_persona_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| next.iter().any(|p| &p.id == id));
+    settings.personas = next;
+    save_settings(&settings)?;
+    Ok(get_personas())
+}
+
 /// Test a profile without persisting it.
+///
+/// For local servers this also reports whether the model is loaded and its keep-alive deadline;
+/// remote providers return `None`.
 #[tauri::command]
 pub async fn test_ai_profile(
     provider: AiProvider,
     base_url: String,
     model: String,
     api_key: String,
-) -> Result<(), String> {
-    use async_openai::{config::OpenAIConfig, Client};
-    use serde_json::Value as JsonValue;
-
+) -> Result<Option<LocalModelStatus>, String> {
     let key = api_key.trim();
-    if key.is_empty() {
+    if key.is_empty() && provider.requires_api_key() {
         return Err("API key is required".to_string());
     }
 
@@ -807,10 +1202,36 @@ pub async fn test_ai_profile(
     if base.is_empty() {
         return Err("Base URL is required".to_string());
     }
+    let base = normalize_api_base(provider, base);
+
+    match provider {
+        AiProvider::Anthropic => {
+            crate::services::ai::anthropic::ping(&base, key, model).await?;
+            Ok(None)
+        }
+        AiProvider::Ollama => {
+            let is_ollama = ollama::ensure_pulled(&base, model).await?;
+            // The ping loads the model, so `/api/ps` afterwards reflects the keep-alive window.
+            ping_chat_completions(&base, key, model).await?;
+            if !is_ollama {
+                return Ok(None);
+            }
+            ollama::model_status(&base, model).await.map(Some)
+        }
+        _ => {
+            ping_chat_completions(&base, key, model).await?;
+            Ok(None)
+        }
+    }
+}
+
+async fn ping_chat_completions(base_url: &str, api_key: &str, model: &str) -> Result<(), String> {
+    use async_openai::{Client, config::OpenAIConfig};
+    use serde_json::Value as JsonValue;
 
     let openai_config = OpenAIConfig::new()
-        .with_api_base(normalize_api_base(provider, base))
-        .with_api_key(key.to_string());
+        .with_api_base(base_url)
+        .with_api_key(api_key.to_string());
     let client = Client::with_config(openai_config);
 
     let request = serde_json::json!({
@@ -836,6 +1257,32 @@ pub async fn test_ai_profile(
     Ok(())
 }
 
+/// List the models served by a local server (Ollama `/api/tags`, or `/v1/models`).
+///
+/// Vision / thinking support and context size come from the model metadata.
+#[tauri::command]
+pub async fn discover_ai_models(
+    provider: AiProvider,
+    base_url: String,
+    api_key: String,
+) -> Result<Vec<AiModel>, String> {
+    if !matches!(provider, AiProvider::Ollama) {
+        return Err("Model discovery is only available for local servers".to_string());
+    }
+
+    let base = base_url.trim();
+    let base = if base.is_empty() {
+        normalize_api_base(provider, default_base_url(provider))
+    } else {
+        normalize_api_base(provider, base)
+    };
+    let models = normalize_models(ollama::discover_models(&base, api_key.trim()).await?);
+    if models.is_empty() {
+        return Err("No models found on the server".to_string());
+    }
+    Ok(models)
+}
+
 #[tauri::command]
 pub fn get_vrm_fps_mode() -> Option<VrmFpsMode> {
     let settings = load_settings();
@@ -911,10 +1358,7 @@ pub fn set_vrm_view_state(
         return Err("VRM url is required".to_string());
     }
     let mut settings = load_settings();
-    settings
-        .vrm
-        .view_states
-        .insert(key.to_string(), view_state);
+    settings.vrm.view_states.insert(key.to_string(), view_state);
     save_settings(&settings)?;
     Ok(())
 }
@@ -934,10 +1378,7 @@ pub fn set_vrm_avatar_state(
     let mut settings = load_settings();
     let mut next = avatar_state;
     next.sanitize();
-    settings
-        .vrm
-        .avatar_states
-        .insert(key.to_string(), next);
+    settings.vrm.avatar_states.insert(key.to_string(), next);
     save_settings(&settings)?;
     Ok(())
 }
@@ -991,31 +1432,7 @@ pub fn set_vrm_emotion_profile(
         return Err("VRM url is required".to_string());
     }
 
-    let mut next: BTreeMap<String, VrmEmotionMotion> = BTreeMap::new();
-    for (emotion, mapping) in profile {
-        let emotion = emotion.trim();
-        if emotion.is_empty() {
-            continue;
-        }
-        let motion_id = mapping.motion_id.and_then(|value| {
-            let trimmed = value.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed.to_string())
-            }
-        });
-        if motion_id.is_none() {
-            continue;
-        }
-        next.insert(
-            emotion.to_string(),
-            VrmEmotionMotion {
-                motion_id,
-                loop_motion: mapping.loop_motion,
-            },
-        );
-    }
+    let next = normalize_emotion_profile(profile);
 
     let mut settings = load_settings();
     if next.is_empty() {
@@ -1119,14 +1536,51 @@ mod tests {
 
         let settings: PersistedSettings = serde_json::from_str(json).expect("deserialize");
         let openai = settings.ai.profiles.get("openai").expect("openai profile");
-        assert_eq!(openai.base_url.as_deref(), Some("https://api.openai.com/v1"));
+        assert_eq!(
+            openai.base_url.as_deref(),
+            Some("https://api.openai.com/v1")
+        );
         assert_eq!(openai.model.as_deref(), Some("gpt-4o-mini"));
         assert_eq!(openai.models.len(), 1);
         assert_eq!(openai.models[0].id, "gpt-4o-mini");
 
-        let deepseek = settings.ai.profiles.get("deepseek").expect("deepseek profile");
-        assert_eq!(deepseek.base_url.as_deref(), Some("https://api.deepseek.com"));
+        let deepseek = settings
+            .ai
+            .profiles
+            .get("deepseek")
+            .expect("deepseek profile");
+        assert_eq!(
+            deepseek.base_url.as_deref(),
+            Some("https://api.deepseek.com")
+        );
         assert_eq!(deepseek.model.as_deref(), Some("deepseek-chat"));
         assert_eq!(deepseek.models.len(), 2);
     }
+
+    #[test]
+    fn test_persona_voice_and_emotion_profile() {
+        // Personas saved before `voice` / `emotionProfile` existed still load.
+        let legacy: Persona =
+            serde_json::from_str(r#"{ "id": "p1", "name": "Cat", "systemPrompt": "Meow." }"#)
+                .expect("deserialize");
+        assert_eq!(legacy.voice, None);
+        assert!(legacy.emotion_profile.is_empty());
+
+        let persona: Persona = serde_json::from_str(
+            r#"{
+              "id": "p2", "name": "Dog", "systemPrompt": "", "voice": "warm",
+              "emotionProfile": {
+                "happy": { "motionId": " wave ", "loopMotion": false },
+                "sad": { "motionId": "  " },
+                " ": { "motionId": "bow" }
+              }
+            }"#,
+        )
+        .expect("deserialize");
+        assert_eq!(persona.voice.as_deref(), Some("warm"));
+        let profile = normalize_emotion_profile(persona.emotion_profile);
+        assert_eq!(profile.len(), 1);
+        assert_eq!(profile["happy"].motion_id.as_deref(), Some("wave"));
+        assert_eq!(profile["happy"].loop_motion, Some(false));
+    }
 }
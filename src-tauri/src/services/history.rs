@@ -2,11 +2,20 @@
 //!
 //! Storage is implemented by `crate::plugins::history::HistoryStore`.
 
+use std::path::Path;
+
+use tauri::Emitter;
+
 use crate::plugins::history::HistoryStore;
 pub use crate::plugins::history::{
-    ConversationDetail, ConversationMessage, ConversationSummary, HistoryBootstrap, HistoryError,
+    ArchivedConversation, AuditEntry, AuditKind, ConversationDetail, ConversationMessage,
+    ConversationSummary, ExportFormat, HistoryBootstrap, HistoryError, ImportFormat,
+    ImportProgress, MessageStatus, MessageVariant, SearchHit, SearchResults, UsageTotals,
 };
 
+/// Event name for `history_import` progress
+pub const EVT_HISTORY_IMPORT_PROGRESS: &str = "history-import-progress";
+
 #[tauri::command]
 pub async fn history_bootstrap(
     store: tauri::State<'_, HistoryStore>,
@@ -41,11 +50,30 @@ pub async fn history_get_conversation_page(
         .await
 }
 
+/// Create and activate a conversation.
+///
+/// `persona_id` defaults to the default persona; an empty id creates one without a persona.
 #[tauri::command]
 pub async fn history_new_conversation(
     store: tauri::State<'_, HistoryStore>,
+    persona_id: Option<String>,
 ) -> Result<ConversationSummary, HistoryError> {
-    store.create_conversation(None, true).await
+    let persona_id = match persona_id {
+        Some(id) => Some(id).filter(|id| !id.trim().is_empty()),
+        None => crate::services::config::default_persona_id(),
+    };
+    store.create_conversation(None, true, persona_id).await
+}
+
+#[tauri::command]
+pub async fn history_set_conversation_persona(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_id: String,
+    persona_id: Option<String>,
+) -> Result<(), HistoryError> {
+    store
+        .set_conversation_persona(&conversation_id, persona_id.as_deref())
+        .await
 }
 
 #[tauri::command]
@@ -80,6 +108,58 @@ pub async fn history_delete_conversation(
     store.delete_conversation(&conversation_id).await
 }
 
+/// Deleted conversations in the trash, most recently deleted first.
+#[tauri::command]
+pub async fn history_list_archived(
+    store: tauri::State<'_, HistoryStore>,
+) -> Result<Vec<ArchivedConversation>, HistoryError> {
+    // Never show entries that are already past the retention period.
+    store.purge_expired_trash().await?;
+    store.list_archived().await
+}
+
+/// Restore a deleted conversation; `set_active` also switches to it.
+#[tauri::command]
+pub async fn history_restore_conversation(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_id: String,
+    set_active: Option<bool>,
+) -> Result<HistoryBootstrap, HistoryError> {
+    store
+        .restore_conversation(&conversation_id, set_active.unwrap_or(false))
+        .await
+}
+
+/// Permanently delete conversations from the trash (the whole trash when `conversation_ids` is
+/// omitted); returns how many were purged.
+#[tauri::command]
+pub async fn history_purge_conversations(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_ids: Option<Vec<String>>,
+) -> Result<u64, HistoryError> {
+    store.purge_conversations(conversation_ids.as_deref()).await
+}
+
+#[tauri::command]
+pub fn history_get_trash_retention_days() -> u32 {
+    crate::services::config::load_history_trash_retention_days()
+}
+
+/// Set how many days deleted conversations are kept (`0` = until purged by hand) and purge the
+/// ones already past it. Returns the number purged.
+#[tauri::command]
+pub async fn history_set_trash_retention_days(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, HistoryStore>,
+    days: u32,
+) -> Result<u64, HistoryError> {
+    // Ensure data dir exists (and is cached) before writing settings.
+    crate::services::paths::data_dir(&app).map_err(HistoryError::internal)?;
+    crate::services::config::save_history_trash_retention_days(days)
+        .map_err(HistoryError::internal)?;
+    store.purge_expired_trash().await
+}
+
 #[tauri::command]
 pub async fn history_fork_conversation(
     store: tauri::State<'_, HistoryStore>,
@@ -99,3 +179,164 @@ pub async fn history_rename_conversation(
 ) -> Result<(), HistoryError> {
     store.rename_conversation(&conversation_id, &title).await
 }
+
+/// Stored answers of an assistant turn (see `chat_regenerate`).
+#[tauri::command]
+pub async fn history_list_message_variants(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_id: String,
+    seq: u32,
+) -> Result<Vec<MessageVariant>, HistoryError> {
+    store.list_message_variants(&conversation_id, seq).await
+}
+
+/// Make `variant` the answer shown (and sent as context) for the assistant turn at `seq`.
+#[tauri::command]
+pub async fn history_select_message_variant(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_id: String,
+    seq: u32,
+    variant: u32,
+) -> Result<ConversationMessage, HistoryError> {
+    store
+        .select_message_variant(&conversation_id, seq, variant)
+        .await
+}
+
+/// Pin or unpin a message so context trimming always keeps it.
+#[tauri::command]
+pub async fn history_set_message_pinned(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_id: String,
+    seq: u32,
+    pinned: bool,
+) -> Result<(), HistoryError> {
+    store
+        .set_message_pinned(&conversation_id, seq, pinned)
+        .await
+}
+
+/// Full-text search over titles and messages; pass `nextOffset` back as `offset` for more.
+#[tauri::command]
+pub async fn history_search(
+    store: tauri::State<'_, HistoryStore>,
+    query: String,
+    offset: Option<u32>,
+    limit: Option<u32>,
+) -> Result<SearchResults, HistoryError> {
+    store.search(&query, offset, limit).await
+}
+
+/// Export a conversation to `path`; a directory gets a file named after the conversation.
+///
+/// Returns the file written. `utc_offset_minutes` localizes the timestamps shown.
+#[tauri::command]
+pub async fn history_export_conversation(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_id: String,
+    format: ExportFormat,
+    path: String,
+    utc_offset_minutes: Option<i32>,
+) -> Result<String, HistoryError> {
+    store
+        .export_conversation(
+            &conversation_id,
+            format,
+            Path::new(path.trim()),
+            utc_offset_minutes.unwrap_or(0),
+        )
+        .await
+        .map(|path| path.display().to_string())
+}
+
+/// Export every conversation into the directory `dir`, one file each; returns the files written.
+#[tauri::command]
+pub async fn history_export_all(
+    store: tauri::State<'_, HistoryStore>,
+    format: ExportFormat,
+    dir: String,
+    utc_offset_minutes: Option<i32>,
+) -> Result<Vec<String>, HistoryError> {
+    let written = store
+        .export_all(
+            format,
+            Path::new(dir.trim()),
+            utc_offset_minutes.unwrap_or(0),
+        )
+        .await?;
+    Ok(written
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect())
+}
+
+/// Import conversations from another app's export file at `path`.
+///
+/// Emits `history-import-progress` as conversations are written; re-importing a file skips the
+/// conversations already imported.
+#[tauri::command]
+pub async fn history_import(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, HistoryStore>,
+    format: ImportFormat,
+    path: String,
+) -> Result<ImportProgress, HistoryError> {
+    store
+        .import_file(format, Path::new(path.trim()), |progress| {
+            let _ = app.emit(EVT_HISTORY_IMPORT_PROGRESS, progress);
+        })
+        .await
+}
+
+#[tauri::command]
+pub async fn history_conversation_usage(
+    store: tauri::State<'_, HistoryStore>,
+    conversation_id: String,
+) -> Result<UsageTotals, HistoryError> {
+    store.conversation_usage(&conversation_id).await
+}
+
+#[tauri::command]
+pub async fn history_usage_by_day(
+    store: tauri::State<'_, HistoryStore>,
+    since_ms: Option<u64>,
+    until_ms: Option<u64>,
+    utc_offset_minutes: Option<i32>,
+) -> Result<Vec<UsageTotals>, HistoryError> {
+    store
+        .usage_by_day(since_ms, until_ms, utc_offset_minutes)
+        .await
+}
+
+#[tauri::command]
+pub async fn history_usage_by_model(
+    store: tauri::State<'_, HistoryStore>,
+    since_ms: Option<u64>,
+    until_ms: Option<u64>,
+) -> Result<Vec<UsageTotals>, HistoryError> {
+    store.usage_by_model(since_ms, until_ms).await
+}
+
+/// Audit log entries (tool calls, screen captures, VLM requests), newest first.
+#[tauri::command]
+pub async fn history_audit_log(
+    store: tauri::State<'_, HistoryStore>,
+    kind: Option<AuditKind>,
+    conversation_id: Option<String>,
+    since_ms: Option<u64>,
+    until_ms: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Vec<AuditEntry>, HistoryError> {
+    store
+        .query_audit(kind, conversation_id.as_deref(), since_ms, until_ms, limit)
+        .await
+}
+
+/// Delete audit entries older than `before_ms` (everything when omitted).
+#[tauri::command]
+pub async fn history_purge_audit_log(
+    store: tauri::State<'_, HistoryStore>,
+    before_ms: Option<u64>,
+) -> Result<u64, HistoryError> {
+    store.purge_audit(before_ms).await
+}
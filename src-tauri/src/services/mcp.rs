@@ -0,0 +1,79 @@
+//! Tauri command surface for MCP tool servers.
+//!
+//! The implementation lives in `crate::plugins::mcp` (treat as a crate-local plugin).
+
+use crate::plugins::mcp::McpManager;
+pub use crate::plugins::mcp::{McpServerState, McpServerStatus};
+use crate::services::config::{self, McpServerConfig, McpTransport};
+
+/// Start the configured servers (called once at startup).
+pub(crate) fn start(app: &tauri::AppHandle, manager: &McpManager) {
+    manager.apply(app, config::load_mcp_servers());
+}
+
+#[tauri::command]
+pub fn get_mcp_servers() -> Vec<McpServerConfig> {
+    config::load_mcp_servers()
+}
+
+/// Replace the server list and apply it: changed servers are restarted, removed ones stopped.
+#[tauri::command]
+pub fn set_mcp_servers(
+    app: tauri::AppHandle,
+    manager: tauri::State<'_, McpManager>,
+    servers: Vec<McpServerConfig>,
+) -> Result<Vec<McpServerConfig>, String> {
+    // Ensure data dir exists (and is cached) before writing settings.
+    let _ = crate::services::paths::data_dir(&app)?;
+
+    let mut next: Vec<McpServerConfig> = Vec::new();
+    for mut server in servers {
+        server.name = server.name.trim().to_string();
+        if server.name.is_empty()
+            || !server
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!(
+                "Invalid MCP server name \"{}\" (use letters, digits, _ and -)",
+                server.name
+            ));
+        }
+        if next.iter().any(|s| s.name == server.name) {
+            return Err(format!("Duplicate MCP server name: {}", server.name));
+        }
+        match &mut server.transport {
+            McpTransport::Stdio { command, .. } => {
+                *command = command.trim().to_string();
+                if command.is_empty() {
+                    return Err(format!("MCP server {} needs a command", server.name));
+                }
+            }
+            McpTransport::Http { url, .. } => {
+                *url = url.trim().to_string();
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    return Err(format!("MCP server {} needs an http(s) URL", server.name));
+                }
+            }
+        }
+        next.push(server);
+    }
+
+    config::save_mcp_servers(next.clone())?;
+    manager.apply(&app, next.clone());
+    Ok(next)
+}
+
+#[tauri::command]
+pub fn mcp_server_status(manager: tauri::State<'_, McpManager>) -> Vec<McpServerStatus> {
+    manager.status()
+}
+
+#[tauri::command]
+pub fn mcp_restart_server(
+    manager: tauri::State<'_, McpManager>,
+    name: String,
+) -> Result<(), String> {
+    manager.restart(name.trim())
+}
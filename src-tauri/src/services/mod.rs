@@ -3,9 +3,12 @@ pub mod ai;
 pub mod config;
 pub mod cursor;
 pub mod history;
+pub mod mcp;
 pub(crate) mod paths;
+pub mod prompt_templates;
 pub mod prompts;
 pub mod retry;
+pub(crate) mod sse;
 #[cfg(feature = "vision")]
 pub mod vision;
 pub mod voice;
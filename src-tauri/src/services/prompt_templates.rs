@@ -0,0 +1,379 @@
+//! Prompt templates: named, user-editable prompts with `{{placeholder}}` variables.
+//!
+//! Templates are stored in settings.json (`promptTemplates`) and rendered here from live
+//! context. Only the placeholders a template uses are gathered, so a template without
+//! `{{ocr_text}}` never captures the screen.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::plugins::history::{AuditContext, HistoryStore};
+use crate::services::ai::commands::{HistoryWrite, start_chat};
+use crate::services::ai::{AiStreamManager, ChatMessage, ChatRequestOptions};
+use crate::services::config::{self, PromptTemplate};
+
+/// Placeholders understood by `render`.
+const PLACEHOLDERS: [&str; 5] = ["selection", "clipboard", "window_title", "date", "ocr_text"];
+
+/// Values for one render; placeholders without a value render as an empty string.
+#[derive(Debug, Default)]
+struct TemplateContext {
+    selection: Option<String>,
+    clipboard: Option<String>,
+    window_title: Option<String>,
+    date: Option<String>,
+    ocr_text: Option<String>,
+}
+
+impl TemplateContext {
+    fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "selection" => self.selection.as_deref(),
+            "clipboard" => self.clipboard.as_deref(),
+            "window_title" => self.window_title.as_deref(),
+            "date" => self.date.as_deref(),
+            "ocr_text" => self.ocr_text.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Placeholder names used by `content`, in order of appearance (may repeat).
+fn placeholders(content: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        out.push(after[..end].trim());
+        rest = &after[end + 2..];
+    }
+    out
+}
+
+/// Replace known placeholders; anything else between braces is kept verbatim.
+fn render(content: &str, context: &TemplateContext) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = after[..end].trim();
+        if PLACEHOLDERS.contains(&name) {
+            out.push_str(context.get(name).unwrap_or(""));
+        } else {
+            out.push_str(&rest[start..start + 2 + end + 2]);
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `YYYY-MM-DD` for a unix timestamp shifted by `utc_offset_minutes`.
+pub(crate) fn format_date(now_ms: u64, utc_offset_minutes: i32) -> String {
+    let local_ms = now_ms as i64 + utc_offset_minutes as i64 * 60_000;
+    let days = local_ms.div_euclid(86_400_000);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's `civil_from_days`).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+async fn read_clipboard() -> Option<String> {
+    let result = tokio::task::spawn_blocking(|| {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    match result {
+        Ok(text) => Some(text),
+        Err(err) => {
+            log::warn!("Clipboard read failed: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "vision")]
+fn smart_window_title() -> Option<String> {
+    if !crate::plugins::vision::runtime_enabled() {
+        return None;
+    }
+    match crate::plugins::vision::get_smart_window() {
+        Ok(window) => window.map(|w| w.title),
+        Err(err) => {
+            log::warn!("Window lookup failed: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "vision"))]
+fn smart_window_title() -> Option<String> {
+    None
+}
+
+#[cfg(feature = "vision")]
+async fn smart_window_text(app: &tauri::AppHandle, audit: &AuditContext) -> Option<String> {
+    if !crate::plugins::vision::runtime_enabled() {
+        return None;
+    }
+    match crate::plugins::vision::capture_smart(app, audit).await {
+        Ok(capture) => Some(capture.text),
+        Err(err) => {
+            log::warn!("Screen capture failed: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "vision"))]
+async fn smart_window_text(_app: &tauri::AppHandle, _audit: &AuditContext) -> Option<String> {
+    None
+}
+
+/// Gather the values `content` refers to; a screen capture is audited under `audit`.
+async fn gather_context(
+    app: &tauri::AppHandle,
+    audit: &AuditContext,
+    content: &str,
+    selection: Option<String>,
+    utc_offset_minutes: Option<i32>,
+) -> TemplateContext {
+    let used = placeholders(content);
+    let uses = |name: &str| used.contains(&name);
+
+    let mut context = TemplateContext {
+        selection,
+        ..Default::default()
+    };
+    if uses("clipboard") {
+        context.clipboard = read_clipboard().await;
+    }
+    if uses("window_title") {
+        context.window_title = smart_window_title();
+    }
+    if uses("date") {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        context.date = Some(format_date(now_ms, utc_offset_minutes.unwrap_or(0)));
+    }
+    if uses("ocr_text") {
+        context.ocr_text = smart_window_text(app, audit).await;
+    }
+    context
+}
+
+fn find_template(template_id: &str) -> Result<PromptTemplate, String> {
+    config::load_prompt_templates()
+        .into_iter()
+        .find(|t| t.id == template_id)
+        .ok_or_else(|| format!("Prompt template not found: {template_id}"))
+}
+
+async fn render_template(
+    app: &tauri::AppHandle,
+    audit: &AuditContext,
+    template: &PromptTemplate,
+    selection: Option<String>,
+    utc_offset_minutes: Option<i32>,
+) -> String {
+    let context =
+        gather_context(app, audit, &template.content, selection, utc_offset_minutes).await;
+    render(&template.content, &context)
+}
+
+#[tauri::command]
+pub fn list_prompt_templates() -> Vec<PromptTemplate> {
+    config::load_prompt_templates()
+}
+
+/// Replace the template list.
+///
+/// Templates without an id get a new one; unknown placeholders are rejected so typos
+/// don't silently reach the model.
+#[tauri::command]
+pub fn set_prompt_templates(
+    app: tauri::AppHandle,
+    templates: Vec<PromptTemplate>,
+) -> Result<Vec<PromptTemplate>, String> {
+    // Ensure data dir exists (and is cached) before writing settings.
+    let _ = crate::services::paths::data_dir(&app)?;
+
+    let mut next: Vec<PromptTemplate> = Vec::new();
+    for template in templates {
+        let name = template.name.trim();
+        if name.is_empty() {
+            return Err("Template name is required".to_string());
+        }
+        if template.content.trim().is_empty() {
+            return Err(format!("Template \"{name}\" is empty"));
+        }
+        if let Some(unknown) = placeholders(&template.content)
+            .into_iter()
+            .find(|p| !PLACEHOLDERS.contains(p))
+        {
+            return Err(format!(
+                "Unknown placeholder {{{{{unknown}}}}} in template \"{name}\""
+            ));
+        }
+        let id = match template.id.trim() {
+            "" => uuid::Uuid::new_v4().to_string(),
+            id => id.to_string(),
+        };
+        if next.iter().any(|t| t.id == id) {
+            return Err(format!("Duplicate template id: {id}"));
+        }
+        if let Some(format) = template.response_format.as_ref() {
+            format
+                .validate()
+                .map_err(|e| format!("Template \"{name}\": {e}"))?;
+        }
+        next.push(PromptTemplate {
+            id,
+            name: name.to_string(),
+            content: template.content,
+            response_format: template.response_format,
+        });
+    }
+
+    config::save_prompt_templates(next)?;
+    Ok(list_prompt_templates())
+}
+
+/// Render a template against the current context.
+///
+/// `selection` is the text selected in the UI; `utc_offset_minutes` localizes `{{date}}`.
+/// A `{{ocr_text}}` capture is audited under `conversation_id`.
+#[tauri::command]
+pub async fn render_prompt_template(
+    app: tauri::AppHandle,
+    template_id: String,
+    selection: Option<String>,
+    utc_offset_minutes: Option<i32>,
+    conversation_id: Option<String>,
+) -> Result<String, String> {
+    let template = find_template(&template_id)?;
+    let audit = AuditContext {
+        conversation_id,
+        ..Default::default()
+    };
+    Ok(render_template(&app, &audit, &template, selection, utc_offset_minutes).await)
+}
+
+/// Render a template and send it as a new user message after `messages` (the conversation so far,
+/// as for `chat_stream`).
+///
+/// Streams like `chat_stream` / `chat_stream_with_tools` (per `tools`) and returns the rendered
+/// text so the caller can show the user turn. The template's `responseFormat` applies unless
+/// `request_options` sets one.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_prompt_template(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, AiStreamManager>,
+    history: tauri::State<'_, HistoryStore>,
+    request_id: String,
+    conversation_id: Option<String>,
+    template_id: String,
+    mut messages: Vec<ChatMessage>,
+    selection: Option<String>,
+    utc_offset_minutes: Option<i32>,
+    model: Option<String>,
+    request_options: Option<ChatRequestOptions>,
+    tools: Option<bool>,
+    voice: Option<bool>,
+) -> Result<String, String> {
+    let template = find_template(&template_id)?;
+    // The capture is sent with this request, so it is audited against it.
+    let config = config::load_ai_config();
+    let audit = AuditContext {
+        conversation_id: conversation_id.clone(),
+        request_id: Some(request_id.clone()),
+        provider: Some(config::provider_key(config.provider).to_string()),
+        model: Some(model.clone().unwrap_or(config.model)),
+    };
+    let content = render_template(&app, &audit, &template, selection, utc_offset_minutes).await;
+    let mut request_options = request_options.unwrap_or_default();
+    if request_options.response_format.is_none() {
+        request_options.response_format = template.response_format;
+    }
+
+    let non_system = || messages.iter().filter(|m| m.role != "system");
+    // Keep history sync in seq mode when the caller sent a paged window.
+    let seq = if non_system().all(|m| m.seq.is_some()) {
+        non_system().filter_map(|m| m.seq).max().map(|seq| seq + 1)
+    } else {
+        None
+    };
+    messages.push(ChatMessage {
+        seq,
+        role: "user".to_string(),
+        content: content.clone(),
+        tool_calls: Vec::new(),
+        pinned: false,
+        attachments: Vec::new(),
+    });
+
+    start_chat(
+        app,
+        streams.inner(),
+        history.inner(),
+        request_id,
+        conversation_id,
+        messages,
+        HistoryWrite::Append {
+            truncate_after_seq: None,
+        },
+        model,
+        Some(request_options),
+        voice,
+        tools.unwrap_or(false),
+    )
+    .await?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_known_placeholders() {
+        let context = TemplateContext {
+            selection: Some("fn main() {}".to_string()),
+            date: Some(format_date(1_735_689_600_000, 8 * 60)),
+            ..Default::default()
+        };
+        let content = "Review {{ selection }} ({{date}}), clip: [{{clipboard}}] {{other}} {{";
+        assert_eq!(
+            placeholders(content),
+            vec!["selection", "date", "clipboard", "other"]
+        );
+        assert_eq!(
+            render(content, &context),
+            "Review fn main() {} (2025-01-01), clip: [] {{other}} {{"
+        );
+
+        assert_eq!(format_date(1_735_689_600_000, -60), "2024-12-31");
+        assert_eq!(format_date(1_709_164_800_000, 0), "2024-02-29");
+    }
+}
@@ -12,15 +12,23 @@ use serde_json::json;
 /// Default system prompt for the AI assistant
 pub const SYSTEM_PROMPT_DEFAULT: &str = r#"你是一个有用的AI助手。请用中文回答用户的问题。"#;
 
-/// System prompt when tool mode is enabled
-pub const SYSTEM_PROMPT_WITH_TOOLS: &str = r#"你是一个智能助手，可以通过工具查看用户的屏幕内容。
+/// Tool-use rules appended to the system prompt when tool mode is enabled
+pub const TOOL_RULES: &str = r#"你可以通过工具查看用户的屏幕内容。
 
 重要规则：
 1. 只有当用户明确需要你查看屏幕、窗口或应用内容时，才使用工具
 2. 对于普通对话、问候、知识问答，直接回答即可，不需要使用工具
-3. 当需要使用工具时，选择最合适的那个
+3. 当需要使用工具时，选择最合适的那个"#;
 
-请用中文回答用户的问题。"#;
+/// System prompt for a chat: `base` (persona or default prompt), plus tool rules in tool mode
+pub fn build_system_prompt(base: &str, tools_active: bool) -> String {
+    let base = base.trim();
+    if tools_active {
+        format!("{}\n\n{}", base, TOOL_RULES)
+    } else {
+        base.to_string()
+    }
+}
 
 // ============================================================================
 // TOOL DEFINITIONS
@@ -54,72 +62,120 @@ pub mod tool_capture_focused {
         对于普通对话不需要使用此工具。";
 }
 
+/// Tool: Describe a window through the vision model (charts, images, layout)
+pub mod tool_describe_screen {
+    pub const NAME: &str = "describe_screen";
+    pub const DESCRIPTION: &str = "截取窗口画面并交给视觉模型描述，可理解图表、图片和界面布局。\
+        只有当用户需要了解屏幕上的图像内容、而文字提取不足以回答时才使用。\
+        对于普通对话不需要使用此工具。";
+    pub const PARAM_QUESTION: &str = "question";
+    pub const PARAM_QUESTION_DESC: &str =
+        "希望视觉模型回答的问题或关注点，如'这张图表的趋势是什么'";
+    pub const PARAM_WINDOW_TITLE: &str = "window_title";
+    pub const PARAM_WINDOW_TITLE_DESC: &str =
+        "要查看的窗口标题（支持模糊匹配）；留空表示当前焦点窗口";
+    /// Used when the model passes an empty question
+    pub const DEFAULT_QUESTION: &str = "请详细描述这张截图中的内容，包括文字、图表和图片。";
+}
+
 // ============================================================================
 // TOOL SCHEMA BUILDERS
 // ============================================================================
 
-/// Build the JSON schema for vision tools.
-///
-/// When `strict` is enabled (e.g. DeepSeek `/beta`), each function includes `strict: true`
-/// and its parameter schema follows strict-mode requirements (`additionalProperties: false`,
-/// and all properties listed in `required`).
-pub fn build_vision_tools_schema(strict: bool) -> serde_json::Value {
-    let mut tools = vec![
-        json!({
-            "type": "function",
-            "function": {
-                "name": tool_list_windows::NAME,
-                "description": tool_list_windows::DESCRIPTION,
-                "parameters": {
-                    "type": "object",
-                    "properties": {},
-                    "required": [],
-                    "additionalProperties": false
-                }
-            }
-        }),
-        json!({
-            "type": "function",
-            "function": {
-                "name": tool_capture_window::NAME,
-                "description": tool_capture_window::DESCRIPTION,
-                "parameters": {
-                    "type": "object",
-                    "properties": {
-                        tool_capture_window::PARAM_WINDOW_TITLE: {
-                            "type": "string",
-                            "description": tool_capture_window::PARAM_WINDOW_TITLE_DESC
-                        }
-                    },
-                    "required": [tool_capture_window::PARAM_WINDOW_TITLE],
-                    "additionalProperties": false
-                }
-            }
-        }),
-        json!({
-            "type": "function",
-            "function": {
-                "name": tool_capture_focused::NAME,
-                "description": tool_capture_focused::DESCRIPTION,
-                "parameters": {
-                    "type": "object",
-                    "properties": {},
-                    "required": [],
-                    "additionalProperties": false
+/// Function definition for `list_visible_windows`.
+pub fn list_windows_function() -> serde_json::Value {
+    json!({
+        "name": tool_list_windows::NAME,
+        "description": tool_list_windows::DESCRIPTION,
+        "parameters": {
+            "type": "object",
+            "properties": {},
+            "required": [],
+            "additionalProperties": false
+        }
+    })
+}
+
+/// Function definition for `capture_window_content`.
+pub fn capture_window_function() -> serde_json::Value {
+    json!({
+        "name": tool_capture_window::NAME,
+        "description": tool_capture_window::DESCRIPTION,
+        "parameters": {
+            "type": "object",
+            "properties": {
+                tool_capture_window::PARAM_WINDOW_TITLE: {
+                    "type": "string",
+                    "description": tool_capture_window::PARAM_WINDOW_TITLE_DESC
                 }
-            }
-        }),
-    ];
-
-    if strict {
-        for tool in &mut tools {
-            if let Some(function) = tool.get_mut("function") {
-                if let serde_json::Value::Object(map) = function {
-                    map.insert("strict".to_string(), json!(true));
+            },
+            "required": [tool_capture_window::PARAM_WINDOW_TITLE],
+            "additionalProperties": false
+        }
+    })
+}
+
+/// Function definition for `capture_focused_window`.
+pub fn capture_focused_function() -> serde_json::Value {
+    json!({
+        "name": tool_capture_focused::NAME,
+        "description": tool_capture_focused::DESCRIPTION,
+        "parameters": {
+            "type": "object",
+            "properties": {},
+            "required": [],
+            "additionalProperties": false
+        }
+    })
+}
+
+/// Function definition for `describe_screen`.
+pub fn describe_screen_function() -> serde_json::Value {
+    json!({
+        "name": tool_describe_screen::NAME,
+        "description": tool_describe_screen::DESCRIPTION,
+        "parameters": {
+            "type": "object",
+            "properties": {
+                tool_describe_screen::PARAM_QUESTION: {
+                    "type": "string",
+                    "description": tool_describe_screen::PARAM_QUESTION_DESC
+                },
+                tool_describe_screen::PARAM_WINDOW_TITLE: {
+                    "type": "string",
+                    "description": tool_describe_screen::PARAM_WINDOW_TITLE_DESC
                 }
-            }
+            },
+            "required": [
+                tool_describe_screen::PARAM_QUESTION,
+                tool_describe_screen::PARAM_WINDOW_TITLE
+            ],
+            "additionalProperties": false
         }
-    }
+    })
+}
+
+/// Wrap function definitions into the `tools` request field.
+///
+/// When `strict` is enabled (e.g. DeepSeek `/beta`), each function includes `strict: true`
+/// and its parameter schema is expected to follow strict-mode requirements
+/// (`additionalProperties: false`, and all properties listed in `required`).
+pub fn build_tools_schema(
+    functions: impl IntoIterator<Item = serde_json::Value>,
+    strict: bool,
+) -> serde_json::Value {
+    let tools = functions
+        .into_iter()
+        .map(|mut function| {
+            if let (true, Some(map)) = (strict, function.as_object_mut()) {
+                map.insert("strict".to_string(), json!(true));
+            }
+            json!({
+                "type": "function",
+                "function": function
+            })
+        })
+        .collect();
 
     serde_json::Value::Array(tools)
 }
@@ -150,12 +206,83 @@ pub fn format_focused_capture(window_name: &str, text: &str) -> String {
     format!("当前焦点窗口 \"{}\" 的内容:\n{}", window_name, text)
 }
 
+/// Format the result of describe_screen tool
+pub fn format_screen_description(window_name: &str, description: &str) -> String {
+    format!(
+        "视觉模型对窗口 \"{}\" 的描述:\n{}",
+        window_name, description
+    )
+}
+
 /// Format tool execution error
-#[allow(dead_code)]
 pub fn format_tool_error(error: &str) -> String {
     format!("工具执行失败: {}", error)
 }
 
+/// Error for a tool call that exceeded its time limit (wrapped by `format_tool_error`)
+pub fn tool_timeout_error(timeout_secs: f64) -> String {
+    format!(
+        "执行超时（{} 秒内未返回结果），已放弃本次调用",
+        timeout_secs
+    )
+}
+
+/// Error for a call to a tool that is not offered for this request (e.g. no vision model)
+pub fn tool_unavailable_error(name: &str) -> String {
+    format!("工具 {} 当前不可用，请改用其他方式完成请求", name)
+}
+
+/// Error for a tool call abandoned because the request was aborted
+pub const TOOL_CANCELLED_ERROR: &str = "请求已取消";
+
+/// Error for a tool call the user refused to allow
+pub const TOOL_DENIED_ERROR: &str = "用户拒绝了本次调用";
+
+/// Error for a tool call whose approval prompt went unanswered
+pub const TOOL_APPROVAL_TIMEOUT_ERROR: &str = "等待用户授权超时，本次调用未执行";
+
+/// Stands in for image attachments when the model has no vision support
+pub fn format_images_omitted_notice(image_count: usize) -> String {
+    format!(
+        "（用户附带了 {} 张图片，但当前模型不支持图像输入，图片已省略。）",
+        image_count
+    )
+}
+
+/// System note describing the JSON answer required by `response_format`
+pub fn format_response_format_instructions(schema: Option<&str>) -> String {
+    match schema {
+        Some(schema) => format!(
+            "请只输出一个符合以下 JSON Schema 的 JSON 值，不要添加任何解释或 Markdown 代码块：\n{}",
+            schema
+        ),
+        None => "请只输出一个 JSON 对象，不要添加任何解释或 Markdown 代码块。".to_string(),
+    }
+}
+
+/// Follow-up sent once when the answer failed `response_format` validation
+pub fn format_response_format_retry(error: &str) -> String {
+    format!(
+        "上一条回复不符合要求的 JSON 格式：{}\n请修正后重新输出完整的 JSON，不要添加任何其他内容。",
+        error
+    )
+}
+
+/// Reasoning-stream note shown while the answer is re-requested
+pub const RESPONSE_FORMAT_RETRY_INDICATOR: &str = "[输出不符合 JSON 格式要求，正在重新生成]\n";
+
+/// User turn sent after an interrupted answer to have the model pick it up (`chat_continue`)
+pub const CONTINUE_GENERATION_PROMPT: &str =
+    "你的上一条回复被中断了。请从中断处直接接着写，不要重复已经写过的内容，也不要添加开场白。";
+
+/// System note inserted where older messages were dropped to fit the context window
+pub fn format_context_trimmed_notice(dropped_count: u32) -> String {
+    format!(
+        "（为适应上下文长度，更早的 {} 条对话消息已省略。）",
+        dropped_count
+    )
+}
+
 // ============================================================================
 // STREAMING UI MESSAGES
 // ============================================================================
@@ -170,32 +297,12 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_build_vision_tools_schema() {
-        let schema = build_vision_tools_schema(false);
-        assert!(schema.is_array());
-        let tools = schema.as_array().unwrap();
-        assert_eq!(tools.len(), 3);
-    }
-
-    #[test]
-    fn test_build_vision_tools_schema_strict() {
-        let schema = build_vision_tools_schema(true);
-        let tools = schema.as_array().unwrap();
-        for tool in tools {
-            assert_eq!(
-                tool.get("function")
-                    .and_then(|f| f.get("strict"))
-                    .and_then(|s| s.as_bool()),
-                Some(true)
-            );
-            assert_eq!(
-                tool.get("function")
-                    .and_then(|f| f.get("parameters"))
-                    .and_then(|p| p.get("additionalProperties"))
-                    .and_then(|v| v.as_bool()),
-                Some(false)
-            );
-        }
+    fn test_build_system_prompt_appends_tool_rules() {
+        let persona = "你是一只猫娘。";
+        assert_eq!(build_system_prompt(persona, false), persona);
+        let with_tools = build_system_prompt(persona, true);
+        assert!(with_tools.starts_with(persona));
+        assert!(with_tools.ends_with(TOOL_RULES));
     }
 
     #[test]
@@ -0,0 +1,101 @@
+//! Incremental `text/event-stream` decoding shared by the Anthropic stream and MCP HTTP servers.
+//!
+//! Bytes are buffered until a whole event has arrived, so multi-byte characters split across
+//! network chunks decode intact.
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct SseEvent {
+    /// `event:` field (empty when the server sends plain `data:` events).
+    pub(crate) event: String,
+    /// `data:` lines joined with `\n`.
+    pub(crate) data: String,
+}
+
+/// Incremental `text/event-stream` decoder (events are separated by a blank line).
+#[derive(Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Next complete event, skipping comment-only / keep-alive blocks.
+    pub(crate) fn next_event(&mut self) -> Option<SseEvent> {
+        loop {
+            let (end, separator_len) = find_event_end(&self.buffer)?;
+            let raw: Vec<u8> = self.buffer.drain(..end + separator_len).collect();
+            let text = String::from_utf8_lossy(&raw[..end]);
+
+            let mut event = SseEvent::default();
+            for line in text.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    event.event = value.trim().to_string();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    if !event.data.is_empty() {
+                        event.data.push('\n');
+                    }
+                    event
+                        .data
+                        .push_str(value.strip_prefix(' ').unwrap_or(value));
+                }
+            }
+            // Comment-only / keep-alive blocks carry no data.
+            if !event.data.is_empty() {
+                return Some(event);
+            }
+        }
+    }
+}
+
+fn find_event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    (0..buffer.len()).find_map(|i| {
+        let rest = &buffer[i..];
+        if rest.starts_with(b"\n\n") {
+            Some((i, 2))
+        } else if rest.starts_with(b"\r\n\r\n") {
+            Some((i, 4))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: &str, data: &str) -> SseEvent {
+        SseEvent {
+            event: event.to_string(),
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_decoder_joins_chunks_and_skips_keep_alives() {
+        let body = ": keep-alive\n\nevent: message\ndata: {\"text\":\"你好\"}\n\n\
+                    data: line 1\r\ndata: line 2\r\n\r\ndata: tail";
+        let mut decoder = SseDecoder::default();
+        let mut events = Vec::new();
+        // One byte at a time: every multi-byte character is split across chunks.
+        for byte in body.as_bytes() {
+            decoder.push(std::slice::from_ref(byte));
+            events.extend(std::iter::from_fn(|| decoder.next_event()));
+        }
+
+        assert_eq!(
+            events,
+            [
+                event("message", "{\"text\":\"你好\"}"),
+                event("", "line 1\nline 2"),
+            ]
+        );
+        // The unterminated event waits for more bytes.
+        decoder.push(b"\n\n");
+        assert_eq!(decoder.next_event(), Some(event("", "tail")));
+        assert_eq!(decoder.next_event(), None);
+    }
+}
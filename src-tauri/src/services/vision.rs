@@ -4,21 +4,10 @@
 
 pub use crate::plugins::vision::{ScreenCaptureResult, VlmAnalysisResult, WindowInfo};
 
-fn vision_runtime_enabled() -> bool {
-    std::env::var("RCAT_VISION")
-        .or_else(|_| std::env::var("VISION_ENABLED"))
-        .ok()
-        .map(|v| {
-            matches!(
-                v.trim().to_ascii_lowercase().as_str(),
-                "1" | "true" | "yes" | "y" | "on"
-            )
-        })
-        .unwrap_or(true)
-}
+use crate::plugins::history::AuditContext;
 
 fn ensure_vision_enabled() -> Result<(), String> {
-    if vision_runtime_enabled() {
+    if crate::plugins::vision::runtime_enabled() {
         Ok(())
     } else {
         Err("Vision disabled".to_string())
@@ -27,19 +16,22 @@ fn ensure_vision_enabled() -> Result<(), String> {
 
 #[tauri::command]
 pub async fn capture_screen_text(
+    app: tauri::AppHandle,
     window_name: Option<String>,
 ) -> Result<ScreenCaptureResult, String> {
     ensure_vision_enabled()?;
-    crate::plugins::vision::capture_screen_text(window_name).await
+    crate::plugins::vision::capture_screen_text(&app, window_name, &AuditContext::default()).await
 }
 
 #[tauri::command]
 pub async fn analyze_screen_vlm(
+    app: tauri::AppHandle,
     prompt: String,
     window_name: Option<String>,
 ) -> Result<VlmAnalysisResult, String> {
     ensure_vision_enabled()?;
-    crate::plugins::vision::analyze_screen_vlm(prompt, window_name).await
+    crate::plugins::vision::analyze_screen_vlm(&app, prompt, window_name, &AuditContext::default())
+        .await
 }
 
 #[tauri::command]
@@ -55,7 +47,7 @@ pub fn get_smart_window() -> Result<Option<WindowInfo>, String> {
 }
 
 #[tauri::command]
-pub async fn capture_smart() -> Result<ScreenCaptureResult, String> {
+pub async fn capture_smart(app: tauri::AppHandle) -> Result<ScreenCaptureResult, String> {
     ensure_vision_enabled()?;
-    crate::plugins::vision::capture_smart().await
+    crate::plugins::vision::capture_smart(&app, &AuditContext::default()).await
 }
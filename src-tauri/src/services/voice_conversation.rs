@@ -5,10 +5,6 @@ use tauri::{Emitter, Manager};
 use tokio::sync::{Mutex, watch};
 use tokio::time::Instant;
 
-use crate::services::ai::{
-    ChatDeltaKind, ChatDonePayload, ChatStreamPayload, EVT_CHAT_DONE, EVT_CHAT_STREAM,
-};
-
 use rcat_voice::turn::{
     AudioFrameRef, SmartTurnBoundaryDetector, TurnBoundaryDetector, TurnEvent, TurnEventKind,
     VadGateTurnDetector,
@@ -234,30 +230,12 @@ fn abort_chat_conversation_best_effort(app: &tauri::AppHandle, conversation_id:
         return;
     }
 
-    let Ok(Some((request_id, handle))) = streams.take_conversation(conversation_id_str) else {
+    let Ok(Some(handle)) = streams.take_conversation(conversation_id_str) else {
         return;
     };
 
-    let handle: tauri::async_runtime::JoinHandle<()> = handle;
+    // The request task announces `chat-done` once its partial answer is saved.
     handle.abort();
-
-    let request_id_str: String = request_id;
-    let _ = app.emit(
-        EVT_CHAT_STREAM,
-        ChatStreamPayload {
-            request_id: request_id_str.clone(),
-            delta: String::new(),
-            kind: ChatDeltaKind::Text,
-            done: true,
-        },
-    );
-    let _ = app.emit(
-        EVT_CHAT_DONE,
-        ChatDonePayload {
-            request_id: request_id_str,
-            conversation_id: Some(conversation_id_str.to_string()),
-        },
-    );
 }
 
 fn env_u64_clamped(key: &str, default: u64, min: u64, max: u64) -> u64 {
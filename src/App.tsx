@@ -1,6 +1,6 @@
 import { useState, useEffect, useRef, useCallback, useMemo } from "react";
 import { MotionConfig } from "framer-motion";
-import type { ChatStatus } from "ai";
+import type { ChatStatus, FileUIPart } from "ai";
 import { useChat } from "@ai-sdk/react";
 
 import {
@@ -10,9 +10,11 @@ import {
   SettingsView,
 } from "./components/views";
 import VrmStage from "@/components/vrm/VrmStage";
+import { setPersonaEmotionOverrides } from "@/components/vrm/emotionProfileStore";
 import {
   EVT_CLICK_THROUGH_STATE,
   EVT_CHAT_DONE,
+  EVT_HISTORY_IMPORT_PROGRESS,
   getRegisteredModelOptions,
 } from "./constants";
 import {
@@ -23,6 +25,7 @@ import {
   useConversationHistory,
   useGeneratingTracker,
   useModelSelection,
+  usePersonas,
   useRouteController,
   useSkinPreference,
   useSyncWindowModeWithConversation,
@@ -32,8 +35,13 @@ import {
 } from "./hooks";
 import { voicePrepare } from "./services";
 import { cn } from "@/lib/utils";
-import { conversationDetailToUiMessages, reportPromiseError } from "@/utils";
+import {
+  conversationDetailToUiMessages,
+  readImageFile,
+  reportPromiseError,
+} from "@/utils";
 import { ChatProvider } from "@/contexts/ChatContext";
+import type { ImportProgress } from "@/types";
 
 type ChatDonePayload = {
   requestId: string;
@@ -65,6 +73,7 @@ function App() {
     [aiConfig?.model, aiConfig?.models, aiConfig?.provider]
   );
   const { selectedModel, setSelectedModel } = useModelSelection(aiConfig, modelOptions);
+  const { personas, refresh: refreshPersonas } = usePersonas();
   const [toolMode, setToolMode] = useState(false);
   const [voiceMode, setVoiceMode] = useState(false);
   const { skinMode, setSkinMode } = useSkinPreference();
@@ -89,6 +98,7 @@ function App() {
     markSeen,
     deleteConversation,
     renameConversation,
+    setConversationPersona,
     refreshList,
   } = useConversationHistory();
 
@@ -99,12 +109,16 @@ function App() {
     voiceMode,
     conversationId: activeConversationId ?? undefined,
   });
-  const { messages, status, sendMessage, error, setMessages, stop } = useChat({
+  const { messages, status, sendMessage, regenerate, error, setMessages, stop } = useChat({
     id: activeConversationId ?? "loading",
     transport,
   });
 
   const busy = isChatBusy(status);
+  const chatSettings = useMemo(
+    () => ({ model: selectedModel, toolMode, voiceMode }),
+    [selectedModel, toolMode, voiceMode]
+  );
   const {
     isConversationGenerating,
     isActiveConversationGenerating,
@@ -149,9 +163,28 @@ function App() {
     setMessages(conversationDetailToUiMessages(activeConversation));
   }, [activeConversation, setMessages]);
 
+  // The open conversation's persona drives the avatar's motions and whether replies are spoken.
+  const activePersonaId = activeConversation?.conversation.personaId ?? null;
+  const activePersona = useMemo(
+    () => personas.find((p) => p.id === activePersonaId) ?? null,
+    [activePersonaId, personas]
+  );
+
+  useEffect(() => {
+    setPersonaEmotionOverrides(activePersona?.emotionProfile);
+  }, [activePersona]);
+
+  const activePersonaVoice = activePersona?.voice ?? null;
+  useEffect(() => {
+    if (activePersonaVoice) setVoiceMode(true);
+  }, [activeConversationId, activePersonaVoice]);
+
   const {
     handleEditMessage,
     handleRegenerateFrom,
+    handleSelectVariant,
+    handleTogglePin,
+    handleContinue,
     handleBranchFrom,
     handleStop,
     handleDeleteConversation,
@@ -160,23 +193,28 @@ function App() {
   } = useConversationActions({
     activeConversationId,
     windowMode,
+    chatSettings,
     messages,
     sendMessage,
+    regenerate,
     setMessages,
     stop,
     busy,
     isConversationGenerating,
+    markGenerating,
     clearGenerating,
     forkConversation,
     deleteConversation,
     renameConversation,
     selectConversation,
+    loadConversation,
     markSeen,
   });
 
   // Input ref for focus handling + width measurement
   const inputRef = useRef<HTMLTextAreaElement>(null);
   const [inputValue, setInputValue] = useState("");
+  const [pendingImages, setPendingImages] = useState<FileUIPart[]>([]);
   const { collapse, toggleExpand } = useToggleExpand({
     windowMode,
     changeMode,
@@ -281,6 +319,40 @@ function App() {
 
   useTauriEvent<ChatDonePayload>(EVT_CHAT_DONE, handleChatDone);
 
+  const handleImportProgress = useCallback(
+    (event: { payload: ImportProgress }) => {
+      const { processed, total, imported } = event.payload;
+      if (processed < total || imported === 0) return;
+      void refreshList().catch(
+        reportPromiseError("App.refreshHistory:import", {
+          onceKey: "App.refreshHistory:import",
+        })
+      );
+    },
+    [refreshList]
+  );
+
+  useTauriEvent<ImportProgress>(
+    EVT_HISTORY_IMPORT_PROGRESS,
+    handleImportProgress
+  );
+
+  const handleRefreshHistory = useCallback(() => {
+    void refreshList().catch(
+      reportPromiseError("App.refreshHistory:settings", {
+        onceKey: "App.refreshHistory:settings",
+      })
+    );
+  }, [refreshList]);
+
+  const handleRefreshPersonas = useCallback(() => {
+    void refreshPersonas().catch(
+      reportPromiseError("App.refreshPersonas", {
+        onceKey: "App.refreshPersonas",
+      })
+    );
+  }, [refreshPersonas]);
+
   const errorText =
     status === "error" && error ? error.message || String(error) : null;
 
@@ -327,9 +399,11 @@ function App() {
     },
     onSubmit: async () => {
       const textToSend = inputValue.trim();
-      if (!textToSend) return;
+      const files = pendingImages;
+      if (!textToSend && files.length === 0) return;
 
       setInputValue("");
+      setPendingImages([]);
       if (activeConversationId) {
         void markSeen(activeConversationId).catch(
           reportPromiseError("App.markSeen:send", {
@@ -338,9 +412,23 @@ function App() {
         );
         markGenerating(activeConversationId);
       }
-      sendMessage({ text: textToSend });
+      sendMessage(
+        files.length > 0 ? { text: textToSend, files } : { text: textToSend }
+      );
     },
     onStop: handleStop,
+    images: pendingImages,
+    onAddImages: (files: File[]) => {
+      void Promise.all(files.map(readImageFile))
+        .then((parts) => setPendingImages((prev) => [...prev, ...parts]))
+        .catch(
+          reportPromiseError("App.readImageFile", {
+            onceKey: "App.readImageFile",
+          })
+        );
+    },
+    onRemoveImage: (index: number) =>
+      setPendingImages((prev) => prev.filter((_, i) => i !== index)),
     onOpenSettings: openSettings,
     conversations,
     activeConversationId,
@@ -356,6 +444,16 @@ function App() {
     model: selectedModel,
     modelOptions,
     onModelChange: setSelectedModel,
+    personaId: activePersonaId,
+    personas,
+    onPersonaChange: activeConversationId
+      ? (personaId: string | null) =>
+          void setConversationPersona(activeConversationId, personaId).catch(
+            reportPromiseError("App.setConversationPersona", {
+              onceKey: "App.setConversationPersona",
+            })
+          )
+      : undefined,
     toolMode,
     onToolModeChange: setToolMode,
     voiceMode,
@@ -374,6 +472,9 @@ function App() {
       ? () => loadOlderMessages(activeConversationId)
       : undefined,
     onRegenerate: handleRegenerateFrom,
+    onSelectVariant: activeConversationId ? handleSelectVariant : undefined,
+    onTogglePin: activeConversationId ? handleTogglePin : undefined,
+    onContinue: activeConversationId ? handleContinue : undefined,
     onBranch: activeConversationId ? handleBranchFrom : undefined,
     onEditMessage: handleEditMessage,
   };
@@ -414,6 +515,8 @@ function App() {
               <SettingsView
                 aiConfig={aiConfig}
                 onRefreshAiConfig={refreshAiConfig}
+                onRefreshHistory={handleRefreshHistory}
+                onRefreshPersonas={handleRefreshPersonas}
                 onClose={closeSettings}
                 skinMode={skinMode}
                 onSkinModeChange={setSkinMode}
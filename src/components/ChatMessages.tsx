@@ -18,6 +18,9 @@ interface ChatMessagesProps {
   hasMoreHistory?: boolean;
   onLoadMoreHistory?: () => void | Promise<unknown>;
   onRegenerate?: (messageId: string) => void;
+  onSelectVariant?: (messageId: string, variant: number) => void;
+  onTogglePin?: (messageId: string, pinned: boolean) => void;
+  onContinue?: (messageId: string) => void;
   onBranch?: (messageId: string) => void | Promise<unknown>;
   onEditMessage?: (messageId: string, newText: string) => void;
 }
@@ -39,6 +42,9 @@ const ChatMessages = ({
   hasMoreHistory = false,
   onLoadMoreHistory,
   onRegenerate,
+  onSelectVariant,
+  onTogglePin,
+  onContinue,
   onBranch,
   onEditMessage,
 }: ChatMessagesProps) => {
@@ -285,6 +291,7 @@ const ChatMessages = ({
   const lastAssistantId = [...messages]
     .reverse()
     .find((message) => message.role === "assistant")?.id;
+  const lastMessageId = messages[messages.length - 1]?.id;
 
   return (
     <div
@@ -326,6 +333,11 @@ const ChatMessages = ({
             const isCopied = copiedMessageId === message.id;
             const isBranching = branchingMessageId === message.id;
             const isBranched = branchedMessageId === message.id;
+            // Only persisted messages (`${conversationId}:${seq}`) can be pinned.
+            const onTogglePinMessage =
+              onTogglePin && conversationId && message.id.startsWith(`${conversationId}:`)
+                ? (pinned: boolean) => onTogglePin(message.id, pinned)
+                : undefined;
 
             if (message.role === "user") {
               return (
@@ -341,6 +353,7 @@ const ChatMessages = ({
                   onCopy={() => handleCopy(message.id, getMessageText(message))}
                   isCopied={isCopied}
                   canEdit={Boolean(onEditMessage)}
+                  onTogglePin={onTogglePinMessage}
                 />
               );
             }
@@ -353,6 +366,17 @@ const ChatMessages = ({
                 onCopy={() => handleCopy(message.id, getMessageText(message))}
                 isCopied={isCopied}
                 onRegenerate={onRegenerate ? () => onRegenerate(message.id) : undefined}
+                onSelectVariant={
+                  onSelectVariant
+                    ? (variant) => onSelectVariant(message.id, variant)
+                    : undefined
+                }
+                onTogglePin={onTogglePinMessage}
+                onContinue={
+                  onContinue && message.id === lastMessageId
+                    ? () => onContinue(message.id)
+                    : undefined
+                }
                 onBranch={onBranch ? () => void handleBranch(message.id) : undefined}
                 onSpeak={() => handleSpeak(getMessageText(message))}
                 isBranching={isBranching}
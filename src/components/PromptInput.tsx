@@ -4,22 +4,26 @@ import {
   useRef,
   type FormEvent,
 } from "react";
+import type { FileUIPart } from "ai";
 import {
   Eye,
   EyeOff,
+  ImagePlus,
   Mic,
   MicOff,
   Plus,
   Settings,
   Volume2,
   VolumeX,
+  XIcon,
 } from "lucide-react";
 import { cn } from "@/lib/utils";
 import type { ModelOption } from "@/constants";
 import { useAutosizeTextarea, useSpeechRecognition } from "@/hooks";
-import type { ConversationSummary } from "@/types";
+import type { ConversationSummary, Persona } from "@/types";
 import { HistoryDropdown } from "@/components/prompt/HistoryDropdown";
 import { ModelSelector } from "@/components/prompt/ModelSelector";
+import { PersonaSelector } from "@/components/prompt/PersonaSelector";
 
 interface PromptInputProps {
   value: string;
@@ -27,6 +31,10 @@ interface PromptInputProps {
   onSubmit: () => void;
   onVoiceSubmit?: (text: string) => void;
   onStop?: () => void;
+  /** Images attached to the next message. */
+  images?: FileUIPart[];
+  onAddImages?: (files: File[]) => void;
+  onRemoveImage?: (index: number) => void;
   onOpenSettings?: () => void;
   conversations?: ConversationSummary[];
   activeConversationId?: string | null;
@@ -42,6 +50,10 @@ interface PromptInputProps {
   model: string;
   modelOptions: ModelOption[];
   onModelChange: (model: string) => void;
+  /** Persona of the open conversation (`null` = none). */
+  personaId?: string | null;
+  personas?: Persona[];
+  onPersonaChange?: (personaId: string | null) => void;
   toolMode?: boolean;
   onToolModeChange?: (enabled: boolean) => void;
   voiceMode?: boolean;
@@ -56,6 +68,9 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
       onSubmit,
       onVoiceSubmit,
       onStop,
+      images = [],
+      onAddImages,
+      onRemoveImage,
       onOpenSettings,
       conversations = [],
       activeConversationId,
@@ -71,6 +86,9 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
       model,
       modelOptions,
       onModelChange,
+      personaId = null,
+      personas = [],
+      onPersonaChange,
       toolMode = false,
       onToolModeChange,
       voiceMode = false,
@@ -79,6 +97,7 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
     ref
   ) => {
     const textareaRef = useRef<HTMLTextAreaElement>(null);
+    const fileInputRef = useRef<HTMLInputElement>(null);
     useImperativeHandle(ref, () => textareaRef.current as HTMLTextAreaElement);
 
     const { isListening, toggleListening, lastError: voiceInputError } = useSpeechRecognition({
@@ -98,7 +117,15 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
         onStop?.();
         return;
       }
-      if (value.trim()) onSubmit();
+      if (value.trim() || images.length > 0) onSubmit();
+    };
+
+    const pickImages = (files: Iterable<File> | null) => {
+      const picked = Array.from(files ?? []).filter((f) =>
+        f.type.startsWith("image/")
+      );
+      if (picked.length > 0) onAddImages?.(picked);
+      return picked.length > 0;
     };
 
     const { autoResize } = useAutosizeTextarea(textareaRef, value);
@@ -117,6 +144,29 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
         )}
         onSubmit={handleSubmit}
       >
+        {images.length > 0 ? (
+          <div className="flex flex-wrap gap-2 px-1">
+            {images.map((image, index) => (
+              <div key={`${index}-${image.url.length}`} className="relative">
+                <img
+                  src={image.url}
+                  alt={image.filename ?? "image"}
+                  className="h-14 w-14 rounded-md border border-border/50 object-cover"
+                />
+                <button
+                  type="button"
+                  className="absolute -right-1.5 -top-1.5 inline-flex h-4 w-4 items-center justify-center rounded-full bg-background text-muted-foreground hover:text-foreground"
+                  onClick={() => onRemoveImage?.(index)}
+                  onPointerDown={(e) => e.stopPropagation()}
+                  title="移除图片"
+                >
+                  <XIcon className="size-3" />
+                </button>
+              </div>
+            ))}
+          </div>
+        ) : null}
+
         <textarea
           ref={textareaRef}
           className={cn(
@@ -136,10 +186,25 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
               submitOrStop();
             }
           }}
+          onPaste={(e) => {
+            if (!onAddImages) return;
+            if (pickImages(e.clipboardData.files)) e.preventDefault();
+          }}
           onPointerDown={(e) => e.stopPropagation()}
           disabled={disabled}
           rows={1}
         />
+        <input
+          ref={fileInputRef}
+          type="file"
+          accept="image/*"
+          multiple
+          className="hidden"
+          onChange={(e) => {
+            pickImages(e.target.files);
+            e.target.value = "";
+          }}
+        />
 
         <div className="flex flex-wrap items-center gap-2">
           <div className="flex min-w-0 flex-1 flex-wrap items-center gap-1">
@@ -223,6 +288,21 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
               )}
             </button>
 
+            <button
+              type="button"
+              className={cn(
+                "inline-flex h-7 w-7 shrink-0 items-center justify-center rounded-md",
+                "text-muted-foreground transition-colors hover:bg-white/10 hover:text-foreground",
+                "disabled:pointer-events-none disabled:opacity-50"
+              )}
+              disabled={disabled || !onAddImages}
+              onClick={() => fileInputRef.current?.click()}
+              onPointerDown={(e) => e.stopPropagation()}
+              title="添加图片"
+            >
+              <ImagePlus className="size-4" />
+            </button>
+
             <button
               type="button"
               className={cn(
@@ -244,6 +324,15 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
               )}
             </button>
 
+            {onPersonaChange && personas.length > 0 && (
+              <PersonaSelector
+                personaId={personaId}
+                personas={personas}
+                disabled={disabled || isGenerating}
+                onPersonaChange={onPersonaChange}
+              />
+            )}
+
             <ModelSelector
               model={model}
               modelOptions={modelOptions}
@@ -261,7 +350,9 @@ export const PromptInput = forwardRef<HTMLTextAreaElement, PromptInputProps>(
                 : "bg-blue-500/90 hover:bg-blue-500",
               "disabled:cursor-not-allowed disabled:opacity-50"
             )}
-            disabled={disabled || (!isGenerating && !value.trim())}
+            disabled={
+              disabled || (!isGenerating && !value.trim() && images.length === 0)
+            }
             onPointerDown={(e) => e.stopPropagation()}
           >
             {isGenerating ? "Stop" : "Send"}
@@ -1,11 +1,16 @@
 import type { UIMessage } from "ai";
 import {
   CheckIcon,
+  ChevronLeftIcon,
+  ChevronRightIcon,
   CopyIcon,
   GitBranchIcon,
   Loader2,
+  PinIcon,
+  PinOffIcon,
   PlayIcon,
   RefreshCcwIcon,
+  StepForwardIcon,
 } from "lucide-react";
 
 import {
@@ -20,6 +25,7 @@ import {
   ReasoningContent,
   ReasoningTrigger,
 } from "@/components/ai-elements/reasoning";
+import { getMessagePinned, getMessageStatus, getMessageVariants } from "@/utils";
 
 type AssistantMessageProps = {
   message: UIMessage;
@@ -27,6 +33,12 @@ type AssistantMessageProps = {
   onCopy: () => void;
   isCopied: boolean;
   onRegenerate?: () => void;
+  /** Show another stored answer of this turn. */
+  onSelectVariant?: (variant: number) => void;
+  /** Keep this message when the context is trimmed (persisted messages only). */
+  onTogglePin?: (pinned: boolean) => void;
+  /** Resume an interrupted answer (only offered for the last message). */
+  onContinue?: () => void;
   onBranch?: () => void;
   onSpeak?: () => void;
   isBranching?: boolean;
@@ -39,11 +51,18 @@ export default function AssistantMessage({
   onCopy,
   isCopied,
   onRegenerate,
+  onSelectVariant,
+  onTogglePin,
+  onContinue,
   onBranch,
   onSpeak,
   isBranching = false,
   isBranched = false,
 }: AssistantMessageProps) {
+  const variants = onSelectVariant ? getMessageVariants(message) : null;
+  const status = getMessageStatus(message);
+  const pinned = getMessagePinned(message);
+
   return (
     <Message from="assistant">
       <MessageContent className="select-text">
@@ -104,10 +123,49 @@ export default function AssistantMessage({
 
           return null;
         })}
+        {status !== "complete" && (
+          <div className="text-xs text-muted-foreground">
+            {status === "aborted"
+              ? "Stopped before finishing"
+              : "Failed before finishing"}
+          </div>
+        )}
       </MessageContent>
 
       {!isStreaming && (
         <MessageActions>
+          {variants && onSelectVariant && (
+            <>
+              <MessageAction
+                label="Previous answer"
+                tooltip="Previous answer"
+                onClick={() => onSelectVariant(variants.active - 1)}
+                disabled={variants.active <= 0}
+              >
+                <ChevronLeftIcon className="size-3" />
+              </MessageAction>
+              <span className="text-xs tabular-nums text-muted-foreground">
+                {variants.active + 1}/{variants.count}
+              </span>
+              <MessageAction
+                label="Next answer"
+                tooltip="Next answer"
+                onClick={() => onSelectVariant(variants.active + 1)}
+                disabled={variants.active >= variants.count - 1}
+              >
+                <ChevronRightIcon className="size-3" />
+              </MessageAction>
+            </>
+          )}
+          {onContinue && status !== "complete" && (
+            <MessageAction
+              label="Continue"
+              tooltip="Continue generating"
+              onClick={onContinue}
+            >
+              <StepForwardIcon className="size-3" />
+            </MessageAction>
+          )}
           {onRegenerate && (
             <MessageAction
               label="Retry"
@@ -140,6 +198,19 @@ export default function AssistantMessage({
               )}
             </MessageAction>
           )}
+          {onTogglePin && (
+            <MessageAction
+              label={pinned ? "Unpin" : "Pin"}
+              tooltip={pinned ? "Unpin message" : "Always keep in context"}
+              onClick={() => onTogglePin(!pinned)}
+            >
+              {pinned ? (
+                <PinOffIcon className="size-3" />
+              ) : (
+                <PinIcon className="size-3" />
+              )}
+            </MessageAction>
+          )}
           {onSpeak && (
             <MessageAction label="Play" tooltip="Play audio" onClick={onSpeak}>
               <PlayIcon className="size-3" />
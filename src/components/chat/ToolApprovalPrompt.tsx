@@ -0,0 +1,109 @@
+import { useCallback, useState } from "react";
+import type { Event } from "@tauri-apps/api/event";
+import { ShieldAlert } from "lucide-react";
+
+import { Button } from "@/components/ui/button";
+import {
+  EVT_CHAT_DONE,
+  EVT_CHAT_TOOL_CALL,
+  EVT_TOOL_APPROVAL_REQUEST,
+} from "@/constants";
+import { useTauriEvents } from "@/hooks";
+import {
+  respondToolApproval,
+  type ToolApprovalDecision,
+  type ToolApprovalRequest,
+} from "@/services";
+import { reportPromiseError } from "@/utils";
+
+type ToolCallEvent = { type: "started" | "finished"; callId: string };
+type ChatDoneEvent = { requestId: string };
+
+/** Consent prompts for sensitive tool calls, oldest first. */
+export default function ToolApprovalPrompt() {
+  const [queue, setQueue] = useState<ToolApprovalRequest[]>([]);
+
+  const drop = useCallback(
+    (match: (request: ToolApprovalRequest) => boolean) =>
+      setQueue((prev) => prev.filter((request) => !match(request))),
+    []
+  );
+
+  useTauriEvents({
+    [EVT_TOOL_APPROVAL_REQUEST]: (event) => {
+      const request = (event as Event<ToolApprovalRequest>).payload;
+      setQueue((prev) => [...prev, request]);
+    },
+    // Answered elsewhere, timed out or aborted: the call finishes either way.
+    [EVT_CHAT_TOOL_CALL]: (event) => {
+      const payload = (event as Event<ToolCallEvent>).payload;
+      if (payload.type === "finished") {
+        drop((request) => request.callId === payload.callId);
+      }
+    },
+    [EVT_CHAT_DONE]: (event) => {
+      const { requestId } = (event as Event<ChatDoneEvent>).payload;
+      drop((request) => request.requestId === requestId);
+    },
+  });
+
+  const respond = useCallback(
+    (request: ToolApprovalRequest, decision: ToolApprovalDecision) => {
+      drop((item) => item.approvalId === request.approvalId);
+      void respondToolApproval(request.approvalId, decision).catch(
+        reportPromiseError("ToolApprovalPrompt.respond")
+      );
+    },
+    [drop]
+  );
+
+  const current = queue[0];
+  if (!current) return null;
+
+  const target = [current.app, current.window].filter(Boolean).join(" · ");
+
+  return (
+    <div className="grid gap-2 rounded-md border border-amber-400/30 bg-amber-950/35 px-3 py-2 text-xs text-amber-50/90">
+      <div className="flex items-start gap-2">
+        <ShieldAlert className="mt-0.5 size-4 shrink-0 text-amber-300" />
+        <div className="min-w-0">
+          <div>
+            AI 请求调用 <span className="font-mono">{current.name}</span>
+            {queue.length > 1 ? `（还有 ${queue.length - 1} 个待确认）` : null}
+          </div>
+          {target ? (
+            <div className="truncate opacity-80" title={target}>
+              {target}
+            </div>
+          ) : null}
+        </div>
+      </div>
+      <div className="flex justify-end gap-1">
+        <Button
+          type="button"
+          size="sm"
+          variant="ghost"
+          onClick={() => respond(current, "deny")}
+        >
+          拒绝
+        </Button>
+        <Button
+          type="button"
+          size="sm"
+          variant="ghost"
+          onClick={() => respond(current, "alwaysAllow")}
+          title={current.app ? `以后允许读取 ${current.app}` : undefined}
+        >
+          始终允许
+        </Button>
+        <Button
+          type="button"
+          size="sm"
+          onClick={() => respond(current, "allowOnce")}
+        >
+          允许一次
+        </Button>
+      </div>
+    </div>
+  );
+}
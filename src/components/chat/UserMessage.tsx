@@ -1,5 +1,12 @@
 import type { UIMessage } from "ai";
-import { CheckIcon, CopyIcon, PencilIcon, XIcon } from "lucide-react";
+import {
+  CheckIcon,
+  CopyIcon,
+  PencilIcon,
+  PinIcon,
+  PinOffIcon,
+  XIcon,
+} from "lucide-react";
 
 import {
   Message,
@@ -8,7 +15,9 @@ import {
   MessageContent,
 } from "@/components/ai-elements/message";
 
-import { getMessageText } from "./messageText";
+import { getMessagePinned } from "@/utils";
+
+import { getImageUrls, getMessageText } from "./messageText";
 
 type UserMessageProps = {
   message: UIMessage;
@@ -21,6 +30,8 @@ type UserMessageProps = {
   onCopy: () => void;
   isCopied: boolean;
   canEdit: boolean;
+  /** Keep this message when the context is trimmed (persisted messages only). */
+  onTogglePin?: (pinned: boolean) => void;
 };
 
 export default function UserMessage({
@@ -34,7 +45,11 @@ export default function UserMessage({
   onCopy,
   isCopied,
   canEdit,
+  onTogglePin,
 }: UserMessageProps) {
+  const imageUrls = getImageUrls(message);
+  const pinned = getMessagePinned(message);
+
   return (
     <Message from="user">
       <MessageContent className="select-text">
@@ -73,7 +88,21 @@ export default function UserMessage({
             </div>
           </div>
         ) : (
-          <span className="select-text">{getMessageText(message)}</span>
+          <>
+            {imageUrls.length > 0 ? (
+              <div className="flex flex-wrap gap-2">
+                {imageUrls.map((url, index) => (
+                  <img
+                    key={index}
+                    src={url}
+                    alt=""
+                    className="max-h-40 max-w-full rounded-md object-contain"
+                  />
+                ))}
+              </div>
+            ) : null}
+            <span className="select-text">{getMessageText(message)}</span>
+          </>
         )}
       </MessageContent>
 
@@ -90,6 +119,19 @@ export default function UserMessage({
               <CopyIcon className="size-3" />
             )}
           </MessageAction>
+          {onTogglePin && (
+            <MessageAction
+              label={pinned ? "Unpin" : "Pin"}
+              tooltip={pinned ? "Unpin message" : "Always keep in context"}
+              onClick={() => onTogglePin(!pinned)}
+            >
+              {pinned ? (
+                <PinOffIcon className="size-3" />
+              ) : (
+                <PinIcon className="size-3" />
+              )}
+            </MessageAction>
+          )}
           {canEdit && (
             <MessageAction
               label="Edit"
@@ -1 +1 @@
-export { getMessageText } from "@/utils";
+export { getImageUrls, getMessageText } from "@/utils";
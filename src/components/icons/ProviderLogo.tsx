@@ -1,7 +1,9 @@
 import type { AiProvider } from "@/types";
 import { cn } from "@/lib/utils";
 
+import anthropicSvg from "../../../anthropic.svg?raw";
 import deepseekSvg from "../../../deepseek-color.svg?raw";
+import ollamaSvg from "../../../ollama.svg?raw";
 import openaiSvg from "../../../openai.svg?raw";
 import vercelSvg from "../../../vercel.svg?raw";
 
@@ -15,6 +17,8 @@ const PROVIDER_SVGS: Record<AiProvider, string> = {
   deepseek: deepseekSvg,
   openai: openaiSvg,
   compatible: vercelSvg,
+  anthropic: anthropicSvg,
+  ollama: ollamaSvg,
 };
 
 export function ProviderLogo({ provider, className, title }: ProviderLogoProps) {
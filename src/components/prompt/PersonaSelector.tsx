@@ -0,0 +1,53 @@
+import type { Persona } from "@/types";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+/** Select value for "no persona" (Radix items cannot use an empty value). */
+const NO_PERSONA = "__none__";
+
+type PersonaSelectorProps = {
+  personaId: string | null;
+  personas: Persona[];
+  disabled?: boolean;
+  onPersonaChange: (personaId: string | null) => void;
+};
+
+export function PersonaSelector({
+  personaId,
+  personas,
+  disabled = false,
+  onPersonaChange,
+}: PersonaSelectorProps) {
+  // A deleted persona behaves like none (the backend falls back to the default prompt).
+  const value =
+    personaId && personas.some((p) => p.id === personaId) ? personaId : NO_PERSONA;
+
+  return (
+    <Select
+      value={value}
+      onValueChange={(next) => onPersonaChange(next === NO_PERSONA ? null : next)}
+      disabled={disabled}
+    >
+      <SelectTrigger
+        className="min-w-[80px] shrink"
+        title="对话角色"
+        onPointerDown={(e) => e.stopPropagation()}
+      >
+        <SelectValue />
+      </SelectTrigger>
+      <SelectContent side="bottom" align="end" sideOffset={4}>
+        <SelectItem value={NO_PERSONA}>无角色</SelectItem>
+        {personas.map((p) => (
+          <SelectItem key={p.id} value={p.id} title={p.name}>
+            {p.name}
+          </SelectItem>
+        ))}
+      </SelectContent>
+    </Select>
+  );
+}
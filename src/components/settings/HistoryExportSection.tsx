@@ -0,0 +1,82 @@
+import { useCallback, useState } from "react";
+
+import { Button } from "@/components/ui/button";
+import { cn } from "@/lib/utils";
+import { historyExportAll } from "@/services";
+import type { ExportFormat } from "@/types";
+
+const inputClassName = cn(
+  "h-8 w-full rounded-md border border-border/50 bg-background/40 px-2 text-xs text-foreground",
+  "placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring"
+);
+
+const FORMATS: { value: ExportFormat; label: string }[] = [
+  { value: "markdown", label: "Markdown" },
+  { value: "html", label: "HTML" },
+  { value: "json", label: "JSON" },
+];
+
+/** Export every conversation (one file each) into a folder. */
+export function HistoryExportSection() {
+  const [dir, setDir] = useState("");
+  const [format, setFormat] = useState<ExportFormat>("markdown");
+  const [exporting, setExporting] = useState(false);
+  const [result, setResult] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleExport = useCallback(() => {
+    const target = dir.trim();
+    if (!target) return;
+    setExporting(true);
+    setResult(null);
+    setError(null);
+    void historyExportAll(format, target)
+      .then((files) => setResult(`已导出 ${files.length} 个对话`))
+      .catch((err) => setError(String(err)))
+      .finally(() => setExporting(false));
+  }, [dir, format]);
+
+  return (
+    <>
+      <div className="text-xs font-semibold text-foreground/80">导出对话</div>
+      <div className="grid gap-2 rounded-lg border border-border/50 bg-background/40 px-3 py-2">
+        <input
+          className={inputClassName}
+          value={dir}
+          onChange={(e) => setDir(e.target.value)}
+          placeholder="导出目录（绝对路径）"
+          spellCheck={false}
+        />
+        <div className="flex items-center gap-1">
+          {FORMATS.map((option) => (
+            <Button
+              key={option.value}
+              type="button"
+              size="sm"
+              variant={format === option.value ? "secondary" : "ghost"}
+              onClick={() => setFormat(option.value)}
+              disabled={exporting}
+            >
+              {option.label}
+            </Button>
+          ))}
+          <Button
+            type="button"
+            size="sm"
+            className="ml-auto"
+            onClick={handleExport}
+            disabled={exporting || !dir.trim()}
+          >
+            {exporting ? "导出中…" : "全部导出"}
+          </Button>
+        </div>
+        <div className="text-xs opacity-70">
+          每个对话一个文件，包含时间、模型与可折叠的思考过程；再次导出会覆盖同名文件。
+        </div>
+
+        {result ? <div className="text-xs text-green-300/90">{result}</div> : null}
+        {error ? <div className="text-xs text-red-200/90">{error}</div> : null}
+      </div>
+    </>
+  );
+}
@@ -0,0 +1,116 @@
+import { useCallback, useState } from "react";
+import type { Event } from "@tauri-apps/api/event";
+
+import { Button } from "@/components/ui/button";
+import { EVT_HISTORY_IMPORT_PROGRESS } from "@/constants";
+import { useTauriEvents } from "@/hooks";
+import { cn } from "@/lib/utils";
+import { historyImport } from "@/services";
+import type { ImportFormat, ImportProgress } from "@/types";
+
+const inputClassName = cn(
+  "h-8 w-full rounded-md border border-border/50 bg-background/40 px-2 text-xs text-foreground",
+  "placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring"
+);
+
+type FormatOption = {
+  value: ImportFormat;
+  label: string;
+  placeholder: string;
+};
+
+const FORMATS: FormatOption[] = [
+  {
+    value: "chatGpt",
+    label: "ChatGPT",
+    placeholder: "conversations.json 的绝对路径",
+  },
+  {
+    value: "deepSeek",
+    label: "DeepSeek",
+    placeholder: "conversations.json 的绝对路径",
+  },
+  { value: "jsonl", label: "JSONL", placeholder: ".jsonl 文件的绝对路径" },
+];
+
+const describe = ({ processed, total, imported, skipped }: ImportProgress) =>
+  `${processed}/${total}：导入 ${imported}，跳过 ${skipped}`;
+
+/** Import conversations from other chat apps' export files. */
+export function HistoryImportSection() {
+  const [path, setPath] = useState("");
+  const [format, setFormat] = useState<ImportFormat>("chatGpt");
+  const [importing, setImporting] = useState(false);
+  const [progress, setProgress] = useState<ImportProgress | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useTauriEvents({
+    [EVT_HISTORY_IMPORT_PROGRESS]: (event) => {
+      setProgress((event as Event<ImportProgress>).payload);
+    },
+  });
+
+  const handleImport = useCallback(() => {
+    const target = path.trim();
+    if (!target) return;
+    setImporting(true);
+    setProgress(null);
+    setError(null);
+    void historyImport(format, target)
+      .then(setProgress)
+      .catch((err) => setError(String(err)))
+      .finally(() => setImporting(false));
+  }, [format, path]);
+
+  const current =
+    FORMATS.find((option) => option.value === format) ?? FORMATS[0];
+
+  return (
+    <>
+      <div className="text-xs font-semibold text-foreground/80">导入对话</div>
+      <div className="grid gap-2 rounded-lg border border-border/50 bg-background/40 px-3 py-2">
+        <div className="flex items-center gap-1">
+          {FORMATS.map((option) => (
+            <Button
+              key={option.value}
+              type="button"
+              size="sm"
+              variant={format === option.value ? "secondary" : "ghost"}
+              onClick={() => setFormat(option.value)}
+              disabled={importing}
+            >
+              {option.label}
+            </Button>
+          ))}
+        </div>
+        <div className="flex items-center gap-2">
+          <input
+            className={inputClassName}
+            value={path}
+            onChange={(e) => setPath(e.target.value)}
+            placeholder={current.placeholder}
+            spellCheck={false}
+          />
+          <Button
+            type="button"
+            size="sm"
+            onClick={handleImport}
+            disabled={importing || !path.trim()}
+          >
+            {importing ? "导入中…" : "导入"}
+          </Button>
+        </div>
+        <div className="text-xs opacity-70">
+          保留原始时间；已导入过的对话会被跳过，可放心重复导入。
+        </div>
+
+        {progress ? (
+          <div className="text-xs opacity-80">{describe(progress)}</div>
+        ) : null}
+        {error ? (
+          <div className="text-xs text-red-200/90">{error}</div>
+        ) : null}
+      </div>
+    </>
+  );
+}
@@ -0,0 +1,165 @@
+import { useCallback, useEffect, useState } from "react";
+
+import { Button } from "@/components/ui/button";
+import { cn } from "@/lib/utils";
+import {
+  historyGetTrashRetentionDays,
+  historyListArchived,
+  historyPurgeConversations,
+  historyRestoreConversation,
+  historySetTrashRetentionDays,
+} from "@/services";
+import type { ArchivedConversation } from "@/types";
+import { isTauriContext } from "@/utils";
+
+const inputClassName = cn(
+  "h-8 w-20 rounded-md border border-border/50 bg-background/40 px-2 text-xs text-foreground",
+  "placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring"
+);
+
+const formatDate = (ms: number) => new Date(ms).toLocaleDateString();
+
+type HistoryTrashSectionProps = {
+  /** Called after a restore so the conversation list can reload. */
+  onRestored: () => void;
+};
+
+/** Deleted conversations: restore, purge, and how long they are kept. */
+export function HistoryTrashSection({ onRestored }: HistoryTrashSectionProps) {
+  const [items, setItems] = useState<ArchivedConversation[]>([]);
+  const [retentionDays, setRetentionDays] = useState("");
+  const [busy, setBusy] = useState(false);
+  // Emptying the trash takes a second click.
+  const [confirmEmpty, setConfirmEmpty] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const reload = useCallback(async () => {
+    const [archived, days] = await Promise.all([
+      historyListArchived(),
+      historyGetTrashRetentionDays(),
+    ]);
+    setItems(archived);
+    setRetentionDays(String(days));
+  }, []);
+
+  useEffect(() => {
+    if (!isTauriContext()) return;
+    void reload().catch((err) => setError(String(err)));
+  }, [reload]);
+
+  const run = useCallback(
+    (action: () => Promise<unknown>) => {
+      setBusy(true);
+      setError(null);
+      void action()
+        .then(() => reload())
+        .catch((err) => setError(String(err)))
+        .finally(() => setBusy(false));
+    },
+    [reload]
+  );
+
+  const handleRestore = (id: string) =>
+    run(async () => {
+      await historyRestoreConversation(id);
+      onRestored();
+    });
+
+  const handlePurge = (id: string) =>
+    run(() => historyPurgeConversations([id]));
+
+  const handleEmpty = () => {
+    if (!confirmEmpty) {
+      setConfirmEmpty(true);
+      return;
+    }
+    setConfirmEmpty(false);
+    run(() => historyPurgeConversations());
+  };
+
+  const handleRetentionSave = () => {
+    const days = Number.parseInt(retentionDays, 10);
+    if (!Number.isFinite(days) || days < 0) {
+      setError("保留天数需为非负整数");
+      return;
+    }
+    run(() => historySetTrashRetentionDays(days));
+  };
+
+  return (
+    <>
+      <div className="text-xs font-semibold text-foreground/80">回收站</div>
+      <div className="grid gap-2 rounded-lg border border-border/50 bg-background/40 px-3 py-2">
+        <div className="flex items-center gap-2 text-xs">
+          <span className="opacity-80">删除后保留</span>
+          <input
+            className={inputClassName}
+            inputMode="numeric"
+            value={retentionDays}
+            onChange={(e) => setRetentionDays(e.target.value)}
+            onBlur={handleRetentionSave}
+            disabled={busy}
+          />
+          <span className="opacity-80">天（0 = 不自动清除）</span>
+        </div>
+
+        {items.length === 0 ? (
+          <div className="text-xs opacity-70">回收站是空的。</div>
+        ) : (
+          <div className="grid gap-1">
+            {items.map((item) => (
+              <div
+                key={item.id}
+                className="flex items-center gap-2 rounded-md border border-border/40 px-2 py-1 text-xs"
+              >
+                <div className="min-w-0 flex-1">
+                  <div className="truncate">{item.title}</div>
+                  <div className="opacity-60">
+                    {item.messageCount} 条消息 · 删除于{" "}
+                    {formatDate(item.archivedAtMs)}
+                    {item.purgeAtMs
+                      ? ` · ${formatDate(item.purgeAtMs)} 后清除`
+                      : null}
+                  </div>
+                </div>
+                <Button
+                  type="button"
+                  size="sm"
+                  variant="ghost"
+                  onClick={() => handleRestore(item.id)}
+                  disabled={busy}
+                >
+                  恢复
+                </Button>
+                <Button
+                  type="button"
+                  size="sm"
+                  variant="ghost"
+                  onClick={() => handlePurge(item.id)}
+                  disabled={busy}
+                >
+                  永久删除
+                </Button>
+              </div>
+            ))}
+            <Button
+              type="button"
+              size="sm"
+              variant="ghost"
+              className="justify-self-end"
+              onClick={handleEmpty}
+              onBlur={() => setConfirmEmpty(false)}
+              disabled={busy}
+            >
+              {confirmEmpty
+                ? `确认永久删除 ${items.length} 个对话？`
+                : "清空回收站"}
+            </Button>
+          </div>
+        )}
+
+        {error ? <div className="text-xs text-red-200/90">{error}</div> : null}
+      </div>
+    </>
+  );
+}
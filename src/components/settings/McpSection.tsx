@@ -0,0 +1,153 @@
+import { useCallback, useEffect, useState } from "react";
+
+import { RefreshCw } from "lucide-react";
+
+import { Button } from "@/components/ui/button";
+import { cn } from "@/lib/utils";
+import {
+  getMcpServerStatus,
+  getMcpServers,
+  restartMcpServer,
+  setMcpServers,
+  type McpServerConfig,
+  type McpServerStatus,
+} from "@/services";
+
+const STATUS_POLL_MS = 3000;
+
+const STATE_LABELS: Record<McpServerStatus["state"], string> = {
+  starting: "启动中",
+  running: "运行中",
+  failed: "失败",
+  stopped: "已停止",
+};
+
+const EXAMPLE = `[
+  {
+    "name": "echo",
+    "transport": { "type": "stdio", "command": "python", "args": ["echo_server.py"] }
+  }
+]`;
+
+/** MCP tool servers: JSON config editor plus live status. */
+export function McpSection() {
+  const [draft, setDraft] = useState("");
+  const [statuses, setStatuses] = useState<McpServerStatus[]>([]);
+  const [saving, setSaving] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const refreshStatus = useCallback(() => {
+    void getMcpServerStatus()
+      .then(setStatuses)
+      .catch(() => {});
+  }, []);
+
+  useEffect(() => {
+    void getMcpServers()
+      .then((servers) =>
+        setDraft(servers.length > 0 ? JSON.stringify(servers, null, 2) : "")
+      )
+      .catch((err) => setError(String(err)));
+    refreshStatus();
+    const timer = window.setInterval(refreshStatus, STATUS_POLL_MS);
+    return () => window.clearInterval(timer);
+  }, [refreshStatus]);
+
+  const handleSave = useCallback(() => {
+    let servers: McpServerConfig[];
+    try {
+      servers = draft.trim() ? JSON.parse(draft) : [];
+      if (!Array.isArray(servers)) throw new Error("需要 JSON 数组");
+    } catch (err) {
+      setError(`JSON 无效：${String(err)}`);
+      return;
+    }
+
+    setSaving(true);
+    setError(null);
+    void setMcpServers(servers)
+      .then((saved) => {
+        setDraft(saved.length > 0 ? JSON.stringify(saved, null, 2) : "");
+        refreshStatus();
+      })
+      .catch((err) => setError(String(err)))
+      .finally(() => setSaving(false));
+  }, [draft, refreshStatus]);
+
+  return (
+    <>
+      <div className="text-xs font-semibold text-foreground/80">MCP 工具</div>
+      <div className="grid gap-2 rounded-lg border border-border/50 bg-background/40 px-3 py-2">
+        {statuses.length > 0 ? (
+          <div className="grid gap-1">
+            {statuses.map((s) => (
+              <div key={s.name} className="flex items-center gap-2 text-xs">
+                <span
+                  className={cn(
+                    "size-2 shrink-0 rounded-full",
+                    s.state === "running" && "bg-emerald-400",
+                    s.state === "starting" && "bg-amber-300",
+                    s.state === "failed" && "bg-red-400",
+                    s.state === "stopped" && "bg-muted-foreground"
+                  )}
+                />
+                <span className="font-medium text-foreground/90">
+                  {s.name}
+                </span>
+                <span className="opacity-70">
+                  {STATE_LABELS[s.state]}
+                  {s.state === "running" ? ` · ${s.tools.length} 个工具` : ""}
+                </span>
+                {s.error ? (
+                  <span
+                    className="min-w-0 truncate text-red-200/90"
+                    title={s.error}
+                  >
+                    {s.error}
+                  </span>
+                ) : null}
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon-sm"
+                  className="ml-auto h-6 w-6"
+                  onClick={() =>
+                    void restartMcpServer(s.name)
+                      .then(refreshStatus)
+                      .catch((err) => setError(String(err)))
+                  }
+                  title="重启"
+                >
+                  <RefreshCw className="size-3" />
+                </Button>
+              </div>
+            ))}
+          </div>
+        ) : null}
+
+        <textarea
+          className={cn(
+            "h-32 w-full resize-y rounded-md border border-border/50 bg-background/40 px-2 py-1 font-mono text-xs text-foreground",
+            "placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring"
+          )}
+          value={draft}
+          onChange={(e) => setDraft(e.target.value)}
+          placeholder={EXAMPLE}
+          spellCheck={false}
+        />
+        <div className="flex justify-end">
+          <Button
+            type="button"
+            size="sm"
+            onClick={handleSave}
+            disabled={saving}
+          >
+            保存
+          </Button>
+        </div>
+
+        {error ? <div className="text-xs text-red-200/90">{error}</div> : null}
+      </div>
+    </>
+  );
+}
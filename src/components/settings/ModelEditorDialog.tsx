@@ -4,6 +4,7 @@ import { XIcon } from "lucide-react";
 
 import { Button } from "@/components/ui/button";
 import { cn } from "@/lib/utils";
+import type { ReasoningEffort } from "@/types";
 
 export type ModelEditorDraft = {
   originalId: string | null;
@@ -13,8 +14,48 @@ export type ModelEditorDraft = {
   supportsVision: boolean;
   supportsThink: boolean;
   special: string;
+  temperature: string;
+  topP: string;
+  presencePenalty: string;
+  frequencyPenalty: string;
+  seed: string;
+  stop: string;
+  reasoningEffort: ReasoningEffort | "";
 };
 
+type NumericParamKey =
+  | "temperature"
+  | "topP"
+  | "presencePenalty"
+  | "frequencyPenalty"
+  | "seed";
+
+type NumericParam = {
+  key: NumericParamKey;
+  label: string;
+  placeholder: string;
+};
+
+const NUMERIC_PARAMS: NumericParam[] = [
+  { key: "temperature", label: "Temperature", placeholder: "0 - 2" },
+  { key: "topP", label: "Top P", placeholder: "0 - 1" },
+  { key: "presencePenalty", label: "Presence", placeholder: "-2 - 2" },
+  { key: "frequencyPenalty", label: "Frequency", placeholder: "-2 - 2" },
+  { key: "seed", label: "Seed", placeholder: "整数" },
+];
+
+const REASONING_EFFORTS: ReasoningEffort[] = [
+  "minimal",
+  "low",
+  "medium",
+  "high",
+];
+
+const inputClassName = cn(
+  "h-8 w-full rounded-md border border-border/50 bg-background/40 px-2 text-xs text-foreground",
+  "placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring"
+);
+
 type ModelEditorDialogProps = {
   draft: ModelEditorDraft;
   setDraft: Dispatch<SetStateAction<ModelEditorDraft | null>>;
@@ -40,7 +81,7 @@ export function ModelEditorDialog({
       }}
     >
       <div
-        className="w-full max-w-sm rounded-xl border border-border/50 bg-background/90 p-3 shadow-xl"
+        className="max-h-full w-full max-w-sm overflow-y-auto rounded-xl border border-border/50 bg-background/90 p-3 shadow-xl"
         onPointerDown={(e) => e.stopPropagation()}
         onKeyDown={(e) => {
           if (e.key !== "Escape") return;
@@ -168,6 +209,68 @@ export function ModelEditorDialog({
             />
           </div>
 
+          <div className="grid gap-1">
+            <div className="text-xs opacity-70">
+              生成参数（可选，留空使用服务端默认；服务商不支持的参数会被忽略）
+            </div>
+            <div className="grid grid-cols-2 gap-2">
+              {NUMERIC_PARAMS.map(({ key, label, placeholder }) => (
+                <div key={key} className="grid gap-1">
+                  <div className="text-xs opacity-70">{label}</div>
+                  <input
+                    className={inputClassName}
+                    value={draft[key]}
+                    onChange={(e) =>
+                      setDraft((prev) =>
+                        prev ? { ...prev, [key]: e.target.value } : prev
+                      )
+                    }
+                    placeholder={placeholder}
+                    inputMode="decimal"
+                  />
+                </div>
+              ))}
+              <div className="grid gap-1">
+                <div className="text-xs opacity-70">Reasoning effort</div>
+                <select
+                  className={inputClassName}
+                  value={draft.reasoningEffort}
+                  disabled={!draft.supportsThink}
+                  onChange={(e) =>
+                    setDraft((prev) =>
+                      prev
+                        ? {
+                            ...prev,
+                            reasoningEffort: e.target
+                              .value as ModelEditorDraft["reasoningEffort"],
+                          }
+                        : prev
+                    )
+                  }
+                >
+                  <option value="">默认</option>
+                  {REASONING_EFFORTS.map((effort) => (
+                    <option key={effort} value={effort}>
+                      {effort}
+                    </option>
+                  ))}
+                </select>
+              </div>
+            </div>
+            <div className="grid gap-1">
+              <div className="text-xs opacity-70">Stop（每行一个，最多 4 个）</div>
+              <textarea
+                className={cn(inputClassName, "h-14 resize-none py-1")}
+                value={draft.stop}
+                onChange={(e) =>
+                  setDraft((prev) =>
+                    prev ? { ...prev, stop: e.target.value } : prev
+                  )
+                }
+              />
+            </div>
+          </div>
+
           {errorText ? (
             <div className="text-xs text-red-200/90">{errorText}</div>
           ) : null}
@@ -0,0 +1,294 @@
+import { useCallback, useEffect, useState } from "react";
+
+import { Pencil, Plus, Star, XIcon } from "lucide-react";
+
+import { Button } from "@/components/ui/button";
+import { EMOTION_OPTIONS, type EmotionId } from "@/components/vrm/emotionTypes";
+import { cn } from "@/lib/utils";
+import { getPersonas, setPersonas, type Persona } from "@/services";
+
+type PersonaDraft = {
+  originalId: string | null;
+  name: string;
+  systemPrompt: string;
+  model: string;
+  voice: string;
+  /** Motion id per emotion (blank = keep the VRM model's own mapping). */
+  motions: Partial<Record<EmotionId, string>>;
+};
+
+const emptyDraft = (): PersonaDraft => ({
+  originalId: null,
+  name: "",
+  systemPrompt: "",
+  model: "",
+  voice: "",
+  motions: {},
+});
+
+const draftOf = (p: Persona): PersonaDraft => ({
+  originalId: p.id,
+  name: p.name,
+  systemPrompt: p.systemPrompt,
+  model: p.model ?? "",
+  voice: p.voice ?? "",
+  motions: Object.fromEntries(
+    Object.entries(p.emotionProfile ?? {}).map(([emotion, mapping]) => [
+      emotion,
+      mapping?.motionId ?? "",
+    ])
+  ),
+});
+
+const inputClassName = cn(
+  "h-8 w-full rounded-md border border-border/50 bg-background/40 px-2 text-xs text-foreground",
+  "placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring"
+);
+
+type PersonaSectionProps = {
+  /** Called after the list was saved (e.g. to refresh the conversation persona picker). */
+  onSaved?: () => void;
+};
+
+/**
+ * Persona list (system prompt / model / voice / avatar motions); the default one is used for new
+ * conversations.
+ */
+export function PersonaSection({ onSaved }: PersonaSectionProps) {
+  const [personas, setPersonaList] = useState<Persona[]>([]);
+  const [defaultPersonaId, setDefaultPersonaId] = useState<string | null>(
+    null
+  );
+  const [draft, setDraft] = useState<PersonaDraft | null>(null);
+  const [saving, setSaving] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    void getPersonas()
+      .then((settings) => {
+        setPersonaList(settings.personas);
+        setDefaultPersonaId(settings.defaultPersonaId);
+      })
+      .catch((err) => setError(String(err)));
+  }, []);
+
+  const save = useCallback(
+    (next: Persona[], nextDefault: string | null) => {
+      setSaving(true);
+      setError(null);
+      return setPersonas(next, nextDefault)
+        .then((settings) => {
+          setPersonaList(settings.personas);
+          setDefaultPersonaId(settings.defaultPersonaId);
+          onSaved?.();
+          return true;
+        })
+        .catch((err) => {
+          setError(String(err));
+          return false;
+        })
+        .finally(() => setSaving(false));
+    },
+    [onSaved]
+  );
+
+  const handleConfirmDraft = useCallback(() => {
+    if (!draft) return;
+    if (!draft.name.trim()) {
+      setError("角色名称不能为空");
+      return;
+    }
+
+    const original = personas.find((p) => p.id === draft.originalId);
+    const emotionProfile: NonNullable<Persona["emotionProfile"]> = {};
+    EMOTION_OPTIONS.forEach(({ id }) => {
+      const motionId = draft.motions[id]?.trim();
+      if (!motionId) return;
+      emotionProfile[id] = {
+        motionId,
+        loopMotion: original?.emotionProfile?.[id]?.loopMotion ?? null,
+      };
+    });
+    const persona: Persona = {
+      ...original,
+      id: original?.id ?? "",
+      name: draft.name,
+      systemPrompt: draft.systemPrompt,
+      model: draft.model.trim() || null,
+      voice: draft.voice.trim() || null,
+      emotionProfile,
+    };
+    const next = original
+      ? personas.map((p) => (p.id === original.id ? persona : p))
+      : [...personas, persona];
+
+    void save(next, defaultPersonaId).then((ok) => {
+      if (ok) setDraft(null);
+    });
+  }, [defaultPersonaId, draft, personas, save]);
+
+  const handleRemove = useCallback(
+    (id: string) => {
+      void save(
+        personas.filter((p) => p.id !== id),
+        defaultPersonaId === id ? null : defaultPersonaId
+      );
+    },
+    [defaultPersonaId, personas, save]
+  );
+
+  const handleToggleDefault = useCallback(
+    (id: string) => {
+      void save(personas, defaultPersonaId === id ? null : id);
+    },
+    [defaultPersonaId, personas, save]
+  );
+
+  return (
+    <>
+      <div className="text-xs font-semibold text-foreground/80">角色</div>
+      <div className="grid gap-2 rounded-lg border border-border/50 bg-background/40 px-3 py-2">
+        <div className="text-xs opacity-70">
+          默认角色用于新对话；对话会记住所用角色，可在输入框旁切换
+        </div>
+        {personas.length > 0 ? (
+          <div className="flex flex-wrap gap-1">
+            {personas.map((p) => (
+              <div
+                key={p.id}
+                className="inline-flex items-center gap-1 rounded-md border border-border/50 bg-background/30 px-1 py-1"
+              >
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon-sm"
+                  className="h-6 w-6"
+                  onClick={() => handleToggleDefault(p.id)}
+                  disabled={saving}
+                  title={defaultPersonaId === p.id ? "取消默认" : "设为默认"}
+                >
+                  <Star
+                    className={cn(
+                      "size-3",
+                      defaultPersonaId === p.id && "fill-current"
+                    )}
+                  />
+                </Button>
+                <span className="max-w-[140px] truncate px-1 text-xs text-foreground/90">
+                  {p.name}
+                </span>
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon-sm"
+                  className="h-6 w-6"
+                  onClick={() => setDraft(draftOf(p))}
+                  disabled={saving}
+                  title="编辑"
+                >
+                  <Pencil className="size-3" />
+                </Button>
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon-sm"
+                  className="h-6 w-6"
+                  onClick={() => handleRemove(p.id)}
+                  disabled={saving}
+                  title="删除"
+                >
+                  <XIcon className="size-3" />
+                </Button>
+              </div>
+            ))}
+          </div>
+        ) : null}
+
+        {draft ? (
+          <div className="grid gap-2">
+            <input
+              className={inputClassName}
+              value={draft.name}
+              onChange={(e) => setDraft({ ...draft, name: e.target.value })}
+              placeholder="名称"
+              autoFocus
+            />
+            <textarea
+              className={cn(inputClassName, "h-24 resize-y py-1")}
+              value={draft.systemPrompt}
+              onChange={(e) =>
+                setDraft({ ...draft, systemPrompt: e.target.value })
+              }
+              placeholder="系统提示词（留空使用默认提示词）"
+            />
+            <div className="grid grid-cols-2 gap-2">
+              <input
+                className={inputClassName}
+                value={draft.model}
+                onChange={(e) => setDraft({ ...draft, model: e.target.value })}
+                placeholder="默认模型（可选）"
+              />
+              <input
+                className={inputClassName}
+                value={draft.voice}
+                onChange={(e) => setDraft({ ...draft, voice: e.target.value })}
+                placeholder="语音（可选，设置后自动朗读）"
+              />
+            </div>
+            <div className="text-xs opacity-70">
+              情绪动作（留空沿用模型设置）
+            </div>
+            <div className="grid grid-cols-2 gap-2">
+              {EMOTION_OPTIONS.map(({ id, label }) => (
+                <input
+                  key={id}
+                  className={inputClassName}
+                  value={draft.motions[id] ?? ""}
+                  onChange={(e) =>
+                    setDraft({
+                      ...draft,
+                      motions: { ...draft.motions, [id]: e.target.value },
+                    })
+                  }
+                  placeholder={`${label} 动作 ID`}
+                />
+              ))}
+            </div>
+            <div className="flex justify-end gap-2">
+              <Button
+                type="button"
+                size="sm"
+                variant="secondary"
+                onClick={() => setDraft(null)}
+                disabled={saving}
+              >
+                取消
+              </Button>
+              <Button
+                type="button"
+                size="sm"
+                onClick={handleConfirmDraft}
+                disabled={saving}
+              >
+                确认
+              </Button>
+            </div>
+          </div>
+        ) : (
+          <Button
+            type="button"
+            size="sm"
+            variant="secondary"
+            onClick={() => setDraft(emptyDraft())}
+            disabled={saving}
+          >
+            <Plus className="size-4" />
+            添加角色
+          </Button>
+        )}
+
+        {error ? <div className="text-xs text-red-200/90">{error}</div> : null}
+      </div>
+    </>
+  );
+}
@@ -0,0 +1,225 @@
+import { useCallback, useEffect, useState } from "react";
+
+import { Pencil, Plus, XIcon } from "lucide-react";
+
+import { Button } from "@/components/ui/button";
+import { cn } from "@/lib/utils";
+import {
+  listPromptTemplates,
+  setPromptTemplates,
+  type PromptTemplate,
+} from "@/services";
+import type { ResponseFormat } from "@/types";
+
+type TemplateDraft = {
+  originalId: string | null;
+  name: string;
+  content: string;
+  /** JSON Schema text; `{}` = any JSON object, empty = plain text. */
+  schema: string;
+};
+
+const schemaText = (format?: ResponseFormat | null) => {
+  if (!format) return "";
+  if (format.type === "json_object") return "{}";
+  return JSON.stringify(format.schema, null, 2);
+};
+
+/** Keeps `name` / `strict` of the format being edited. */
+const parseSchema = (
+  text: string,
+  previous?: ResponseFormat | null
+): ResponseFormat | undefined => {
+  if (!text.trim()) return undefined;
+  const schema = JSON.parse(text);
+  if (typeof schema !== "object" || schema === null || Array.isArray(schema)) {
+    throw new Error("需要 JSON 对象");
+  }
+  return Object.keys(schema).length === 0
+    ? { type: "json_object" }
+    : previous?.type === "json_schema"
+      ? { ...previous, schema }
+      : { type: "json_schema", schema, strict: false };
+};
+
+const inputClassName = cn(
+  "h-8 w-full rounded-md border border-border/50 bg-background/40 px-2 text-xs text-foreground",
+  "placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring"
+);
+
+/** Prompt templates with `{{placeholder}}` variables, rendered by the backend. */
+export function PromptTemplateSection() {
+  const [templates, setTemplateList] = useState<PromptTemplate[]>([]);
+  const [draft, setDraft] = useState<TemplateDraft | null>(null);
+  const [saving, setSaving] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    void listPromptTemplates()
+      .then(setTemplateList)
+      .catch((err) => setError(String(err)));
+  }, []);
+
+  const save = useCallback((next: PromptTemplate[]) => {
+    setSaving(true);
+    setError(null);
+    return setPromptTemplates(next)
+      .then((saved) => {
+        setTemplateList(saved);
+        return true;
+      })
+      .catch((err) => {
+        setError(String(err));
+        return false;
+      })
+      .finally(() => setSaving(false));
+  }, []);
+
+  const handleConfirmDraft = useCallback(() => {
+    if (!draft) return;
+    if (!draft.name.trim()) {
+      setError("模板名称不能为空");
+      return;
+    }
+
+    let responseFormat: ResponseFormat | undefined;
+    try {
+      const previous = templates.find((t) => t.id === draft.originalId);
+      responseFormat = parseSchema(draft.schema, previous?.responseFormat);
+    } catch (err) {
+      setError(`JSON Schema 无效：${String(err)}`);
+      return;
+    }
+
+    const template: PromptTemplate = {
+      id: draft.originalId ?? "",
+      name: draft.name,
+      content: draft.content,
+      ...(responseFormat ? { responseFormat } : {}),
+    };
+    const next = draft.originalId
+      ? templates.map((t) => (t.id === draft.originalId ? template : t))
+      : [...templates, template];
+
+    void save(next).then((ok) => {
+      if (ok) setDraft(null);
+    });
+  }, [draft, save, templates]);
+
+  return (
+    <>
+      <div className="text-xs font-semibold text-foreground/80">提示词模板</div>
+      <div className="grid gap-2 rounded-lg border border-border/50 bg-background/40 px-3 py-2">
+        <div className="text-xs opacity-70">
+          {
+            "可用变量：{{selection}} {{clipboard}} {{window_title}} {{date}} {{ocr_text}}"
+          }
+        </div>
+        {templates.length > 0 ? (
+          <div className="flex flex-wrap gap-1">
+            {templates.map((t) => (
+              <div
+                key={t.id}
+                className="inline-flex items-center gap-1 rounded-md border border-border/50 bg-background/30 px-1 py-1"
+              >
+                <span className="max-w-[160px] truncate px-1 text-xs text-foreground/90">
+                  {t.name}
+                </span>
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon-sm"
+                  className="h-6 w-6"
+                  onClick={() =>
+                    setDraft({
+                      originalId: t.id,
+                      name: t.name,
+                      content: t.content,
+                      schema: schemaText(t.responseFormat),
+                    })
+                  }
+                  disabled={saving}
+                  title="编辑"
+                >
+                  <Pencil className="size-3" />
+                </Button>
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon-sm"
+                  className="h-6 w-6"
+                  onClick={() =>
+                    void save(templates.filter((x) => x.id !== t.id))
+                  }
+                  disabled={saving}
+                  title="删除"
+                >
+                  <XIcon className="size-3" />
+                </Button>
+              </div>
+            ))}
+          </div>
+        ) : null}
+
+        {draft ? (
+          <div className="grid gap-2">
+            <input
+              className={inputClassName}
+              value={draft.name}
+              onChange={(e) => setDraft({ ...draft, name: e.target.value })}
+              placeholder="名称"
+              autoFocus
+            />
+            <textarea
+              className={cn(inputClassName, "h-24 resize-y py-1")}
+              value={draft.content}
+              onChange={(e) => setDraft({ ...draft, content: e.target.value })}
+              placeholder="例如：把下面的内容翻译成英文：{{selection}}"
+            />
+            <textarea
+              className={cn(inputClassName, "h-16 resize-y py-1 font-mono")}
+              value={draft.schema}
+              onChange={(e) => setDraft({ ...draft, schema: e.target.value })}
+              placeholder="输出 JSON Schema（可选；{} 表示任意 JSON 对象）"
+              spellCheck={false}
+            />
+            <div className="flex justify-end gap-2">
+              <Button
+                type="button"
+                size="sm"
+                variant="secondary"
+                onClick={() => setDraft(null)}
+                disabled={saving}
+              >
+                取消
+              </Button>
+              <Button
+                type="button"
+                size="sm"
+                onClick={handleConfirmDraft}
+                disabled={saving}
+              >
+                确认
+              </Button>
+            </div>
+          </div>
+        ) : (
+          <Button
+            type="button"
+            size="sm"
+            variant="secondary"
+            onClick={() =>
+              setDraft({ originalId: null, name: "", content: "", schema: "" })
+            }
+            disabled={saving}
+          >
+            <Plus className="size-4" />
+            添加模板
+          </Button>
+        )}
+
+        {error ? <div className="text-xs text-red-200/90">{error}</div> : null}
+      </div>
+    </>
+  );
+}
@@ -0,0 +1,82 @@
+import { useCallback, useEffect, useState } from "react";
+
+import { XIcon } from "lucide-react";
+
+import { Button } from "@/components/ui/button";
+import {
+  getToolApprovalRules,
+  setToolApprovalRules,
+  type ToolApprovalRule,
+} from "@/services";
+
+const describeRule = (rule: ToolApprovalRule) => {
+  const target = [rule.app, rule.window].filter(Boolean).join(" · ");
+  return target ? `${rule.tool} · ${target}` : `${rule.tool} · 全部窗口`;
+};
+
+/** Remembered "always allow" decisions for tools that ask before running. */
+export function ToolApprovalSection() {
+  const [rules, setRules] = useState<ToolApprovalRule[]>([]);
+  const [saving, setSaving] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    void getToolApprovalRules()
+      .then(setRules)
+      .catch((err) => setError(String(err)));
+  }, []);
+
+  const revoke = useCallback(
+    (index: number) => {
+      setSaving(true);
+      setError(null);
+      void setToolApprovalRules(rules.filter((_, i) => i !== index))
+        .then(setRules)
+        .catch((err) => setError(String(err)))
+        .finally(() => setSaving(false));
+    },
+    [rules]
+  );
+
+  return (
+    <>
+      <div className="text-xs font-semibold text-foreground/80">工具授权</div>
+      <div className="grid gap-2 rounded-lg border border-border/50 bg-background/40 px-3 py-2">
+        {rules.length > 0 ? (
+          <div className="grid gap-1">
+            {rules.map((rule, index) => (
+              <div
+                key={describeRule(rule)}
+                className="flex items-center gap-2 text-xs"
+              >
+                <span
+                  className="min-w-0 truncate text-foreground/90"
+                  title={describeRule(rule)}
+                >
+                  {describeRule(rule)}
+                </span>
+                <Button
+                  type="button"
+                  variant="ghost"
+                  size="icon-sm"
+                  className="ml-auto h-6 w-6"
+                  onClick={() => revoke(index)}
+                  disabled={saving}
+                  title="撤销"
+                >
+                  <XIcon className="size-3" />
+                </Button>
+              </div>
+            ))}
+          </div>
+        ) : (
+          <div className="text-xs opacity-70">
+            截屏类工具每次调用前都会询问；选择“始终允许”后会记录在这里。
+          </div>
+        )}
+
+        {error ? <div className="text-xs text-red-200/90">{error}</div> : null}
+      </div>
+    </>
+  );
+}
@@ -4,6 +4,7 @@ import { Shrink } from "lucide-react";
 import { ContextUsageIndicator } from "@/components/ai-elements/context";
 import { Capsule } from "@/components";
 import ChatMessages from "@/components/ChatMessages";
+import ToolApprovalPrompt from "@/components/chat/ToolApprovalPrompt";
 import PromptInput from "@/components/PromptInput";
 import { useChatContext } from "@/contexts/ChatContext";
 import { estimateLanguageModelUsageFromMessages } from "@/utils";
@@ -49,6 +50,7 @@ export function ResultView({ className }: ResultViewProps) {
       <Capsule {...capsuleProps} />
       <PromptInput {...promptProps} />
       {showChat ? <ChatMessages {...chatProps} /> : null}
+      <ToolApprovalPrompt />
       {errorText ? (
         <div className="rounded-md border border-red-500/30 bg-red-950/35 px-3 py-2 text-xs text-red-100/90">
           {errorText}
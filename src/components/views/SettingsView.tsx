@@ -1,6 +1,6 @@
 import { useCallback, useEffect, useMemo, useState } from "react";
 
-import { Eye, EyeOff, Pencil, Plus, XIcon } from "lucide-react";
+import { Eye, EyeOff, Pencil, Plus, RefreshCw, XIcon } from "lucide-react";
 
 import { Capsule } from "@/components";
 import { Button } from "@/components/ui/button";
@@ -17,14 +17,39 @@ import {
   ModelEditorDialog,
   type ModelEditorDraft,
 } from "@/components/settings/ModelEditorDialog";
+import { McpSection } from "@/components/settings/McpSection";
+import { PersonaSection } from "@/components/settings/PersonaSection";
+import { PromptTemplateSection } from "@/components/settings/PromptTemplateSection";
+import { ToolApprovalSection } from "@/components/settings/ToolApprovalSection";
+import { HistoryExportSection } from "@/components/settings/HistoryExportSection";
+import { HistoryImportSection } from "@/components/settings/HistoryImportSection";
+import { HistoryTrashSection } from "@/components/settings/HistoryTrashSection";
 import { useChatContext } from "@/contexts/ChatContext";
-import type { AiConfig, AiModel, AiProvider, SkinMode } from "@/types";
+import type {
+  AiConfig,
+  AiModel,
+  AiProvider,
+  GenerationParams,
+  SkinMode,
+} from "@/types";
 import { cn } from "@/lib/utils";
-import { setAiProfile, setAiProvider, testAiProfile } from "@/services";
+import {
+  discoverAiModels,
+  setAiFallbacks,
+  setAiProfile,
+  setAiProvider,
+  testAiProfile,
+  type AiFallbackTarget,
+  type LocalModelStatus,
+} from "@/services";
 
 export type SettingsViewProps = {
   aiConfig: AiConfig | null;
   onRefreshAiConfig: () => Promise<AiConfig | null>;
+  /** Reload the conversation list (e.g. after restoring one from the trash). */
+  onRefreshHistory: () => void;
+  /** Reload personas after editing them (feeds the conversation persona picker). */
+  onRefreshPersonas: () => void;
   onClose: () => void;
   skinMode: SkinMode;
   onSkinModeChange: (mode: SkinMode) => void;
@@ -34,8 +59,24 @@ const PROVIDER_LABELS: Record<AiProvider, string> = {
   deepseek: "DeepSeek",
   openai: "OpenAI",
   compatible: "OpenAI-compatible",
+  anthropic: "Anthropic",
+  ollama: "Ollama (本地)",
+};
+
+// Local servers accept unauthenticated requests.
+const API_KEY_OPTIONAL: ReadonlySet<AiProvider> = new Set(["ollama"]);
+
+const formatLocalStatus = (status: LocalModelStatus | null) => {
+  if (!status) return "";
+  if (!status.loaded) return "（模型未驻留内存）";
+  const until = status.keepAliveUntil ? new Date(status.keepAliveUntil) : null;
+  if (!until || Number.isNaN(until.getTime())) return "（模型已加载）";
+  return `（模型已加载，保持至 ${until.toLocaleTimeString()}）`;
 };
 
+const optionalNumberText = (value: number | null | undefined) =>
+  typeof value === "number" ? String(value) : "";
+
 const SKIN_MODE_LABELS: Record<SkinMode, string> = {
   off: "关闭",
   vrm: "VRM",
@@ -44,6 +85,8 @@ const SKIN_MODE_LABELS: Record<SkinMode, string> = {
 export function SettingsView({
   aiConfig,
   onRefreshAiConfig,
+  onRefreshHistory,
+  onRefreshPersonas,
   onClose,
   skinMode,
   onSkinModeChange,
@@ -66,6 +109,7 @@ export function SettingsView({
   const [apiKey, setApiKey] = useState("");
   const [showApiKey, setShowApiKey] = useState(false);
   const [testing, setTesting] = useState(false);
+  const [discovering, setDiscovering] = useState(false);
 
   useEffect(() => {
     setProvider(initialProvider);
@@ -104,7 +148,8 @@ export function SettingsView({
     [onRefreshAiConfig]
   );
 
-  const busy = providerSaving || testing;
+  const busy = providerSaving || testing || discovering;
+  const apiKeyOptional = API_KEY_OPTIONAL.has(provider);
 
   const normalizedModels = useMemo(() => {
     const out: AiModel[] = [];
@@ -122,6 +167,7 @@ export function SettingsView({
         supportsVision: Boolean(m.supportsVision),
         supportsThink: Boolean(m.supportsThink),
         special: (m.special ?? "").trim() || null,
+        params: m.params,
       });
     }
 
@@ -150,6 +196,13 @@ export function SettingsView({
       supportsVision: false,
       supportsThink: false,
       special: "",
+      temperature: "",
+      topP: "",
+      presencePenalty: "",
+      frequencyPenalty: "",
+      seed: "",
+      stop: "",
+      reasoningEffort: "",
     });
   }, []);
 
@@ -163,6 +216,13 @@ export function SettingsView({
       supportsVision: Boolean(m.supportsVision),
       supportsThink: Boolean(m.supportsThink),
       special: m.special ?? "",
+      temperature: optionalNumberText(m.params?.temperature),
+      topP: optionalNumberText(m.params?.topP),
+      presencePenalty: optionalNumberText(m.params?.presencePenalty),
+      frequencyPenalty: optionalNumberText(m.params?.frequencyPenalty),
+      seed: optionalNumberText(m.params?.seed),
+      stop: (m.params?.stop ?? []).join("\n"),
+      reasoningEffort: m.params?.reasoningEffort ?? "",
     });
   }, []);
 
@@ -209,6 +269,83 @@ export function SettingsView({
       return;
     }
 
+    const parseNumberInRange = (
+      raw: string,
+      label: string,
+      min: number,
+      max: number
+    ) => {
+      const trimmed = raw.trim();
+      if (!trimmed)
+        return { value: null as number | null, error: null as string | null };
+      const n = Number(trimmed);
+      if (!Number.isFinite(n) || n < min || n > max) {
+        return {
+          value: null as number | null,
+          error: `${label} 需要在 ${min} 到 ${max} 之间`,
+        };
+      }
+      return { value: n, error: null as string | null };
+    };
+
+    const temperature = parseNumberInRange(
+      modelEditor.temperature,
+      "Temperature",
+      0,
+      2
+    );
+    const topP = parseNumberInRange(modelEditor.topP, "Top P", 0, 1);
+    const presencePenalty = parseNumberInRange(
+      modelEditor.presencePenalty,
+      "Presence penalty",
+      -2,
+      2
+    );
+    const frequencyPenalty = parseNumberInRange(
+      modelEditor.frequencyPenalty,
+      "Frequency penalty",
+      -2,
+      2
+    );
+    const paramError =
+      temperature.error ??
+      topP.error ??
+      presencePenalty.error ??
+      frequencyPenalty.error;
+    if (paramError) {
+      setModelEditorError(paramError);
+      return;
+    }
+
+    const seedText = modelEditor.seed.trim();
+    const seed = seedText ? Number(seedText) : null;
+    if (seed !== null && !Number.isSafeInteger(seed)) {
+      setModelEditorError("Seed 需要为整数");
+      return;
+    }
+
+    const stop = modelEditor.stop
+      .split("\n")
+      .filter((s) => s.trim().length > 0);
+    if (stop.length > 4) {
+      setModelEditorError("Stop 最多 4 个");
+      return;
+    }
+
+    const params: GenerationParams = {
+      temperature: temperature.value,
+      topP: topP.value,
+      // Not editable here: requests fall back to maxOutput.
+      maxTokens:
+        normalizedModels.find((m) => m.id === modelEditor.originalId)?.params
+          ?.maxTokens ?? null,
+      presencePenalty: presencePenalty.value,
+      frequencyPenalty: frequencyPenalty.value,
+      seed,
+      stop,
+      reasoningEffort: modelEditor.reasoningEffort || null,
+    };
+
     const special = modelEditor.special.trim() || null;
     const originalId = modelEditor.originalId?.trim() ?? null;
 
@@ -228,6 +365,7 @@ export function SettingsView({
       supportsVision: modelEditor.supportsVision,
       supportsThink: modelEditor.supportsThink,
       special,
+      params,
     };
 
     setModels((prev) => {
@@ -282,18 +420,20 @@ export function SettingsView({
     setError(null);
     setSuccess(null);
 
+    let statusText = "";
     void testAiProfile({ provider, baseUrl, model, apiKey })
-      .then(() =>
-        setAiProfile({
+      .then((status) => {
+        statusText = formatLocalStatus(status);
+        return setAiProfile({
           provider,
           baseUrl,
           model,
           apiKey,
           models: normalizedModels,
-        })
-      )
+        });
+      })
       .then(() => onRefreshAiConfig())
-      .then(() => setSuccess("测试成功，已保存"))
+      .then(() => setSuccess(`测试成功，已保存${statusText}`))
       .catch((err) => {
         setError(String(err));
         void onRefreshAiConfig();
@@ -301,6 +441,62 @@ export function SettingsView({
       .finally(() => setTesting(false));
   }, [apiKey, baseUrl, model, normalizedModels, onRefreshAiConfig, provider]);
 
+  const handleDiscoverModels = useCallback(() => {
+    setDiscovering(true);
+    setError(null);
+    setSuccess(null);
+
+    void discoverAiModels({ provider, baseUrl, apiKey })
+      .then((discovered) => {
+        setModels(discovered);
+        setModel((current) => {
+          const selected = current.trim();
+          if (selected && discovered.some((m) => m.id === selected)) {
+            return current;
+          }
+          return discovered[0]?.id ?? current;
+        });
+        setSuccess(`已获取 ${discovered.length} 个模型`);
+      })
+      .catch((err) => setError(String(err)))
+      .finally(() => setDiscovering(false));
+  }, [apiKey, baseUrl, provider]);
+
+  const fallbacks = useMemo(
+    () => aiConfig?.fallbacks ?? [],
+    [aiConfig?.fallbacks]
+  );
+
+  const saveFallbacks = useCallback(
+    (next: AiFallbackTarget[]) => {
+      setTesting(true);
+      setError(null);
+      setSuccess(null);
+
+      void setAiFallbacks(next)
+        .then(() => onRefreshAiConfig())
+        .catch((err) => setError(String(err)))
+        .finally(() => setTesting(false));
+    },
+    [onRefreshAiConfig]
+  );
+
+  const handleAddFallback = useCallback(() => {
+    const target = model.trim();
+    if (!target) return;
+    if (fallbacks.some((f) => f.provider === provider && f.model === target)) {
+      return;
+    }
+    saveFallbacks([...fallbacks, { provider, model: target }]);
+  }, [fallbacks, model, provider, saveFallbacks]);
+
+  const handleRemoveFallback = useCallback(
+    (index: number) => {
+      saveFallbacks(fallbacks.filter((_, i) => i !== index));
+    },
+    [fallbacks, saveFallbacks]
+  );
+
   const handleSave = useCallback(() => {
     setTesting(true);
     setError(null);
@@ -421,6 +617,20 @@ export function SettingsView({
                         </SelectItemText>
                       </div>
                     </SelectItem>
+                    <SelectItem
+                      value="anthropic"
+                      textValue={PROVIDER_LABELS.anthropic}
+                    >
+                      <div className="flex items-center gap-2">
+                        <ProviderLogo
+                          provider="anthropic"
+                          className="size-3.5 shrink-0"
+                        />
+                        <SelectItemText>
+                          {PROVIDER_LABELS.anthropic}
+                        </SelectItemText>
+                      </div>
+                    </SelectItem>
                     <SelectItem
                       value="compatible"
                       textValue={PROVIDER_LABELS.compatible}
@@ -435,6 +645,20 @@ export function SettingsView({
                         </SelectItemText>
                       </div>
                     </SelectItem>
+                    <SelectItem
+                      value="ollama"
+                      textValue={PROVIDER_LABELS.ollama}
+                    >
+                      <div className="flex items-center gap-2">
+                        <ProviderLogo
+                          provider="ollama"
+                          className="size-3.5 shrink-0"
+                        />
+                        <SelectItemText>
+                          {PROVIDER_LABELS.ollama}
+                        </SelectItemText>
+                      </div>
+                    </SelectItem>
                   </SelectContent>
                 </Select>
               </div>
@@ -515,6 +739,18 @@ export function SettingsView({
                     <Plus className="size-4" />
                     添加模型
                   </Button>
+                  {provider === "ollama" ? (
+                    <Button
+                      type="button"
+                      size="sm"
+                      variant="secondary"
+                      onClick={handleDiscoverModels}
+                      disabled={busy}
+                    >
+                      <RefreshCw className="size-4" />
+                      从服务器获取模型
+                    </Button>
+                  ) : null}
                 </div>
 
                 <div className="grid gap-1">
@@ -529,7 +765,9 @@ export function SettingsView({
                       onChange={(e) => setApiKey(e.target.value)}
                       disabled={busy}
                       type={showApiKey ? "text" : "password"}
-                      placeholder="请输入 API Key"
+                      placeholder={
+                        apiKeyOptional ? "本地服务可留空" : "请输入 API Key"
+                      }
                       autoComplete="off"
                       spellCheck={false}
                     />
@@ -546,6 +784,55 @@ export function SettingsView({
                   </div>
                 </div>
 
+                <div className="grid gap-1">
+                  <div className="text-xs opacity-70">
+                    备用模型（当前服务失败时依次尝试）
+                  </div>
+                  {fallbacks.length > 0 ? (
+                    <div className="flex flex-wrap gap-1">
+                      {fallbacks.map((f, index) => (
+                        <div
+                          key={`${f.provider}:${f.model}`}
+                          className="inline-flex items-center gap-1 rounded-md border border-border/50 bg-background/30 px-1 py-1"
+                        >
+                          <ProviderLogo
+                            provider={f.provider}
+                            className="size-3 shrink-0"
+                          />
+                          <span
+                            className="max-w-[180px] truncate px-1 text-xs text-foreground/90"
+                            title={`${PROVIDER_LABELS[f.provider]} · ${f.model}`}
+                          >
+                            {index + 1}. {f.model}
+                          </span>
+                          <Button
+                            type="button"
+                            variant="ghost"
+                            size="icon-sm"
+                            className="h-6 w-6"
+                            onClick={() => handleRemoveFallback(index)}
+                            disabled={busy}
+                            title="删除"
+                          >
+                            <XIcon className="size-3" />
+                          </Button>
+                        </div>
+                      ))}
+                    </div>
+                  ) : null}
+                  <Button
+                    type="button"
+                    size="sm"
+                    variant="secondary"
+                    onClick={handleAddFallback}
+                    disabled={busy || !model.trim()}
+                    title="使用该服务商已保存的 Base URL / API Key"
+                  >
+                    <Plus className="size-4" />
+                    将当前模型加入备用
+                  </Button>
+                </div>
+
                 {error ? (
                   <div className="text-xs text-red-200/90">{error}</div>
                 ) : null}
@@ -571,7 +858,7 @@ export function SettingsView({
                       busy ||
                       !baseUrl.trim() ||
                       !model.trim() ||
-                      !apiKey.trim() ||
+                      (!apiKeyOptional && !apiKey.trim()) ||
                       normalizedModels.length === 0
                     }
                   >
@@ -580,6 +867,20 @@ export function SettingsView({
                 </div>
               </div>
             </div>
+
+            <PersonaSection onSaved={onRefreshPersonas} />
+
+            <PromptTemplateSection />
+
+            <McpSection />
+
+            <ToolApprovalSection />
+
+            <HistoryExportSection />
+
+            <HistoryImportSection />
+
+            <HistoryTrashSection onRestored={onRefreshHistory} />
           </div>
         </div>
       </div>
@@ -124,6 +124,27 @@ export const getCachedEmotionProfile = (url: string | null): EmotionProfile => {
   return readLocalProfile(normalized) ?? makeDefaultProfile();
 };
 
+/** Motions of the active conversation's persona, applied on top of the model's own profile. */
+let personaOverrides: Partial<EmotionProfile> = {};
+
+export const setPersonaEmotionOverrides = (
+  profile: PersistedVrmEmotionProfile | null | undefined
+) => {
+  const normalized = normalizeProfile(profile ?? {});
+  const next: Partial<EmotionProfile> = {};
+  EMOTION_OPTIONS.forEach((item) => {
+    if (normalized[item.id].motionId) next[item.id] = normalized[item.id];
+  });
+  personaOverrides = next;
+};
+
+/** Profile the avatar plays: the model's mappings with the persona's overrides applied. */
+export const getActiveEmotionProfile = (url: string | null): EmotionProfile => {
+  const base = getCachedEmotionProfile(url);
+  if (Object.keys(personaOverrides).length === 0) return base;
+  return { ...base, ...personaOverrides };
+};
+
 export const loadEmotionProfile = async (url: string | null) => {
   const normalized = url?.trim() ?? "";
   loadSeq += 1;
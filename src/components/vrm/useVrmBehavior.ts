@@ -32,7 +32,7 @@ import {
   subscribeExpressionBindings,
 } from "@/components/vrm/expressionBindingsStore";
 import {
-  getCachedEmotionProfile,
+  getActiveEmotionProfile,
   loadEmotionProfile,
 } from "@/components/vrm/emotionProfileStore";
 import { buildEmotionExpressions } from "@/components/vrm/emotionRecipes";
@@ -537,7 +537,7 @@ export const useVrmBehavior = ({
     const url = vrmUrlRef.current;
     if (controller && url) {
       const emotion = emotionRef.current;
-      const profile = getCachedEmotionProfile(url);
+      const profile = getActiveEmotionProfile(url);
       const mapping = profile[emotion];
       const desiredMotionId = mapping?.motionId ?? null;
       const desiredLoopMotion = mapping?.loopMotion ?? true;
@@ -13,6 +13,21 @@ export const EVT_CHAT_DONE = 'chat-done' as const;
 /** AI chat error event */
 export const EVT_CHAT_ERROR = 'chat-error' as const;
 
+/** AI tool-call activity event (started / finished) */
+export const EVT_CHAT_TOOL_CALL = 'chat-tool-call' as const;
+
+/** Sensitive tool call waiting for the user's approval */
+export const EVT_TOOL_APPROVAL_REQUEST = 'tool-approval-request' as const;
+
+/** AI context budgeting event (older messages dropped to fit the model window) */
+export const EVT_CHAT_CONTEXT_TRIMMED = 'chat-context-trimmed' as const;
+
+/** AI profile (provider + model) answering a request, sent before the first delta */
+export const EVT_CHAT_PROFILE = 'chat-profile' as const;
+
+/** History import progress (conversations processed / imported / skipped) */
+export const EVT_HISTORY_IMPORT_PROGRESS = 'history-import-progress' as const;
+
 /** Voice ASR result event (streamed from backend) */
 export const EVT_VOICE_ASR_RESULT = 'voice-asr-result' as const;
 
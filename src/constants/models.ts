@@ -15,9 +15,15 @@ export const OPENAI_MODEL_OPTIONS: ModelOption[] = [
   { id: "gpt-4o-mini", name: "GPT-4o Mini" },
 ];
 
+export const ANTHROPIC_MODEL_OPTIONS: ModelOption[] = [
+  { id: "claude-sonnet-4-5", name: "Claude Sonnet 4.5" },
+  { id: "claude-haiku-4-5", name: "Claude Haiku 4.5" },
+];
+
 const MODEL_NAME_MAP = new Map<string, string>([
   ...DEEPSEEK_MODEL_OPTIONS,
   ...OPENAI_MODEL_OPTIONS,
+  ...ANTHROPIC_MODEL_OPTIONS,
 ].map((m) => [m.id, m.name]));
 
 type ModelLike = { id: string; name?: string | null };
@@ -65,6 +71,9 @@ export const getRegisteredModelOptions = (
       return withConfigured(OPENAI_MODEL_OPTIONS);
     case "deepseek":
       return withConfigured(DEEPSEEK_MODEL_OPTIONS);
+    case "anthropic":
+      return withConfigured(ANTHROPIC_MODEL_OPTIONS);
+    case "ollama":
     case "compatible": {
       return configured ? [{ id: configured, name: configured }] : [];
     }
@@ -2,18 +2,28 @@ import { useCallback } from "react";
 import type { UIMessage } from "ai";
 
 import type { WindowMode } from "@/types";
-import { chatAbortConversation, voiceStop } from "@/services";
+import {
+  chatAbortConversation,
+  chatContinue,
+  historySelectMessageVariant,
+  historySetMessagePinned,
+  voiceStop,
+} from "@/services";
 import { getMessageText, reportPromiseError } from "@/utils";
 
 type UseConversationActionsParams = {
   activeConversationId: string | null;
   windowMode: WindowMode;
+  /** Model and modes used for requests started outside `useChat` (continue). */
+  chatSettings: { model: string; toolMode: boolean; voiceMode: boolean };
   messages: UIMessage[];
   sendMessage: (payload: { text: string }) => void;
+  regenerate: (options: { messageId: string }) => Promise<void>;
   setMessages: (messages: UIMessage[]) => void;
   stop: () => void;
   busy: boolean;
   isConversationGenerating: (conversationId: string) => boolean;
+  markGenerating: (conversationId: string) => void;
   clearGenerating: (conversationId: string) => void;
   forkConversation: (
     conversationId: string,
@@ -22,23 +32,28 @@ type UseConversationActionsParams = {
   deleteConversation: (conversationId: string) => Promise<unknown>;
   renameConversation: (conversationId: string, title: string) => Promise<void>;
   selectConversation: (conversationId: string) => Promise<void>;
+  loadConversation: (conversationId: string) => Promise<unknown>;
   markSeen: (conversationId: string) => Promise<void>;
 };
 
 export function useConversationActions({
   activeConversationId,
   windowMode,
+  chatSettings,
   messages,
   sendMessage,
+  regenerate,
   setMessages,
   stop,
   busy,
   isConversationGenerating,
+  markGenerating,
   clearGenerating,
   forkConversation,
   deleteConversation,
   renameConversation,
   selectConversation,
+  loadConversation,
   markSeen,
 }: UseConversationActionsParams) {
   const parseHistorySeq = useCallback(
@@ -76,6 +91,18 @@ export function useConversationActions({
       const messageIndex = messages.findIndex((m) => m.id === messageId);
       if (messageIndex === -1) return;
 
+      // The last persisted answer is regenerated in place so the current one is kept as a variant.
+      if (
+        messageIndex === messages.length - 1 &&
+        messages[messageIndex].role === "assistant" &&
+        parseHistorySeq(messageId)
+      ) {
+        void regenerate({ messageId }).catch(
+          reportPromiseError("App.regenerate", { onceKey: "App.regenerate" })
+        );
+        return;
+      }
+
       // Find the user message before this assistant message
       const userMessageBefore = messages
         .slice(0, messageIndex)
@@ -98,7 +125,74 @@ export function useConversationActions({
       // Resend the user message
       sendMessage({ text: userText });
     },
-    [activeConversationId, messages, sendMessage, setMessages]
+    [messages, parseHistorySeq, regenerate, sendMessage, setMessages]
+  );
+
+  const handleSelectVariant = useCallback(
+    (messageId: string, variant: number) => {
+      const conversationId = activeConversationId;
+      const seq = parseHistorySeq(messageId);
+      if (!conversationId || !seq) return;
+
+      void historySelectMessageVariant(conversationId, seq, variant)
+        .then(() => loadConversation(conversationId))
+        .catch(
+          reportPromiseError("App.selectMessageVariant", {
+            onceKey: "App.selectMessageVariant",
+          })
+        );
+    },
+    [activeConversationId, loadConversation, parseHistorySeq]
+  );
+
+  const handleTogglePin = useCallback(
+    (messageId: string, pinned: boolean) => {
+      const conversationId = activeConversationId;
+      const seq = parseHistorySeq(messageId);
+      if (!conversationId || !seq) return;
+
+      void historySetMessagePinned(conversationId, seq, pinned)
+        .then(() => loadConversation(conversationId))
+        .catch(
+          reportPromiseError("App.setMessagePinned", {
+            onceKey: "App.setMessagePinned",
+          })
+        );
+    },
+    [activeConversationId, loadConversation, parseHistorySeq]
+  );
+
+  const handleContinue = useCallback(
+    (messageId: string) => {
+      const conversationId = activeConversationId;
+      const seq = parseHistorySeq(messageId);
+      if (!conversationId || !seq) return;
+      if (busy || isConversationGenerating(conversationId)) return;
+
+      // Runs detached from `useChat`; `chat-done` reloads the extended message.
+      markGenerating(conversationId);
+      void chatContinue({
+        conversationId,
+        seq,
+        model: chatSettings.model || undefined,
+        tools: chatSettings.toolMode,
+        voice: chatSettings.voiceMode,
+      }).catch((error) => {
+        clearGenerating(conversationId);
+        reportPromiseError("App.chatContinue", {
+          onceKey: "App.chatContinue",
+        })(error);
+      });
+    },
+    [
+      activeConversationId,
+      busy,
+      chatSettings,
+      clearGenerating,
+      isConversationGenerating,
+      markGenerating,
+      parseHistorySeq,
+    ]
   );
 
   const handleBranchFrom = useCallback(
@@ -226,6 +320,9 @@ export function useConversationActions({
   return {
     handleEditMessage,
     handleRegenerateFrom,
+    handleSelectVariant,
+    handleTogglePin,
+    handleContinue,
     handleBranchFrom,
     handleStop,
     handleDeleteConversation,
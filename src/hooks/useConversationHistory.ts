@@ -12,6 +12,7 @@ import {
   historyNewConversation,
   historyRenameConversation,
   historySetActiveConversation,
+  historySetConversationPersona,
 } from "@/services/history";
 import { isTauriContext, reportPromiseError } from "@/utils";
 
@@ -199,6 +200,31 @@ export function useConversationHistory() {
     [refreshList]
   );
 
+  const setConversationPersona = useCallback(
+    async (conversationId: string, personaId: string | null) => {
+      if (!isTauriContext()) return;
+
+      await historySetConversationPersona(conversationId, personaId);
+      setState((prev) => ({
+        ...prev,
+        conversations: prev.conversations.map((c) =>
+          c.id === conversationId ? { ...c, personaId } : c
+        ),
+        activeConversation:
+          prev.activeConversation?.conversation.id === conversationId
+            ? {
+                ...prev.activeConversation,
+                conversation: {
+                  ...prev.activeConversation.conversation,
+                  personaId,
+                },
+              }
+            : prev.activeConversation,
+      }));
+    },
+    []
+  );
+
   return {
     ...state,
     refreshList,
@@ -211,5 +237,6 @@ export function useConversationHistory() {
     clearConversation,
     deleteConversation,
     renameConversation,
+    setConversationPersona,
   };
 }
@@ -0,0 +1,35 @@
+import { useCallback, useEffect, useState } from "react";
+
+import { getPersonas, type Persona } from "@/services";
+import { isTauriContext, reportPromiseError } from "@/utils";
+
+export function usePersonas(): {
+  personas: Persona[];
+  refresh: () => Promise<Persona[]>;
+} {
+  const [personas, setPersonaList] = useState<Persona[]>([]);
+
+  const refresh = useCallback(async () => {
+    if (!isTauriContext()) return [];
+    const next = await getPersonas();
+    setPersonaList(next.personas);
+    return next.personas;
+  }, []);
+
+  useEffect(() => {
+    if (!isTauriContext()) return;
+
+    let active = true;
+    void getPersonas()
+      .then((next) => {
+        if (active) setPersonaList(next.personas);
+      })
+      .catch(reportPromiseError("usePersonas", { onceKey: "usePersonas" }));
+
+    return () => {
+      active = false;
+    };
+  }, []);
+
+  return { personas, refresh };
+}
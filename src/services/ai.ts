@@ -1,6 +1,18 @@
 import { invoke } from "@tauri-apps/api/core";
 
+import { createRequestId } from "./tauriChatTransport";
+
 export const chatAbortConversation = async (conversationId: string): Promise<void> => {
   await invoke("chat_abort_conversation", { conversationId });
 };
 
+/** Resume an aborted or failed answer; the text is appended to the same history message. */
+export const chatContinue = async (params: {
+  conversationId: string;
+  seq: number;
+  model?: string;
+  tools?: boolean;
+  voice?: boolean;
+}): Promise<void> => {
+  await invoke("chat_continue", { requestId: createRequestId(), ...params });
+};
@@ -1,7 +1,23 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { AiConfig, AiModel, AiProvider } from "@/bindings/tauri-types";
+import type {
+  AiConfig,
+  AiFallbackTarget,
+  AiModel,
+  AiProvider,
+  LocalModelStatus,
+  Persona,
+  PersonaSettings,
+} from "@/bindings/tauri-types";
 
-export type { AiConfig, AiModel, AiProvider } from "@/bindings/tauri-types";
+export type {
+  AiConfig,
+  AiFallbackTarget,
+  AiModel,
+  AiProvider,
+  LocalModelStatus,
+  Persona,
+  PersonaSettings,
+} from "@/bindings/tauri-types";
 
 export const getAiConfig = () => invoke<AiConfig>("get_ai_config");
 
@@ -16,9 +32,25 @@ export const setAiProfile = (params: {
   models: AiModel[];
 }) => invoke<AiConfig>("set_ai_profile", params);
 
+export const setAiFallbacks = (fallbacks: AiFallbackTarget[]) =>
+  invoke<AiConfig>("set_ai_fallbacks", { fallbacks });
+
 export const testAiProfile = (params: {
   provider: AiProvider;
   baseUrl: string;
   model: string;
   apiKey: string;
-}) => invoke<void>("test_ai_profile", params);
+}) => invoke<LocalModelStatus | null>("test_ai_profile", params);
+
+export const discoverAiModels = (params: {
+  provider: AiProvider;
+  baseUrl: string;
+  apiKey: string;
+}) => invoke<AiModel[]>("discover_ai_models", params);
+
+export const getPersonas = () => invoke<PersonaSettings>("get_personas");
+
+export const setPersonas = (
+  personas: Persona[],
+  defaultPersonaId: string | null
+) => invoke<PersonaSettings>("set_personas", { personas, defaultPersonaId });
@@ -1,9 +1,16 @@
 import { invoke } from "@tauri-apps/api/core";
 
 import type {
+  ArchivedConversation,
   ConversationDetail,
+  ConversationMessage,
   ConversationSummary,
+  ExportFormat,
   HistoryBootstrap,
+  ImportFormat,
+  ImportProgress,
+  MessageVariant,
+  SearchResults,
 } from "@/types";
 
 export const historyBootstrap = () =>
@@ -26,8 +33,20 @@ export const historyGetConversationPage = (
     limit: limit ?? null,
   });
 
-export const historyNewConversation = () =>
-  invoke<ConversationSummary>("history_new_conversation");
+/** `personaId`: omit for the default persona, `""` for none. */
+export const historyNewConversation = (personaId?: string) =>
+  invoke<ConversationSummary>("history_new_conversation", {
+    personaId: personaId ?? null,
+  });
+
+export const historySetConversationPersona = (
+  conversationId: string,
+  personaId: string | null
+) =>
+  invoke<void>("history_set_conversation_persona", {
+    conversationId,
+    personaId,
+  });
 
 export const historySetActiveConversation = (conversationId: string) =>
   invoke<void>("history_set_active_conversation", { conversationId });
@@ -48,3 +67,94 @@ export const historyForkConversation = (
 
 export const historyRenameConversation = (conversationId: string, title: string) =>
   invoke<void>("history_rename_conversation", { conversationId, title });
+
+export const historyListMessageVariants = (conversationId: string, seq: number) =>
+  invoke<MessageVariant[]>("history_list_message_variants", { conversationId, seq });
+
+export const historySelectMessageVariant = (
+  conversationId: string,
+  seq: number,
+  variant: number
+) =>
+  invoke<ConversationMessage>("history_select_message_variant", {
+    conversationId,
+    seq,
+    variant,
+  });
+
+/** Pinned messages are kept when the context is trimmed to fit the model. */
+export const historySetMessagePinned = (conversationId: string, seq: number, pinned: boolean) =>
+  invoke<void>("history_set_message_pinned", { conversationId, seq, pinned });
+
+/** Snippet matches are wrapped in `\u0002` … `\u0003`. */
+export const historySearch = (query: string, offset?: number, limit?: number) =>
+  invoke<SearchResults>("history_search", {
+    query,
+    offset: offset ?? null,
+    limit: limit ?? null,
+  });
+
+const utcOffsetMinutes = () => -new Date().getTimezoneOffset();
+
+/**
+ * `path` is a file, or a directory to name the file after the conversation.
+ * Resolves to the file written.
+ */
+export const historyExportConversation = (
+  conversationId: string,
+  format: ExportFormat,
+  path: string
+) =>
+  invoke<string>("history_export_conversation", {
+    conversationId,
+    format,
+    path,
+    utcOffsetMinutes: utcOffsetMinutes(),
+  });
+
+/** One file per conversation in `dir`; resolves to the files written. */
+export const historyExportAll = (format: ExportFormat, dir: string) =>
+  invoke<string[]>("history_export_all", {
+    format,
+    dir,
+    utcOffsetMinutes: utcOffsetMinutes(),
+  });
+
+/**
+ * Import another app's export file (absolute `path`); progress arrives as
+ * `history-import-progress`. Conversations imported before are skipped.
+ */
+export const historyImport = (format: ImportFormat, path: string) =>
+  invoke<ImportProgress>("history_import", { format, path });
+
+/** Deleted conversations in the trash, most recently deleted first. */
+export const historyListArchived = () =>
+  invoke<ArchivedConversation[]>("history_list_archived");
+
+export const historyRestoreConversation = (
+  conversationId: string,
+  setActive = false
+) =>
+  invoke<HistoryBootstrap>("history_restore_conversation", {
+    conversationId,
+    setActive,
+  });
+
+/**
+ * Omit `conversationIds` to empty the whole trash; resolves to the count
+ * purged.
+ */
+export const historyPurgeConversations = (conversationIds?: string[]) =>
+  invoke<number>("history_purge_conversations", {
+    conversationIds: conversationIds ?? null,
+  });
+
+export const historyGetTrashRetentionDays = () =>
+  invoke<number>("history_get_trash_retention_days");
+
+/**
+ * `0` keeps deleted conversations until purged by hand; resolves to the
+ * count purged now.
+ */
+export const historySetTrashRetentionDays = (days: number) =>
+  invoke<number>("history_set_trash_retention_days", { days });
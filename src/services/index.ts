@@ -9,3 +9,6 @@ export * from './window';
 export * from './history';
 export * from './voice';
 export * from './vrmSettings';
+export * from './promptTemplates';
+export * from './mcp';
+export * from './toolApproval';
@@ -0,0 +1,20 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { McpServerConfig, McpServerStatus } from "@/bindings/tauri-types";
+
+export type {
+  McpServerConfig,
+  McpServerState,
+  McpServerStatus,
+  McpTransport,
+} from "@/bindings/tauri-types";
+
+export const getMcpServers = () => invoke<McpServerConfig[]>("get_mcp_servers");
+
+export const setMcpServers = (servers: McpServerConfig[]) =>
+  invoke<McpServerConfig[]>("set_mcp_servers", { servers });
+
+export const getMcpServerStatus = () =>
+  invoke<McpServerStatus[]>("mcp_server_status");
+
+export const restartMcpServer = (name: string) =>
+  invoke<void>("mcp_restart_server", { name });
@@ -0,0 +1,53 @@
+import { invoke } from "@tauri-apps/api/core";
+import type {
+  GenerationParams,
+  PromptTemplate,
+  ResponseFormat,
+} from "@/bindings/tauri-types";
+
+export type { PromptTemplate } from "@/bindings/tauri-types";
+
+type TemplateChatMessage = {
+  seq?: number;
+  role: string;
+  content: string;
+};
+
+const utcOffsetMinutes = () => -new Date().getTimezoneOffset();
+
+export const listPromptTemplates = () =>
+  invoke<PromptTemplate[]>("list_prompt_templates");
+
+export const setPromptTemplates = (templates: PromptTemplate[]) =>
+  invoke<PromptTemplate[]>("set_prompt_templates", { templates });
+
+/** Fill `{{selection}}`, `{{clipboard}}`, `{{window_title}}`, `{{date}}`, `{{ocr_text}}`. */
+export const renderPromptTemplate = (
+  templateId: string,
+  selection?: string,
+  conversationId?: string
+) =>
+  invoke<string>("render_prompt_template", {
+    templateId,
+    selection: selection ?? null,
+    utcOffsetMinutes: utcOffsetMinutes(),
+    conversationId: conversationId ?? null,
+  });
+
+/** Render a template and stream it as the next user turn; resolves to the rendered text. */
+export const runPromptTemplate = (params: {
+  requestId: string;
+  conversationId?: string;
+  templateId: string;
+  messages: TemplateChatMessage[];
+  selection?: string;
+  model?: string;
+  /** `responseFormat` overrides the template's own. */
+  requestOptions?: { params?: GenerationParams; responseFormat?: ResponseFormat };
+  tools?: boolean;
+  voice?: boolean;
+}) =>
+  invoke<string>("run_prompt_template", {
+    ...params,
+    utcOffsetMinutes: utcOffsetMinutes(),
+  });
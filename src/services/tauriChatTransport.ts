@@ -2,7 +2,8 @@ import { createUIMessageStream, type ChatTransport, type UIMessage } from "ai";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { EVT_CHAT_ERROR, EVT_CHAT_STREAM } from "@/constants";
-import { isTauriContext, reportPromiseError } from "@/utils";
+import type { ChatAttachment } from "@/types";
+import { getImageAttachments, isTauriContext, reportPromiseError } from "@/utils";
 
 type TauriChatTransportOptions = {
   getModel?: () => string;
@@ -27,13 +28,14 @@ type TauriChatErrorPayload = {
 const createPartId = () =>
   `part_${Date.now().toString(36)}_${Math.random().toString(36).slice(2, 8)}`;
 
-const createRequestId = () =>
+export const createRequestId = () =>
   `req_${Date.now().toString(36)}_${Math.random().toString(36).slice(2, 10)}`;
 
 type ApiChatMessage = {
   seq?: number;
   role: string;
   content: string;
+  attachments?: ChatAttachment[];
 };
 
 const parseHistorySeq = (conversationId: string, messageId: string): number | null => {
@@ -71,14 +73,17 @@ const convertMessagesToApi = (
         )
         .map((part) => part.text)
         .join("\n"),
+      attachments: getImageAttachments(msg),
     }))
-    .filter((msg) => msg.content.trim() !== "");
+    .filter((msg) => msg.content.trim() !== "" || msg.attachments.length > 0);
 
   let nextSeq = persistedMaxSeq + 1;
   const apiMessages: ApiChatMessage[] = filtered.map((m) => {
     const parsed = conversationId ? parseHistorySeq(conversationId, m.id) : null;
     const seq = parsed ?? nextSeq++;
-    return { seq, role: m.role, content: m.content };
+    return m.attachments.length > 0
+      ? { seq, role: m.role, content: m.content, attachments: m.attachments }
+      : { seq, role: m.role, content: m.content };
   });
 
   return {
@@ -90,7 +95,7 @@ const convertMessagesToApi = (
 export const createTauriChatTransport = (
   options: TauriChatTransportOptions = {}
 ): ChatTransport<UIMessage> => ({
-  async sendMessages({ messages, abortSignal }) {
+  async sendMessages({ messages, abortSignal, trigger, messageId }) {
     return createUIMessageStream<UIMessage>({
       originalMessages: messages,
       execute: async ({ writer }) => {
@@ -99,6 +104,11 @@ export const createTauriChatTransport = (
           messages,
           conversationId
         );
+        // Retrying a persisted answer keeps the old one as a variant (backend reads the context).
+        const regenerateSeq =
+          trigger === "regenerate-message" && conversationId && messageId
+            ? parseHistorySeq(conversationId, messageId)
+            : null;
         if (apiMessages.length === 0) {
           writer.write({ type: "error", errorText: "No messages to send." });
           return;
@@ -232,23 +242,30 @@ export const createTauriChatTransport = (
           }
         );
 
+        const useTools = options.getToolMode?.() ?? false;
         const invokeParams: Record<string, unknown> = {
           requestId,
-          messages: apiMessages,
           voice: options.getVoiceMode?.() ?? false,
         };
         if (model) invokeParams.model = model;
-        if (conversationId) {
+        let commandName: string;
+        if (regenerateSeq !== null) {
+          commandName = "chat_regenerate";
           invokeParams.conversationId = conversationId;
-          if (typeof truncateAfterSeq === "number") {
-            invokeParams.truncateAfterSeq = truncateAfterSeq;
+          invokeParams.seq = regenerateSeq;
+          invokeParams.tools = useTools;
+        } else {
+          invokeParams.messages = apiMessages;
+          if (conversationId) {
+            invokeParams.conversationId = conversationId;
+            if (typeof truncateAfterSeq === "number") {
+              invokeParams.truncateAfterSeq = truncateAfterSeq;
+            }
           }
+          // Choose the appropriate command based on tool mode
+          commandName = useTools ? 'chat_stream_with_tools' : 'chat_stream';
         }
 
-        // Choose the appropriate command based on tool mode
-        const useTools = options.getToolMode?.() ?? false;
-        const commandName = useTools ? 'chat_stream_with_tools' : 'chat_stream';
-
         // Interrupt any previous voice playback/stream before starting a new chat request.
         await invoke("voice_stop").catch(() => {});
 
@@ -0,0 +1,22 @@
+import { invoke } from "@tauri-apps/api/core";
+import type {
+  ToolApprovalDecision,
+  ToolApprovalRule,
+} from "@/bindings/tauri-types";
+
+export type {
+  ToolApprovalDecision,
+  ToolApprovalRequest,
+  ToolApprovalRule,
+} from "@/bindings/tauri-types";
+
+export const respondToolApproval = (
+  approvalId: string,
+  decision: ToolApprovalDecision
+) => invoke<void>("tool_approval_respond", { approvalId, decision });
+
+export const getToolApprovalRules = () =>
+  invoke<ToolApprovalRule[]>("get_tool_approval_rules");
+
+export const setToolApprovalRules = (rules: ToolApprovalRule[]) =>
+  invoke<ToolApprovalRule[]>("set_tool_approval_rules", { rules });
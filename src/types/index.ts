@@ -6,9 +6,29 @@ export type {
   AiProvider,
   AiConfig,
   AiModel,
+  GenerationParams,
+  ReasoningEffort,
+  Persona,
+  PersonaSettings,
+  PromptTemplate,
+  ResponseFormat,
+  McpServerConfig,
+  McpServerStatus,
+  ToolApprovalRule,
+  ToolApprovalRequest,
+  ToolApprovalDecision,
+  ChatAttachment,
   ConversationDetail,
   ConversationMessage,
   ConversationSummary,
+  ArchivedConversation,
+  MessageStatus,
+  MessageVariant,
+  SearchHit,
+  SearchResults,
+  ExportFormat,
+  ImportFormat,
+  ImportProgress,
   HistoryError,
   HistoryBootstrap,
   WindowMode,
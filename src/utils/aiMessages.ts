@@ -1,6 +1,6 @@
-import type { UIMessage } from "ai";
+import type { FileUIPart, UIMessage } from "ai";
 
-import type { ConversationDetail } from "@/types";
+import type { ChatAttachment, ConversationDetail, MessageStatus } from "@/types";
 
 export const getMessageText = (message: UIMessage): string => {
   return message.parts
@@ -9,6 +9,60 @@ export const getMessageText = (message: UIMessage): string => {
     .join("\n");
 };
 
+const isImagePart = (part: UIMessage["parts"][number]): part is FileUIPart =>
+  part.type === "file" && part.mediaType.startsWith("image/");
+
+/** Image file parts of a message, as backend attachments. */
+export const getImageAttachments = (message: UIMessage): ChatAttachment[] =>
+  message.parts
+    .filter(isImagePart)
+    .map((part) => ({ type: "image", url: part.url }));
+
+export const getImageUrls = (message: UIMessage): string[] =>
+  message.parts.filter(isImagePart).map((part) => part.url);
+
+/** Read a picked or pasted image into a data-URL file part for `sendMessage`. */
+export const readImageFile = (file: File): Promise<FileUIPart> =>
+  new Promise((resolve, reject) => {
+    const reader = new FileReader();
+    reader.onload = () =>
+      resolve({
+        type: "file",
+        mediaType: file.type || "image/png",
+        filename: file.name,
+        url: String(reader.result),
+      });
+    reader.onerror = () => reject(reader.error);
+    reader.readAsDataURL(file);
+  });
+
+/** Stored answers of a persisted assistant turn (`null` when it was never regenerated). */
+export type MessageVariants = { active: number; count: number };
+
+export const getMessageVariants = (message: UIMessage): MessageVariants | null => {
+  const metadata = message.metadata as
+    | { activeVariant?: number; variantCount?: number }
+    | undefined;
+  const count = metadata?.variantCount ?? 0;
+  if (count < 2) return null;
+  return { active: metadata?.activeVariant ?? 0, count };
+};
+
+/** Whether a persisted assistant answer finished, or was cut short (and can be continued). */
+export const getMessageStatus = (message: UIMessage): MessageStatus => {
+  const metadata = message.metadata as { status?: MessageStatus } | undefined;
+  return metadata?.status ?? "complete";
+};
+
+/** Whether the user pinned a persisted message (kept when the context is trimmed). */
+export const getMessagePinned = (message: UIMessage): boolean => {
+  const metadata = message.metadata as { pinned?: boolean } | undefined;
+  return metadata?.pinned ?? false;
+};
+
+const attachmentMediaType = (url: string) =>
+  /^data:([^;,]+)/.exec(url)?.[1] ?? "image/jpeg";
+
 export const conversationDetailToUiMessages = (
   detail: ConversationDetail
 ): UIMessage[] => {
@@ -20,7 +74,29 @@ export const conversationDetailToUiMessages = (
     if (m.content) {
       parts.push({ type: "text", text: m.content });
     }
-    return { id: m.id, role: m.role as UIMessage["role"], parts } as UIMessage;
+    for (const attachment of m.attachments ?? []) {
+      if (attachment.type !== "image") continue;
+      parts.push({
+        type: "file",
+        mediaType: attachmentMediaType(attachment.url),
+        url: attachment.url,
+      });
+    }
+    const metadata =
+      m.role === "assistant"
+        ? {
+            activeVariant: m.activeVariant,
+            variantCount: m.variantCount,
+            status: m.status,
+            pinned: m.pinned,
+          }
+        : { pinned: m.pinned };
+    return {
+      id: m.id,
+      role: m.role as UIMessage["role"],
+      parts,
+      metadata,
+    } as UIMessage;
   });
 };
 